@@ -1,51 +1,154 @@
-use std::{path::Path, process::Command, thread, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, RwLock},
+    thread,
+    time::Duration,
+};
 
-use egui::{Button, CursorIcon, RichText, Style, TopBottomPanel};
+use egui::{Button, Color32, CursorIcon, DragValue, RichText, TopBottomPanel};
 use glium::{
     backend::glutin::Display,
     glutin::{
-        event::{ElementState, ModifiersState, MouseScrollDelta, VirtualKeyCode, WindowEvent},
-        event_loop::EventLoopProxy,
-        window::Fullscreen,
+        dpi::{PhysicalPosition, PhysicalSize},
+        event::{
+            ElementState, KeyboardInput, ModifiersState, MouseScrollDelta, VirtualKeyCode,
+            WindowEvent,
+        },
     },
 };
 use image::imageops::FilterType;
 
-use crate::{min, rect::Rect, util::UserEvent, vec2::Vec2};
+use crate::{
+    min,
+    util::{display_name, log, ImageData, Proxy, UserEvent},
+    vec2::Vec2,
+};
 
 pub mod image_view;
-use image_view::ImageView;
+use image_view::{ChannelMode, GpuView, ImageView};
 
 pub mod image_list;
 
+pub mod action;
+use action::Action;
+
 mod clipboard;
 
+mod acquire;
+mod analyze;
+mod associations;
+mod batch;
 mod color;
+mod color_picker;
+mod command_palette;
+mod contact_sheet;
+mod export_frames;
+mod export_view;
+mod filmstrip;
+mod frames;
+mod fullscreen;
+mod gallery;
+mod gpu_resize;
+mod grid;
+mod overlay;
+mod progress;
+mod toast;
+use command_palette::CommandPalette;
+use log_window::LogWindow;
+use overlay::InfoOverlay;
+use progress::Busy;
+use toast::Toasts;
 mod help;
+mod i18n;
+mod import_frames;
+mod levels;
+use i18n::tr;
+pub mod keymap;
+mod log_window;
 mod menu_bar;
 mod metadata;
+mod minimap;
 
 pub mod op_queue;
-use op_queue::{Op, OpQueue, Output};
+mod playback;
+mod playlist;
+mod preferences;
+use op_queue::{apply_edit, Op, OpQueue, Output};
+use preferences::Preferences;
 
 pub mod crop;
+mod delete;
+pub mod download;
 
 pub mod load_image;
 
-mod save_image;
+mod save_check;
+pub mod save_conflict;
+pub mod save_image;
+mod save_options;
 use crop::Crop;
+use save_check::SaveProblems;
+use save_conflict::SaveConflict;
+use save_options::SaveOptions;
 
 mod undo_stack;
 
 mod cache;
 
-mod resize;
+mod crop_preset;
+mod diagnostics;
+mod empty_state;
+mod oversized;
+mod pan;
+mod panorama;
+pub mod placement;
+mod report;
+pub mod resize;
+mod rubber_band;
+mod scratch;
+mod slideshow;
+pub mod startup;
+mod tabs;
+mod thumbnails;
+mod untitled;
+mod video;
+mod wipe;
+use analyze::ColorsWindow;
+use color_picker::ColorPicker;
+use contact_sheet::ContactSheet;
+use crop_preset::CropPreset;
+use diagnostics::Diagnostics;
+use filmstrip::Filmstrip;
+use frames::FramesPanel;
+use gallery::Gallery;
+use import_frames::ImportFrames;
+use keymap::{Keymap, ShortcutEditor};
+use levels::LevelsWindow;
+use minimap::Minimap;
+use oversized::OversizedPrompt;
+use panorama::{AutoScroll, Axis};
+use playback::PlaybackPositions;
+use report::Report;
 use resize::Resize;
+use rubber_band::RubberBand;
+use scratch::Scratch;
+use slideshow::Slideshow;
+use startup::Startup;
+use tabs::{close_warning, tab_name, Tabs};
 
-use self::undo_stack::UndoFrame;
+mod rotate;
+use rotate::Rotate;
+pub mod settings;
+use settings::{Settings, Zoom, ZoomAnchor};
 
 const TOP_BAR_SIZE: f32 = 26.0;
 const BOTTOM_BAR_SIZE: f32 = 27.0;
+/// Zoom limits, far beyond these the view math overflows or loses all precision.
+const MIN_SCALE: f32 = 0.01;
+const MAX_SCALE: f32 = 100.0;
+/// Tint of the arrows whose image is cached.
+const CACHED_COLOR: Color32 = Color32::from_rgb(110, 200, 110);
 
 pub struct App {
     exit: bool,
@@ -54,18 +157,76 @@ pub struct App {
     pub size: Vec2<f32>,
     pub position: Vec2<i32>,
     fullscreen: bool,
+    /// Set while the window is minimized or has no area, nothing is drawn then.
+    minimized: bool,
+    windowed_geometry: Option<(PhysicalPosition<i32>, PhysicalSize<u32>)>,
     pub top_bar_size: f32,
     pub bottom_bar_size: f32,
-    proxy: EventLoopProxy<UserEvent>,
+    proxy: Proxy,
     modifiers: ModifiersState,
     mouse_position: Vec2<f32>,
+    /// Physical pixels per logical pixel on the monitor the window is on.
+    scale_factor: f32,
     current_filename: String,
+    /// Where the last save went, pasted images are saved there by default.
+    last_save_dir: Option<PathBuf>,
     op_queue: OpQueue,
     pub crop: Box<Crop>,
     resize: Resize,
+    crop_preset: CropPreset,
+    color_picker: ColorPicker,
+    /// The pixel under the cursor and its color, for the readout in the bottom bar.
+    inspected: Option<((u32, u32), [u8; 4])>,
+    colors: ColorsWindow,
+    levels: LevelsWindow,
+    playback: PlaybackPositions,
+    report: Report,
+    contact_sheet: ContactSheet,
+    log_window: LogWindow,
     help_visible: bool,
     color_visible: bool,
+    /// Set while the next click on the image sets the white balance.
+    picking_gray: bool,
     metadata_visible: bool,
+    preferences: Preferences,
+    diagnostics: Diagnostics,
+    save_options: SaveOptions,
+    save_check: Option<SaveProblems>,
+    save_conflict: Option<SaveConflict>,
+    pub settings: Settings,
+    command_palette: CommandPalette,
+    dialog_open: bool,
+    rotate: Rotate,
+    export_supersample: bool,
+    pending_export: Option<Arc<RwLock<ImageData>>>,
+    toasts: Toasts,
+    info_overlay: InfoOverlay,
+    busy: Busy,
+    /// The last image that could not be loaded and why, shown on the empty screen.
+    load_error: Option<(PathBuf, String)>,
+    oversized: Option<OversizedPrompt>,
+    import_frames: Option<ImportFrames>,
+    scratch: Scratch,
+    auto_scroll: AutoScroll,
+    tabs: Tabs,
+    rubber_band: RubberBand,
+    /// An op that is waiting for the full resolution image to be loaded.
+    /// An op that waits for the full resolution frames, with the size of the image on disk
+    /// it was made for.
+    pending_op: Option<(Op, Vec2<u32>)>,
+    slideshow: Slideshow,
+    filmstrip: Filmstrip,
+    gallery: Gallery,
+    frames: FramesPanel,
+    minimap: Minimap,
+    /// New images are shown at the scale and position of the last one instead of fit.
+    lock_view: bool,
+    keymap: Keymap,
+    shortcuts: ShortcutEditor,
+    /// How images are scaled when they are shown if not as the settings say.
+    zoom: Option<Zoom>,
+    /// Whether a text field has the keyboard, the shortcuts leave it alone then.
+    text_focus: bool,
 }
 
 impl App {
@@ -73,331 +234,502 @@ impl App {
         !self.op_queue.working() && self.image_view.is_some()
     }
 
+    /// Whether the current image has more than one frame.
+    pub fn animated(&self) -> bool {
+        match self.image_view.as_ref() {
+            Some(view) => view.image_data.read().unwrap().frames.len() > 1,
+            None => false,
+        }
+    }
+
+    /// Whether the window may close. The first attempt while pasted images are
+    /// unsaved or any tab has unsaved edits only warns about them.
+    pub fn confirm_close(&mut self) -> bool {
+        if self.scratch.warned {
+            return true;
+        }
+        let mut edited = self.tabs.edited();
+        // pasted images on screen are counted with the other pasted ones
+        let current = self.op_queue.edited() && self.scratch.position().is_none();
+        if current {
+            edited.insert(0, tab_name(&self.current_filename));
+        }
+        match close_warning(self.scratch.unsaved(), &edited) {
+            Some(warning) => {
+                self.scratch.warned = true;
+                self.toasts.push(warning);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Input is ignored while a native file dialog is open
+    /// so nothing can change underneath it.
+    pub fn accepts_input(&self) -> bool {
+        !self.dialog_open
+    }
+
+    pub fn minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Windows that use the keyboard themselves suppress the global shortcuts.
+    fn keyboard_captured(&self) -> bool {
+        self.resize.visible
+            || self.command_palette.visible
+            || self.rotate.visible
+            || self.crop_preset.visible
+            || self.preferences.visible
+            || self.shortcuts.capturing.is_some()
+            || self.diagnostics.visible
+            || self.save_options.visible()
+            || self.save_check.is_some()
+            || self.save_conflict.is_some()
+            || self.tabs.prompting()
+            || self.oversized.is_some()
+            || self.text_focus
+    }
+
     pub fn poll(&mut self, display: &Display) {
+        let mut pending_op = None;
+        let mut scratch_changed = false;
         while let Some((output, stack)) = self.op_queue.poll() {
+            // an image pasted to the scratch list is shown like any other
+            let output = match output {
+                Output::ScratchPasted(image_data) => {
+                    self.scratch.push(image_data.clone());
+                    Output::ImageLoaded(image_data, None)
+                }
+                output => output,
+            };
+            scratch_changed |= self.scratch.position().is_some();
+
+            let resized = matches!(output, Output::Resize(_) | Output::BakeAspect(..));
+            let output = {
+                let mut target = self
+                    .image_view
+                    .as_deref_mut()
+                    .map(|view| GpuView::new(view, display));
+                apply_edit(output, target.as_mut(), stack)
+            };
+            let output = match output {
+                Some(output) => output,
+                None => {
+                    if resized {
+                        self.best_fit();
+                    }
+                    continue;
+                }
+            };
+
             match output {
+                Output::FullResolution(frames, full_size) => {
+                    if let Some(view) = self.image_view.as_mut() {
+                        view.set_full_resolution(frames, full_size, display);
+                    }
+                    pending_op = self
+                        .pending_op
+                        .take()
+                        .map(|(op, from)| match &self.image_view {
+                            Some(view) => op.rescaled(from, view.oriented_true_size()),
+                            None => op,
+                        });
+                }
                 Output::ImageLoaded(image_data, path) => {
+                    if let Some(path) = &path {
+                        log::info!("loaded {}", path.to_string_lossy());
+                        self.diagnostics.opened(path);
+                    }
+                    let frames_edited = stack.original().is_some();
                     stack.clear();
+                    self.pending_op = None;
+                    self.load_error = None;
+                    self.info_overlay.show_briefly();
+                    // saved scratch entries have a path but are still browsed as the list
+                    let scratch_path = self.scratch.show(&image_data);
+                    scratch_changed |= self.scratch.position().is_some();
                     self.current_filename = if let Some(path) = &path {
-                        self.op_queue.image_list.change_dir(&path);
-                        path.file_name().unwrap().to_str().unwrap().to_string()
+                        self.op_queue.image_list.change_dir(path);
+                        display_name(path)
+                    } else if let Some(path) = &scratch_path {
+                        display_name(path)
+                    } else if let Some(name) = image_data
+                        .read()
+                        .unwrap()
+                        .source
+                        .as_deref()
+                        .and_then(download::file_name)
+                    {
+                        name.to_string()
                     } else {
-                        String::new()
+                        String::from(untitled::UNTITLED)
                     };
 
-                    let view = Box::new(ImageView::new(display, image_data, path));
+                    let warnings = image_data.read().unwrap().warnings.join("\n");
+                    if !warnings.is_empty() {
+                        self.toasts
+                            .push(format!("Loaded with warnings:\n{}", warnings));
+                    }
+
+                    let mut locked = None;
+                    if let Some(view) = &self.image_view {
+                        self.playback.remember(view, frames_edited);
+                        if self.lock_view {
+                            locked = Some((view.scale, view.position));
+                        }
+                    }
+                    let mut view =
+                        Box::new(ImageView::new(display, image_data, path.or(scratch_path)));
+                    if let Some(playback) = self.playback.take(&view) {
+                        view.restore_playback(display, playback);
+                    } else if !self.settings.autoplay()
+                        && view.image_data.read().unwrap().frames.len() > 1
+                    {
+                        // paused animations schedule no wake-ups
+                        view.set_paused(display, true);
+                    }
+                    let size = view.true_size();
                     self.resize
-                        .set_size(Vec2::new(view.size.x() as u32, view.size.y() as u32));
+                        .set_size(Vec2::new(size.x() as u32, size.y() as u32));
+                    if let Some((scale, position)) = locked {
+                        view.scale = scale;
+                        view.position = position;
+                    }
                     self.image_view = Some(view);
 
-                    let window_context = display.gl_window();
-                    let window = window_context.window();
+                    display.gl_window().window().set_title(&self.window_title());
 
-                    if self.current_filename.is_empty() {
-                        window.set_title("Simp");
-                    } else {
-                        window.set_title(&self.current_filename.to_string());
+                    if locked.is_none() {
+                        self.initial_zoom();
                     }
-
-                    self.best_fit();
+                    self.slideshow.restart();
                 }
-                Output::FlipHorizontal => {
-                    self.image_view.as_mut().unwrap().flip_horizontal(display);
-                    stack.push(UndoFrame::FlipHorizontal);
-                }
-                Output::FlipVertical => {
-                    self.image_view.as_mut().unwrap().flip_vertical(display);
-                    stack.push(UndoFrame::FlipVertical);
-                }
-                Output::Rotate(dir) => {
-                    self.image_view.as_mut().unwrap().rotate(dir);
-                    stack.push(UndoFrame::Rotate(dir));
-                }
-                Output::Resize(mut frames) => {
+                Output::ColorAnalysis(key, analysis) => {
                     if let Some(ref mut view) = self.image_view {
-                        view.swap_frames(&mut frames, display);
-                        stack.push(UndoFrame::Resize(frames));
+                        view.color_analysis = Some((key, analysis));
                     }
-                    self.best_fit();
                 }
-                Output::Color(mut frames) => {
+                Output::ResizePreview(request, image) => {
                     if let Some(ref mut view) = self.image_view {
-                        view.swap_frames(&mut frames, display);
-                        stack.push(UndoFrame::Color(frames));
-                        view.hue = 0.0;
-                        view.contrast = 0.0;
-                        view.saturation = 0.0;
-                        view.lightness = 0.0;
-                    }
-                }
-                Output::Crop(mut frames, rotation) => {
-                    if let Some(ref mut view) = self.image_view {
-                        view.rotation = 0;
-                        view.swap_frames(&mut frames, display);
-                        stack.push(UndoFrame::Crop { frames, rotation })
-                    }
-                }
-                Output::Undo => {
-                    let frame = stack.undo();
-                    if let Some(frame) = frame {
-                        match frame {
-                            UndoFrame::Rotate(rot) => {
-                                self.image_view.as_mut().unwrap().rotate(-*rot);
-                            }
-                            UndoFrame::FlipHorizontal => {
-                                self.image_view.as_mut().unwrap().flip_horizontal(display);
-                            }
-                            UndoFrame::FlipVertical => {
-                                self.image_view.as_mut().unwrap().flip_vertical(display);
-                            }
-                            UndoFrame::Crop { frames, rotation } => {
-                                let view = self.image_view.as_mut().unwrap();
-                                view.swap_frames(frames, display);
-                                std::mem::swap(&mut view.rotation, rotation);
-                            }
-                            UndoFrame::Resize(frames) => {
-                                let view = self.image_view.as_mut().unwrap();
-                                view.swap_frames(frames, display);
-                            }
-                            UndoFrame::Color(frames) => {
-                                let view = self.image_view.as_mut().unwrap();
-                                view.swap_frames(frames, display);
-                            }
-                        }
-                    }
-                }
-                Output::Redo => {
-                    let frame = stack.redo();
-                    if let Some(frame) = frame {
-                        match frame {
-                            UndoFrame::Rotate(rot) => {
-                                self.image_view.as_mut().unwrap().rotate(*rot);
-                            }
-                            UndoFrame::FlipHorizontal => {
-                                self.image_view.as_mut().unwrap().flip_horizontal(display);
-                            }
-                            UndoFrame::FlipVertical => {
-                                self.image_view.as_mut().unwrap().flip_vertical(display);
-                            }
-                            UndoFrame::Crop { frames, rotation } => {
-                                let view = self.image_view.as_mut().unwrap();
-                                view.swap_frames(frames, display);
-                                std::mem::swap(&mut view.rotation, rotation);
-                            }
-                            UndoFrame::Resize(frames) => {
-                                let view = self.image_view.as_mut().unwrap();
-                                view.swap_frames(frames, display);
-                            }
-                            UndoFrame::Color(frames) => {
-                                let view = self.image_view.as_mut().unwrap();
-                                view.swap_frames(frames, display);
-                            }
-                        }
+                        view.add_resize_preview(display, request, image);
                     }
                 }
                 Output::Close => {
                     self.image_view = None;
                     stack.clear();
+                    self.current_filename.clear();
+                    display.gl_window().window().set_title(&self.window_title());
                     self.op_queue.image_list.clear();
-                    self.crop.cropping = false;
+                    self.crop.cancel();
+                    self.auto_scroll.stop();
                     self.op_queue.cache.clear();
                 }
+                Output::SiblingDir(image) => match image {
+                    Some(path) => pending_op = Some(Op::LoadPath(path, true)),
+                    None => self.toasts.push("There is no other folder with images"),
+                },
+                Output::ScratchPasted(_) => unreachable!("turned into ImageLoaded above"),
+                Output::Rotate(_)
+                | Output::FlipHorizontal
+                | Output::FlipVertical
+                | Output::Resize(_)
+                | Output::RotateArbitrary(_)
+                | Output::Color(_)
+                | Output::Levels(_)
+                | Output::Filter(_)
+                | Output::BakeAspect(..)
+                | Output::Frames(_)
+                | Output::Crop(..)
+                | Output::Undo
+                | Output::Redo => unreachable!("applied above"),
                 // indicates that the operation is done with no output
                 Output::Done => (),
             }
         }
+
+        // edits change the size of the entries so they are counted again after every output
+        if scratch_changed {
+            self.update_pinned();
+        }
+
+        if let Some(op) = pending_op {
+            self.queue(op);
+        }
+        self.open_pending_tab(display);
     }
 
     pub fn handle_user_event(&mut self, display: &Display, event: &mut UserEvent) {
         self.poll(display);
         match event {
             UserEvent::QueueLoad(path) => {
-                self.queue(Op::LoadPath(path.to_path_buf(), false));
+                self.open_path(path.to_path_buf(), false);
+            }
+            UserEvent::WipeLoaded(path, image) => {
+                self.wipe_loaded(display, path, image);
+            }
+            UserEvent::ImportFrames(paths) => self.pick_frames(std::mem::take(paths)),
+            UserEvent::OpenTabs(paths) => {
+                self.open_in_tabs(std::mem::take(paths));
+                self.open_pending_tab(display);
+            }
+            UserEvent::HandOff(paths) => {
+                log::info!("opening {} paths from another launch", paths.len());
+                if paths.len() > 1 {
+                    self.open_in_tabs(std::mem::take(paths));
+                    self.open_pending_tab(display);
+                } else if let Some(path) = paths.pop() {
+                    self.open_path(path, false);
+                }
+                display.gl_window().window().focus_window();
+            }
+            UserEvent::Deleted(path) => self.handle_deleted(path.to_path_buf()),
+            UserEvent::ExportReport(path) => self.export_report(path.to_path_buf()),
+            UserEvent::ExportContactSheet(path) => self.export_contact_sheet(path.to_path_buf()),
+            UserEvent::ExportDiagnostics(path) => {
+                self.export_diagnostics(display, path.to_path_buf())
             }
-            UserEvent::QueueSave(path) => {
-                self.queue(Op::Save(path.to_path_buf()));
+            UserEvent::SavePlaylist(path) => {
+                let path = path.to_path_buf();
+                self.save_playlist(&path);
+            }
+            UserEvent::QueueSave(path) => self.request_save(path.to_path_buf(), None),
+            UserEvent::TooLarge(path, size, can_downsample) => {
+                log::info!(
+                    "{} is too large at {} x {}",
+                    path.to_string_lossy(),
+                    size.0,
+                    size.1
+                );
+                self.busy.fail();
+                self.oversized = Some(OversizedPrompt {
+                    path: path.to_path_buf(),
+                    size: *size,
+                    can_downsample: *can_downsample,
+                });
+            }
+            UserEvent::LoadFailed(path, reason) => {
+                log::warn!("could not load {}: {}", path.to_string_lossy(), reason);
+                self.busy.fail();
+                if self.image_view.is_some() {
+                    let error = format!("Could not open {}: {}", path.to_string_lossy(), reason);
+                    thread::spawn(move || {
+                        msgbox::create("Error", &error, msgbox::IconType::Error).unwrap()
+                    });
+                }
+                // without an image the error stays on the empty screen instead
+                self.load_error = Some((path.to_path_buf(), reason.clone()));
             }
             UserEvent::ErrorMessage(error) => {
+                log::error!("{}", error);
+                self.busy.fail();
                 let error = error.clone();
                 thread::spawn(move || {
                     msgbox::create("Error", &error, msgbox::IconType::Error).unwrap()
                 });
             }
+            UserEvent::QueueExport(path) => {
+                if let Some(image_data) = self.pending_export.take() {
+                    self.request_save(path.to_path_buf(), Some(image_data));
+                }
+            }
+            UserEvent::ExportFrames(dir) => {
+                let stem = export_frames::frame_stem(&self.save_name());
+                self.queue(Op::ExportFrames(dir.to_path_buf(), stem));
+            }
+            UserEvent::Toast(text) => self.toasts.push(text.clone()),
+            UserEvent::Saved(path, image_data) => {
+                self.last_save_dir = path.parent().map(Path::to_path_buf);
+                // pasted images take the path they were saved to, the others stay as they are
+                let scratch = self.scratch.saved(image_data, path.to_path_buf());
+                if let Some(view) = self.image_view.as_mut() {
+                    if Arc::ptr_eq(&view.image_data, image_data) {
+                        self.op_queue.mark_saved();
+                    }
+                    if Arc::ptr_eq(&view.image_data, image_data) && (scratch || view.path.is_none())
+                    {
+                        view.path = Some(path.to_path_buf());
+                        self.current_filename = display_name(path);
+                        display.gl_window().window().set_title(&self.window_title());
+                    }
+                }
+            }
+            UserEvent::RevealToast(text, path) => {
+                self.toasts.push_reveal(text.clone(), path.to_path_buf())
+            }
+            UserEvent::DialogClosed => {
+                self.dialog_open = false;
+                self.pending_export = None;
+            }
             UserEvent::Exit => self.exit = true,
             UserEvent::Wake => (),
         };
     }
 
     pub fn handle_window_event(&mut self, display: &Display, event: &WindowEvent<'_>) {
+        log_window_event(event);
         match event {
             WindowEvent::Resized(size) => {
+                // minimizing reports a size of zero, keep the last real size for when it comes back
+                if size.width == 0 || size.height == 0 {
+                    self.minimized = true;
+                    return;
+                }
+
+                let was_fitted = self.minimized && self.fitted();
+                self.minimized = false;
                 *self.size.mut_x() = size.width as f32;
                 *self.size.mut_y() = size.height as f32;
+                if was_fitted {
+                    self.best_fit();
+                }
             }
-            WindowEvent::Moved(position) => {
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                // the scale stays image pixels per physical pixel, only a fitted image changes
+                let fitted = self.fitted();
+                let largest_fitted = !fitted && self.largest_fitted();
+                let old_size = self.size;
+                self.scale_factor = *scale_factor as f32;
+                self.size = Vec2::new(new_inner_size.width as f32, new_inner_size.height as f32);
+                if fitted {
+                    self.best_fit();
+                } else if largest_fitted {
+                    self.largest_fit();
+                } else if let Some(view) = self.image_view.as_mut() {
+                    // keep the image where it was relative to the window
+                    if old_size.x() > 0.0 && old_size.y() > 0.0 {
+                        view.position = Vec2::new(
+                            view.position.x() * self.size.x() / old_size.x(),
+                            view.position.y() * self.size.y() / old_size.y(),
+                        );
+                    }
+                }
+            }
+            // fullscreen windows report the monitor corner, the windowed place is kept
+            WindowEvent::Moved(position) if !self.fullscreen => {
                 *self.position.mut_x() = position.x;
-                *self.position.mut_x() = position.y;
+                *self.position.mut_y() = position.y;
             }
             WindowEvent::CursorMoved { position, .. } => {
                 self.mouse_position.set_x(position.x as f32);
                 self.mouse_position.set_y(position.y as f32);
             }
-            WindowEvent::MouseWheel { delta, .. } => {
-                if !self.metadata_visible {
-                    let scroll = match delta {
-                        MouseScrollDelta::LineDelta(_, y) => *y,
-                        MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
-                    };
+            WindowEvent::MouseWheel { delta, .. }
+                if !self.metadata_visible && !self.gallery.visible =>
+            {
+                let scroll = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
+                };
 
-                    if self.crop.inner.is_none() {
-                        self.zoom(scroll, self.mouse_position);
-                    }
+                if self.crop.inner.is_none() {
+                    self.zoom(scroll, self.zoom_anchor());
                 }
             }
             WindowEvent::ModifiersChanged(state) => self.modifiers = *state,
             WindowEvent::DroppedFile(path) => {
+                // with Ctrl held the file opens next to the current one
+                if self.modifiers.ctrl() && self.image_view.is_some() {
+                    self.new_tab(display);
+                }
                 self.op_queue.cache.clear();
-                self.queue(Op::LoadPath(path.to_path_buf(), true));
+                self.open_path(path.to_path_buf(), true);
+            }
+            // releasing is handled even when the keyboard is captured so the comparison never sticks
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(VirtualKeyCode::Backslash),
+                        state: ElementState::Released,
+                        ..
+                    },
+                ..
+            }
+            | WindowEvent::Focused(false) => {
+                if let Some(view) = self.image_view.as_mut() {
+                    view.set_comparing(display, None);
+                }
+                if matches!(event, WindowEvent::Focused(false)) {
+                    self.end_rotate_drag(true);
+                }
             }
-            WindowEvent::KeyboardInput { input, .. } if !self.resize.visible => {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(VirtualKeyCode::R),
+                        state: ElementState::Released,
+                        ..
+                    },
+                ..
+            } if self.rotate.dragging => self.end_rotate_drag(false),
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if self.shortcuts.capturing.is_some() => self.capture_shortcut(*key),
+            WindowEvent::KeyboardInput { input, .. } if !self.keyboard_captured() => {
                 if let Some(key) = input.virtual_keycode {
                     match input.state {
-                        ElementState::Pressed => match key {
-                            VirtualKeyCode::Delete => {
-                                if let Some(ref view) = self.image_view {
-                                    if let Some(ref path) = view.path {
-                                        delete(path, self.proxy.clone());
-                                    }
-                                }
-                            }
-
-                            VirtualKeyCode::H if self.modifiers.ctrl() => self.help_visible = true,
-                            VirtualKeyCode::O if self.modifiers.ctrl() => {
-                                load_image::open(self.proxy.clone(), display)
-                            }
-                            VirtualKeyCode::S if self.modifiers.ctrl() => save_image::open(
-                                self.current_filename.clone(),
-                                self.proxy.clone(),
-                                display,
-                            ),
-                            VirtualKeyCode::W if self.modifiers.ctrl() => self.exit = true,
-                            VirtualKeyCode::N if self.modifiers.ctrl() => new_window(),
-
-                            VirtualKeyCode::F => {
-                                self.largest_fit();
-                            }
-                            VirtualKeyCode::B => {
-                                self.best_fit();
-                            }
-
-                            VirtualKeyCode::Q => {
-                                if self.image_view.is_some() {
-                                    self.queue(Op::Rotate(-1))
-                                }
-                            }
-                            VirtualKeyCode::E => {
-                                if self.image_view.is_some() {
-                                    self.queue(Op::Rotate(1))
+                        ElementState::Pressed => {
+                            if key == VirtualKeyCode::Backslash {
+                                // a no-op until the pixels were edited
+                                let original = self.op_queue.original().map(<[_]>::to_vec);
+                                if let (Some(view), Some(original)) =
+                                    (self.image_view.as_mut(), original)
+                                {
+                                    view.set_comparing(display, Some(original));
                                 }
-                            }
-
-                            VirtualKeyCode::F5 => {
-                                if let Some(image) = self.image_view.as_ref() {
-                                    if let Some(path) = &image.path {
-                                        let buf = path.to_path_buf();
-                                        self.queue(Op::LoadPath(buf, false));
+                            } else if key == VirtualKeyCode::Escape
+                                && self.op_queue.cancel_download()
+                            {
+                                log::info!("canceling the download");
+                            } else if key == VirtualKeyCode::Escape && self.color_picker.picking {
+                                self.color_picker.picking = false;
+                            } else if key == VirtualKeyCode::Escape && self.picking_gray {
+                                self.picking_gray = false;
+                            } else if key == VirtualKeyCode::Escape && self.rotate.dragging {
+                                self.end_rotate_drag(true);
+                            } else if !self.crop_key(key) && !self.pan_key(key) {
+                                if key == VirtualKeyCode::R && self.modifiers.is_empty() {
+                                    self.begin_rotate_drag();
+                                } else if let Some(action) = self
+                                    .keymap
+                                    .action(key, self.modifiers)
+                                    // the play key only counts on a video, anywhere else it is left alone
+                                    .filter(|action| {
+                                        *action != Action::PlayVideo || self.showing_video()
+                                    })
+                                {
+                                    if action == Action::AutoScroll {
+                                        self.start_auto_scroll(Some(key));
+                                    } else {
+                                        self.dispatch(display, action);
                                     }
+                                } else if key == VirtualKeyCode::Escape && self.fullscreen {
+                                    self.exit_fullscreen(display);
                                 }
                             }
-
-                            VirtualKeyCode::C if self.modifiers.ctrl() => {
-                                if self.view_available() {
-                                    self.queue(Op::Copy);
-                                }
-                            }
-                            VirtualKeyCode::V if self.modifiers.ctrl() => {
-                                if !self.op_queue.working() {
-                                    self.queue(Op::Paste);
-                                }
-                            }
-                            VirtualKeyCode::X if self.modifiers.ctrl() => {
-                                self.crop.cropping = true;
-                            }
-
-                            VirtualKeyCode::Z if self.modifiers.ctrl() => {
-                                self.queue(Op::Undo);
-                            }
-                            VirtualKeyCode::Y if self.modifiers.ctrl() => {
-                                self.queue(Op::Redo);
-                            }
-
-                            VirtualKeyCode::R if self.modifiers.ctrl() => {
-                                self.resize.visible = true;
-                            }
-
-                            VirtualKeyCode::Left | VirtualKeyCode::D => {
-                                if self.crop.inner.is_none() && self.view_available() {
-                                    self.queue(Op::Prev);
-                                }
-                            }
-
-                            VirtualKeyCode::Right | VirtualKeyCode::A => {
-                                if self.crop.inner.is_none() && self.view_available() {
-                                    self.queue(Op::Next);
-                                }
-                            }
-                            VirtualKeyCode::F4 if self.modifiers.ctrl() => {
-                                self.queue(Op::Close);
-                            }
-
-                            VirtualKeyCode::F11 => {
-                                let window_context = display.gl_window();
-                                let window = window_context.window();
-                                let fullscreen = window.fullscreen();
-                                if fullscreen.is_some() {
-                                    window.set_fullscreen(None);
-                                    self.fullscreen = false;
-                                    self.top_bar_size = TOP_BAR_SIZE;
-                                    self.bottom_bar_size = BOTTOM_BAR_SIZE;
-                                } else {
-                                    window.set_fullscreen(Some(Fullscreen::Borderless(None)));
-                                    self.fullscreen = true;
-                                    self.top_bar_size = 0.0;
-                                    self.bottom_bar_size = 0.0;
-                                }
-                            }
-                            VirtualKeyCode::Escape => {
-                                let window_context = display.gl_window();
-                                let window = window_context.window();
-                                let fullscreen = window.fullscreen();
-                                if fullscreen.is_some() {
-                                    window.set_fullscreen(None);
-                                    self.fullscreen = false;
-                                }
-                            }
-                            _ => (),
-                        },
-                        ElementState::Released => (),
+                        }
+                        ElementState::Released => self.release_auto_scroll(key),
                     }
                 }
             }
-            WindowEvent::ReceivedCharacter(c) if !self.resize.visible => match c {
+            WindowEvent::ReceivedCharacter(c) if !self.keyboard_captured() => match c {
                 '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
                     if let Some(ref mut view) = self.image_view {
                         let zoom = c.to_digit(10).unwrap() as f32;
-                        view.scale = zoom;
-                    }
-                }
-                '+' => {
-                    if self.crop.inner.is_none() {
-                        self.zoom(1.0, self.size / 2.0);
-                    }
-                }
-                '-' => {
-                    if self.crop.inner.is_none() {
-                        self.zoom(-1.0, self.size / 2.0);
+                        view.scale = zoom.clamp(MIN_SCALE, MAX_SCALE);
                     }
                 }
+                '+' => self.dispatch(display, Action::ZoomIn),
+                '-' => self.dispatch(display, Action::ZoomOut),
                 _ => (),
             },
             _ => (),
@@ -405,41 +737,113 @@ impl App {
     }
 
     pub fn handle_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        i18n::set_language(self.settings.language);
         if self.op_queue.working() {
             ctx.output().cursor_icon = CursorIcon::Progress;
-        } else if self.crop.cropping {
+        } else if self.crop.cropping || self.color_picker.picking || self.picking_gray {
             ctx.output().cursor_icon = CursorIcon::Crosshair;
         }
         if !self.fullscreen {
             self.menu_bar(display, ctx);
-            self.bottom_bar(ctx);
+            self.tab_bar(display, ctx);
+            self.bottom_bar(display, ctx);
+            self.filmstrip_ui(ctx);
+        }
+        self.color_history_ui(ctx);
+        if self.gallery_ui(ctx) {
+            self.inspected = None;
+        } else {
+            self.main_area(display, ctx);
+            self.color_preview_ui(ctx);
         }
-        self.main_area(display, ctx);
-        self.resize_ui(ctx);
+        self.resize_ui(display, ctx);
+        self.crop_preset_ui(ctx);
+        self.crop_toolbar_ui(ctx);
+        self.report_ui(ctx);
+        self.contact_sheet_ui(display, ctx);
+        self.log_ui(ctx);
         self.help_ui(ctx);
         self.color_ui(ctx);
         self.metadata_ui(ctx);
+        self.frames_ui(display, ctx);
+        self.apply_theme(ctx);
+        self.apply_scale(ctx);
+        self.apply_motion(ctx);
+        self.preferences_ui(ctx);
+        self.shortcuts_ui(ctx);
+        self.diagnostics_ui(display, ctx);
+        self.save_options_ui(ctx);
+        self.save_check_ui(display, ctx);
+        self.save_conflict_ui(ctx);
+        self.close_prompt_ui(display, ctx);
+        self.oversized_ui(ctx);
+        self.import_frames_ui(ctx);
+        self.colors_ui(ctx);
+        self.levels_ui(ctx);
+        self.rotate_ui(ctx);
+        self.command_palette_ui(display, ctx);
+        self.toast_ui(ctx);
+        self.comparison_badge(ctx);
+        self.info_overlay_ui(ctx);
+        self.text_focus = ctx.wants_keyboard_input();
+    }
+
+    fn comparison_badge(&mut self, ctx: &egui::Context) {
+        if let Some(view) = self.image_view.as_ref() {
+            if view.comparing() {
+                egui::Area::new("comparison badge")
+                    .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0 + self.top_bar_size])
+                    .interactable(false)
+                    .show(ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.label(RichText::new("Original").strong());
+                        });
+                    });
+            }
+        }
     }
 
-    pub fn main_area(&mut self, _display: &Display, ctx: &egui::Context) {
-        let frame = egui::Frame::dark_canvas(&Style::default()).multiply_with_opacity(0.0);
+    pub fn main_area(&mut self, display: &Display, ctx: &egui::Context) {
+        let frame = egui::Frame::dark_canvas(&ctx.style()).multiply_with_opacity(0.0);
         egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
             if self.image_view.is_none() {
-                ui.centered_and_justified(|ui| {
-                    ui.label(
-                        RichText::new("Open File: Ctrl + O\n\nPaste: Ctrl + V\n\nHelp: Ctrl + H")
-                            .size(20.0),
-                    );
-                });
+                self.inspected = None;
+                self.empty_state(display, ui);
+                return;
             }
 
-            let res = ui.interact(egui::Rect::EVERYTHING, ui.id(), egui::Sense::drag());
+            self.grid_ui(ui);
+            self.wipe_ui(ui);
+            self.minimap_ui(ui);
+            self.straighten_ui(ui);
+            let res = ui.interact(
+                egui::Rect::EVERYTHING,
+                ui.id(),
+                egui::Sense::click_and_drag(),
+            );
+            self.inspected = match &self.image_view {
+                Some(view) if res.hovered() => view.inspect(self.mouse_position),
+                _ => None,
+            };
+            if self.color_picker.picking && res.clicked_by(egui::PointerButton::Primary) {
+                self.pick_color(self.mouse_position);
+                return;
+            }
+            if self.picking_gray && res.clicked_by(egui::PointerButton::Primary) {
+                self.pick_gray_point();
+                return;
+            }
 
             if let Some(ref mut image) = self.image_view {
                 if res.dragged_by(egui::PointerButton::Primary) {
                     let vec = res.drag_delta();
                     let delta = Vec2::from((vec.x, vec.y));
                     if self.crop.cropping {
+                        // the mouse takes over a selection made with the keys
+                        if res.drag_started() && self.crop.keyboard {
+                            self.crop.inner = None;
+                            self.crop.keyboard = false;
+                        }
                         if let Some(ref mut inner) = self.crop.inner {
                             inner.current += delta;
                         } else {
@@ -449,46 +853,126 @@ impl App {
                                 current: cursor_pos,
                             });
                         }
+                    } else if self.rotate.dragging {
+                        image.preview_angle += delta.x() * rotate::DRAG_DEGREES_PER_PIXEL;
+                    } else if self.settings.rubber_band {
+                        self.rubber_band.drag(image.position, delta);
                     } else {
                         image.position += delta;
                     }
-                } else if self.crop.cropping {
-                    if let Some(ref inner) = self.crop.inner {
-                        let mut size = inner.current - inner.start;
-                        *size.mut_x() = size.x().abs();
-                        *size.mut_y() = size.y().abs();
-
-                        let start = Vec2::new(
-                            min!(inner.start.x(), inner.current.x()),
-                            min!(inner.start.y(), inner.current.y()),
-                        );
-
-                        self.queue(Op::Crop(Rect::new(start, size)));
-                        self.crop.inner = None;
-                        self.crop.cropping = false;
-                    }
+                } else if self.crop.cropping && self.crop.inner.is_some() && !self.crop.keyboard {
+                    self.commit_crop();
+                } else {
+                    self.rubber_band.release();
                 }
             }
         });
     }
 
-    fn bottom_bar(&mut self, ctx: &egui::Context) {
+    fn bottom_bar(&mut self, display: &Display, ctx: &egui::Context) {
         TopBottomPanel::bottom("bottom").show(ctx, |ui| {
             ui.with_layout(egui::Layout::left_to_right(), |ui| {
+                let mut action = None;
                 if self.image_view.is_some() {
                     ui.add_enabled_ui(self.view_available() && !self.crop.cropping, |ui| {
-                        if ui.small_button("⬅").clicked() {
-                            self.queue(Op::Prev);
-                        }
-                        if ui.small_button("➡").clicked() {
-                            self.queue(Op::Next);
+                        // green when the neighbour is decoded and shows without waiting
+                        let browsing_folder = self.scratch.position().is_none();
+                        for (text, forward) in [("⬅", false), ("➡", true)] {
+                            let text = RichText::new(text);
+                            let text = if browsing_folder && self.op_queue.neighbour_cached(forward)
+                            {
+                                text.color(CACHED_COLOR)
+                            } else {
+                                text
+                            };
+                            if ui.add(Button::new(text).small()).clicked() {
+                                self.browse(display, forward);
+                            }
                         }
                     });
                 }
 
                 if let Some(image) = self.image_view.as_mut() {
-                    ui.label(format!("{} x {}", image.size.x(), image.size.y()));
+                    if let Some(badge) = video::badge(&image.image_data.read().unwrap()) {
+                        ui.label(badge)
+                            .on_hover_text("Press Enter to play it in the system video player");
+                    }
+                    let size = image.true_size();
+                    ui.label(format!("{} x {}", size.x(), size.y()));
+                    if image.aspect_corrected() {
+                        ui.label("Aspect corrected");
+                    }
+                    if image.channel_mode != ChannelMode::Normal {
+                        ui.label(RichText::new(image.channel_mode.name()).strong());
+                    }
                     ui.label(format!("Zoom: {}%", (image.scale * 100.0).round()));
+                    if self.lock_view {
+                        ui.label("🔒")
+                            .on_hover_text("The zoom and position are kept when browsing");
+                    }
+                    if image.high_dynamic_range() {
+                        ui.add(
+                            DragValue::new(&mut image.exposure)
+                                .speed(0.05)
+                                .clamp_range(-16.0..=16.0)
+                                .fixed_decimals(1)
+                                .prefix("Exposure: ")
+                                .suffix(" EV"),
+                        )
+                        .on_hover_text("Only changes how the image is shown");
+                        ui.checkbox(&mut image.tone_map, "Tone map")
+                            .on_hover_text("Roll off highlights instead of clipping them at white");
+                    }
+                    if let Some(rect) = self.crop.selection(image) {
+                        ui.label(format!("Crop: {} x {}", rect.size.x(), rect.size.y()));
+                    }
+                    if let Some((index, len)) = self.scratch.position() {
+                        ui.label(format!("clipboard {}/{}", index + 1, len));
+                    }
+                    if let Some(playback) = image.playback() {
+                        let toggle = if playback.finished {
+                            ("⟲", "Replay")
+                        } else if playback.paused {
+                            ("▶", "Play")
+                        } else {
+                            ("⏸", "Pause")
+                        };
+                        for (text, hover, clicked) in [
+                            ("⏮", "Previous frame", Action::PrevFrame),
+                            (toggle.0, toggle.1, Action::TogglePlayback),
+                            ("⏭", "Next frame", Action::NextFrame),
+                        ] {
+                            if ui.small_button(text).on_hover_text(hover).clicked() {
+                                action = Some(clicked);
+                            }
+                        }
+                        ui.label(format!("Frame {}/{}", playback.index + 1, playback.frames));
+                    }
+                    if let Some((uploaded, frames)) = image.upload_progress() {
+                        ui.label(
+                            RichText::new(format!("Preparing animation… {}/{}", uploaded, frames))
+                                .weak(),
+                        );
+                    }
+                }
+                if let Some(action) = action {
+                    self.dispatch(display, action);
+                }
+
+                // at the far end so the changing text does not shift the rest of the bar
+                if let Some(((x, y), [r, g, b, a])) = self.inspected {
+                    ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                        ui.label(RichText::new(format!("{} {} {} {}", r, g, b, a)).monospace());
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                        ui.painter().rect(
+                            rect,
+                            2.0,
+                            Color32::from_rgba_unmultiplied(r, g, b, a),
+                            ui.visuals().widgets.noninteractive.bg_stroke,
+                        );
+                        ui.label(RichText::new(format!("{}, {}", x, y)).monospace());
+                    });
                 }
             });
         });
@@ -500,50 +984,110 @@ impl App {
 
         if let Some(ref mut image) = self.image_view {
             update_delay(&mut self.delay, &image.animate(display));
+            // keep going without input until the whole animation is on the GPU
+            if image.upload_frames(display) {
+                update_delay(&mut self.delay, &Some(Duration::ZERO));
+            }
+
+            // once a NaN gets in it spreads through all the view math, so start over
+            if !image.scale.is_finite() || image.scale <= 0.0 {
+                image.scale = 1.0;
+            }
+            if !image.position.x().is_finite() || !image.position.y().is_finite() {
+                image.position = self.size / 2.0;
+            }
+        }
+
+        let scroll_update = self.step_auto_scroll();
+        update_delay(&mut self.delay, &scroll_update);
+        let slideshow_update = self.step_slideshow(display);
+        update_delay(&mut self.delay, &slideshow_update);
+        update_delay(&mut self.delay, &self.toasts.next_expiry());
+        let progress_update = self.update_progress(display);
+        update_delay(&mut self.delay, &progress_update);
+        if self.fullscreen {
+            update_delay(&mut self.delay, &self.info_overlay.next_update());
+        }
+
+        let top_bar = self.top_bar_pixels();
+        let bars = top_bar + self.bottom_bar_pixels();
+        self.op_queue.set_fit_size(Some((
+            self.size.x() as u32,
+            (self.size.y() - bars).max(1.0) as u32,
+        )));
+
+        let needs_full_resolution = self
+            .image_view
+            .as_ref()
+            .is_some_and(|view| view.needs_full_resolution());
+        if needs_full_resolution && !self.op_queue.working() {
+            self.request_full_resolution();
+        }
+        // edited pixels can not be rendered again
+        let vector_scale = self
+            .image_view
+            .as_ref()
+            .filter(|_| self.op_queue.original().is_none())
+            .and_then(|view| view.wanted_vector_scale());
+        if let (Some(scale), false) = (vector_scale, self.op_queue.working()) {
+            self.request_vector_scale(scale);
         }
 
+        let max_overshoot = if self.settings.rubber_band {
+            rubber_band::MAX_OVERSHOOT * self.pixels_per_point()
+        } else {
+            0.0
+        };
         if let Some(ref mut image) = self.image_view {
             let image_size = image.real_size();
             let mut window_size = self.size;
-            window_size.set_y(window_size.y() - self.top_bar_size - self.bottom_bar_size);
+            window_size.set_y(window_size.y() - bars);
 
+            let wanted = self.rubber_band.position(image.position);
+            let mut position = wanted;
             if image_size.x() < window_size.x() {
-                image.position.set_x(self.size.x() / 2.0);
+                position.set_x(self.size.x() / 2.0);
             } else {
-                if image.position.x() - image_size.x() / 2.0 > 0.0 {
-                    image.position.set_x(image_size.x() / 2.0);
+                if position.x() - image_size.x() / 2.0 > 0.0 {
+                    position.set_x(image_size.x() / 2.0);
                 }
 
-                if image.position.x() + image_size.x() / 2.0 < window_size.x() {
-                    image.position.set_x(window_size.x() - image_size.x() / 2.0);
+                if position.x() + image_size.x() / 2.0 < window_size.x() {
+                    position.set_x(window_size.x() - image_size.x() / 2.0);
                 }
             }
 
             if image_size.y() < window_size.y() {
-                image.position.set_y(self.size.y() / 2.0);
+                position.set_y(self.size.y() / 2.0);
             } else {
-                if image.position.y() - image_size.y() / 2.0 > self.top_bar_size {
-                    image
-                        .position
-                        .set_y(image_size.y() / 2.0 + self.top_bar_size);
+                if position.y() - image_size.y() / 2.0 > top_bar {
+                    position.set_y(image_size.y() / 2.0 + top_bar);
                 }
 
-                if image.position.y() + image_size.y() / 2.0 < window_size.y() + self.top_bar_size {
-                    image
-                        .position
-                        .set_y((window_size.y() - image_size.y() / 2.0) + self.top_bar_size);
+                if position.y() + image_size.y() / 2.0 < window_size.y() + top_bar {
+                    position.set_y((window_size.y() - image_size.y() / 2.0) + top_bar);
                 }
             }
+
+            // the overshoot is shown on top of the clamped position, never stored in it
+            let overshoot = self.rubber_band.overshoot(
+                wanted - position,
+                max_overshoot,
+                self.settings.rubber_band && self.settings.autoplay(),
+            );
+            image.position = position + overshoot;
+            update_delay(&mut self.delay, &self.rubber_band.next_frame());
         }
 
         (self.exit, self.delay)
     }
 
-    pub fn resize_ui(&mut self, ctx: &egui::Context) {
+    pub fn resize_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        let mut preview = None;
         if self.resize.visible {
             let mut open = self.image_view.is_some();
             let mut resized = false;
-            egui::Window::new("Resize")
+            egui::Window::new(tr("Resize"))
                 .id(egui::Id::new("resize window"))
                 .collapsible(false)
                 .resizable(false)
@@ -551,12 +1095,12 @@ impl App {
                 .show(ctx, |ui| {
                     egui::Grid::new("resize grid").show(ui, |ui| {
                         ui.with_layout(egui::Layout::right_to_left(), |ui| {
-                            ui.label("Width: ");
+                            ui.label(tr("Width:"));
                         });
                         let w_focus = ui.text_edit_singleline(&mut self.resize.width).has_focus();
                         ui.end_row();
                         ui.with_layout(egui::Layout::right_to_left(), |ui| {
-                            ui.label("Height: ");
+                            ui.label(tr("Height:"));
                         });
                         let h_focus = ui.text_edit_singleline(&mut self.resize.height).has_focus();
                         ui.end_row();
@@ -565,13 +1109,19 @@ impl App {
                         self.resize.width.retain(|c| c.is_numeric());
 
                         ui.with_layout(egui::Layout::right_to_left(), |ui| {
-                            ui.label("Maintain aspect ratio: ");
+                            ui.label(tr("Maintain aspect ratio:"));
                         });
                         ui.checkbox(&mut self.resize.maintain_aspect_ratio, "");
                         ui.end_row();
 
                         ui.with_layout(egui::Layout::right_to_left(), |ui| {
-                            ui.label("Resample: ");
+                            ui.label(tr("Preview:"));
+                        });
+                        ui.checkbox(&mut self.resize.preview, "");
+                        ui.end_row();
+
+                        ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                            ui.label(tr("Resample:"));
                         });
                         let selected = &mut self.resize.resample;
                         egui::ComboBox::new("filter", "")
@@ -609,27 +1159,37 @@ impl App {
                         let width = self.resize.width.parse::<u32>();
                         let height = self.resize.height.parse::<u32>();
 
-                        if self.resize.maintain_aspect_ratio && w_focus && width.is_ok() {
-                            let width = *width.as_ref().unwrap();
-                            let size = self.image_view.as_ref().unwrap().size;
+                        if let (true, true, Ok(width)) =
+                            (self.resize.maintain_aspect_ratio, w_focus, &width)
+                        {
+                            let width = *width;
+                            let size = self.image_view.as_ref().unwrap().true_size();
                             let ratio = width as f32 / size.x();
                             self.resize.height = ((ratio * size.y()) as u32).to_string();
                         }
 
-                        if self.resize.maintain_aspect_ratio && h_focus && height.is_ok() {
-                            let height = *height.as_ref().unwrap();
-                            let size = self.image_view.as_ref().unwrap().size;
+                        if let (true, true, Ok(height)) =
+                            (self.resize.maintain_aspect_ratio, h_focus, &height)
+                        {
+                            let height = *height;
+                            let size = self.image_view.as_ref().unwrap().true_size();
                             let ratio = height as f32 / size.y();
                             self.resize.width = ((ratio * size.x()) as u32).to_string();
                         }
 
+                        if let (true, Ok(width), Ok(height)) =
+                            (self.resize.preview, &width, &height)
+                        {
+                            preview = Some((Vec2::new(*width, *height), self.resize.resample));
+                        }
+
                         ui.with_layout(
                             egui::Layout::top_down_justified(egui::Align::Center),
                             |ui| {
                                 if ui
                                     .add_enabled(
                                         width.is_ok() && height.is_ok() && self.view_available(),
-                                        Button::new("Cancel"),
+                                        Button::new(tr("Cancel")),
                                     )
                                     .clicked()
                                 {
@@ -644,7 +1204,7 @@ impl App {
                                 if ui
                                     .add_enabled(
                                         width.is_ok() && height.is_ok() && self.view_available(),
-                                        Button::new("Resize"),
+                                        Button::new(tr("Resize")),
                                     )
                                     .clicked()
                                 {
@@ -662,17 +1222,99 @@ impl App {
                 });
             self.resize.visible = open && !resized;
         }
+
+        if !self.resize.visible {
+            preview = None;
+        }
+        if let Some(view) = self.image_view.as_mut() {
+            view.set_resize_preview(display, preview);
+        }
+        // the cpu fallback waits for whatever op is running
+        if !self.op_queue.working() {
+            let request = self
+                .image_view
+                .as_mut()
+                .and_then(|view| view.preview_request.take());
+            if let Some(request) = request {
+                self.queue(Op::ResizePreview(request));
+            }
+        }
     }
 
     pub fn queue(&mut self, op: Op) {
-        self.op_queue
-            .queue(op, self.image_view.as_ref().map(|v| v.as_ref()))
+        // while a scratch entry is shown the list is browsed instead of the folder
+        let op = match op {
+            Op::Next | Op::Prev if self.scratch.position().is_some() => {
+                match self.scratch.step(matches!(op, Op::Next)) {
+                    Some(image_data) => Op::Show(image_data),
+                    None => return,
+                }
+            }
+            op => op,
+        };
+
+        let downscaled = self.image_view.as_ref().filter(|v| v.downscaled());
+        // the op runs once the full image is loaded, while busy the queue refuses it instead
+        if let Some(view) =
+            downscaled.filter(|_| op.needs_full_resolution() && !self.op_queue.working())
+        {
+            self.pending_op = Some((op, view.oriented_true_size()));
+            self.request_full_resolution();
+            return;
+        }
+
+        let state = self.image_view.as_ref().map(|view| view.state());
+        self.op_queue.queue(op, state.as_ref())
+    }
+
+    /// Goes to the next or previous image. A neighbour that is cached already is swapped in
+    /// right away, the same way a finished load is, instead of on the next turn of the loop.
+    pub fn browse(&mut self, display: &Display, forward: bool) {
+        if self.crop.inner.is_some() || !self.view_available() {
+            return;
+        }
+        let cached = self.scratch.position().is_none() && self.op_queue.neighbour_cached(forward);
+        self.queue(if forward { Op::Next } else { Op::Prev });
+        if cached {
+            self.poll(display);
+        }
+    }
+
+    fn request_full_resolution(&mut self) {
+        if let Some(view) = self.image_view.as_mut() {
+            view.full_resolution_requested = true;
+        }
+        self.op_queue.queue(
+            Op::FullResolution,
+            self.image_view.as_ref().map(|view| view.state()).as_ref(),
+        );
+    }
+
+    fn request_vector_scale(&mut self, scale: f32) {
+        if let Some(view) = self.image_view.as_mut() {
+            view.vector_scale_requested = scale;
+        }
+        self.op_queue.queue(
+            Op::Rasterize(scale),
+            self.image_view.as_ref().map(|view| view.state()).as_ref(),
+        );
+    }
+
+    /// The point zooming with the wheel or the keys keeps in place.
+    fn zoom_anchor(&self) -> Vec2<f32> {
+        match self.settings.zoom_anchor {
+            ZoomAnchor::Cursor => self.mouse_position,
+            ZoomAnchor::Center => self.size / 2.0,
+        }
     }
 
     fn zoom(&mut self, zoom: f32, mouse_position: Vec2<f32>) {
         if let Some(ref mut image) = self.image_view {
             let old_scale = image.scale;
-            image.scale += image.scale * zoom as f32 / 10.0;
+            if old_scale <= 0.0 {
+                return;
+            }
+            image.scale = zoom_scale(old_scale, zoom, self.settings.zoom_step);
 
             let new_size = image.scaled();
             if (new_size.x() < 100.0 || new_size.y() < 100.0)
@@ -687,79 +1329,195 @@ impl App {
         }
     }
 
+    /// The scale that makes the image fill the space between the bars,
+    /// `None` when either the window or the image has no area.
+    fn fit_scale(&self) -> Option<f32> {
+        let view = self.image_view.as_ref()?;
+        let available = Vec2::new(
+            self.size.x(),
+            self.size.y() - self.top_bar_pixels() - self.bottom_bar_pixels(),
+        );
+        let image = view.display_size();
+        if available.x() <= 0.0 || available.y() <= 0.0 || image.x() <= 0.0 || image.y() <= 0.0 {
+            return None;
+        }
+        // a panorama fills the window with its short side so it can be panned along
+        Some(match self.panorama().filter(|_| self.settings.smart_fit) {
+            Some(Axis::Horizontal) => available.y() / image.y(),
+            Some(Axis::Vertical) => available.x() / image.x(),
+            None => min!(available.x() / image.x(), available.y() / image.y()),
+        })
+    }
+
+    /// Centers the image, or puts the start of a panorama at the start of the window.
+    fn fit_position(&self) -> Vec2<f32> {
+        let view = match self.image_view.as_ref() {
+            Some(view) => view,
+            None => return self.size / 2.0,
+        };
+        let size = view.real_size();
+        let top_bar = self.top_bar_pixels();
+        match self.panorama().filter(|_| self.settings.smart_fit) {
+            Some(Axis::Horizontal) => Vec2::new(size.x() / 2.0, self.size.y() / 2.0),
+            Some(Axis::Vertical) => Vec2::new(self.size.x() / 2.0, top_bar + size.y() / 2.0),
+            None => self.size / 2.0,
+        }
+    }
+
+    /// Physical pixels per egui point, the monitor scale with the interface scale on top.
+    pub fn pixels_per_point(&self) -> f32 {
+        self.scale_factor * self.settings.interface_scale / 100.0
+    }
+
+    /// The bar sizes are in points, the image is laid out in physical pixels.
+    fn top_bar_pixels(&self) -> f32 {
+        (self.top_bar_size + self.tab_bar_size()) * self.pixels_per_point()
+    }
+
+    /// Images held outside the cache, the pasted ones and those in other tabs,
+    /// count against its limit.
+    fn update_pinned(&self) {
+        let images = self.scratch.images().chain(self.tabs.images()).cloned();
+        self.op_queue.cache.set_pinned(images.collect());
+    }
+
+    fn bottom_bar_pixels(&self) -> f32 {
+        (self.bottom_bar_size + self.filmstrip_size()) * self.pixels_per_point()
+    }
+
+    /// Whether the image is shown at the largest fit scale, which is larger than the best
+    /// fit only for images smaller than the window.
+    fn largest_fitted(&self) -> bool {
+        match (self.fit_scale(), &self.image_view) {
+            (Some(scaling), Some(view)) => (view.scale - scaling).abs() < 0.0001,
+            _ => false,
+        }
+    }
+
+    /// Whether the image is shown at the best fit scale.
+    fn fitted(&self) -> bool {
+        match (self.fit_scale(), &self.image_view) {
+            (Some(scaling), Some(view)) => (view.scale - min!(scaling, 1.0)).abs() < 0.0001,
+            _ => false,
+        }
+    }
+
     pub fn best_fit(&mut self) {
-        if let Some(ref mut view) = self.image_view {
-            let scaling = min!(
-                self.size.x() / view.size.x(),
-                (self.size.y() - self.top_bar_size - self.bottom_bar_size) / view.size.y()
-            );
-            view.scale = min!(scaling, 1.0);
-            view.position = self.size / 2.0;
+        if let Some(scaling) = self.fit_scale() {
+            self.image_view.as_mut().unwrap().scale = min!(scaling, 1.0);
+            let position = self.fit_position();
+            self.image_view.as_mut().unwrap().position = position;
         }
     }
 
     pub fn largest_fit(&mut self) {
-        if let Some(ref mut view) = self.image_view {
-            let scaling = min!(
-                self.size.x() / view.size.x(),
-                (self.size.y() - self.top_bar_size - self.bottom_bar_size) / view.size.y()
-            );
-            view.scale = scaling;
-            view.position = self.size / 2.0;
+        if let Some(scaling) = self.fit_scale() {
+            self.image_view.as_mut().unwrap().scale = scaling;
+            let position = self.fit_position();
+            self.image_view.as_mut().unwrap().position = position;
         }
     }
 
     pub fn new(
-        proxy: EventLoopProxy<UserEvent>,
+        proxy: Proxy,
         size: [f32; 2],
         position: [i32; 2],
+        settings: Settings,
+        keymap: Keymap,
+        startup: Startup,
         display: &Display,
     ) -> Self {
-        App {
+        let mut app = App {
             exit: false,
             delay: None,
             image_view: None,
             size: Vec2::from(size),
             position: Vec2::from(position),
             fullscreen: false,
+            minimized: false,
+            windowed_geometry: None,
             top_bar_size: TOP_BAR_SIZE,
             bottom_bar_size: BOTTOM_BAR_SIZE,
-            op_queue: OpQueue::new(proxy.clone()),
+            op_queue: OpQueue::new(proxy.clone(), &settings),
             proxy,
             modifiers: ModifiersState::empty(),
             mouse_position: Vec2::default(),
+            scale_factor: display.gl_window().window().scale_factor() as f32,
             current_filename: String::new(),
+            last_save_dir: None,
             crop: Box::new(Crop::new(display)),
             resize: Resize::default(),
+            crop_preset: CropPreset::default(),
+            color_picker: ColorPicker::default(),
+            inspected: None,
+            colors: ColorsWindow::default(),
+            levels: LevelsWindow::default(),
+            playback: PlaybackPositions::default(),
+            report: Report::default(),
+            contact_sheet: ContactSheet::default(),
+            log_window: LogWindow::default(),
             help_visible: false,
             color_visible: false,
+            picking_gray: false,
             metadata_visible: false,
+            preferences: Preferences::default(),
+            diagnostics: Diagnostics::default(),
+            save_options: SaveOptions::default(),
+            save_check: None,
+            save_conflict: None,
+            settings,
+            command_palette: CommandPalette::default(),
+            dialog_open: false,
+            rotate: Rotate::default(),
+            export_supersample: false,
+            pending_export: None,
+            toasts: Toasts::default(),
+            info_overlay: InfoOverlay::default(),
+            busy: Busy::default(),
+            load_error: None,
+            oversized: None,
+            import_frames: None,
+            scratch: Scratch::default(),
+            auto_scroll: AutoScroll::default(),
+            tabs: Tabs::default(),
+            rubber_band: RubberBand::default(),
+            pending_op: None,
+            slideshow: Slideshow::default(),
+            filmstrip: Filmstrip::default(),
+            gallery: Gallery::default(),
+            frames: FramesPanel::default(),
+            minimap: Minimap::default(),
+            lock_view: false,
+            keymap,
+            shortcuts: ShortcutEditor::default(),
+            zoom: startup.zoom,
+            text_focus: false,
+        };
+        log::set_level(app.settings.log_level);
+        if startup.fullscreen {
+            app.toggle_fullscreen(display);
         }
-    }
-}
-
-pub fn delete<P: AsRef<Path>>(path: P, proxy: EventLoopProxy<UserEvent>) {
-    let path = path.as_ref().to_path_buf();
-    thread::spawn(move || {
-        let dialog = rfd::MessageDialog::new()
-            .set_level(rfd::MessageLevel::Warning)
-            .set_title("Move to trash")
-            .set_description("Are you sure you want to move this to trash?")
-            .set_buttons(rfd::MessageButtons::YesNo)
-            .show();
-
-        if dialog {
-            if let Err(error) = trash::delete(path) {
-                let _ = proxy.send_event(UserEvent::ErrorMessage(error.to_string()));
-            }
+        if startup.slideshow {
+            let interval = startup
+                .interval
+                .unwrap_or_else(|| app.settings.slideshow_interval());
+            app.slideshow.start(interval);
         }
-    });
+        app
+    }
 }
 
 fn new_window() {
     let _ = Command::new(std::env::current_exe().unwrap()).spawn();
 }
 
+/// The scale after zooming `zoom` steps of `step` percent from `scale`. A scale that is already
+/// outside the limits, like a best fit of a huge image, is kept but never pushed further out.
+fn zoom_scale(scale: f32, zoom: f32, step: f32) -> f32 {
+    let new_scale = scale + scale * zoom * step / 100.0;
+    new_scale.clamp(MIN_SCALE.min(scale), MAX_SCALE.max(scale))
+}
+
 fn update_delay(old: &mut Option<Duration>, new: &Option<Duration>) {
     if let Some(ref mut old_time) = old {
         if let Some(ref new_time) = new {
@@ -772,12 +1530,80 @@ fn update_delay(old: &mut Option<Duration>, new: &Option<Duration>) {
     }
 }
 
+/// Notable window events are logged at debug level, the frequent ones only at trace level.
+fn log_window_event(event: &WindowEvent<'_>) {
+    match event {
+        WindowEvent::Resized(size) => log::debug!("resized to {} x {}", size.width, size.height),
+        WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+            log::debug!("scale factor changed to {}", scale_factor)
+        }
+        WindowEvent::Focused(focused) => log::debug!("focused {}", focused),
+        WindowEvent::DroppedFile(path) => log::debug!("dropped {}", path.to_string_lossy()),
+        WindowEvent::Moved(position) => log::trace!("moved to {}, {}", position.x, position.y),
+        WindowEvent::KeyboardInput { input, .. } => {
+            log::trace!("key {:?} {:?}", input.virtual_keycode, input.state)
+        }
+        WindowEvent::MouseInput { button, state, .. } => {
+            log::trace!("mouse {:?} {:?}", button, state)
+        }
+        _ => (),
+    }
+}
+
 fn filter_name(filter: &FilterType) -> &'static str {
     match filter {
-        FilterType::Nearest => "Nearest Neighbor",
-        FilterType::Triangle => "Linear Filter",
-        FilterType::CatmullRom => "Cubic Filter",
-        FilterType::Gaussian => "Gaussian Filter",
-        FilterType::Lanczos3 => "Lanczos",
+        FilterType::Nearest => tr("Nearest Neighbor"),
+        FilterType::Triangle => tr("Linear Filter"),
+        FilterType::CatmullRom => tr("Cubic Filter"),
+        FilterType::Gaussian => tr("Gaussian Filter"),
+        FilterType::Lanczos3 => tr("Lanczos"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{zoom_scale, MAX_SCALE, MIN_SCALE};
+
+    const STEP: f32 = 10.0;
+
+    #[test]
+    fn zoom_in_stops_at_max_scale() {
+        let mut scale = 1.0;
+        for _ in 0..10_000 {
+            scale = zoom_scale(scale, 1.0, STEP);
+        }
+        assert_eq!(scale, MAX_SCALE);
+        assert_eq!(zoom_scale(scale, 1.0, STEP), MAX_SCALE);
+    }
+
+    #[test]
+    fn zoom_out_stops_at_min_scale() {
+        let mut scale = 1.0;
+        for _ in 0..10_000 {
+            scale = zoom_scale(scale, -1.0, STEP);
+        }
+        assert_eq!(scale, MIN_SCALE);
+        assert!(scale.is_normal());
+        assert_eq!(zoom_scale(scale, -1.0, STEP), MIN_SCALE);
+    }
+
+    #[test]
+    fn zoom_keeps_scale_outside_limits_without_going_further() {
+        let small = MIN_SCALE / 4.0;
+        assert_eq!(zoom_scale(small, -1.0, STEP), small);
+        assert!(zoom_scale(small, 1.0, STEP) > small);
+    }
+
+    #[test]
+    fn zoom_step_is_a_percentage() {
+        assert_eq!(zoom_scale(1.0, 1.0, 50.0), 1.5);
+        assert_eq!(zoom_scale(1.0, -1.0, 5.0), 0.95);
+        for step in [5.0, 25.0, 50.0] {
+            let mut scale = 1.0;
+            for _ in 0..10_000 {
+                scale = zoom_scale(scale, -1.0, step);
+            }
+            assert_eq!(scale, MIN_SCALE);
+        }
     }
 }
@@ -0,0 +1,81 @@
+use std::{
+    sync::{mpsc::Sender, Arc, RwLock},
+    thread,
+};
+
+use super::op_queue::Output;
+use crate::util::{ImageData, Proxy, UserEvent};
+
+/// Whether images can be acquired from a scanner or camera on this platform.
+pub const SUPPORTED: bool = cfg!(windows);
+
+/// Shows the system acquire dialog and opens the result as an unsaved image, like a paste.
+pub fn acquire(proxy: Proxy, sender: Sender<Output>) {
+    thread::spawn(move || {
+        match acquire_image() {
+            Ok(image_data) => {
+                let _ = sender.send(Output::ImageLoaded(Arc::new(RwLock::new(image_data)), None));
+            }
+            Err(error) => {
+                // canceling the dialog is not an error
+                if let Some(error) = error {
+                    let _ = proxy.send_event(UserEvent::ErrorMessage(error));
+                }
+                let _ = sender.send(Output::Done);
+            }
+        }
+        let _ = proxy.send_event(UserEvent::Wake);
+    });
+}
+
+/// A shim that runs the Windows Image Acquisition common dialog through a powershell script,
+/// which saves the scan to a temporary bitmap. WIA is not called over COM from here.
+/// Returns `Err(None)` if the dialog was canceled.
+#[cfg(windows)]
+fn acquire_image() -> Result<ImageData, Option<String>> {
+    use std::{env, fs, os::windows::process::CommandExt, process::Command};
+
+    use super::load_image::{load_uncached, LoadOptions};
+
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    const WIA_FORMAT_BMP: &str = "{B96B3CAB-0728-11D3-9D7B-0000F81EF32E}";
+
+    let path = env::temp_dir().join(format!("simp-acquire-{}.bmp", nanoid::nanoid!()));
+    let script = format!(
+        "$ErrorActionPreference = 'Stop'; \
+         $image = (New-Object -ComObject WIA.CommonDialog).ShowAcquireImage(0, 0, 65536, '{}'); \
+         if ($image -eq $null) {{ exit 2 }}; \
+         $image.SaveFile('{}')",
+        WIA_FORMAT_BMP,
+        path.to_string_lossy().replace('\'', "''")
+    );
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|error| Some(error.to_string()))?;
+
+    match output.status.code() {
+        Some(0) => (),
+        Some(2) => return Err(None),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Some(format!(
+                "Could not acquire an image: {}",
+                stderr.trim()
+            )));
+        }
+    }
+
+    let res = load_uncached(&path, LoadOptions::default()).map_err(|error| Some(error.to_string()));
+    let _ = fs::remove_file(&path);
+    res
+}
+
+#[cfg(not(windows))]
+fn acquire_image() -> Result<ImageData, Option<String>> {
+    Err(Some(String::from(
+        "Acquiring images is not supported on this platform",
+    )))
+}
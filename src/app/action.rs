@@ -0,0 +1,832 @@
+use std::{
+    ffi::{OsStr, OsString},
+    sync::{Arc, RwLock},
+};
+
+use glium::{
+    backend::glutin::Display,
+    glutin::event::{ModifiersState, VirtualKeyCode},
+};
+
+use super::{
+    acquire, associations,
+    color::Filter,
+    delete::DeleteMode,
+    export_frames::{self, frame_stem},
+    image_view::{oriented, ChannelMode},
+    import_frames, load_image, new_window,
+    op_queue::Op,
+    playlist, report, save_image, App,
+};
+use crate::{
+    util::{Image, ImageData, UserEvent},
+    vec2::Vec2,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Open,
+    ImportFrames,
+    SaveAs,
+    ExportView,
+    ExportFrame,
+    ExportFrames,
+    Reload,
+    Close,
+    NewWindow,
+    NewTab,
+    NextTab,
+    PrevTab,
+    CloseTab,
+    RegisterAssociations,
+    UnregisterAssociations,
+    Exit,
+    Undo,
+    Redo,
+    Copy,
+    CopyDataUri,
+    Paste,
+    PasteToScratch,
+    Acquire,
+    Resize,
+    Color,
+    Levels,
+    Metadata,
+    PickColor,
+    ColorHistory,
+    AnalyzeColors,
+    RotateLeft,
+    RotateRight,
+    FreeRotate,
+    Straighten,
+    FlipHorizontal,
+    FlipVertical,
+    Channel(ChannelMode),
+    Filter(Filter),
+    ZoomIn,
+    ZoomOut,
+    BestFit,
+    LargestFit,
+    AutoScroll,
+    Slideshow,
+    Filmstrip,
+    Gallery,
+    Wipe,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    Grid,
+    LockView,
+    PlayVideo,
+    ToggleAspectCorrection,
+    BakeAspect,
+    Crop,
+    CropToPreset,
+    Delete,
+    TrashNow,
+    DeletePermanently,
+    Prev,
+    Next,
+    PrevDir,
+    NextDir,
+    MoveEarlier,
+    MoveLater,
+    SavePlaylist,
+    FolderReport,
+    ContactSheet,
+    TogglePlayback,
+    PrevFrame,
+    NextFrame,
+    OnionSkin,
+    Frames,
+    Fullscreen,
+    InfoOverlay,
+    Preferences,
+    Shortcuts,
+    CommandPalette,
+    Help,
+    ExportDiagnostics,
+    ShowLog,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Open,
+        Action::ImportFrames,
+        Action::SaveAs,
+        Action::ExportView,
+        Action::ExportFrame,
+        Action::ExportFrames,
+        Action::Reload,
+        Action::Close,
+        Action::NewWindow,
+        Action::NewTab,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::CloseTab,
+        Action::RegisterAssociations,
+        Action::UnregisterAssociations,
+        Action::Exit,
+        Action::Undo,
+        Action::Redo,
+        Action::Copy,
+        Action::CopyDataUri,
+        Action::Paste,
+        Action::PasteToScratch,
+        Action::Acquire,
+        Action::Resize,
+        Action::Color,
+        Action::Levels,
+        Action::Metadata,
+        Action::PickColor,
+        Action::ColorHistory,
+        Action::AnalyzeColors,
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::FreeRotate,
+        Action::Straighten,
+        Action::FlipHorizontal,
+        Action::FlipVertical,
+        Action::Channel(ChannelMode::Normal),
+        Action::Channel(ChannelMode::Alpha),
+        Action::Channel(ChannelMode::Opaque),
+        Action::Channel(ChannelMode::Translucent),
+        Action::Channel(ChannelMode::Red),
+        Action::Channel(ChannelMode::Green),
+        Action::Channel(ChannelMode::Blue),
+        Action::Filter(Filter::Grayscale),
+        Action::Filter(Filter::Sepia),
+        Action::Filter(Filter::Invert),
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::BestFit,
+        Action::LargestFit,
+        Action::AutoScroll,
+        Action::Slideshow,
+        Action::Filmstrip,
+        Action::Gallery,
+        Action::Wipe,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::Grid,
+        Action::LockView,
+        Action::PlayVideo,
+        Action::ToggleAspectCorrection,
+        Action::BakeAspect,
+        Action::Crop,
+        Action::CropToPreset,
+        Action::Delete,
+        Action::TrashNow,
+        Action::DeletePermanently,
+        Action::Prev,
+        Action::Next,
+        Action::PrevDir,
+        Action::NextDir,
+        Action::MoveEarlier,
+        Action::MoveLater,
+        Action::SavePlaylist,
+        Action::FolderReport,
+        Action::ContactSheet,
+        Action::TogglePlayback,
+        Action::PrevFrame,
+        Action::NextFrame,
+        Action::OnionSkin,
+        Action::Frames,
+        Action::Fullscreen,
+        Action::InfoOverlay,
+        Action::Preferences,
+        Action::Shortcuts,
+        Action::CommandPalette,
+        Action::Help,
+        Action::ExportDiagnostics,
+        Action::ShowLog,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Open => "Open image",
+            Action::ImportFrames => "Import frames as animation",
+            Action::SaveAs => "Save as",
+            Action::ExportView => "Export view as image",
+            Action::ExportFrame => "Export current frame",
+            Action::ExportFrames => "Export all frames",
+            Action::Reload => "Reload image",
+            Action::Close => "Close image",
+            Action::NewWindow => "New window",
+            Action::NewTab => "New tab",
+            Action::NextTab => "Next tab",
+            Action::PrevTab => "Previous tab",
+            Action::CloseTab => "Close tab",
+            Action::RegisterAssociations => "Register file associations",
+            Action::UnregisterAssociations => "Remove file associations",
+            Action::Exit => "Exit",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::Copy => "Copy",
+            Action::CopyDataUri => "Copy as data URI",
+            Action::Paste => "Paste",
+            Action::PasteToScratch => "Paste into clipboard list",
+            Action::Acquire => "Acquire from scanner or camera",
+            Action::Resize => "Resize",
+            Action::Color => "Color",
+            Action::Levels => "Levels",
+            Action::Metadata => "Metadata",
+            Action::PickColor => "Pick color",
+            Action::ColorHistory => "Picked colors",
+            Action::AnalyzeColors => "Analyze colors",
+            Action::RotateLeft => "Rotate left",
+            Action::RotateRight => "Rotate right",
+            Action::FreeRotate => "Free rotate",
+            Action::Straighten => "Straighten along a line",
+            Action::FlipHorizontal => "Flip horizontal",
+            Action::FlipVertical => "Flip vertical",
+            Action::Filter(filter) => filter.name(),
+            Action::Channel(mode) => match mode {
+                ChannelMode::Normal => "Channels: normal",
+                ChannelMode::Alpha => "Channels: alpha as grayscale",
+                ChannelMode::Opaque => "Channels: ignore alpha",
+                ChannelMode::Translucent => "Channels: highlight translucent pixels",
+                ChannelMode::Red => "Channels: red",
+                ChannelMode::Green => "Channels: green",
+                ChannelMode::Blue => "Channels: blue",
+            },
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::BestFit => "Best fit",
+            Action::LargestFit => "Largest fit",
+            Action::AutoScroll => "Auto-scroll panorama",
+            Action::Slideshow => "Slideshow",
+            Action::Filmstrip => "Filmstrip",
+            Action::Gallery => "Gallery",
+            Action::Wipe => "Wipe compare with another image",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::PanUp => "Pan up",
+            Action::PanDown => "Pan down",
+            Action::Grid => "Grid overlay",
+            Action::LockView => "Keep zoom and position when browsing",
+            Action::PlayVideo => "Play video in the system player",
+            Action::ToggleAspectCorrection => "Correct pixel aspect ratio",
+            Action::BakeAspect => "Bake aspect correction",
+            Action::Crop => "Crop",
+            Action::CropToPreset => "Crop & resize to preset",
+            Action::Delete => "Delete image",
+            Action::TrashNow => "Move to trash without asking",
+            Action::DeletePermanently => "Delete permanently",
+            Action::Prev => "Previous image",
+            Action::Next => "Next image",
+            Action::PrevDir => "Previous folder",
+            Action::NextDir => "Next folder",
+            Action::MoveEarlier => "Move image earlier in list",
+            Action::MoveLater => "Move image later in list",
+            Action::SavePlaylist => "Save list as playlist",
+            Action::FolderReport => "Export folder report",
+            Action::ContactSheet => "Export contact sheet",
+            Action::TogglePlayback => "Pause or play animation",
+            Action::PrevFrame => "Previous frame",
+            Action::NextFrame => "Next frame",
+            Action::OnionSkin => "Onion skin",
+            Action::Frames => "Edit animation frames",
+            Action::Fullscreen => "Fullscreen",
+            Action::InfoOverlay => "Toggle fullscreen info overlay",
+            Action::Preferences => "Preferences",
+            Action::Shortcuts => "Keyboard shortcuts",
+            Action::CommandPalette => "Command palette",
+            Action::Help => "Help",
+            Action::ExportDiagnostics => "Export diagnostic bundle",
+            Action::ShowLog => "Show log",
+        }
+    }
+
+    /// Whether the action can be used on this platform.
+    pub fn supported(&self) -> bool {
+        match self {
+            Action::Acquire => acquire::SUPPORTED,
+            Action::RegisterAssociations | Action::UnregisterAssociations => {
+                associations::SUPPORTED
+            }
+            _ => true,
+        }
+    }
+
+    pub fn hotkey(&self) -> Option<&'static str> {
+        match self {
+            Action::Open => Some("Ctrl + O"),
+            Action::SaveAs => Some("Ctrl + S"),
+            Action::Reload => Some("F5"),
+            Action::Close => Some("Ctrl + F4"),
+            Action::NewWindow => Some("Ctrl + N"),
+            Action::NewTab => Some("Ctrl + T"),
+            Action::NextTab => Some("Ctrl + Tab"),
+            Action::PrevTab => Some("Ctrl + Shift + Tab"),
+            Action::Exit => Some("Ctrl + W"),
+            Action::Undo => Some("Ctrl + Z"),
+            Action::Redo => Some("Ctrl + Y"),
+            Action::Copy => Some("Ctrl + C"),
+            Action::Paste => Some("Ctrl + V"),
+            Action::PasteToScratch => Some("Ctrl + Shift + V"),
+            Action::Resize => Some("Ctrl + R"),
+            Action::RotateLeft => Some("Q"),
+            Action::RotateRight => Some("E"),
+            Action::ZoomIn => Some("+ or Mousewheel up"),
+            Action::ZoomOut => Some("- or Mousewheel down"),
+            Action::BestFit => Some("B"),
+            Action::LargestFit => Some("F"),
+            Action::AutoScroll => Some("P"),
+            Action::PlayVideo => Some("Enter"),
+            Action::Crop => Some("Ctrl + X"),
+            Action::Delete => Some("Delete"),
+            Action::TrashNow => Some("Shift + Delete"),
+            Action::Prev => Some("Left or D"),
+            Action::Next => Some("Right or A"),
+            Action::PrevDir => Some("Ctrl + Alt + Left"),
+            Action::NextDir => Some("Ctrl + Alt + Right"),
+            Action::Channel(mode) => match mode {
+                ChannelMode::Normal => None,
+                ChannelMode::Alpha => Some("Shift + A"),
+                ChannelMode::Opaque => Some("Shift + O"),
+                ChannelMode::Translucent => Some("Shift + T"),
+                ChannelMode::Red => Some("Shift + R"),
+                ChannelMode::Green => Some("Shift + G"),
+                ChannelMode::Blue => Some("Shift + B"),
+            },
+            Action::MoveEarlier => Some("Ctrl + Shift + Up"),
+            Action::MoveLater => Some("Ctrl + Shift + Down"),
+            Action::TogglePlayback => Some("Space"),
+            Action::PrevFrame => Some(","),
+            Action::NextFrame => Some("."),
+            Action::Fullscreen => Some("F11"),
+            Action::InfoOverlay => Some("I"),
+            Action::PickColor => Some("K"),
+            Action::CommandPalette => Some("Ctrl + Shift + P"),
+            Action::Preferences => Some("Ctrl + ,"),
+            Action::Help => Some("Ctrl + H"),
+            Action::Filmstrip => Some("Ctrl + F"),
+            Action::Gallery => Some("G"),
+            Action::PanLeft => Some("Shift + Left"),
+            Action::PanRight => Some("Shift + Right"),
+            Action::PanUp => Some("Shift + Up"),
+            Action::PanDown => Some("Shift + Down"),
+            Action::Grid => Some("Ctrl + G"),
+            Action::Levels => Some("Ctrl + L"),
+            Action::LockView => Some("L"),
+            Action::ExportView
+            | Action::CloseTab
+            | Action::ExportFrame
+            | Action::ExportFrames
+            | Action::ImportFrames
+            | Action::ExportDiagnostics
+            | Action::ShowLog
+            | Action::CopyDataUri
+            | Action::Acquire
+            | Action::RegisterAssociations
+            | Action::UnregisterAssociations
+            | Action::Color
+            | Action::Filter(_)
+            | Action::Metadata
+            | Action::FreeRotate
+            | Action::Straighten
+            | Action::FlipHorizontal
+            | Action::FlipVertical
+            | Action::ToggleAspectCorrection
+            | Action::BakeAspect
+            | Action::SavePlaylist
+            | Action::DeletePermanently
+            | Action::OnionSkin
+            | Action::Frames
+            | Action::Slideshow
+            | Action::Shortcuts
+            | Action::CropToPreset
+            | Action::FolderReport
+            | Action::ContactSheet
+            | Action::ColorHistory
+            | Action::AnalyzeColors
+            | Action::Wipe => None,
+        }
+    }
+
+    pub fn from_key(key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Action> {
+        let ctrl = modifiers.ctrl();
+        let shift = !ctrl && modifiers.shift();
+        let action = match key {
+            VirtualKeyCode::A if shift => Action::Channel(ChannelMode::Alpha),
+            VirtualKeyCode::O if shift => Action::Channel(ChannelMode::Opaque),
+            VirtualKeyCode::T if shift => Action::Channel(ChannelMode::Translucent),
+            VirtualKeyCode::R if shift => Action::Channel(ChannelMode::Red),
+            VirtualKeyCode::G if shift => Action::Channel(ChannelMode::Green),
+            VirtualKeyCode::B if shift => Action::Channel(ChannelMode::Blue),
+            VirtualKeyCode::Left if shift => Action::PanLeft,
+            VirtualKeyCode::Right if shift => Action::PanRight,
+            VirtualKeyCode::Up if shift => Action::PanUp,
+            VirtualKeyCode::Down if shift => Action::PanDown,
+
+            VirtualKeyCode::Delete if modifiers.shift() => Action::TrashNow,
+            VirtualKeyCode::Delete => Action::Delete,
+
+            VirtualKeyCode::P if ctrl && modifiers.shift() => Action::CommandPalette,
+            VirtualKeyCode::Up if ctrl && modifiers.shift() => Action::MoveEarlier,
+            VirtualKeyCode::Down if ctrl && modifiers.shift() => Action::MoveLater,
+            VirtualKeyCode::Left if ctrl && modifiers.alt() => Action::PrevDir,
+            VirtualKeyCode::Right if ctrl && modifiers.alt() => Action::NextDir,
+            VirtualKeyCode::H if ctrl => Action::Help,
+            VirtualKeyCode::F if ctrl => Action::Filmstrip,
+            VirtualKeyCode::G if ctrl => Action::Grid,
+            VirtualKeyCode::L if ctrl => Action::Levels,
+            VirtualKeyCode::O if ctrl => Action::Open,
+            VirtualKeyCode::S if ctrl => Action::SaveAs,
+            VirtualKeyCode::W if ctrl => Action::Exit,
+            VirtualKeyCode::N if ctrl => Action::NewWindow,
+            VirtualKeyCode::T if ctrl => Action::NewTab,
+            VirtualKeyCode::Tab if ctrl && modifiers.shift() => Action::PrevTab,
+            VirtualKeyCode::Tab if ctrl => Action::NextTab,
+
+            VirtualKeyCode::F => Action::LargestFit,
+            VirtualKeyCode::B => Action::BestFit,
+            VirtualKeyCode::P => Action::AutoScroll,
+            VirtualKeyCode::G => Action::Gallery,
+            VirtualKeyCode::L => Action::LockView,
+            VirtualKeyCode::Return => Action::PlayVideo,
+
+            VirtualKeyCode::Q => Action::RotateLeft,
+            VirtualKeyCode::E => Action::RotateRight,
+
+            VirtualKeyCode::F5 => Action::Reload,
+
+            VirtualKeyCode::C if ctrl => Action::Copy,
+            VirtualKeyCode::V if ctrl && modifiers.shift() => Action::PasteToScratch,
+            VirtualKeyCode::V if ctrl => Action::Paste,
+            VirtualKeyCode::X if ctrl => Action::Crop,
+
+            VirtualKeyCode::Z if ctrl => Action::Undo,
+            VirtualKeyCode::Y if ctrl => Action::Redo,
+
+            VirtualKeyCode::R if ctrl => Action::Resize,
+
+            VirtualKeyCode::Left | VirtualKeyCode::D => Action::Prev,
+            VirtualKeyCode::Right | VirtualKeyCode::A => Action::Next,
+            VirtualKeyCode::F4 if ctrl => Action::Close,
+
+            VirtualKeyCode::F11 => Action::Fullscreen,
+            VirtualKeyCode::Space => Action::TogglePlayback,
+            VirtualKeyCode::Comma if ctrl => Action::Preferences,
+            VirtualKeyCode::Comma => Action::PrevFrame,
+            VirtualKeyCode::Period => Action::NextFrame,
+            VirtualKeyCode::I => Action::InfoOverlay,
+            VirtualKeyCode::K => Action::PickColor,
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+impl App {
+    pub fn dispatch(&mut self, display: &Display, action: Action) {
+        if let Some(reason) = self.file_required(action) {
+            self.toasts.push(reason);
+            return;
+        }
+        match action {
+            Action::Open => {
+                if !self.dialog_open {
+                    self.dialog_open = true;
+                    load_image::open(self.proxy.clone(), display);
+                }
+            }
+            Action::ImportFrames => {
+                if !self.dialog_open {
+                    self.dialog_open = true;
+                    import_frames::open_dialog(self.proxy.clone(), display);
+                }
+            }
+            Action::SaveAs => {
+                if self.image_view.is_some() && !self.dialog_open {
+                    self.dialog_open = true;
+                    save_image::open(
+                        self.save_name(),
+                        None,
+                        self.save_directory(),
+                        self.proxy.clone(),
+                        display,
+                        UserEvent::QueueSave,
+                    );
+                }
+            }
+            Action::ExportView => {
+                if !self.dialog_open {
+                    if let Some(image_data) = self.render_view(display) {
+                        self.pending_export = Some(Arc::new(RwLock::new(image_data)));
+                        self.dialog_open = true;
+                        save_image::open(
+                            OsString::from("view.png"),
+                            None,
+                            self.save_directory(),
+                            self.proxy.clone(),
+                            display,
+                            UserEvent::QueueExport,
+                        );
+                    }
+                }
+            }
+            Action::ExportFrame => {
+                if self.view_available() && self.animated() && !self.dialog_open {
+                    let view = self.image_view.as_ref().unwrap();
+                    let guard = view.image_data.read().unwrap();
+                    let frame = &guard.frames[view.index];
+                    // shader only color adjustments are left out, rotation and flips are baked in
+                    let buffer = oriented(
+                        frame.buffer(),
+                        view.rotation,
+                        view.horizontal_flip,
+                        view.vertical_flip,
+                    )
+                    .into_owned();
+                    let name = frame_filename(&self.save_name(), view.index);
+                    drop(guard);
+
+                    self.pending_export =
+                        Some(Arc::new(RwLock::new(ImageData::from(vec![Image::new(
+                            buffer,
+                        )]))));
+                    self.dialog_open = true;
+                    let directory = self.save_directory();
+                    save_image::open(
+                        name,
+                        None,
+                        directory,
+                        self.proxy.clone(),
+                        display,
+                        UserEvent::QueueExport,
+                    );
+                }
+            }
+            Action::ExportFrames => {
+                if self.view_available() && self.animated() && !self.dialog_open {
+                    self.dialog_open = true;
+                    export_frames::folder_dialog(
+                        self.save_directory(),
+                        self.proxy.clone(),
+                        display,
+                    );
+                }
+            }
+            Action::Reload => {
+                if let Some(image) = self.image_view.as_ref() {
+                    if let Some(path) = &image.path {
+                        let buf = path.to_path_buf();
+                        self.queue(Op::LoadPath(buf, false));
+                    }
+                }
+            }
+            Action::Close => self.queue(Op::Close),
+            Action::NewWindow => new_window(),
+            Action::NewTab => self.new_tab(display),
+            Action::NextTab => self.cycle_tabs(display, true),
+            Action::PrevTab => self.cycle_tabs(display, false),
+            Action::CloseTab => self.close_tab(display, self.tabs.active()),
+            Action::RegisterAssociations => {
+                if associations::SUPPORTED {
+                    associations::register(self.proxy.clone());
+                }
+            }
+            Action::UnregisterAssociations => {
+                if associations::SUPPORTED {
+                    associations::unregister(self.proxy.clone());
+                }
+            }
+            Action::Exit => self.exit = self.confirm_close(),
+            Action::Undo => self.queue(Op::Undo),
+            Action::Redo => self.queue(Op::Redo),
+            Action::Copy => {
+                if self.view_available() {
+                    self.queue(Op::Copy(self.crop_selection()));
+                }
+            }
+            Action::CopyDataUri => {
+                if self.view_available() {
+                    self.queue(Op::CopyDataUri(self.crop_selection()));
+                }
+            }
+            Action::Paste => {
+                if !self.op_queue.working() {
+                    self.queue(Op::Paste);
+                }
+            }
+            Action::PasteToScratch => {
+                if !self.op_queue.working() {
+                    self.queue(Op::PasteToScratch);
+                }
+            }
+            Action::Acquire => {
+                if acquire::SUPPORTED && !self.op_queue.working() {
+                    self.queue(Op::Acquire);
+                }
+            }
+            Action::Resize => self.resize.visible = true,
+            Action::Color => {
+                if self.image_view.is_some() {
+                    self.color_visible = true;
+                }
+            }
+            Action::Levels => {
+                if self.image_view.is_some() {
+                    self.levels.visible = true;
+                }
+            }
+            Action::Metadata => {
+                if self.image_view.is_some() {
+                    self.metadata_visible = true;
+                }
+            }
+            Action::PickColor => {
+                if self.image_view.is_some() {
+                    self.color_picker.picking = !self.color_picker.picking;
+                    self.color_picker.visible = true;
+                }
+            }
+            Action::ColorHistory => self.color_picker.visible = !self.color_picker.visible,
+            Action::AnalyzeColors => self.analyze_colors(),
+            Action::Preferences => self.preferences.visible = true,
+            Action::Shortcuts => self.shortcuts.visible = true,
+            Action::RotateLeft => {
+                if self.image_view.is_some() {
+                    self.queue(Op::Rotate(-1));
+                }
+            }
+            Action::RotateRight => {
+                if self.image_view.is_some() {
+                    self.queue(Op::Rotate(1));
+                }
+            }
+            Action::FreeRotate => {
+                if self.view_available() {
+                    self.rotate.visible = true;
+                }
+            }
+            Action::Straighten => {
+                if self.view_available() && !self.crop.cropping {
+                    self.rotate.visible = true;
+                    self.rotate.straightening = true;
+                }
+            }
+            Action::FlipHorizontal => {
+                if self.image_view.is_some() {
+                    self.queue(Op::FlipHorizontal);
+                }
+            }
+            Action::FlipVertical => {
+                if self.image_view.is_some() {
+                    self.queue(Op::FlipVertical);
+                }
+            }
+            Action::Filter(filter) => {
+                if self.view_available() {
+                    self.queue(Op::Filter(filter));
+                }
+            }
+            Action::Channel(mode) => {
+                if let Some(view) = self.image_view.as_mut() {
+                    // pressing the shortcut of the active mode goes back to normal
+                    view.channel_mode = if view.channel_mode == mode {
+                        ChannelMode::Normal
+                    } else {
+                        mode
+                    };
+                }
+            }
+            Action::ZoomIn => {
+                if self.crop.inner.is_none() {
+                    self.zoom(1.0, self.zoom_anchor());
+                }
+            }
+            Action::ZoomOut => {
+                if self.crop.inner.is_none() {
+                    self.zoom(-1.0, self.zoom_anchor());
+                }
+            }
+            Action::BestFit => self.best_fit(),
+            Action::LargestFit => self.largest_fit(),
+            // from the menu or the palette it runs to the end, the key only scrolls while held
+            Action::AutoScroll => {
+                if self.auto_scroll.active() {
+                    self.auto_scroll.stop();
+                } else {
+                    self.start_auto_scroll(None);
+                }
+            }
+            Action::Slideshow => self.toggle_slideshow(),
+            Action::Filmstrip => self.toggle_filmstrip(),
+            Action::Gallery => self.toggle_gallery(),
+            Action::Wipe => self.toggle_wipe(display),
+            Action::PanLeft => self.pan(Vec2::new(-1.0, 0.0)),
+            Action::PanRight => self.pan(Vec2::new(1.0, 0.0)),
+            Action::PanUp => self.pan(Vec2::new(0.0, -1.0)),
+            Action::PanDown => self.pan(Vec2::new(0.0, 1.0)),
+            Action::Grid => self.settings.grid = !self.settings.grid,
+            Action::LockView => self.lock_view = !self.lock_view,
+            Action::PlayVideo => self.play_video(),
+            Action::ToggleAspectCorrection => {
+                if let Some(view) = self.image_view.as_mut() {
+                    if view.non_square_pixels() {
+                        view.toggle_aspect_correction(display);
+                        self.best_fit();
+                    }
+                }
+            }
+            Action::BakeAspect => {
+                if self.view_available() && self.image_view.as_ref().unwrap().non_square_pixels() {
+                    self.queue(Op::BakeAspect);
+                }
+            }
+            Action::Crop => {
+                self.crop.target = None;
+                self.crop.fixed = false;
+                self.start_crop();
+            }
+            Action::CropToPreset => {
+                if self.image_view.is_some() {
+                    self.crop_preset.visible = true;
+                }
+            }
+            Action::Delete => self.delete_current(DeleteMode::Trash),
+            Action::TrashNow => self.delete_current(DeleteMode::TrashNow),
+            Action::DeletePermanently => self.delete_current(DeleteMode::Permanent),
+            Action::Prev => self.browse(display, false),
+            Action::Next => self.browse(display, true),
+            Action::PrevDir => {
+                if self.crop.inner.is_none() && self.view_available() {
+                    self.queue(Op::PrevDir);
+                }
+            }
+            Action::NextDir => {
+                if self.crop.inner.is_none() && self.view_available() {
+                    self.queue(Op::NextDir);
+                }
+            }
+            Action::MoveEarlier => self.move_current(-1),
+            Action::MoveLater => self.move_current(1),
+            Action::SavePlaylist => {
+                if self.op_queue.image_list.position().is_some() && !self.dialog_open {
+                    self.dialog_open = true;
+                    playlist::save_dialog(self.proxy.clone(), display);
+                }
+            }
+            Action::FolderReport => {
+                if self.op_queue.image_list.position().is_some() && !self.dialog_open {
+                    self.dialog_open = true;
+                    report::save_dialog(self.proxy.clone(), display);
+                }
+            }
+            Action::ContactSheet => {
+                if self.op_queue.image_list.position().is_some() {
+                    self.contact_sheet.open = true;
+                }
+            }
+            Action::TogglePlayback => {
+                if self.animated() {
+                    let view = self.image_view.as_mut().unwrap();
+                    if view.finished() {
+                        view.replay(display);
+                    } else {
+                        let paused = !view.paused();
+                        view.set_paused(display, paused);
+                    }
+                }
+            }
+            Action::PrevFrame => {
+                if let Some(view) = self.image_view.as_mut() {
+                    view.step_frame(display, -1);
+                }
+            }
+            Action::NextFrame => {
+                if let Some(view) = self.image_view.as_mut() {
+                    view.step_frame(display, 1);
+                }
+            }
+            Action::Frames => {
+                self.frames.visible = !self.frames.visible && self.animated();
+            }
+            Action::OnionSkin => {
+                if let Some(view) = self.image_view.as_mut() {
+                    let onion_skin = !view.onion_skin();
+                    view.set_onion_skin(display, onion_skin);
+                }
+            }
+            Action::Fullscreen => self.toggle_fullscreen(display),
+            Action::InfoOverlay => self.info_overlay.toggle(),
+            Action::CommandPalette => self.command_palette.open(),
+            Action::Help => self.help_visible = true,
+            Action::ExportDiagnostics => self.diagnostics.visible = true,
+            Action::ShowLog => self.log_window.open = true,
+        }
+    }
+}
+
+/// Suggested name when exporting a single frame, frames are numbered from 1.
+fn frame_filename(filename: &OsStr, index: usize) -> OsString {
+    let mut name = frame_stem(filename);
+    name.push(format!("_frame{}.png", index + 1));
+    name
+}
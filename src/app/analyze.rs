@@ -0,0 +1,282 @@
+use std::{
+    sync::{mpsc::Sender, Arc, RwLock},
+    thread,
+};
+
+use egui::{Color32, RichText, Sense, Vec2 as EguiVec2};
+use image::DynamicImage;
+
+use super::{
+    color_picker::hex,
+    op_queue::{Op, Output},
+    App,
+};
+use crate::util::{ImageData, Proxy, UserEvent};
+
+/// Images are shrunk to at most this size before analyzing, the colors barely change.
+const SAMPLE_SIZE: u32 = 256;
+const PALETTE_SIZE: usize = 8;
+const SWATCH_SIZE: f32 = 24.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorAnalysis {
+    pub mean: [u8; 3],
+    /// Dominant colors with the share of pixels they stand for, largest first.
+    pub palette: Vec<([u8; 3], f32)>,
+    /// How many pixels have each value, of the brightness and then the red, green and blue.
+    pub histogram: Box<[[u32; 256]; 4]>,
+}
+
+/// The frame and edit generation an analysis belongs to.
+pub type AnalysisKey = (u64, usize);
+
+#[derive(Default)]
+pub struct ColorsWindow {
+    pub visible: bool,
+}
+
+pub fn analyze(
+    image_data: Arc<RwLock<ImageData>>,
+    key: AnalysisKey,
+    proxy: Proxy,
+    sender: Sender<Output>,
+) {
+    thread::spawn(move || {
+        let guard = image_data.read().unwrap();
+        let index = key.1.min(guard.frames.len() - 1);
+        let analysis = analyze_image(guard.frames[index].buffer());
+        let _ = sender.send(Output::ColorAnalysis(key, analysis));
+        let _ = proxy.send_event(UserEvent::Wake);
+    });
+}
+
+fn analyze_image(image: &DynamicImage) -> ColorAnalysis {
+    let sample = if image.width() > SAMPLE_SIZE || image.height() > SAMPLE_SIZE {
+        image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgba8()
+    } else {
+        image.to_rgba8()
+    };
+    let mut pixels: Vec<[u8; 3]> = sample
+        .pixels()
+        .filter(|p| p.0[3] >= 128)
+        .map(|p| [p.0[0], p.0[1], p.0[2]])
+        .collect();
+    // fully transparent images still get an answer
+    if pixels.is_empty() {
+        pixels = sample.pixels().map(|p| [p.0[0], p.0[1], p.0[2]]).collect();
+    }
+
+    ColorAnalysis {
+        mean: average(&pixels),
+        histogram: histogram(&pixels),
+        palette: median_cut(pixels, PALETTE_SIZE),
+    }
+}
+
+fn histogram(pixels: &[[u8; 3]]) -> Box<[[u32; 256]; 4]> {
+    let mut histogram = Box::new([[0; 256]; 4]);
+    for &[r, g, b] in pixels {
+        let luma = (r as u32 * 2126 + g as u32 * 7152 + b as u32 * 722) / 10000;
+        histogram[0][luma as usize] += 1;
+        histogram[1][r as usize] += 1;
+        histogram[2][g as usize] += 1;
+        histogram[3][b as usize] += 1;
+    }
+    histogram
+}
+
+fn average(pixels: &[[u8; 3]]) -> [u8; 3] {
+    if pixels.is_empty() {
+        return [0; 3];
+    }
+
+    let mut sum = [0u64; 3];
+    for pixel in pixels {
+        for channel in 0..3 {
+            sum[channel] += pixel[channel] as u64;
+        }
+    }
+    let len = pixels.len() as u64;
+    [
+        (sum[0] / len) as u8,
+        (sum[1] / len) as u8,
+        (sum[2] / len) as u8,
+    ]
+}
+
+/// The channel with the largest spread in `pixels` and how large it is.
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = pixels.iter().map(|p| p[channel]).min().unwrap_or(0);
+            let max = pixels.iter().map(|p| p[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
+
+/// Splits the colors into at most `count` boxes along their widest channel
+/// and returns the average of each box with its share of the pixels.
+fn median_cut(pixels: Vec<[u8; 3]>, count: usize) -> Vec<([u8; 3], f32)> {
+    let total = pixels.len();
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, pixels)| (index, widest_channel(pixels)))
+            .max_by_key(|(_, (_, range))| *range);
+        let (index, channel) = match widest {
+            Some((index, (channel, range))) if range > 0 => (index, channel),
+            _ => break,
+        };
+
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|p| p[channel]);
+        // split where the value changes so identical colors stay in one box
+        let middle = pixels[pixels.len() / 2][channel];
+        let split = match pixels.iter().position(|p| p[channel] >= middle) {
+            Some(0) => pixels.iter().position(|p| p[channel] > middle).unwrap(),
+            Some(split) => split,
+            None => unreachable!(),
+        };
+        let upper = pixels.split_off(split);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    let mut palette: Vec<_> = boxes
+        .iter()
+        .map(|pixels| (average(pixels), pixels.len() as f32 / total as f32))
+        .collect();
+    palette.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    palette
+}
+
+fn swatch(ui: &mut egui::Ui, color: [u8; 3]) -> bool {
+    let [r, g, b] = color;
+    let (rect, response) = ui.allocate_exact_size(EguiVec2::splat(SWATCH_SIZE), Sense::click());
+    ui.painter()
+        .rect_filled(rect, 2.0, Color32::from_rgb(r, g, b));
+    let label = ui.button(hex([r, g, b, 255]));
+    response.on_hover_text("Click to copy").clicked() || label.clicked()
+}
+
+impl App {
+    /// The analysis of what is currently shown, if it has been made. While an animation
+    /// plays the analysis of whichever frame was picked stays, so it is not redone every frame.
+    pub fn current_analysis(&self) -> Option<&ColorAnalysis> {
+        let view = self.image_view.as_ref()?;
+        let (generation, index) = view.analysis_key();
+        let playing = view.image_data.read().unwrap().frames.len() > 1 && !view.paused();
+        match &view.color_analysis {
+            Some(((g, i), analysis)) if *g == generation && (*i == index || playing) => {
+                Some(analysis)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn analyze_colors(&mut self) {
+        if self.image_view.is_none() {
+            return;
+        }
+        self.colors.visible = true;
+        if self.current_analysis().is_none() {
+            self.queue(Op::AnalyzeColors);
+        }
+    }
+
+    pub fn colors_ui(&mut self, ctx: &egui::Context) {
+        if !self.colors.visible {
+            return;
+        }
+
+        let mut open = self.image_view.is_some();
+        let mut copy = None;
+        let analysis = self.current_analysis().cloned();
+        egui::Window::new("Colors")
+            .id(egui::Id::new("colors window"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| match &analysis {
+                Some(analysis) => {
+                    ui.label(RichText::new("Average").strong());
+                    ui.horizontal(|ui| {
+                        if swatch(ui, analysis.mean) {
+                            copy = Some(analysis.mean);
+                        }
+                    });
+
+                    ui.separator();
+                    ui.label(RichText::new("Dominant colors").strong());
+                    egui::Grid::new("palette grid").show(ui, |ui| {
+                        for (color, share) in &analysis.palette {
+                            if swatch(ui, *color) {
+                                copy = Some(*color);
+                            }
+                            ui.label(format!("{:.1}%", share * 100.0));
+                            ui.end_row();
+                        }
+                    });
+                }
+                None => {
+                    ui.label("Analyzing…");
+                }
+            });
+
+        // the frame or the pixels changed while the window was open
+        if open && analysis.is_none() && !self.op_queue.working() {
+            self.queue(Op::AnalyzeColors);
+        }
+
+        if let Some([r, g, b]) = copy {
+            self.copy_color([r, g, b, 255]);
+        }
+        self.colors.visible = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    #[test]
+    fn two_colors_split_evenly() {
+        let mut image = RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 255]));
+        for x in 0..5 {
+            for y in 0..10 {
+                image.put_pixel(x, y, Rgba([0, 0, 255, 255]));
+            }
+        }
+
+        let analysis = analyze_image(&DynamicImage::ImageRgba8(image));
+        assert_eq!(analysis.mean, [127, 0, 127]);
+        assert_eq!(analysis.palette.len(), 2);
+        for (color, share) in &analysis.palette {
+            assert!(*color == [255, 0, 0] || *color == [0, 0, 255]);
+            assert!((share - 0.5).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_are_ignored() {
+        let mut image = RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 0]));
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+
+        let analysis = analyze_image(&DynamicImage::ImageRgba8(image));
+        assert_eq!(analysis.mean, [10, 20, 30]);
+        assert_eq!(analysis.palette, vec![([10, 20, 30], 1.0)]);
+        assert_eq!(analysis.histogram[1][10], 1);
+        assert_eq!(analysis.histogram[3].iter().sum::<u32>(), 1);
+        assert_eq!(analysis.histogram[0][18], 1);
+    }
+}
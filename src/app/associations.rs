@@ -0,0 +1,191 @@
+use std::thread;
+
+use crate::util::{Proxy, UserEvent};
+
+/// Whether file associations can be registered from the app on this platform.
+pub const SUPPORTED: bool = cfg!(target_os = "windows");
+
+/// Registers simp as a handler for every supported extension for the current user.
+pub fn register(proxy: Proxy) {
+    thread::spawn(move || {
+        let confirmed = rfd::MessageDialog::new()
+            .set_title("Register file associations")
+            .set_description(
+                "Add simp to the programs that can open the supported image types? \
+                 You can pick it as the default from the Open with menu afterwards.",
+            )
+            .set_buttons(rfd::MessageButtons::YesNo)
+            .show();
+        if !confirmed {
+            return;
+        }
+
+        let event = match platform::register() {
+            Ok(_) => UserEvent::Toast(String::from("Registered file associations")),
+            Err(error) => UserEvent::ErrorMessage(error),
+        };
+        let _ = proxy.send_event(event);
+    });
+}
+
+pub fn unregister(proxy: Proxy) {
+    thread::spawn(move || {
+        let event = match platform::unregister() {
+            Ok(_) => UserEvent::Toast(String::from("Removed file associations")),
+            Err(error) => UserEvent::ErrorMessage(error),
+        };
+        let _ = proxy.send_event(event);
+    });
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::{env, ffi::OsStr, io, os::windows::ffi::OsStrExt, path::PathBuf, ptr};
+
+    use winapi::{
+        shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS},
+        um::{
+            winnt::REG_SZ,
+            winreg::{RegDeleteKeyValueW, RegDeleteTreeW, RegSetKeyValueW, HKEY_CURRENT_USER},
+        },
+    };
+
+    use crate::util::extensions::EXTENSIONS;
+
+    const PROG_ID: &str = "simp.image";
+    const CLASSES: &str = r"Software\Classes";
+
+    const SHCNE_ASSOCCHANGED: i32 = 0x08000000;
+    const SHCNF_IDLIST: u32 = 0;
+
+    #[link(name = "shell32")]
+    extern "system" {
+        fn SHChangeNotify(event_id: i32, flags: u32, item1: *const u8, item2: *const u8);
+    }
+
+    fn wide(text: &str) -> Vec<u16> {
+        OsStr::new(text).encode_wide().chain(Some(0)).collect()
+    }
+
+    fn check(status: i32) -> io::Result<()> {
+        match status as u32 {
+            ERROR_SUCCESS => Ok(()),
+            _ => Err(io::Error::from_raw_os_error(status)),
+        }
+    }
+
+    /// Writes a string value, creating the key if it does not exist.
+    /// An empty `name` sets the default value of the key.
+    fn set_value(key: &str, name: &str, value: &str) -> io::Result<()> {
+        let key = wide(key);
+        let name = wide(name);
+        let value = wide(value);
+        unsafe {
+            check(RegSetKeyValueW(
+                HKEY_CURRENT_USER,
+                key.as_ptr(),
+                if name.len() > 1 {
+                    name.as_ptr()
+                } else {
+                    ptr::null()
+                },
+                REG_SZ,
+                value.as_ptr() as *const _,
+                (value.len() * 2) as u32,
+            ))
+        }
+    }
+
+    fn ignore_missing(res: io::Result<()>) -> io::Result<()> {
+        match res {
+            Err(error) if error.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => Ok(()),
+            res => res,
+        }
+    }
+
+    fn notify_changed() {
+        unsafe { SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, ptr::null(), ptr::null()) };
+    }
+
+    /// The path of the running executable, refusing locations that will not be around later.
+    fn executable() -> Result<PathBuf, String> {
+        let exe = env::current_exe()
+            .map_err(|error| format!("Could not find the path of simp: {}", error))?;
+        if exe.starts_with(env::temp_dir()) {
+            return Err(format!(
+                "Simp is running from a temporary directory:\n{}\n\n\
+                 Move it somewhere permanent before registering file associations, \
+                 otherwise they will stop working when the directory is cleaned up.",
+                exe.to_string_lossy()
+            ));
+        }
+        Ok(exe)
+    }
+
+    pub fn register() -> Result<(), String> {
+        let exe = executable()?;
+        let exe = exe.to_string_lossy();
+        let prog_id = format!(r"{}\{}", CLASSES, PROG_ID);
+
+        let res = (|| {
+            set_value(&prog_id, "", "Image")?;
+            set_value(
+                &format!(r"{}\DefaultIcon", prog_id),
+                "",
+                &format!("\"{}\",0", exe),
+            )?;
+            set_value(
+                &format!(r"{}\shell\open\command", prog_id),
+                "",
+                &format!("\"{}\" \"%1\"", exe),
+            )?;
+
+            let mut extensions: Vec<_> = EXTENSIONS.iter().collect();
+            extensions.sort();
+            for ext in extensions {
+                set_value(
+                    &format!(r"{}\.{}\OpenWithProgids", CLASSES, ext),
+                    PROG_ID,
+                    "",
+                )?;
+            }
+            Ok(())
+        })();
+
+        notify_changed();
+        res.map_err(|error: io::Error| format!("Could not register file associations: {}", error))
+    }
+
+    pub fn unregister() -> Result<(), String> {
+        let res = (|| {
+            for ext in EXTENSIONS.iter() {
+                let key = wide(&format!(r"{}\.{}\OpenWithProgids", CLASSES, ext));
+                let name = wide(PROG_ID);
+                ignore_missing(check(unsafe {
+                    RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), name.as_ptr())
+                }))?;
+            }
+
+            let key = wide(&format!(r"{}\{}", CLASSES, PROG_ID));
+            ignore_missing(check(unsafe {
+                RegDeleteTreeW(HKEY_CURRENT_USER, key.as_ptr())
+            }))
+        })();
+
+        notify_changed();
+        res.map_err(|error: io::Error| format!("Could not remove file associations: {}", error))
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    const UNSUPPORTED: &str = "File associations can only be registered on Windows";
+
+    pub fn register() -> Result<(), String> {
+        Err(String::from(UNSUPPORTED))
+    }
+
+    pub fn unregister() -> Result<(), String> {
+        Err(String::from(UNSUPPORTED))
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use egui::{Button, ProgressBar};
+
+use super::i18n::{tr, trf};
+use crate::util::{Proxy, UserEvent};
+
+/// Work on every image of the folder that runs in the background, like a report or a
+/// contact sheet.
+pub struct BatchJob {
+    done: Arc<AtomicUsize>,
+    total: usize,
+    cancel: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+}
+
+/// What the thread of a batch job uses to spread the work and to see if it was canceled.
+pub struct Batch {
+    done: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    proxy: Proxy,
+}
+
+impl Batch {
+    pub fn canceled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    /// Runs `work` on every path over all cores and returns the results in order.
+    /// Paths that were not reached before the job was canceled have no result.
+    pub fn map<T: Send>(
+        &self,
+        paths: &[PathBuf],
+        work: impl Fn(&Path) -> T + Sync,
+    ) -> Vec<Option<T>> {
+        let results = Mutex::new((0..paths.len()).map(|_| None).collect::<Vec<_>>());
+        let next = AtomicUsize::new(0);
+        let workers = thread::available_parallelism().map_or(4, |n| n.get());
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let (results, next, work) = (&results, &next, &work);
+                let (done, cancel) = (&self.done, &self.cancel);
+                let proxy = self.proxy.clone();
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= paths.len() || cancel.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let result = work(&paths[index]);
+                    results.lock().unwrap()[index] = Some(result);
+                    done.fetch_add(1, Ordering::SeqCst);
+                    let _ = proxy.send_event(UserEvent::Wake);
+                });
+            }
+        });
+        results.into_inner().unwrap()
+    }
+}
+
+impl BatchJob {
+    /// Runs `run` on its own thread over `total` images. The event it returns is sent
+    /// once it is done.
+    pub fn start(
+        total: usize,
+        proxy: Proxy,
+        run: impl FnOnce(&Batch) -> UserEvent + Send + 'static,
+    ) -> Self {
+        let job = Self {
+            done: Arc::new(AtomicUsize::new(0)),
+            total,
+            cancel: Arc::new(AtomicBool::new(false)),
+            finished: Arc::new(AtomicBool::new(false)),
+        };
+
+        let batch = Batch {
+            done: job.done.clone(),
+            cancel: job.cancel.clone(),
+            proxy: proxy.clone(),
+        };
+        let finished = job.finished.clone();
+        thread::spawn(move || {
+            let event = run(&batch);
+            finished.store(true, Ordering::SeqCst);
+            let _ = proxy.send_event(event);
+        });
+        job
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    /// Shows how many images are done, with a button to cancel the rest.
+    pub fn progress_ui(&self, ctx: &egui::Context, title: &str, id: &str) {
+        let done = self.done.load(Ordering::SeqCst);
+        egui::Window::new(title)
+            .id(egui::Id::new(id))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add(
+                    ProgressBar::new(done as f32 / self.total as f32)
+                        .text(trf("{} of {}", &[&done, &self.total])),
+                );
+                let canceling = self.cancel.load(Ordering::SeqCst);
+                if ui
+                    .add_enabled(!canceling, Button::new(tr("Cancel")))
+                    .clicked()
+                {
+                    self.cancel.store(true, Ordering::SeqCst);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_keep_the_order_of_the_paths() {
+        let (proxy, _events) = Proxy::channel();
+        let batch = Batch {
+            done: Arc::new(AtomicUsize::new(0)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            proxy,
+        };
+        let paths: Vec<_> = (0..50).map(|i| PathBuf::from(i.to_string())).collect();
+        let results = batch.map(&paths, |path| path.to_string_lossy().len());
+        let expected: Vec<_> = paths
+            .iter()
+            .map(|path| Some(path.as_os_str().len()))
+            .collect();
+        assert_eq!(results, expected);
+        assert_eq!(batch.done.load(Ordering::SeqCst), 50);
+
+        batch.cancel.store(true, Ordering::SeqCst);
+        assert!(batch.map(&paths, |_| ()).iter().all(Option::is_none));
+    }
+}
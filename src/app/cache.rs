@@ -1,67 +1,129 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc, Mutex, RwLock,
     },
 };
 
+use image::RgbaImage;
 use lru::LruCache;
 
 use crate::util::ImageData;
 
+/// How many thumbnails are kept, at most 50 MB of them. They count against the size limit.
+const THUMBNAILS: usize = 500;
+
+/// A small version of an image, `None` for a file that could not be decoded so it is not
+/// tried again.
+pub type Thumbnail = Option<Arc<RgbaImage>>;
+
 pub struct Cache {
     lru: Mutex<LruCache<PathBuf, Arc<RwLock<ImageData>>>>,
+    thumbnails: Mutex<LruCache<PathBuf, Thumbnail>>,
     total_size: AtomicUsize,
-    max_size: usize,
+    max_size: AtomicUsize,
+    /// Images kept outside the cache that still count against its limit. Some of them can
+    /// be in the cache too, those only count once.
+    pinned: Mutex<Vec<Arc<RwLock<ImageData>>>>,
+    thumbnail_size: AtomicUsize,
 }
 
 impl Cache {
     pub fn new(max_size: usize) -> Cache {
         Self {
             lru: Mutex::new(LruCache::new(100)),
+            thumbnails: Mutex::new(LruCache::new(THUMBNAILS)),
             total_size: AtomicUsize::new(0),
-            max_size,
+            max_size: AtomicUsize::new(max_size),
+            pinned: Mutex::new(Vec::new()),
+            thumbnail_size: AtomicUsize::new(0),
         }
     }
 
-    pub fn put(&self, path: PathBuf, image: Arc<RwLock<ImageData>>) {
-        let size: usize = image
+    fn image_size(image: &Arc<RwLock<ImageData>>) -> usize {
+        image
             .read()
             .unwrap()
             .frames
             .iter()
             .map(|image| image.buffer().as_bytes().len())
+            .sum()
+    }
+
+    fn thumbnail_bytes(thumbnail: &Thumbnail) -> usize {
+        thumbnail
+            .as_ref()
+            .map_or(0, |thumbnail| thumbnail.as_raw().len())
+    }
+
+    /// Bytes that count against the limit without being in the image cache.
+    fn outside(&self, lru: &LruCache<PathBuf, Arc<RwLock<ImageData>>>) -> usize {
+        let pinned: usize = self
+            .pinned
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|image| !lru.iter().any(|(_, cached)| Arc::ptr_eq(cached, image)))
+            .map(Self::image_size)
             .sum();
-        if size >= self.max_size {
-            return;
-        }
+        pinned + self.thumbnail_size.load(Ordering::SeqCst)
+    }
 
-        let mut guard = self.lru.lock().unwrap();
-        while size + self.total_size.load(Ordering::SeqCst) > self.max_size {
-            let removed = guard.pop_lru();
-            match removed {
+    /// Evicts the least recently used images until `size` more bytes fit. A pinned image
+    /// that is evicted is still held, so what is outside is counted again each time.
+    fn make_room(&self, lru: &mut LruCache<PathBuf, Arc<RwLock<ImageData>>>, size: usize) {
+        let max_size = self.max_size.load(Ordering::SeqCst);
+        while size + self.outside(lru) + self.total_size.load(Ordering::SeqCst) > max_size {
+            match lru.pop_lru() {
                 Some((_, value)) => {
-                    let removed: usize = value
-                        .read()
-                        .unwrap()
-                        .frames
-                        .iter()
-                        .map(|image| image.buffer().as_bytes().len())
-                        .sum();
-                    self.total_size.fetch_sub(removed, Ordering::SeqCst);
+                    self.total_size
+                        .fetch_sub(Self::image_size(&value), Ordering::SeqCst);
                 }
                 None => break,
             }
         }
+    }
+
+    /// Changes the limit, evicting images right away if the cache shrinks.
+    pub fn set_max_size(&self, max_size: usize) {
+        self.max_size.store(max_size, Ordering::SeqCst);
+        let mut guard = self.lru.lock().unwrap();
+        self.make_room(&mut guard, 0);
+    }
+
+    /// Sets the images held outside the cache, evicting to keep the total in the limit.
+    pub fn set_pinned(&self, images: Vec<Arc<RwLock<ImageData>>>) {
+        *self.pinned.lock().unwrap() = images;
+        let mut guard = self.lru.lock().unwrap();
+        self.make_room(&mut guard, 0);
+    }
+
+    pub fn put(&self, path: PathBuf, image: Arc<RwLock<ImageData>>) {
+        let size = Self::image_size(&image);
+        let mut guard = self.lru.lock().unwrap();
+        let available = self
+            .max_size
+            .load(Ordering::SeqCst)
+            .saturating_sub(self.outside(&guard));
+        if size >= available {
+            return;
+        }
+
+        self.make_room(&mut guard, size);
 
         self.total_size.fetch_add(size, Ordering::SeqCst);
         guard.put(path, image);
     }
 
+    /// Forgets the image and its thumbnail, for files that changed on disk.
     #[allow(clippy::ptr_arg)]
     pub fn pop(&self, path: &PathBuf) {
         self.lru.lock().unwrap().pop(path);
+        if let Some(thumbnail) = self.thumbnails.lock().unwrap().pop(path) {
+            self.thumbnail_size
+                .fetch_sub(Self::thumbnail_bytes(&thumbnail), Ordering::SeqCst);
+        }
     }
 
     #[allow(clippy::ptr_arg)]
@@ -77,5 +139,68 @@ impl Cache {
     pub fn clear(&self) {
         self.total_size.store(0, Ordering::SeqCst);
         self.lru.lock().unwrap().clear();
+        self.thumbnails.lock().unwrap().clear();
+        self.thumbnail_size.store(0, Ordering::SeqCst);
+    }
+
+    /// Keeps a thumbnail, evicting images if it does not fit in the limit with them.
+    pub fn put_thumbnail(&self, path: PathBuf, thumbnail: Thumbnail) {
+        let size = Self::thumbnail_bytes(&thumbnail);
+        let mut freed = 0;
+        {
+            let mut thumbnails = self.thumbnails.lock().unwrap();
+            if thumbnails.len() == thumbnails.cap() && !thumbnails.contains(&path) {
+                if let Some((_, evicted)) = thumbnails.pop_lru() {
+                    freed += Self::thumbnail_bytes(&evicted);
+                }
+            }
+            if let Some(replaced) = thumbnails.put(path, thumbnail) {
+                freed += Self::thumbnail_bytes(&replaced);
+            }
+        }
+        self.thumbnail_size.fetch_add(size, Ordering::SeqCst);
+        self.thumbnail_size.fetch_sub(freed, Ordering::SeqCst);
+
+        let mut guard = self.lru.lock().unwrap();
+        self.make_room(&mut guard, 0);
+    }
+
+    /// The thumbnail of `path`, `None` if none was made yet.
+    pub fn thumbnail(&self, path: &Path) -> Option<Thumbnail> {
+        self.thumbnails
+            .lock()
+            .unwrap()
+            .get(&path.to_path_buf())
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Image;
+
+    fn image(width: u32) -> Arc<RwLock<ImageData>> {
+        let frame = Image::from(RgbaImage::new(width, 1));
+        Arc::new(RwLock::new(ImageData::from(vec![frame])))
+    }
+
+    #[test]
+    fn pinned_images_in_the_cache_count_once() {
+        let cache = Cache::new(100);
+        let (tab, other) = (image(10), image(10));
+        cache.put(PathBuf::from("tab.png"), tab.clone());
+        cache.set_pinned(vec![tab.clone()]);
+        // 40 bytes in the cache and pinned, 40 more still fit
+        cache.put(PathBuf::from("other.png"), other);
+        assert!(cache.contains(&PathBuf::from("tab.png")));
+        assert!(cache.contains(&PathBuf::from("other.png")));
+
+        // evicting the pinned image frees nothing, so the other one has to go
+        cache.set_max_size(60);
+        assert!(!cache.contains(&PathBuf::from("other.png")));
+        cache.set_pinned(Vec::new());
+        cache.put(PathBuf::from("other.png"), image(10));
+        assert!(cache.contains(&PathBuf::from("other.png")));
     }
 }
@@ -1,62 +1,71 @@
 use std::{
     borrow::Cow,
-    sync::{mpsc::Sender, Arc, RwLock},
+    io::Cursor,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc, RwLock,
+    },
     thread,
 };
 
-use glium::glutin::event_loop::EventLoopProxy;
 use image::{
-    imageops::{flip_horizontal_in_place, flip_vertical_in_place},
-    EncodableLayout, GenericImageView, ImageBuffer, Rgba,
+    DynamicImage, EncodableLayout, GenericImageView, ImageBuffer, ImageOutputFormat, Rgba,
 };
 
-use super::{image_view::ImageView, op_queue::Output};
-use crate::util::{Image, ImageData, UserEvent};
+use super::{
+    download,
+    image_view::{oriented, pixel_bounds, ViewState},
+    load_image::LoadOptions,
+    op_queue::{Output, Progress},
+};
+use crate::{
+    rect::Rect,
+    util::{log, Image, ImageData, Proxy, UserEvent},
+    vec2::Vec2,
+};
+
+/// Puts `text` on the clipboard, false when that is not possible.
+pub fn copy_text(text: String) -> bool {
+    match arboard::Clipboard::new() {
+        Ok(mut clipboard) => clipboard.set_text(text).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// The first frame as it is shown, cut down to `selection` if there is one.
+fn shown<'a>(
+    frame: &'a DynamicImage,
+    rotation: i32,
+    horizontal_flip: bool,
+    vertical_flip: bool,
+    selection: Option<Rect>,
+) -> Cow<'a, DynamicImage> {
+    let buffer = oriented(frame, rotation, horizontal_flip, vertical_flip);
+    let (width, height) = buffer.dimensions();
+    match selection.and_then(|cut| pixel_bounds(cut, Vec2::new(width, height))) {
+        Some([x, y, width, height]) => Cow::Owned(buffer.crop_imm(x, y, width, height)),
+        None => buffer,
+    }
+}
 
-pub fn copy(view: &ImageView, proxy: EventLoopProxy<UserEvent>, sender: Sender<Output>) {
+pub fn copy(view: &ViewState, selection: Option<Rect>, proxy: Proxy, sender: Sender<Output>) {
     let image_data = view.image_data.clone();
     let rotation = view.rotation;
     let horizontal_flip = view.horizontal_flip;
     let vertical_flip = view.vertical_flip;
+    let selection = selection.map(|cut| view.frame_rect(cut));
 
     thread::spawn(move || {
         let guard = image_data.read().unwrap();
         let frame = guard.frames.first().unwrap();
-        let buffer = match rotation {
-            0 => Cow::Borrowed(frame.buffer()),
-            1 => Cow::Owned(frame.buffer().rotate270()),
-            2 => Cow::Owned(frame.buffer().rotate180()),
-            3 => Cow::Owned(frame.buffer().rotate90()),
-            _ => unreachable!("image is rotated more then 360 degrees"),
-        };
-
-        let buffer = match buffer {
-            Cow::Owned(mut buffer) => {
-                if horizontal_flip {
-                    flip_horizontal_in_place(&mut buffer);
-                }
-
-                if vertical_flip {
-                    flip_vertical_in_place(&mut buffer);
-                }
-                Cow::Owned(buffer)
-            }
-            Cow::Borrowed(buffer) => {
-                if !horizontal_flip && !vertical_flip {
-                    Cow::Borrowed(buffer)
-                } else {
-                    let mut buffer = buffer.clone();
-                    if horizontal_flip {
-                        flip_horizontal_in_place(&mut buffer);
-                    }
-
-                    if vertical_flip {
-                        flip_vertical_in_place(&mut buffer);
-                    }
-                    Cow::Owned(buffer)
-                }
-            }
-        };
+        let buffer = shown(
+            frame.buffer(),
+            rotation,
+            horizontal_flip,
+            vertical_flip,
+            selection,
+        );
 
         let (width, height) = buffer.dimensions();
         let buffer = buffer.to_rgba8();
@@ -66,8 +75,9 @@ pub fn copy(view: &ImageView, proxy: EventLoopProxy<UserEvent>, sender: Sender<O
             bytes: Cow::Borrowed(buffer.as_bytes()),
         };
 
-        if let Ok(mut clipboard) = arboard::Clipboard::new() {
-            let _ = clipboard.set_image(image_data);
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_image(image_data)) {
+            Ok(_) => log::info!("copied {} x {} image", width, height),
+            Err(error) => log::warn!("copying failed: {}", error),
         }
 
         let _ = sender.send(Output::Done);
@@ -75,23 +85,112 @@ pub fn copy(view: &ImageView, proxy: EventLoopProxy<UserEvent>, sender: Sender<O
     });
 }
 
-pub fn paste(proxy: EventLoopProxy<UserEvent>, sender: Sender<Output>) {
+/// Data URIs larger than this are hard to paste anywhere so the user gets a warning.
+const DATA_URI_WARNING_SIZE: usize = 2 * 1024 * 1024;
+
+pub fn copy_data_uri(
+    view: &ViewState,
+    selection: Option<Rect>,
+    proxy: Proxy,
+    sender: Sender<Output>,
+) {
+    let image_data = view.image_data.clone();
+    let rotation = view.rotation;
+    let horizontal_flip = view.horizontal_flip;
+    let vertical_flip = view.vertical_flip;
+    let selection = selection.map(|cut| view.frame_rect(cut));
+
     thread::spawn(move || {
-        if let Ok(mut clipboard) = arboard::Clipboard::new() {
-            if let Ok(image_data) = clipboard.get_image() {
+        let guard = image_data.read().unwrap();
+        let frame = guard.frames.first().unwrap();
+        let buffer = shown(
+            frame.buffer(),
+            rotation,
+            horizontal_flip,
+            vertical_flip,
+            selection,
+        );
+
+        let mut png = Vec::new();
+        let res = buffer.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png);
+        drop(guard);
+
+        let message = match res {
+            Ok(_) => {
+                let uri = format!("data:image/png;base64,{}", base64::encode(&png));
+                let len = uri.len();
+                match arboard::Clipboard::new().and_then(|mut c| c.set_text(uri)) {
+                    Ok(_) if len > DATA_URI_WARNING_SIZE => format!(
+                        "Copied data URI, but it is {:.1} MB large",
+                        len as f64 / (1024.0 * 1024.0)
+                    ),
+                    Ok(_) => String::from("Copied data URI"),
+                    Err(error) => format!("Could not copy data URI: {}", error),
+                }
+            }
+            Err(error) => format!("Could not encode png: {}", error),
+        };
+        log::info!("{}", message);
+
+        let _ = sender.send(Output::Done);
+        let _ = proxy.send_event(UserEvent::Toast(message));
+    });
+}
+
+/// Opens the image on the clipboard, or adds it to the scratch list when `scratch` is set.
+/// A web address on the clipboard is downloaded, decoded with `options`.
+pub fn paste(
+    proxy: Proxy,
+    sender: Sender<Output>,
+    progress: Progress,
+    options: LoadOptions,
+    cancel: Arc<AtomicBool>,
+    scratch: bool,
+) {
+    thread::spawn(move || {
+        let show = |image_data: ImageData| {
+            let image_data = Arc::new(RwLock::new(image_data));
+            let _ = sender.send(if scratch {
+                Output::ScratchPasted(image_data)
+            } else {
+                Output::ImageLoaded(image_data, None)
+            });
+            let _ = proxy.send_event(UserEvent::Wake);
+        };
+        let pasted = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_image());
+        match pasted {
+            Ok(image_data) => {
                 let width = image_data.width;
                 let height = image_data.height;
+                log::info!("pasted {} x {} image", width, height);
                 let mut data = Vec::with_capacity(image_data.bytes.len());
-                data.extend_from_slice(&*image_data.bytes);
+                data.extend_from_slice(&image_data.bytes);
                 let image = ImageBuffer::<Rgba<u8>, _>::from_raw(width as u32, height as u32, data)
                     .unwrap();
-                let _ = sender.send(Output::ImageLoaded(
-                    Arc::new(RwLock::new(ImageData::from(vec![Image::from(image)]))),
-                    None,
-                ));
-                let _ = proxy.send_event(UserEvent::Wake);
+                show(ImageData::from(vec![Image::from(image)]));
                 return;
             }
+            Err(error) => log::debug!("no image pasted: {}", error),
+        }
+
+        let text = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text());
+        if let Some(url) = text.ok().as_deref().and_then(download::url) {
+            match download::download(url, options, &progress, &cancel) {
+                Ok(image_data) => {
+                    show(image_data);
+                    return;
+                }
+                Err(_) if cancel.load(Ordering::SeqCst) => {
+                    let _ =
+                        proxy.send_event(UserEvent::Toast(String::from("Canceled the download")));
+                }
+                Err(error) => {
+                    let _ = proxy.send_event(UserEvent::ErrorMessage(format!(
+                        "Could not open {}: {}",
+                        url, error
+                    )));
+                }
+            }
         }
         // if it fails we must still notify the main thread that we are not doing work
         let _ = sender.send(Output::Done);
@@ -1,7 +1,244 @@
 use egui::{Button, Slider};
+use image::{DynamicImage, ImageBuffer, Pixel};
 
 use super::{op_queue::Op, App};
 
+/// The gamma the shader and these adjustments treat stored values as encoded with.
+const ENCODING_GAMMA: f32 = 2.2;
+
+/// Exposure, brightness and gamma, applied in that order before the hue and contrast. The
+/// shader previews them with the same formulas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    /// Added to every channel, in percent of white.
+    pub brightness: f32,
+    /// Values are raised to one over it, above one brightens the shadows.
+    pub gamma: f32,
+    /// Stops the light is scaled by, like a longer or shorter exposure.
+    pub exposure: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            gamma: 1.0,
+            exposure: 0.0,
+        }
+    }
+}
+
+impl Tone {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Adjusts a channel stored as it is shown, from zero to one, without clamping.
+    fn apply(&self, value: f32) -> f32 {
+        // exposure scales linear light, which is the stored value to the power of the gamma
+        let value = value * (self.exposure / ENCODING_GAMMA).exp2();
+        let value = value + self.brightness / 100.0;
+        value.max(0.0).powf(1.0 / self.gamma)
+    }
+
+    /// `buffer` with every color channel adjusted, alpha is left alone.
+    pub fn adjust(&self, buffer: &DynamicImage) -> DynamicImage {
+        if self.is_identity() {
+            return buffer.clone();
+        }
+        map_colors(buffer, |_, value| self.apply(value))
+    }
+}
+
+/// Gains on red, green and blue that warm or cool the light and shift it between green and
+/// magenta, applied first. Gray images are left gray.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WhiteBalance {
+    /// Above zero is warmer, red is raised and blue lowered by up to half a stop each.
+    pub temperature: f32,
+    /// Above zero is more magenta, green is lowered by up to half a stop.
+    pub tint: f32,
+}
+
+impl WhiteBalance {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// What linear red, green and blue are multiplied by.
+    pub fn gains(&self) -> [f32; 3] {
+        [
+            (self.temperature / 200.0).exp2(),
+            (-self.tint / 200.0).exp2(),
+            (-self.temperature / 200.0).exp2(),
+        ]
+    }
+
+    /// The white balance that turns `color` gray, if it has some of every channel.
+    pub fn neutral(color: [u8; 3]) -> Option<Self> {
+        if color.contains(&0) {
+            return None;
+        }
+        // stops of linear light
+        let [r, g, b] = color.map(|value| ENCODING_GAMMA * (value as f32 / 255.0).log2());
+        Some(Self {
+            temperature: (100.0 * (b - r)).clamp(-100.0, 100.0),
+            tint: (200.0 * (g - (r + b) / 2.0)).clamp(-100.0, 100.0),
+        })
+    }
+
+    /// `buffer` with the gains applied, alpha is left alone.
+    pub fn adjust(&self, buffer: &DynamicImage) -> DynamicImage {
+        if self.is_identity() {
+            return buffer.clone();
+        }
+        let gains = self.gains().map(|gain| gain.powf(1.0 / ENCODING_GAMMA));
+        map_colors(buffer, |channel, value| match channel {
+            Some(channel) => value * gains[channel],
+            None => value,
+        })
+    }
+}
+
+/// Edits that turn every pixel into another color in one click.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Filter {
+    Grayscale,
+    Sepia,
+    Invert,
+}
+
+/// What red, green and blue are mixed into for sepia, by row.
+const SEPIA: [[f32; 3]; 3] = [
+    [0.393, 0.769, 0.189],
+    [0.349, 0.686, 0.168],
+    [0.272, 0.534, 0.131],
+];
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::Grayscale, Filter::Sepia, Filter::Invert];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::Grayscale => "Grayscale",
+            Filter::Sepia => "Sepia",
+            Filter::Invert => "Invert colors",
+        }
+    }
+
+    /// `buffer` filtered, alpha is left alone. Grayscale images keep one channel and sepia
+    /// images get three, the bit depth stays.
+    pub fn apply(self, buffer: &DynamicImage) -> DynamicImage {
+        fn sepia<P: Pixel>(
+            mut buffer: ImageBuffer<P, Vec<P::Subpixel>>,
+            from: impl Fn(f32) -> P::Subpixel,
+        ) -> ImageBuffer<P, Vec<P::Subpixel>>
+        where
+            P::Subpixel: Into<f32>,
+        {
+            for pixel in buffer.pixels_mut() {
+                let channels = pixel.channels_mut();
+                let color: [f32; 3] = [channels[0].into(), channels[1].into(), channels[2].into()];
+                for (channel, row) in channels.iter_mut().zip(SEPIA) {
+                    *channel = from(row.iter().zip(color).map(|(a, b)| a * b).sum());
+                }
+            }
+            buffer
+        }
+
+        match self {
+            Filter::Grayscale => buffer.grayscale(),
+            Filter::Invert => {
+                let mut buffer = buffer.clone();
+                buffer.invert();
+                buffer
+            }
+            Filter::Sepia => {
+                let color = buffer.color();
+                let depth = color.bytes_per_pixel() / color.channel_count();
+                let small = |value: f32| value.round() as u8;
+                let large = |value: f32| value.round() as u16;
+                match (depth, color.has_alpha()) {
+                    (1, false) => DynamicImage::ImageRgb8(sepia(buffer.to_rgb8(), small)),
+                    (1, true) => DynamicImage::ImageRgba8(sepia(buffer.to_rgba8(), small)),
+                    (2, false) => DynamicImage::ImageRgb16(sepia(buffer.to_rgb16(), large)),
+                    (2, true) => DynamicImage::ImageRgba16(sepia(buffer.to_rgba16(), large)),
+                    (_, false) => DynamicImage::ImageRgb32F(sepia(buffer.to_rgb32f(), |v| v)),
+                    (_, true) => DynamicImage::ImageRgba32F(sepia(buffer.to_rgba32f(), |v| v)),
+                }
+            }
+        }
+    }
+}
+
+/// `buffer` with every color channel run through `curve`, alpha is left alone. The curve gets
+/// the red, green or blue channel, `None` for gray, and the value as the shader shows it from
+/// zero to one. Integer images are clamped and looked up in a table of every value.
+pub fn map_colors(
+    buffer: &DynamicImage,
+    curve: impl Fn(Option<usize>, f32) -> f32,
+) -> DynamicImage {
+    fn map<P: Pixel>(
+        mut buffer: ImageBuffer<P, Vec<P::Subpixel>>,
+        f: impl Fn(usize, P::Subpixel) -> P::Subpixel,
+    ) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let colors = P::COLOR_MODEL.trim_end_matches('A').len();
+        for pixel in buffer.pixels_mut() {
+            for (channel, value) in pixel.channels_mut()[..colors].iter_mut().enumerate() {
+                *value = f(channel, *value);
+            }
+        }
+        buffer
+    }
+
+    let curve = &curve;
+    let channel = |gray: bool, channel: usize| (!gray).then_some(channel);
+    let table = |gray: bool, max: u32| -> Vec<Vec<f32>> {
+        (0..if gray { 1 } else { 3 })
+            .map(|index| {
+                (0..=max)
+                    .map(|value| {
+                        let value = curve(channel(gray, index), value as f32 / max as f32);
+                        (value.clamp(0.0, 1.0) * max as f32).round()
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+    let small = |gray| {
+        let table = table(gray, u8::MAX as u32);
+        move |index: usize, value: u8| table[index][value as usize] as u8
+    };
+    let large = |gray| {
+        let table = table(gray, u16::MAX as u32);
+        move |index: usize, value: u16| table[index][value as usize] as u16
+    };
+    // floating point images are linear, they are adjusted as the shader shows them
+    let float = |gray| {
+        move |index: usize, value: f32| {
+            curve(
+                channel(gray, index),
+                value.max(0.0).powf(1.0 / ENCODING_GAMMA),
+            )
+            .max(0.0)
+            .powf(ENCODING_GAMMA)
+        }
+    };
+    match buffer.clone() {
+        DynamicImage::ImageLuma8(buffer) => DynamicImage::ImageLuma8(map(buffer, small(true))),
+        DynamicImage::ImageLumaA8(buffer) => DynamicImage::ImageLumaA8(map(buffer, small(true))),
+        DynamicImage::ImageRgb8(buffer) => DynamicImage::ImageRgb8(map(buffer, small(false))),
+        DynamicImage::ImageRgba8(buffer) => DynamicImage::ImageRgba8(map(buffer, small(false))),
+        DynamicImage::ImageLuma16(buffer) => DynamicImage::ImageLuma16(map(buffer, large(true))),
+        DynamicImage::ImageLumaA16(buffer) => DynamicImage::ImageLumaA16(map(buffer, large(true))),
+        DynamicImage::ImageRgb16(buffer) => DynamicImage::ImageRgb16(map(buffer, large(false))),
+        DynamicImage::ImageRgba16(buffer) => DynamicImage::ImageRgba16(map(buffer, large(false))),
+        DynamicImage::ImageRgb32F(buffer) => DynamicImage::ImageRgb32F(map(buffer, float(false))),
+        DynamicImage::ImageRgba32F(buffer) => DynamicImage::ImageRgba32F(map(buffer, float(false))),
+        other => DynamicImage::ImageRgba8(map(other.into_rgba8(), small(false))),
+    }
+}
+
 impl App {
     pub fn color_ui(&mut self, ctx: &egui::Context) {
         if self.color_visible && self.image_view.is_some() {
@@ -46,6 +283,63 @@ impl App {
                             -100.0..=100.0,
                         ));
                         ui.end_row();
+                        ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                            ui.label("Brightness: ");
+                        });
+                        ui.add(Slider::new(
+                            &mut self.image_view.as_mut().unwrap().tone.brightness,
+                            -100.0..=100.0,
+                        ));
+                        ui.end_row();
+                        ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                            ui.label("Gamma: ");
+                        });
+                        ui.add(
+                            Slider::new(
+                                &mut self.image_view.as_mut().unwrap().tone.gamma,
+                                0.2..=5.0,
+                            )
+                            .logarithmic(true),
+                        );
+                        ui.end_row();
+                        ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                            ui.label("Exposure: ");
+                        });
+                        ui.add(
+                            Slider::new(
+                                &mut self.image_view.as_mut().unwrap().tone.exposure,
+                                -4.0..=4.0,
+                            )
+                            .suffix(" EV"),
+                        );
+                        ui.end_row();
+                        ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                            ui.label("Temperature: ");
+                        });
+                        ui.add(Slider::new(
+                            &mut self.image_view.as_mut().unwrap().white_balance.temperature,
+                            -100.0..=100.0,
+                        ));
+                        ui.end_row();
+                        ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                            ui.label("Tint: ");
+                        });
+                        ui.add(Slider::new(
+                            &mut self.image_view.as_mut().unwrap().white_balance.tint,
+                            -100.0..=100.0,
+                        ));
+                        ui.end_row();
+                        ui.label("");
+                        if ui
+                            .selectable_label(self.picking_gray, "Pick gray point")
+                            .on_hover_text(
+                                "Click something gray in the image to neutralize the color cast",
+                            )
+                            .clicked()
+                        {
+                            self.picking_gray = !self.picking_gray;
+                        }
+                        ui.end_row();
                         ui.end_row();
 
                         ui.with_layout(
@@ -72,11 +366,15 @@ impl App {
                                     let saturation = view.saturation;
                                     let contrast = view.contrast;
                                     let lightness = view.lightness;
+                                    let tone = view.tone;
+                                    let white_balance = view.white_balance;
                                     self.queue(Op::Color {
                                         hue,
                                         saturation,
                                         contrast,
                                         lightness,
+                                        tone,
+                                        white_balance,
                                     });
                                 }
                             },
@@ -90,8 +388,124 @@ impl App {
                     view.contrast = 0.0;
                     view.saturation = 0.0;
                     view.lightness = 0.0;
+                    view.tone = Tone::default();
+                    view.white_balance = WhiteBalance::default();
                 }
+                self.picking_gray = false;
             }
         }
     }
+
+    /// Sets the white balance that turns the pixel under the cursor gray.
+    pub fn pick_gray_point(&mut self) {
+        self.picking_gray = false;
+        let color = match self.inspected {
+            Some((_, [r, g, b, _])) => [r, g, b],
+            None => return,
+        };
+        match (WhiteBalance::neutral(color), self.image_view.as_mut()) {
+            (Some(white_balance), Some(view)) => view.white_balance = white_balance,
+            (None, _) => self
+                .toasts
+                .push("The gray point needs some of every color, pick a lighter pixel"),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{LumaA, Rgb, Rgb32FImage, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn filters_keep_alpha_and_depth() {
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([200, 100, 50, 77]),
+        ));
+
+        let gray = Filter::Grayscale.apply(&image);
+        assert_eq!(gray.color(), image::ColorType::La8);
+        assert_eq!(gray.into_luma_alpha8().get_pixel(0, 0)[1], 77);
+
+        let sepia = Filter::Sepia.apply(&image).into_rgba8();
+        assert_eq!(sepia.get_pixel(0, 0), &image::Rgba([165, 147, 114, 77]));
+        let sepia = Filter::Sepia.apply(&DynamicImage::ImageLuma16(
+            image::ImageBuffer::from_pixel(1, 1, image::Luma([60000])),
+        ));
+        assert_eq!(sepia.color(), image::ColorType::Rgb16);
+        assert_eq!(sepia.into_rgb16().get_pixel(0, 0)[0], u16::MAX);
+
+        let inverted = Filter::Invert.apply(&image).into_rgba8();
+        assert_eq!(inverted.get_pixel(0, 0), &image::Rgba([55, 155, 205, 77]));
+    }
+
+    #[test]
+    fn gray_point_is_neutralized() {
+        let cast = [130, 140, 160];
+        let white_balance = WhiteBalance::neutral(cast).unwrap();
+        assert!(white_balance.temperature > 0.0);
+        assert!(white_balance.tint < 0.0);
+
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb(cast)));
+        let balanced = white_balance.adjust(&image).into_rgb8();
+        let [r, g, b] = balanced.get_pixel(0, 0).0;
+        assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1, "{:?}", [r, g, b]);
+
+        assert_eq!(WhiteBalance::neutral([0, 20, 30]), None);
+        let gray =
+            DynamicImage::ImageLuma8(image::ImageBuffer::from_pixel(1, 1, image::Luma([90])));
+        assert_eq!(white_balance.adjust(&gray), gray);
+    }
+
+    #[test]
+    fn tone_adjusts_color_but_not_alpha() {
+        let gray = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([64, 128, 255])));
+        assert_eq!(Tone::default().adjust(&gray), gray);
+
+        let brighter = Tone {
+            brightness: 20.0,
+            ..Tone::default()
+        };
+        assert_eq!(
+            brighter.adjust(&gray).into_rgb8().get_pixel(0, 0),
+            &Rgb([115, 179, 255])
+        );
+
+        // one stop doubles the light, 2.2 is the gamma values are stored with
+        let exposed = Tone {
+            exposure: 1.0,
+            ..Tone::default()
+        };
+        let expected = (64.0 * 2f32.powf(1.0 / 2.2)).round() as u8;
+        assert_eq!(
+            exposed.adjust(&gray).into_rgb8().get_pixel(0, 0)[0],
+            expected
+        );
+
+        let gamma = Tone {
+            gamma: 2.0,
+            ..Tone::default()
+        };
+        let adjusted = gamma.adjust(&DynamicImage::ImageLumaA8(image::ImageBuffer::from_pixel(
+            1,
+            1,
+            LumaA([64, 100]),
+        )));
+        assert_eq!(
+            adjusted.into_luma_alpha8().get_pixel(0, 0),
+            &LumaA([128, 100])
+        );
+
+        // floating point images are linear and not clipped
+        let float = DynamicImage::ImageRgb32F(Rgb32FImage::from_pixel(1, 1, Rgb([0.25, 2.0, 0.0])));
+        let adjusted = exposed.adjust(&float).into_rgb32f();
+        let pixel = adjusted.get_pixel(0, 0);
+        assert!((pixel[0] - 0.5).abs() < 1e-4);
+        assert!((pixel[1] - 4.0).abs() < 1e-3);
+        assert_eq!(pixel[2], 0.0);
+    }
 }
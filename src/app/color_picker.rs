@@ -0,0 +1,274 @@
+use std::fmt::Write as _;
+
+use egui::{Button, Color32, Id, RichText, Sense, Vec2 as EguiVec2};
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use super::{clipboard::copy_text, App};
+use crate::vec2::Vec2;
+
+/// How many picked colors are kept.
+const HISTORY_SIZE: usize = 16;
+const SWATCH_SIZE: f32 = 24.0;
+
+#[derive(Default)]
+pub struct ColorPicker {
+    /// Set while the next click on the image picks a color.
+    pub picking: bool,
+    pub visible: bool,
+    /// Most recent first.
+    history: Vec<[u8; 4]>,
+}
+
+impl ColorPicker {
+    fn push(&mut self, color: [u8; 4]) {
+        if self.history.first() == Some(&color) {
+            return;
+        }
+        self.history.insert(0, color);
+        self.history.truncate(HISTORY_SIZE);
+    }
+}
+
+/// How picked colors are copied.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorFormat {
+    #[default]
+    Hex,
+    /// The css `rgb()` function, `rgba()` for translucent colors.
+    Rgb,
+}
+
+impl ColorFormat {
+    pub const ALL: [ColorFormat; 2] = [ColorFormat::Hex, ColorFormat::Rgb];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorFormat::Hex => "#RRGGBB",
+            ColorFormat::Rgb => "rgb()",
+        }
+    }
+
+    pub fn format(self, color: [u8; 4]) -> String {
+        match self {
+            ColorFormat::Hex => hex(color),
+            ColorFormat::Rgb => css_rgb(color),
+        }
+    }
+}
+
+pub fn hex(color: [u8; 4]) -> String {
+    let [r, g, b, a] = color;
+    if a == 255 {
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    } else {
+        format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a)
+    }
+}
+
+fn css_rgb(color: [u8; 4]) -> String {
+    let [r, g, b, a] = color;
+    if a == 255 {
+        format!("rgb({}, {}, {})", r, g, b)
+    } else {
+        // two decimals tell every alpha apart that matters on screen
+        let alpha = (a as f32 / 255.0 * 100.0).round() / 100.0;
+        format!("rgba({}, {}, {}, {})", r, g, b, alpha)
+    }
+}
+
+/// The history as a GIMP palette, oldest color first.
+fn gimp_palette(colors: &[[u8; 4]]) -> String {
+    let mut out = String::from("GIMP Palette\nName: Simp\nColumns: 8\n#\n");
+    for color in colors.iter().rev() {
+        let [r, g, b, _] = *color;
+        let _ = writeln!(out, "{:3} {:3} {:3}\t{}", r, g, b, &hex(*color)[1..7]);
+    }
+    out
+}
+
+fn hex_list(colors: &[[u8; 4]]) -> String {
+    let lines: Vec<_> = colors.iter().rev().map(|color| hex(*color)).collect();
+    lines.join("\n")
+}
+
+impl App {
+    /// Reads the pixel under `position` and copies it as hex.
+    pub fn pick_color(&mut self, position: Vec2<f32>) {
+        let view = match self.image_view.as_ref() {
+            Some(view) => view,
+            None => return,
+        };
+        let (x, y) = match view.pixel_at(position) {
+            Some(pixel) => pixel,
+            None => return,
+        };
+        let color = {
+            let guard = view.image_data.read().unwrap();
+            let buffer = guard.frames[view.index].buffer();
+            if x >= buffer.width() || y >= buffer.height() {
+                return;
+            }
+            buffer.get_pixel(x, y).0
+        };
+
+        self.color_picker.picking = false;
+        self.color_picker.visible = true;
+        self.color_picker.push(color);
+        self.copy_color(color);
+    }
+
+    pub fn copy_color(&mut self, color: [u8; 4]) {
+        let text = self.settings.color_format.format(color);
+        if copy_text(text.clone()) {
+            self.toasts.push(format!("Copied {}", text));
+        }
+    }
+
+    /// A swatch of the pixel under the cursor while picking.
+    pub fn color_preview_ui(&self, ctx: &egui::Context) {
+        let color = match self.inspected {
+            Some((_, color)) if self.color_picker.picking || self.picking_gray => color,
+            _ => return,
+        };
+        let [r, g, b, a] = color;
+        egui::show_tooltip_at_pointer(ctx, Id::new("color preview"), |ui| {
+            ui.horizontal(|ui| {
+                let (rect, _) =
+                    ui.allocate_exact_size(EguiVec2::splat(SWATCH_SIZE), Sense::hover());
+                ui.painter().rect(
+                    rect,
+                    2.0,
+                    Color32::from_rgba_unmultiplied(r, g, b, a),
+                    ui.visuals().widgets.noninteractive.bg_stroke,
+                );
+                ui.label(RichText::new(self.settings.color_format.format(color)).monospace());
+            });
+        });
+    }
+
+    pub fn color_history_ui(&mut self, ctx: &egui::Context) {
+        if !self.color_picker.visible {
+            return;
+        }
+
+        let mut copy = None;
+        let mut export = None;
+        egui::SidePanel::right("color history")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Picked colors").strong());
+                    ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                        if ui.small_button("✖").clicked() {
+                            self.color_picker.visible = false;
+                        }
+                    });
+                });
+
+                let picking = self.color_picker.picking;
+                if ui
+                    .add(Button::new("Pick color").fill(if picking {
+                        ui.visuals().selection.bg_fill
+                    } else {
+                        ui.visuals().widgets.inactive.bg_fill
+                    }))
+                    .clicked()
+                {
+                    self.color_picker.picking = !picking;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Copy as");
+                    for format in ColorFormat::ALL {
+                        ui.radio_value(&mut self.settings.color_format, format, format.name());
+                    }
+                });
+
+                let history = &self.color_picker.history;
+                if history.is_empty() {
+                    ui.label("Click on the image to pick a color.");
+                    return;
+                }
+
+                ui.horizontal_wrapped(|ui| {
+                    ui.set_max_width(4.0 * (SWATCH_SIZE + ui.spacing().item_spacing.x));
+                    for color in history {
+                        let [r, g, b, a] = *color;
+                        let (rect, response) =
+                            ui.allocate_exact_size(EguiVec2::splat(SWATCH_SIZE), Sense::click());
+                        ui.painter().rect_filled(
+                            rect,
+                            2.0,
+                            Color32::from_rgba_unmultiplied(r, g, b, a),
+                        );
+                        let text = self.settings.color_format.format(*color);
+                        if response.on_hover_text(text).clicked() {
+                            copy = Some(*color);
+                        }
+                    }
+                });
+
+                ui.separator();
+                if ui.button("Copy as GIMP palette").clicked() {
+                    export = Some(gimp_palette(history));
+                }
+                if ui.button("Copy as hex list").clicked() {
+                    export = Some(hex_list(history));
+                }
+                if ui.button("Clear").clicked() {
+                    self.color_picker.history.clear();
+                }
+            });
+
+        if let Some(color) = copy {
+            self.copy_color(color);
+        }
+        if let Some(text) = export {
+            if copy_text(text) {
+                self.toasts.push(String::from("Copied palette"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_skips_repeated_colors() {
+        let mut picker = ColorPicker::default();
+        picker.push([1, 2, 3, 255]);
+        picker.push([1, 2, 3, 255]);
+        picker.push([4, 5, 6, 255]);
+        picker.push([1, 2, 3, 255]);
+        assert_eq!(picker.history.len(), 3);
+
+        for i in 0..40 {
+            picker.push([i, 0, 0, 255]);
+        }
+        assert_eq!(picker.history.len(), HISTORY_SIZE);
+        assert_eq!(picker.history[0], [39, 0, 0, 255]);
+    }
+
+    #[test]
+    fn palette_formats() {
+        let colors = [[0, 128, 255, 255], [255, 0, 0, 128]];
+        assert_eq!(hex_list(&colors), "#FF000080\n#0080FF");
+        assert!(gimp_palette(&colors).ends_with("255   0   0\tFF0000\n  0 128 255\t0080FF\n"));
+    }
+
+    #[test]
+    fn colors_copy_as_css() {
+        assert_eq!(
+            ColorFormat::Rgb.format([0, 128, 255, 255]),
+            "rgb(0, 128, 255)"
+        );
+        assert_eq!(
+            ColorFormat::Rgb.format([255, 0, 0, 128]),
+            "rgba(255, 0, 0, 0.5)"
+        );
+        assert_eq!(ColorFormat::Rgb.format([1, 2, 3, 0]), "rgba(1, 2, 3, 0)");
+        assert_eq!(ColorFormat::Hex.format([0, 128, 255, 255]), "#0080FF");
+    }
+}
@@ -0,0 +1,135 @@
+use std::cmp::Reverse;
+
+use egui::{Align, Align2, Key, RichText, ScrollArea};
+use glium::Display;
+
+use super::{action::Action, i18n::tr, App};
+
+#[derive(Default)]
+pub struct CommandPalette {
+    pub visible: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn open(&mut self) {
+        self.visible = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    fn matches(&self) -> Vec<Action> {
+        let mut matches: Vec<(i32, Action)> = Action::ALL
+            .iter()
+            .filter(|action| action.supported())
+            .filter_map(|action| fuzzy_score(&self.query, tr(action.name())).map(|s| (s, *action)))
+            .collect();
+        // the sort is stable so equal scores keep the registry order
+        matches.sort_by_key(|(score, _)| Reverse(*score));
+        matches.into_iter().map(|(_, action)| action).collect()
+    }
+}
+
+impl App {
+    pub fn command_palette_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        if !self.command_palette.visible {
+            return;
+        }
+
+        let matches = self.command_palette.matches();
+        let palette = &mut self.command_palette;
+        let keymap = &self.keymap;
+
+        {
+            let input = ctx.input();
+            if input.key_pressed(Key::ArrowDown) && palette.selected + 1 < matches.len() {
+                palette.selected += 1;
+            }
+            if input.key_pressed(Key::ArrowUp) && palette.selected > 0 {
+                palette.selected -= 1;
+            }
+        }
+        palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = None;
+        let mut closed = ctx.input().key_pressed(Key::Escape);
+        egui::Window::new("Command Palette")
+            .id(egui::Id::new("command palette window"))
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let old_query = palette.query.clone();
+                let res = ui.text_edit_singleline(&mut palette.query);
+                res.request_focus();
+                if palette.query != old_query {
+                    palette.selected = 0;
+                }
+
+                if ui.input().key_pressed(Key::Enter) {
+                    chosen = matches.get(palette.selected).copied();
+                    closed = true;
+                }
+
+                ui.separator();
+
+                ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (index, action) in matches.iter().enumerate() {
+                        let selected = index == palette.selected;
+                        let text = match keymap.hotkey(*action) {
+                            Some(hotkey) => format!("{}    {}", tr(action.name()), hotkey),
+                            None => tr(action.name()).to_string(),
+                        };
+                        let res = ui.selectable_label(selected, RichText::new(text));
+                        if selected {
+                            res.scroll_to_me(Some(Align::Center));
+                        }
+                        if res.clicked() {
+                            chosen = Some(*action);
+                            closed = true;
+                        }
+                    }
+                });
+            });
+
+        if closed {
+            self.command_palette.visible = false;
+        }
+
+        if let Some(action) = chosen {
+            self.dispatch(display, action);
+        }
+    }
+}
+
+/// Scores how well `query` matches `text` as a case insensitive subsequence.
+/// Returns `None` when some character of the query can not be found in order.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    let mut chars = text.char_indices();
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        loop {
+            let (index, c) = chars.next()?;
+            if c.to_ascii_lowercase() == q {
+                if index == 0 || text[..index].ends_with(' ') {
+                    score += 10;
+                }
+                if let Some(last) = last_match {
+                    if last + 1 == index {
+                        score += 5;
+                    }
+                }
+                score += 1;
+                last_match = Some(index);
+                break;
+            }
+        }
+    }
+
+    Some(score)
+}
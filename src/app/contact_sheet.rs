@@ -0,0 +1,426 @@
+use std::{
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use egui::{Button, DragValue};
+use glium::Display;
+use image::{imageops, DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+
+use super::{
+    batch::BatchJob,
+    i18n::{tr, trf},
+    thumbnails::thumbnail,
+    App,
+};
+use crate::{
+    image_io::{load::load_svg, save},
+    util::{display_name, Image, Proxy, UserEvent},
+};
+
+pub const COLUMNS_RANGE: RangeInclusive<u32> = 1..=20;
+pub const SIZE_RANGE: RangeInclusive<u32> = 64..=1024;
+/// Space around and between the cells.
+const GAP: u32 = 8;
+const LABEL_HEIGHT: u32 = 20;
+const FONT_SIZE: u32 = 12;
+/// Sheets are split before they get taller than this, plenty of viewers give up on larger images.
+const MAX_SHEET_HEIGHT: u32 = 8192;
+const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const PLACEHOLDER: Rgba<u8> = Rgba([224, 224, 224, 255]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SheetOptions {
+    pub columns: u32,
+    /// The longest side of a thumbnail in pixels.
+    pub size: u32,
+    pub labels: bool,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            columns: 6,
+            size: 256,
+            labels: true,
+        }
+    }
+}
+
+impl SheetOptions {
+    fn cell_height(&self) -> u32 {
+        if self.labels {
+            self.size + LABEL_HEIGHT
+        } else {
+            self.size
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.columns * (self.size + GAP) + GAP
+    }
+
+    /// How many images fit on one sheet.
+    fn per_sheet(&self) -> usize {
+        let rows = ((MAX_SHEET_HEIGHT - GAP) / (self.cell_height() + GAP)).max(1);
+        (rows * self.columns) as usize
+    }
+
+    /// Top left corner of the cell at `index` on its sheet.
+    fn cell_origin(&self, index: usize) -> (u32, u32) {
+        let column = index as u32 % self.columns;
+        let row = index as u32 / self.columns;
+        (
+            GAP + column * (self.size + GAP),
+            GAP + row * (self.cell_height() + GAP),
+        )
+    }
+}
+
+/// The ranges of images that go on each sheet.
+fn pages(count: usize, per_sheet: usize) -> Vec<Range<usize>> {
+    (0..count)
+        .step_by(per_sheet)
+        .map(|start| start..(start + per_sheet).min(count))
+        .collect()
+}
+
+/// Where sheet `index` goes, a single sheet keeps the chosen name and the others get numbered.
+fn sheet_path(path: &Path, index: usize, sheets: usize) -> PathBuf {
+    if sheets <= 1 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}-{}", stem, index + 1);
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+fn is_jpeg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Shortens `text` so it fits under a thumbnail of `width` pixels.
+fn fit_label(text: &str, width: u32) -> String {
+    // a rough average glyph width, names are cut a little early rather than overlapping
+    let max = (width as f32 / (FONT_SIZE as f32 * 0.6)) as usize;
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut fitted: String = text.chars().take(max.saturating_sub(1)).collect();
+    fitted.push('…');
+    fitted
+}
+
+/// Renders a line of `text` centered in an area of `width` by `height` pixels at `x` and `y`
+/// on the sheet, with its baseline `baseline` pixels from the top of it.
+fn draw_text(
+    sheet: &mut RgbaImage,
+    text: &str,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    baseline: u32,
+) {
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="{}" text-anchor="middle" fill="black"><text x="{}" y="{}">{}</text></svg>"#,
+        width,
+        height,
+        FONT_SIZE,
+        width / 2,
+        baseline,
+        escape_xml(text)
+    );
+    if let Some(frames) = load_svg(svg.as_bytes()) {
+        imageops::overlay(sheet, &frames[0].buffer().to_rgba8(), x as i64, y as i64);
+    }
+}
+
+/// Draws a sheet. `cells` holds the file name and thumbnail of every image on it, images
+/// that failed to decode get a placeholder. Text is rendered as an svg the size of the
+/// part of the cell it goes in, so a large sheet never has to be rasterized twice.
+fn compose(options: &SheetOptions, cells: &[(String, Option<RgbaImage>)]) -> RgbaImage {
+    let rows = (cells.len() as u32).div_ceil(options.columns);
+    let height = rows * (options.cell_height() + GAP) + GAP;
+    let mut sheet = RgbaImage::from_pixel(options.width(), height, BACKGROUND);
+
+    let size = options.size;
+    let center = options.size / 2;
+    for (index, (name, thumbnail)) in cells.iter().enumerate() {
+        let (x, y) = options.cell_origin(index);
+        match thumbnail {
+            Some(thumbnail) => {
+                let left = x + (options.size - thumbnail.width()) / 2;
+                let top = y + (options.size - thumbnail.height()) / 2;
+                imageops::overlay(&mut sheet, thumbnail, left as i64, top as i64);
+            }
+            None => {
+                let placeholder = RgbaImage::from_pixel(options.size, options.size, PLACEHOLDER);
+                imageops::overlay(&mut sheet, &placeholder, x as i64, y as i64);
+                draw_text(&mut sheet, "Could not load", x, y, size, size, center);
+            }
+        }
+        if options.labels {
+            let label = fit_label(name, options.size);
+            let baseline = LABEL_HEIGHT - 6;
+            draw_text(
+                &mut sheet,
+                &label,
+                x,
+                y + size,
+                size,
+                LABEL_HEIGHT,
+                baseline,
+            );
+        }
+    }
+    sheet
+}
+
+fn write_sheet(path: &Path, sheet: RgbaImage) -> Result<(), String> {
+    let result = if is_jpeg(path) {
+        let image = Image::new(DynamicImage::ImageRgb8(
+            DynamicImage::ImageRgba8(sheet).into_rgb8(),
+        ));
+        save::jpeg(path, &image, 90)
+    } else {
+        let image = Image::new(DynamicImage::ImageRgba8(sheet));
+        save::save_with_format(path, &image, ImageOutputFormat::Png)
+    };
+    result.map_err(|error| error.to_string())
+}
+
+#[derive(Default)]
+pub struct ContactSheet {
+    /// Whether the options window is open.
+    pub open: bool,
+    options: SheetOptions,
+    /// The sheets that are being made in the background.
+    job: Option<BatchJob>,
+}
+
+pub fn save_dialog(proxy: Proxy, display: &Display) {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_file_name("contact sheet.png")
+        .set_parent(display.gl_window().window())
+        .add_filter("PNG", &["png"])
+        .add_filter("JPEG", &["jpg", "jpeg"]);
+
+    thread::spawn(move || {
+        if let Some(file) = futures_executor::block_on(dialog.save_file()) {
+            let _ = proxy.send_event(UserEvent::ExportContactSheet(file.path().to_path_buf()));
+        }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
+    });
+}
+
+impl App {
+    pub fn export_contact_sheet(&mut self, path: PathBuf) {
+        if self.contact_sheet.job.is_some() {
+            return;
+        }
+
+        let paths = match self.op_queue.image_list.paths() {
+            Some(paths) if !paths.is_empty() => paths,
+            _ => return,
+        };
+
+        let options = self.contact_sheet.options;
+        let job = BatchJob::start(paths.len(), self.proxy.clone(), move |batch| {
+            let pages = pages(paths.len(), options.per_sheet());
+            let mut written = 0;
+            let mut error = None;
+
+            // one sheet at a time so only its thumbnails are held in memory
+            for (index, page) in pages.iter().enumerate() {
+                let on_sheet = &paths[page.clone()];
+                let thumbnails = batch.map(on_sheet, |path| thumbnail(path, options.size));
+                if batch.canceled() {
+                    break;
+                }
+
+                let cells: Vec<_> = on_sheet
+                    .iter()
+                    .map(|path| display_name(path))
+                    .zip(thumbnails.into_iter().map(Option::flatten))
+                    .collect();
+                let sheet = compose(&options, &cells);
+                if let Err(message) = write_sheet(&sheet_path(&path, index, pages.len()), sheet) {
+                    error = Some(message);
+                    break;
+                }
+                written += 1;
+            }
+
+            if let Some(error) = error {
+                UserEvent::ErrorMessage(error)
+            } else if batch.canceled() {
+                UserEvent::Toast(trf(
+                    "Canceled the contact sheet after {} of {} sheets",
+                    &[&written, &pages.len()],
+                ))
+            } else if pages.len() == 1 {
+                UserEvent::RevealToast(
+                    trf("Wrote a contact sheet of {} images", &[&paths.len()]),
+                    path,
+                )
+            } else {
+                UserEvent::RevealToast(
+                    trf(
+                        "Wrote {} contact sheets of {} images",
+                        &[&pages.len(), &paths.len()],
+                    ),
+                    sheet_path(&path, 0, pages.len()),
+                )
+            }
+        });
+
+        self.contact_sheet.job = Some(job);
+    }
+
+    pub fn contact_sheet_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        if let Some(job) = &self.contact_sheet.job {
+            if job.finished() {
+                self.contact_sheet.job = None;
+            }
+        }
+
+        if let Some(job) = &self.contact_sheet.job {
+            job.progress_ui(ctx, tr("Contact sheet"), "contact sheet progress window");
+            return;
+        }
+
+        if !self.contact_sheet.open {
+            return;
+        }
+
+        let count = self
+            .op_queue
+            .image_list
+            .paths()
+            .map_or(0, |paths| paths.len());
+        let mut open = true;
+        let mut export = false;
+        let options = &mut self.contact_sheet.options;
+        egui::Window::new(tr("Export contact sheet"))
+            .id(egui::Id::new("contact sheet window"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("contact sheet grid").show(ui, |ui| {
+                    ui.label(tr("Columns"));
+                    ui.add(DragValue::new(&mut options.columns).clamp_range(COLUMNS_RANGE));
+                    ui.end_row();
+
+                    ui.label(tr("Thumbnail size"));
+                    ui.add(
+                        DragValue::new(&mut options.size)
+                            .clamp_range(SIZE_RANGE)
+                            .suffix(" px"),
+                    );
+                    ui.end_row();
+
+                    ui.label(tr("File names"));
+                    ui.checkbox(&mut options.labels, tr("Print under each image"));
+                    ui.end_row();
+                });
+
+                let sheets = pages(count, options.per_sheet()).len();
+                ui.label(if sheets == 1 {
+                    trf(
+                        "{} images on one sheet, {} pixels wide",
+                        &[&count, &options.width()],
+                    )
+                } else {
+                    trf(
+                        "{} images on {} sheets, {} pixels wide",
+                        &[&count, &sheets, &options.width()],
+                    )
+                });
+
+                ui.add_space(4.0);
+                let can_export = count > 0 && !self.dialog_open;
+                if ui
+                    .add_enabled(can_export, Button::new(tr("Export…")))
+                    .clicked()
+                {
+                    export = true;
+                }
+            });
+
+        if export {
+            self.dialog_open = true;
+            self.contact_sheet.open = false;
+            save_dialog(self.proxy.clone(), display);
+        } else if !open {
+            self.contact_sheet.open = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_folders_are_split_into_numbered_sheets() {
+        let options = SheetOptions {
+            columns: 4,
+            size: 1024,
+            labels: true,
+        };
+        // 1044 pixels a row, seven rows fit under the limit
+        assert_eq!(options.per_sheet(), 28);
+        let pages = pages(60, options.per_sheet());
+        assert_eq!(pages, vec![0..28, 28..56, 56..60]);
+
+        let path = Path::new("out/sheet.png");
+        assert_eq!(sheet_path(path, 0, 1), PathBuf::from("out/sheet.png"));
+        assert_eq!(sheet_path(path, 2, 3), PathBuf::from("out/sheet-3.png"));
+    }
+
+    #[test]
+    fn broken_images_get_a_placeholder() {
+        let options = SheetOptions {
+            columns: 2,
+            size: 64,
+            labels: false,
+        };
+        let thumbnail = thumbnail(Path::new("does/not/exist.png"), options.size);
+        assert!(thumbnail.is_none());
+
+        let cells = vec![
+            (String::from("a.png"), Some(RgbaImage::new(64, 32))),
+            (String::from("b.png"), thumbnail),
+            (String::from("c.png"), None),
+        ];
+        let sheet = compose(&options, &cells);
+        assert_eq!(sheet.dimensions(), (options.width(), 2 * (64 + GAP) + GAP));
+        let (x, y) = options.cell_origin(1);
+        assert_eq!(*sheet.get_pixel(x + 1, y + 1), PLACEHOLDER);
+        let (x, y) = options.cell_origin(2);
+        assert_eq!(*sheet.get_pixel(x + 1, y + 1), PLACEHOLDER);
+    }
+
+    #[test]
+    fn labels_are_shortened_to_the_cell() {
+        assert_eq!(fit_label("a.png", 256), "a.png");
+        let label = fit_label(&"x".repeat(100), 64);
+        assert!(label.ends_with('…'));
+        assert!(label.chars().count() <= 9);
+    }
+}
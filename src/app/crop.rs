@@ -1,9 +1,101 @@
 use glium::{
-    backend::glutin::Display, draw_parameters::DrawParameters, implement_vertex,
-    index::PrimitiveType, program::Program, uniform, Blend, IndexBuffer, Surface, VertexBuffer,
+    backend::glutin::Display,
+    draw_parameters::DrawParameters,
+    glutin::event::{ModifiersState, VirtualKeyCode},
+    implement_vertex,
+    index::PrimitiveType,
+    program::Program,
+    uniform, Blend, IndexBuffer, Surface, VertexBuffer,
 };
 
-use crate::vec2::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{i18n::tr, image_view::ImageView, op_queue::Op, settings::Settings, App};
+use crate::{rect::Rect, vec2::Vec2};
+
+/// The grid sizes selections can snap to, in image pixels.
+pub const GRID_SIZES: [u32; 5] = [1, 2, 4, 8, 16];
+/// How many grid steps the arrow keys move the selection with Ctrl held.
+const LARGE_STEP: f32 = 10.0;
+
+/// Lines drawn inside the selection to help with the composition.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Guides {
+    #[default]
+    None,
+    Thirds,
+    GoldenRatio,
+}
+
+impl Guides {
+    pub const ALL: [Guides; 3] = [Guides::None, Guides::Thirds, Guides::GoldenRatio];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Guides::None => "None",
+            Guides::Thirds => "Rule of thirds",
+            Guides::GoldenRatio => "Golden ratio",
+        }
+    }
+
+    /// How far into the selection the first line of each pair is, the second one is as far
+    /// from the other side. 0 draws none.
+    pub fn fraction(self) -> f32 {
+        match self {
+            Guides::None => 0.0,
+            Guides::Thirds => 1.0 / 3.0,
+            Guides::GoldenRatio => 1.0 - 1.0 / 1.618_034,
+        }
+    }
+}
+
+/// The shape the selection keeps while it is dragged, unless a preset size sets it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AspectRatio {
+    #[default]
+    Free,
+    Square,
+    FourThree,
+    SixteenNine,
+    /// The width and height in the settings.
+    Custom,
+}
+
+impl AspectRatio {
+    pub const ALL: [AspectRatio; 5] = [
+        AspectRatio::Free,
+        AspectRatio::Square,
+        AspectRatio::FourThree,
+        AspectRatio::SixteenNine,
+        AspectRatio::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AspectRatio::Free => "Free",
+            AspectRatio::Square => "1:1",
+            AspectRatio::FourThree => "4:3",
+            AspectRatio::SixteenNine => "16:9",
+            AspectRatio::Custom => "Custom",
+        }
+    }
+
+    /// Width and height in the ratio, the other way around for `portrait`. `None` when free.
+    pub fn ratio(self, custom: [u32; 2], portrait: bool) -> Option<Vec2<u32>> {
+        let [width, height] = match self {
+            AspectRatio::Free => return None,
+            AspectRatio::Square => [1, 1],
+            AspectRatio::FourThree => [4, 3],
+            AspectRatio::SixteenNine => [16, 9],
+            AspectRatio::Custom => custom,
+        };
+        Some(if portrait {
+            Vec2::new(height, width)
+        } else {
+            Vec2::new(width, height)
+        })
+    }
+}
 
 #[derive(Copy, Clone)]
 pub struct Vertex {
@@ -21,6 +113,19 @@ implement_vertex!(Vertex, position);
 pub struct Crop {
     pub inner: Option<Inner>,
     pub cropping: bool,
+    /// When set the selection keeps the aspect ratio of this size
+    /// and the cropped image is resized to exactly this size.
+    pub target: Option<Vec2<u32>>,
+    /// When set together with `target` the selection is exactly that size
+    /// and dragging moves it instead of resizing it.
+    pub fixed: bool,
+    /// The aspect ratio the selection keeps when there is no `target`.
+    pub aspect: Option<Vec2<u32>>,
+    /// Edges of the selection land on multiples of this many image pixels.
+    pub grid: u32,
+    /// Set while the selection is edited with the keys. `inner` is then in pixels of the
+    /// image on disk as it is shown instead of on screen, so zooming does not move it.
+    pub keyboard: bool,
     vertices: VertexBuffer<Vertex>,
     indices: IndexBuffer<u8>,
     shader: Box<Program>,
@@ -31,6 +136,243 @@ pub struct Inner {
     pub current: Vec2<f32>,
 }
 
+/// The crop state of a tab that is not on screen, the grid is shared by all of them.
+#[derive(Default)]
+pub struct Selection {
+    inner: Option<Inner>,
+    cropping: bool,
+    target: Option<Vec2<u32>>,
+    fixed: bool,
+    keyboard: bool,
+}
+
+impl Inner {
+    /// The corner opposite of `start`, moved so the selection has the aspect ratio of `target`.
+    pub fn end(&self, target: Option<Vec2<u32>>) -> Vec2<f32> {
+        let target = match target {
+            Some(target) if target.x() > 0 && target.y() > 0 => target,
+            _ => return self.current,
+        };
+
+        let ratio = target.x() as f32 / target.y() as f32;
+        let size = self.current - self.start;
+        let width = size.x().abs().max(size.y().abs() * ratio);
+        let height = width / ratio;
+        self.start + Vec2::new(width.copysign(size.x()), height.copysign(size.y()))
+    }
+
+    /// Selects the central 80% of `image`, or the largest part of it with the aspect ratio of
+    /// `target`. A fixed selection is only centered, its size is the target.
+    pub fn centered(image: Vec2<u32>, target: Option<Vec2<u32>>, fixed: bool) -> Self {
+        let image = Vec2::new(image.x() as f32, image.y() as f32);
+        let center = image / 2.0;
+        let mut size = image * 0.8;
+        if let Some(target) = target.filter(|t| t.x() > 0 && t.y() > 0) {
+            let ratio = target.x() as f32 / target.y() as f32;
+            if size.x() / size.y() > ratio {
+                size.set_x(size.y() * ratio);
+            } else {
+                size.set_y(size.x() / ratio);
+            }
+        }
+        if fixed {
+            Self {
+                start: center,
+                current: center,
+            }
+        } else {
+            Self {
+                start: center - size / 2.0,
+                current: center + size / 2.0,
+            }
+        }
+    }
+
+    /// Arrows move the selection and resize it with Shift, Ctrl takes larger steps.
+    /// Returns whether the key was one of them.
+    pub fn step(
+        &mut self,
+        key: VirtualKeyCode,
+        modifiers: ModifiersState,
+        image: Vec2<u32>,
+        target: Option<Vec2<u32>>,
+        fixed: bool,
+        grid: u32,
+    ) -> bool {
+        let direction = match key {
+            VirtualKeyCode::Left => Vec2::new(-1.0, 0.0),
+            VirtualKeyCode::Right => Vec2::new(1.0, 0.0),
+            VirtualKeyCode::Up => Vec2::new(0.0, -1.0),
+            VirtualKeyCode::Down => Vec2::new(0.0, 1.0),
+            _ => return false,
+        };
+        let mut step = grid.max(1) as f32;
+        if modifiers.ctrl() {
+            step *= LARGE_STEP;
+        }
+        let delta = direction * step;
+        let image = Vec2::new(image.x() as f32, image.y() as f32);
+
+        if modifiers.shift() && !fixed {
+            let min = grid.max(1) as f32;
+            let mut current = self.current + delta;
+            current.set_x(current.x().min(image.x()).max(self.start.x() + min));
+            current.set_y(current.y().min(image.y()).max(self.start.y() + min));
+            // the other side follows so the aspect lock has nothing to undo
+            if let Some(target) = target.filter(|t| t.x() > 0 && t.y() > 0) {
+                let ratio = target.x() as f32 / target.y() as f32;
+                if direction.x() != 0.0 {
+                    current.set_y(self.start.y() + (current.x() - self.start.x()) / ratio);
+                } else {
+                    current.set_x(self.start.x() + (current.y() - self.start.y()) * ratio);
+                }
+                if current.x() > image.x() || current.y() > image.y() {
+                    return true;
+                }
+            }
+            self.current = current;
+        } else {
+            // keep the whole selection on the image while moving it
+            let low = Vec2::new(
+                self.start.x().min(self.current.x()),
+                self.start.y().min(self.current.y()),
+            );
+            let high = Vec2::new(
+                self.start.x().max(self.current.x()),
+                self.start.y().max(self.current.y()),
+            );
+            let (low_limit, high_limit) = match target {
+                // a fixed selection is centered on `current`
+                Some(target) if fixed => (
+                    Vec2::new(target.x() as f32, target.y() as f32) / 2.0,
+                    image - Vec2::new(target.x() as f32, target.y() as f32) / 2.0,
+                ),
+                _ => (Vec2::default(), image),
+            };
+            let delta = Vec2::new(
+                delta
+                    .x()
+                    .min(high_limit.x() - high.x())
+                    .max(low_limit.x() - low.x()),
+                delta
+                    .y()
+                    .min(high_limit.y() - high.y())
+                    .max(low_limit.y() - low.y()),
+            );
+            self.start += delta;
+            self.current += delta;
+        }
+        true
+    }
+}
+
+/// The selection for numbers typed into the crop toolbar, `[x, y, width, height]` in pixels of
+/// the image on disk as it is shown, changed from `before`. An aspect lock follows the side that was
+/// typed, and the selection is kept on the image.
+pub fn typed_selection(
+    before: [u32; 4],
+    typed: [u32; 4],
+    image: Vec2<u32>,
+    shape: Option<Vec2<u32>>,
+    fixed: bool,
+) -> Inner {
+    let [x, y, mut width, mut height] = typed;
+    let x = x.min(image.x().saturating_sub(1));
+    let y = y.min(image.y().saturating_sub(1));
+    let (max_width, max_height) = (image.x() - x, image.y() - y);
+    if let Some(shape) = shape.filter(|s| s.x() > 0 && s.y() > 0 && !fixed) {
+        let ratio = shape.x() as f32 / shape.y() as f32;
+        let from_width = |width: u32| (width as f32 / ratio).round() as u32;
+        let from_height = |height: u32| (height as f32 * ratio).round() as u32;
+        // the side that was typed is kept on the image first and drives the other one,
+        // if that one doesn't fit either it drives the first one back
+        if height != before[3] && width == before[2] {
+            height = height.clamp(1, max_height);
+            width = from_height(height);
+        } else {
+            width = width.clamp(1, max_width);
+            height = from_width(width);
+        }
+        if width > max_width {
+            width = max_width;
+            height = from_width(width);
+        }
+        if height > max_height {
+            height = max_height;
+            width = from_height(height);
+        }
+    }
+    let start = Vec2::new(x as f32, y as f32);
+    let size = Vec2::new(
+        width.clamp(1, max_width) as f32,
+        height.clamp(1, max_height) as f32,
+    );
+    if fixed {
+        // a fixed selection is centered on `current`
+        let center = start + size / 2.0;
+        Inner {
+            start: center,
+            current: center,
+        }
+    } else {
+        Inner {
+            start,
+            current: start + size,
+        }
+    }
+}
+
+/// Rounds to the nearest multiple of `grid` that is not zero.
+pub fn round_to_grid(value: u32, grid: u32) -> u32 {
+    let grid = grid.max(1);
+    ((value + grid / 2) / grid * grid).max(grid)
+}
+
+/// The rectangle a drag from `start` to `current` selects, everything in pixels of the
+/// image on disk as it is shown, however small it was decoded. Edges are snapped to `grid` and kept inside `image`, a fixed
+/// selection is `target` large and centered on `current`. `None` when nothing fits.
+pub fn selection(
+    start: Vec2<f32>,
+    current: Vec2<f32>,
+    image: Vec2<u32>,
+    target: Option<Vec2<u32>>,
+    fixed: bool,
+    grid: u32,
+) -> Option<Rect> {
+    let grid = grid.max(1) as f32;
+    let snap = |value: f32| (value / grid).round() * grid;
+    // the largest multiple of the grid below `max`, the image itself need not be one
+    let floor = |max: f32| (max / grid).floor() * grid;
+
+    match target {
+        Some(target) if fixed => {
+            let size = Vec2::new(target.x() as f32, target.y() as f32);
+            if target.x() > image.x() || target.y() > image.y() {
+                return None;
+            }
+            let x =
+                snap(current.x() - size.x() / 2.0).clamp(0.0, floor(image.x() as f32 - size.x()));
+            let y =
+                snap(current.y() - size.y() / 2.0).clamp(0.0, floor(image.y() as f32 - size.y()));
+            Some(Rect::new(Vec2::new(x, y), size))
+        }
+        target => {
+            let end = Inner { start, current }.end(target);
+            let x = |value: f32| snap(value).clamp(0.0, floor(image.x() as f32));
+            let y = |value: f32| snap(value).clamp(0.0, floor(image.y() as f32));
+            let (left, right) = (x(start.x().min(end.x())), x(start.x().max(end.x())));
+            let (top, bottom) = (y(start.y().min(end.y())), y(start.y().max(end.y())));
+            if right <= left || bottom <= top {
+                return None;
+            }
+            Some(Rect::new(
+                Vec2::new(left, top),
+                Vec2::new(right - left, bottom - top),
+            ))
+        }
+    }
+}
+
 impl Crop {
     pub fn new(display: &Display) -> Self {
         let shader = Box::new(
@@ -58,20 +400,93 @@ impl Crop {
         Self {
             inner: None,
             cropping: false,
+            target: None,
+            fixed: false,
+            aspect: None,
+            grid: 1,
+            keyboard: false,
             vertices,
             indices,
             shader,
         }
     }
 
-    pub fn render(&self, target: &mut glium::Frame, size: Vec2<f32>) {
-        if let Some(ref inner) = self.inner {
+    /// The size whose aspect ratio the selection keeps, a preset wins over the aspect lock.
+    pub fn shape(&self) -> Option<Vec2<u32>> {
+        self.target.or(self.aspect)
+    }
+
+    /// The rectangle that gets cropped if the drag ends now, in pixels of the image on disk
+    /// as it is shown.
+    pub fn selection(&self, view: &ImageView) -> Option<Rect> {
+        let inner = self.inner.as_ref()?;
+        let (start, current) = if self.keyboard {
+            (inner.start, inner.current)
+        } else {
+            (
+                view.screen_to_pixels(inner.start),
+                view.screen_to_pixels(inner.current),
+            )
+        };
+        selection(
+            start,
+            current,
+            view.oriented_true_size(),
+            self.shape(),
+            self.fixed,
+            self.grid,
+        )
+    }
+
+    /// Leaves crop mode without cropping.
+    pub fn cancel(&mut self) {
+        self.inner = None;
+        self.cropping = false;
+        self.target = None;
+        self.keyboard = false;
+    }
+
+    /// Takes the selection out to keep it with a tab that goes off screen.
+    pub fn take_selection(&mut self) -> Selection {
+        let selection = Selection {
+            inner: self.inner.take(),
+            cropping: self.cropping,
+            target: self.target.take(),
+            fixed: self.fixed,
+            keyboard: self.keyboard,
+        };
+        self.cancel();
+        selection
+    }
+
+    pub fn restore_selection(&mut self, selection: Selection) {
+        self.inner = selection.inner;
+        self.cropping = selection.cropping;
+        self.target = selection.target;
+        self.fixed = selection.fixed;
+        self.keyboard = selection.keyboard;
+    }
+
+    pub fn render(
+        &self,
+        target: &mut glium::Frame,
+        size: Vec2<f32>,
+        view: Option<&ImageView>,
+        guides: Guides,
+    ) {
+        let view = match view {
+            Some(view) => view,
+            None => return,
+        };
+        if let Some(rect) = self.selection(view) {
+            let start = view.pixels_to_screen(rect.position);
+            let end = view.pixels_to_screen(rect.position + rect.size);
             target
                 .draw(
                     &self.vertices,
                     &self.indices,
                     &self.shader,
-                    &uniform! { start: *inner.start, end: *inner.current, size: *size },
+                    &uniform! { start: *start, end: *end, size: *size, guide: guides.fraction() },
                     &DrawParameters {
                         blend: Blend::alpha_blending(),
                         ..DrawParameters::default()
@@ -81,3 +496,383 @@ impl Crop {
         }
     }
 }
+
+impl App {
+    /// The aspect lock and guides to pick from while cropping, along the top of the image, and
+    /// the size of the selection next to it.
+    pub fn crop_toolbar_ui(&mut self, ctx: &egui::Context) {
+        if !self.crop.cropping {
+            return;
+        }
+        let (image, rect) = match self.image_view.as_ref() {
+            Some(view) => (view.oriented_true_size(), self.crop.selection(view)),
+            None => return,
+        };
+        let shape = self.crop.shape();
+        let fixed = self.crop.fixed && self.crop.target.is_some();
+        let settings = &mut self.settings;
+        let preset = self.crop.target.is_some();
+        let mut typed = None;
+        let mut apply = false;
+        egui::Area::new("crop toolbar")
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 8.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        // a preset has its own shape
+                        ui.add_enabled_ui(!preset, |ui| {
+                            ui.label(tr("Aspect:"));
+                            egui::ComboBox::from_id_source("crop aspect")
+                                .selected_text(tr(settings.crop_aspect.name()))
+                                .show_ui(ui, |ui| {
+                                    for aspect in AspectRatio::ALL {
+                                        ui.selectable_value(
+                                            &mut settings.crop_aspect,
+                                            aspect,
+                                            tr(aspect.name()),
+                                        );
+                                    }
+                                });
+                            if settings.crop_aspect == AspectRatio::Custom {
+                                let [width, height] = &mut settings.crop_custom_aspect;
+                                for (value, separator) in [(width, ":"), (height, "")] {
+                                    ui.add(
+                                        egui::DragValue::new(value)
+                                            .clamp_range(Settings::CROP_ASPECT_RANGE),
+                                    );
+                                    ui.label(separator);
+                                }
+                            }
+                            if settings.crop_aspect != AspectRatio::Free
+                                && settings.crop_aspect != AspectRatio::Square
+                            {
+                                ui.checkbox(&mut settings.crop_portrait, tr("Portrait"));
+                            }
+                        });
+                        ui.separator();
+                        ui.label(tr("Guides:"));
+                        for guides in Guides::ALL {
+                            ui.radio_value(&mut settings.crop_guides, guides, tr(guides.name()));
+                        }
+                    });
+
+                    let rect = match rect {
+                        Some(rect) => rect,
+                        None => return,
+                    };
+                    let before = [
+                        rect.position.x(),
+                        rect.position.y(),
+                        rect.size.x(),
+                        rect.size.y(),
+                    ]
+                    .map(|value| value as u32);
+                    let mut values = before;
+                    ui.horizontal(|ui| {
+                        let fields = [
+                            ("X:", 0..=image.x().saturating_sub(1), true),
+                            ("Y:", 0..=image.y().saturating_sub(1), true),
+                            ("Width:", 1..=image.x(), !fixed),
+                            ("Height:", 1..=image.y(), !fixed),
+                        ];
+                        for ((label, range, enabled), value) in fields.into_iter().zip(&mut values)
+                        {
+                            ui.label(tr(label));
+                            ui.add_enabled(
+                                enabled,
+                                egui::DragValue::new(value).clamp_range(range).suffix(" px"),
+                            );
+                        }
+                        ui.separator();
+                        apply = ui.button(tr("Crop")).clicked();
+                    });
+                    if values != before {
+                        typed = Some((before, values));
+                    }
+                });
+            });
+        self.crop.aspect = self.crop_aspect();
+        if let Some((before, values)) = typed {
+            self.crop.inner = Some(typed_selection(before, values, image, shape, fixed));
+            self.crop.keyboard = true;
+        }
+        if apply {
+            self.commit_crop();
+            return;
+        }
+
+        // the size follows the corner that is dragged
+        let view = match self.image_view.as_ref() {
+            Some(view) => view,
+            None => return,
+        };
+        if let (Some(rect), Some(inner)) = (self.crop.selection(view), &self.crop.inner) {
+            let corner = if self.crop.keyboard {
+                view.pixels_to_screen(rect.position + rect.size)
+            } else {
+                inner.current
+            };
+            let ppp = self.pixels_per_point();
+            egui::Area::new("crop size")
+                .fixed_pos([corner.x() / ppp + 12.0, corner.y() / ppp + 12.0])
+                .interactable(false)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(format!("{} × {}", rect.size.x(), rect.size.y()));
+                    });
+                });
+        }
+    }
+
+    /// The aspect ratio the settings lock the selection to.
+    fn crop_aspect(&self) -> Option<Vec2<u32>> {
+        self.settings.crop_aspect.ratio(
+            self.settings.crop_custom_aspect,
+            self.settings.crop_portrait,
+        )
+    }
+
+    /// The crop selection while cropping, copies take only that part of the image.
+    pub fn crop_selection(&self) -> Option<Rect> {
+        let view = self.image_view.as_ref()?;
+        self.crop
+            .cropping
+            .then(|| self.crop.selection(view))
+            .flatten()
+    }
+
+    /// Enters crop mode with a selection in the middle that the keys can move and resize.
+    pub fn start_crop(&mut self) {
+        self.crop.aspect = self.crop_aspect();
+        if let Some(view) = self.image_view.as_ref() {
+            let inner = Inner::centered(
+                view.oriented_true_size(),
+                self.crop.shape(),
+                self.crop.fixed,
+            );
+            self.crop.inner = Some(inner);
+            self.crop.cropping = true;
+            self.crop.keyboard = true;
+        }
+    }
+
+    /// Handles a key while cropping with the keyboard, returns whether it was used.
+    pub fn crop_key(&mut self, key: VirtualKeyCode) -> bool {
+        if !self.crop.cropping {
+            return false;
+        }
+        match key {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter if self.crop.keyboard => {
+                self.commit_crop();
+                true
+            }
+            VirtualKeyCode::Escape => {
+                self.crop.cancel();
+                true
+            }
+            key if self.crop.keyboard => {
+                let shape = self.crop.shape();
+                let (view, inner) = match (self.image_view.as_ref(), self.crop.inner.as_mut()) {
+                    (Some(view), Some(inner)) => (view, inner),
+                    _ => return false,
+                };
+                inner.step(
+                    key,
+                    self.modifiers,
+                    view.oriented_true_size(),
+                    shape,
+                    self.crop.fixed,
+                    self.crop.grid,
+                )
+            }
+            _ => false,
+        }
+    }
+
+    /// Crops to the selection and leaves crop mode, shared by releasing the mouse and Enter.
+    pub fn commit_crop(&mut self) {
+        let view = match self.image_view.as_ref() {
+            Some(view) => view,
+            None => return,
+        };
+        let selection = self.crop.selection(view);
+        // a fixed size is cut out as it is, anything else is resized to the target
+        let resize = self.crop.target.filter(|_| !self.crop.fixed);
+        match (selection, self.crop.target) {
+            (Some(rect), _) => self.queue(Op::Crop(rect, resize)),
+            (None, Some(target)) if self.crop.fixed => self.toasts.push(format!(
+                "The image is smaller than {} × {}",
+                target.x(),
+                target.y()
+            )),
+            (None, _) => (),
+        }
+        self.crop.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_snaps_to_the_grid() {
+        let image = Vec2::new(1000, 500);
+        let rect = selection(
+            Vec2::new(13.3, 7.9),
+            Vec2::new(203.7, 101.2),
+            image,
+            None,
+            false,
+            16,
+        )
+        .unwrap();
+        assert_eq!(
+            rect,
+            Rect::new(Vec2::new(16.0, 0.0), Vec2::new(192.0, 96.0))
+        );
+
+        // 1000 is not a multiple of 16 so the selection stops short of the edge
+        let rect = selection(
+            Vec2::new(900.0, 0.0),
+            Vec2::new(1200.0, 40.0),
+            image,
+            None,
+            false,
+            16,
+        )
+        .unwrap();
+        assert_eq!(rect.right(), 992.0);
+        assert!(selection(
+            Vec2::new(3.0, 3.0),
+            Vec2::new(5.0, 5.0),
+            image,
+            None,
+            false,
+            16
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn fixed_selection_moves_without_resizing() {
+        let image = Vec2::new(2000, 1500);
+        let target = Some(Vec2::new(1920, 1080));
+        for current in [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1000.3, 700.6),
+            Vec2::new(5000.0, 5000.0),
+        ] {
+            let rect = selection(Vec2::new(0.0, 0.0), current, image, target, true, 8).unwrap();
+            assert_eq!(rect.size, Vec2::new(1920.0, 1080.0));
+            assert_eq!(rect.x() % 8.0, 0.0);
+            assert_eq!(rect.y() % 8.0, 0.0);
+            assert!(rect.right() <= 2000.0 && rect.bottom() <= 1500.0);
+        }
+        assert!(selection(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1000, 1000),
+            target,
+            true,
+            1
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn sizes_round_to_the_grid() {
+        assert_eq!(round_to_grid(1083, 8), 1080);
+        assert_eq!(round_to_grid(1085, 2), 1086);
+        assert_eq!(round_to_grid(3, 16), 16);
+        assert_eq!(round_to_grid(1081, 1), 1081);
+    }
+
+    #[test]
+    fn aspect_lock_shapes_the_selection() {
+        assert_eq!(AspectRatio::Free.ratio([3, 2], true), None);
+        assert_eq!(
+            AspectRatio::SixteenNine.ratio([3, 2], false),
+            Some(Vec2::new(16, 9))
+        );
+        let portrait = AspectRatio::Custom.ratio([3, 2], true);
+        assert_eq!(portrait, Some(Vec2::new(2, 3)));
+
+        let rect = selection(
+            Vec2::new(10.0, 10.0),
+            Vec2::new(110.0, 40.0),
+            Vec2::new(1000, 1000),
+            portrait,
+            false,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            rect,
+            Rect::new(Vec2::new(10.0, 10.0), Vec2::new(100.0, 150.0))
+        );
+    }
+
+    #[test]
+    fn typed_numbers_move_and_size_the_selection() {
+        let image = Vec2::new(1000, 800);
+        let before = [10, 20, 300, 200];
+        let inner = typed_selection(before, [50, 20, 300, 200], image, None, false);
+        assert_eq!(inner.start, Vec2::new(50.0, 20.0));
+        assert_eq!(inner.current, Vec2::new(350.0, 220.0));
+
+        // the lock follows the side that was typed
+        let square = Some(Vec2::new(1, 1));
+        let inner = typed_selection(before, [10, 20, 300, 120], image, square, false);
+        assert_eq!(inner.current, Vec2::new(130.0, 140.0));
+        let inner = typed_selection(before, [10, 20, 400, 200], image, square, false);
+        assert_eq!(inner.current, Vec2::new(410.0, 420.0));
+
+        // kept on the image
+        let inner = typed_selection(before, [900, 20, 300, 200], image, None, false);
+        assert_eq!(inner.current, Vec2::new(1000.0, 220.0));
+        // and still square when locked
+        let before = [10, 20, 300, 300];
+        let inner = typed_selection(before, [900, 20, 300, 300], image, square, false);
+        assert_eq!(inner.current, Vec2::new(1000.0, 120.0));
+        let inner = typed_selection(before, [10, 20, 900, 300], image, square, false);
+        assert_eq!(inner.current, Vec2::new(790.0, 800.0));
+    }
+
+    #[test]
+    fn guides_split_the_selection() {
+        assert_eq!(Guides::None.fraction(), 0.0);
+        assert!((Guides::Thirds.fraction() * 3.0 - 1.0).abs() < 1e-6);
+        // the larger part is to the whole as the smaller part is to the larger
+        let small = Guides::GoldenRatio.fraction();
+        assert!(((1.0 - small) / 1.0 - small / (1.0 - small)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn keys_move_and_resize_the_selection_inside_the_image() {
+        let image = Vec2::new(100, 50);
+        let mut inner = Inner::centered(image, None, false);
+        assert_eq!(
+            (inner.start, inner.current),
+            (Vec2::new(10.0, 5.0), Vec2::new(90.0, 45.0))
+        );
+
+        let none = ModifiersState::empty();
+        for _ in 0..20 {
+            inner.step(VirtualKeyCode::Right, none, image, None, false, 1);
+        }
+        assert_eq!((inner.start.x(), inner.current.x()), (20.0, 100.0));
+
+        let shift = ModifiersState::SHIFT;
+        inner.step(VirtualKeyCode::Left, shift, image, None, false, 1);
+        inner.step(
+            VirtualKeyCode::Up,
+            shift | ModifiersState::CTRL,
+            image,
+            None,
+            false,
+            1,
+        );
+        assert_eq!(inner.current, Vec2::new(99.0, 35.0));
+    }
+}
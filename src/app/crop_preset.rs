@@ -0,0 +1,157 @@
+use egui::{Button, RichText};
+
+use super::{
+    crop::{round_to_grid, GRID_SIZES},
+    i18n::tr,
+    App,
+};
+use crate::vec2::Vec2;
+
+/// Sizes the selection is resized to after cropping.
+const PRESETS: &[(&str, u32, u32)] = &[
+    ("Widescreen", 1920, 1080),
+    ("Square", 1080, 1080),
+    ("Portrait", 1080, 1350),
+    ("Story", 1080, 1920),
+    ("Link preview", 1200, 630),
+];
+
+/// Sizes that are cut out as they are, the selection only moves.
+const FIXED_PRESETS: &[(&str, u32, u32)] = &[
+    ("Full HD", 1920, 1080),
+    ("HD", 1280, 720),
+    ("Square", 512, 512),
+];
+
+pub struct CropPreset {
+    pub visible: bool,
+    /// Cut out exactly the size instead of resizing the selection to it.
+    fixed: bool,
+    /// Index into the presets of the mode, `None` for a custom size.
+    selected: Option<usize>,
+    width: String,
+    height: String,
+}
+
+impl Default for CropPreset {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            fixed: false,
+            selected: Some(0),
+            width: PRESETS[0].1.to_string(),
+            height: PRESETS[0].2.to_string(),
+        }
+    }
+}
+
+impl CropPreset {
+    fn presets(&self) -> &'static [(&'static str, u32, u32)] {
+        if self.fixed {
+            FIXED_PRESETS
+        } else {
+            PRESETS
+        }
+    }
+
+    fn size(&self) -> Option<Vec2<u32>> {
+        match self.selected {
+            Some(index) => {
+                let (_, width, height) = self.presets()[index];
+                Some(Vec2::new(width, height))
+            }
+            None => {
+                let width = self.width.parse::<u32>().ok().filter(|w| *w > 0)?;
+                let height = self.height.parse::<u32>().ok().filter(|h| *h > 0)?;
+                Some(Vec2::new(width, height))
+            }
+        }
+    }
+
+    /// Moves the custom size onto the grid.
+    fn round_custom(&mut self, grid: u32) {
+        for value in [&mut self.width, &mut self.height] {
+            if let Ok(number) = value.parse::<u32>() {
+                *value = round_to_grid(number, grid).to_string();
+            }
+        }
+    }
+}
+
+impl App {
+    pub fn crop_preset_ui(&mut self, ctx: &egui::Context) {
+        if !self.crop_preset.visible {
+            return;
+        }
+
+        let mut open = self.image_view.is_some();
+        let mut start = false;
+        let preset = &mut self.crop_preset;
+        let grid = &mut self.crop.grid;
+        let old_grid = *grid;
+        let mut entered = false;
+        egui::Window::new(tr("Crop & resize"))
+            .id(egui::Id::new("crop preset window"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let fixed = preset.fixed;
+                    ui.radio_value(&mut preset.fixed, false, tr("Resize selection"));
+                    ui.radio_value(&mut preset.fixed, true, tr("Exact size"));
+                    if preset.fixed != fixed {
+                        preset.selected = Some(0);
+                    }
+                });
+                ui.separator();
+
+                for (index, (name, width, height)) in preset.presets().iter().enumerate() {
+                    let label = format!("{} ({} × {})", tr(name), width, height);
+                    ui.radio_value(&mut preset.selected, Some(index), label);
+                }
+                ui.radio_value(&mut preset.selected, None, tr("Custom"));
+
+                ui.add_enabled_ui(preset.selected.is_none(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Width:"));
+                        entered |= ui.text_edit_singleline(&mut preset.width).lost_focus();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Height:"));
+                        entered |= ui.text_edit_singleline(&mut preset.height).lost_focus();
+                    });
+                });
+                preset.width.retain(|c| c.is_numeric());
+                preset.height.retain(|c| c.is_numeric());
+
+                ui.horizontal(|ui| {
+                    ui.label(tr("Snap to grid:"));
+                    for size in GRID_SIZES {
+                        ui.radio_value(grid, size, format!("{} px", size));
+                    }
+                });
+
+                let hint = if preset.fixed {
+                    tr("Drag over the image or use the arrows to move the area, it is cut out as it is.")
+                } else {
+                    tr("Drag over the image or use the arrows to pick the area, it is resized after.")
+                };
+                ui.label(RichText::new(hint).small());
+                start = ui
+                    .add_enabled(preset.size().is_some(), Button::new(tr("Select area")))
+                    .clicked();
+            });
+
+        if entered || start || self.crop.grid != old_grid {
+            self.crop_preset.round_custom(self.crop.grid);
+        }
+        if start {
+            self.crop.target = self.crop_preset.size();
+            self.crop.fixed = self.crop_preset.fixed;
+            self.start_crop();
+            open = false;
+        }
+        self.crop_preset.visible = open;
+    }
+}
@@ -0,0 +1,81 @@
+use std::{fs, path::PathBuf, thread};
+
+use super::{op_queue::Op, App};
+use crate::util::{Proxy, UserEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeleteMode {
+    /// Move to trash after asking first.
+    Trash,
+    /// Move to trash without asking.
+    TrashNow,
+    /// Remove the file for good, for drives where the trash does not work. Always asks first.
+    Permanent,
+}
+
+pub fn delete(path: PathBuf, mode: DeleteMode, proxy: Proxy) {
+    thread::spawn(move || {
+        let confirmed = match mode {
+            DeleteMode::Trash => rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Warning)
+                .set_title("Move to trash")
+                .set_description("Are you sure you want to move this to trash?")
+                .set_buttons(rfd::MessageButtons::YesNo)
+                .show(),
+            DeleteMode::Permanent => rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Warning)
+                .set_title("Delete permanently")
+                .set_description(
+                    "Are you sure you want to delete this permanently? This can not be undone.",
+                )
+                .set_buttons(rfd::MessageButtons::YesNo)
+                .show(),
+            DeleteMode::TrashNow => true,
+        };
+
+        if !confirmed {
+            return;
+        }
+
+        let res = match mode {
+            DeleteMode::Trash | DeleteMode::TrashNow => {
+                trash::delete(&path).map_err(|error| error.to_string())
+            }
+            DeleteMode::Permanent => fs::remove_file(&path).map_err(|error| error.to_string()),
+        };
+
+        let _ = match res {
+            Ok(_) => proxy.send_event(UserEvent::Deleted(path)),
+            Err(error) => proxy.send_event(UserEvent::Toast(format!(
+                "Could not delete {}: {}",
+                path.to_string_lossy(),
+                error
+            ))),
+        };
+    });
+}
+
+impl App {
+    pub fn delete_current(&mut self, mode: DeleteMode) {
+        if let Some(path) = self.image_view.as_ref().and_then(|view| view.path.clone()) {
+            delete(path, mode, self.proxy.clone());
+        }
+    }
+
+    /// Drops a deleted file from the list and moves on to the next image if it was the current one.
+    pub fn handle_deleted(&mut self, path: PathBuf) {
+        self.op_queue.cache.pop(&path);
+        let next = self.op_queue.image_list.remove(&path);
+
+        let current = self
+            .image_view
+            .as_ref()
+            .is_some_and(|view| view.path.as_ref() == Some(&path));
+        if current {
+            match next {
+                Some(next) => self.queue(Op::LoadPath(next, true)),
+                None => self.queue(Op::Close),
+            }
+        }
+    }
+}
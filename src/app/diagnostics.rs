@@ -0,0 +1,224 @@
+use std::{
+    collections::VecDeque,
+    io::Cursor,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use egui::RichText;
+use glium::Display;
+use image::{DynamicImage, ImageOutputFormat};
+
+use super::{action::Action, image_view::oriented, keymap::Keymap, App};
+use crate::util::{log, write_atomic, zip, Proxy, UserEvent};
+
+/// The included copy of the image is at most this large on each side.
+const IMAGE_SIZE: u32 = 1024;
+/// How many of the files opened since the start are listed.
+const RECENT: usize = 20;
+
+#[derive(Default)]
+pub struct Diagnostics {
+    pub visible: bool,
+    include_image: bool,
+    /// The files opened since the start, the latest first.
+    recent: VecDeque<PathBuf>,
+}
+
+impl Diagnostics {
+    /// Remembers that `path` was opened, for the list of recent files in the bundle.
+    pub fn opened(&mut self, path: &Path) {
+        self.recent.retain(|recent| recent != path);
+        self.recent.push_front(path.to_path_buf());
+        self.recent.truncate(RECENT);
+    }
+}
+
+fn save_dialog(proxy: Proxy, display: &Display) {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_file_name("simp-diagnostics.zip")
+        .set_parent(display.gl_window().window())
+        .add_filter("Zip", &["zip"]);
+
+    thread::spawn(move || {
+        if let Some(file) = futures_executor::block_on(dialog.save_file()) {
+            let _ = proxy.send_event(UserEvent::ExportDiagnostics(file.path().to_path_buf()));
+        }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
+    });
+}
+
+/// The program, the system it runs on and what OpenGL reports about the GPU.
+fn system_info(display: &Display) -> String {
+    format!(
+        "{} {}\nos: {} {}\nopengl version: {}\nopengl vendor: {}\nopengl renderer: {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        display.get_opengl_version_string(),
+        display.get_opengl_vendor_string(),
+        display.get_opengl_renderer_string(),
+    )
+}
+
+/// Every action with the keys it is bound to, marking the ones the user changed.
+fn keybindings(keymap: &Keymap) -> String {
+    let mut text = String::new();
+    for &action in Action::ALL {
+        let keys = keymap
+            .hotkey(action)
+            .unwrap_or_else(|| String::from("none"));
+        let rebound = if keymap.rebound(action) {
+            " (rebound)"
+        } else {
+            ""
+        };
+        text.push_str(&format!("{}: {}{}\n", action.name(), keys, rebound));
+    }
+    text
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+        .map_err(|error| error.to_string())?;
+    Ok(bytes)
+}
+
+impl App {
+    pub fn diagnostics_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        if !self.diagnostics.visible {
+            return;
+        }
+
+        let has_image = self.image_view.is_some();
+        let diagnostics = &mut self.diagnostics;
+        let mut open = true;
+        let mut export = false;
+        egui::Window::new("Export diagnostic bundle")
+            .id(egui::Id::new("diagnostics window"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("A zip file to attach to bug reports. It contains the settings, the");
+                ui.label("keyboard shortcuts, the files opened recently, the recent log and");
+                ui.label("the names of the GPU and its driver.");
+                ui.add_space(4.0);
+                ui.add_enabled_ui(has_image, |ui| {
+                    ui.checkbox(
+                        &mut diagnostics.include_image,
+                        "Include a downscaled copy of the current image",
+                    );
+                });
+                ui.label(RichText::new("Leave it out if the image is private.").small());
+                ui.separator();
+                ui.horizontal(|ui| {
+                    export = ui.button("Export…").clicked();
+                    if ui.button("Cancel").clicked() {
+                        diagnostics.visible = false;
+                    }
+                });
+            });
+
+        if ctx.input().key_pressed(egui::Key::Escape) {
+            open = false;
+        }
+        if export && !self.dialog_open {
+            self.dialog_open = true;
+            save_dialog(self.proxy.clone(), display);
+            open = false;
+        }
+        self.diagnostics.visible &= open;
+    }
+
+    /// Writes the bundle on another thread and shows a toast that can reveal it when done.
+    pub fn export_diagnostics(&mut self, display: &Display, path: PathBuf) {
+        let settings = toml::to_string_pretty(&self.config()).unwrap_or_else(|e| e.to_string());
+        let system = system_info(display);
+        let keys = keybindings(&self.keymap);
+        let recent: Vec<String> = self
+            .diagnostics
+            .recent
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        let recent = recent.join("\n");
+        let image = match (&self.image_view, self.diagnostics.include_image) {
+            (Some(view), true) => {
+                let guard = view.image_data.read().unwrap();
+                let buffer = guard.frames[view.index]
+                    .buffer()
+                    .thumbnail(IMAGE_SIZE, IMAGE_SIZE);
+                Some(
+                    oriented(
+                        &buffer,
+                        view.rotation,
+                        view.horizontal_flip,
+                        view.vertical_flip,
+                    )
+                    .into_owned(),
+                )
+            }
+            _ => None,
+        };
+        log::info!("exporting diagnostics to {}", path.to_string_lossy());
+        let log = log::lines().join("\n");
+
+        let proxy = self.proxy.clone();
+        thread::spawn(move || {
+            let result = image.as_ref().map(encode_png).transpose().and_then(|png| {
+                let mut files = vec![
+                    ("settings.toml", settings.as_bytes()),
+                    ("keybindings.txt", keys.as_bytes()),
+                    ("recent.txt", recent.as_bytes()),
+                    ("log.txt", log.as_bytes()),
+                    ("system.txt", system.as_bytes()),
+                ];
+                if let Some(png) = &png {
+                    files.push(("image.png", png));
+                }
+                write_atomic(&path, &zip::write(&files)).map_err(|error| error.to_string())
+            });
+
+            let event = match result {
+                Ok(()) => UserEvent::RevealToast(
+                    format!("Wrote the diagnostic bundle to {}", path.to_string_lossy()),
+                    path,
+                ),
+                Err(error) => UserEvent::ErrorMessage(format!(
+                    "Could not write the diagnostic bundle: {}",
+                    error
+                )),
+            };
+            let _ = proxy.send_event(event);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::keymap::Binding;
+
+    #[test]
+    fn bundle_lists_recent_files_and_rebound_keys() {
+        let mut diagnostics = Diagnostics::default();
+        for name in ["a.png", "b.png", "a.png"] {
+            diagnostics.opened(Path::new(name));
+        }
+        assert_eq!(diagnostics.recent, [Path::new("a.png"), Path::new("b.png")]);
+        for index in 0..RECENT * 2 {
+            diagnostics.opened(&PathBuf::from(index.to_string()));
+        }
+        assert_eq!(diagnostics.recent.len(), RECENT);
+
+        let mut keymap = Keymap::default();
+        keymap.bind(Action::RotateLeft, Binding::parse("J").unwrap());
+        let text = keybindings(&keymap);
+        assert!(text.contains(&format!("{}: J (rebound)\n", Action::RotateLeft.name())));
+        assert_eq!(text.lines().count(), Action::ALL.len());
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    io::Read,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use ureq::AgentBuilder;
+
+use super::{
+    load_image::{load_bytes, LoadOptions},
+    op_queue::{Output, Progress},
+};
+use crate::util::{log, ImageData, Proxy, UserEvent};
+
+/// Downloads larger than this are stopped, nothing that big is an image meant for viewing.
+const MAX_BYTES: u64 = 1024 * 1024 * 1024;
+/// How long connecting or waiting for the next bytes may take before the download fails.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The address if `text` is nothing but an http or https URL.
+pub fn url(text: &str) -> Option<&str> {
+    let text = text.trim();
+    let rest = ["https://", "http://"].iter().find_map(|scheme| {
+        text.get(..scheme.len())
+            .filter(|start| start.eq_ignore_ascii_case(scheme))
+            .map(|_| &text[scheme.len()..])
+    })?;
+    (!rest.is_empty() && !text.contains(char::is_whitespace)).then_some(text)
+}
+
+/// The last part of the path of `url`, what the image is called until it is saved.
+pub fn file_name(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let (_, after_scheme) = path.split_once("://")?;
+    let (_, path) = after_scheme.split_once('/')?;
+    path.rsplit('/').next().filter(|name| !name.is_empty())
+}
+
+/// Downloads `url` and decodes it with `options`, reporting how far the download is.
+/// Stops between reads once `cancel` is set.
+pub fn download(
+    url: &str,
+    options: LoadOptions,
+    progress: &Progress,
+    cancel: &AtomicBool,
+) -> Result<ImageData, String> {
+    let agent = AgentBuilder::new()
+        .timeout_connect(TIMEOUT)
+        .timeout_read(TIMEOUT)
+        .build();
+    let response = agent.get(url).call().map_err(|error| match error {
+        ureq::Error::Status(code, response) => {
+            format!("the server answered {} {}", code, response.status_text())
+        }
+        ureq::Error::Transport(error) => error.to_string(),
+    })?;
+
+    let total = response
+        .header("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok());
+    let too_large = || String::from("the file is larger than 1 GiB");
+    if total.is_some_and(|total| total > MAX_BYTES) {
+        return Err(too_large());
+    }
+
+    let mut reader = response.into_reader();
+    let mut bytes = Vec::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(String::from("canceled"));
+        }
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|error| error.to_string())?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&buffer[..read]);
+        if bytes.len() as u64 > MAX_BYTES {
+            return Err(too_large());
+        }
+        if let Some(total) = total {
+            progress.set(bytes.len(), total as usize);
+        }
+    }
+    log::info!("downloaded {} bytes from {}", bytes.len(), url);
+
+    // the extension helps the formats that can not be told apart by their content
+    let name = file_name(url).unwrap_or("download");
+    let mut image_data =
+        load_bytes(Path::new(name), &bytes, options).map_err(|error| error.to_string())?;
+    image_data.source = Some(url.to_string());
+    Ok(image_data)
+}
+
+/// Opens the image at `url` as an unsaved image, like a paste.
+pub fn open(
+    url: String,
+    options: LoadOptions,
+    proxy: Proxy,
+    sender: Sender<Output>,
+    progress: Progress,
+    cancel: Arc<AtomicBool>,
+) {
+    thread::spawn(move || {
+        match download(&url, options, &progress, &cancel) {
+            Ok(image_data) => {
+                let _ = sender.send(Output::ImageLoaded(Arc::new(RwLock::new(image_data)), None));
+            }
+            Err(_) if cancel.load(Ordering::SeqCst) => {
+                log::info!("canceled downloading {}", url);
+                let _ = sender.send(Output::Done);
+                let _ = proxy.send_event(UserEvent::Toast(String::from("Canceled the download")));
+            }
+            Err(error) => {
+                log::warn!("downloading {} failed: {}", url, error);
+                let _ = sender.send(Output::Done);
+                let _ = proxy.send_event(UserEvent::ErrorMessage(format!(
+                    "Could not open {}: {}",
+                    url, error
+                )));
+            }
+        }
+        let _ = proxy.send_event(UserEvent::Wake);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Cursor, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::app::load_image::LoadError;
+
+    #[test]
+    fn only_web_addresses_are_urls() {
+        assert_eq!(
+            url("  https://example.com/a.png\n"),
+            Some("https://example.com/a.png")
+        );
+        assert_eq!(url("HTTP://example.com"), Some("HTTP://example.com"));
+        assert_eq!(url("https://"), None);
+        assert_eq!(url("ftp://example.com/a.png"), None);
+        assert_eq!(url("see https://example.com/a.png"), None);
+        assert_eq!(url("/home/me/a.png"), None);
+    }
+
+    #[test]
+    fn names_come_from_the_path() {
+        assert_eq!(
+            file_name("https://example.com/img/cat.jpg?size=large#top"),
+            Some("cat.jpg")
+        );
+        assert_eq!(file_name("https://example.com/"), None);
+        assert_eq!(file_name("https://example.com"), None);
+    }
+
+    /// Answers one request with `body` on a local port and returns the address to ask.
+    fn serve(body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}/img/cat.png", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        address
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbaImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn downloads_are_decoded_with_the_limits() {
+        let progress = Progress::new();
+        let cancel = AtomicBool::new(false);
+        let url = serve(png(40, 20));
+        let image_data = download(&url, LoadOptions::default(), &progress, &cancel).unwrap();
+        assert_eq!(image_data.frames[0].buffer().width(), 40);
+        assert_eq!(image_data.source.as_deref(), Some(url.as_str()));
+        assert_eq!(progress.get(), Some(1.0));
+
+        let limited = LoadOptions {
+            max_pixels: Some(100),
+            ..LoadOptions::default()
+        };
+        let error = download(&serve(png(40, 20)), limited, &progress, &cancel).unwrap_err();
+        assert_eq!(error, LoadError::TooLarge((40, 20), false).to_string());
+
+        cancel.store(true, Ordering::SeqCst);
+        assert!(download(
+            &serve(png(40, 20)),
+            LoadOptions::default(),
+            &progress,
+            &cancel
+        )
+        .is_err());
+    }
+}
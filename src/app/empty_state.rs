@@ -0,0 +1,60 @@
+use std::collections::HashSet;
+
+use egui::{CollapsingHeader, Color32, RichText};
+use glium::Display;
+
+use super::{action::Action, op_queue::Op, App};
+use crate::util::{
+    display_name,
+    extensions::{PHOTOSHOP, RASTER, RAW, VECTOR},
+};
+
+impl App {
+    /// What is shown in place of the image when nothing is open.
+    pub fn empty_state(&mut self, display: &Display, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 4.0);
+            ui.label(
+                RichText::new("Open File: Ctrl + O\n\nPaste: Ctrl + V\n\nHelp: Ctrl + H")
+                    .size(20.0),
+            );
+            ui.add_space(20.0);
+
+            if let Some((path, reason)) = self.load_error.clone() {
+                let name = display_name(&path);
+                ui.colored_label(
+                    Color32::from_rgb(255, 110, 110),
+                    format!("Could not open {}: {}", name, reason),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Retry").clicked() {
+                        self.load_error = None;
+                        self.queue(Op::LoadPath(path, false));
+                    }
+                    if ui.button("Open file…").clicked() {
+                        self.dispatch(display, Action::Open);
+                    }
+                });
+                ui.add_space(20.0);
+            }
+
+            CollapsingHeader::new("Supported formats").show(ui, |ui| {
+                for (kind, extensions) in [
+                    ("Raster", &*RASTER),
+                    ("Vector", &*VECTOR),
+                    ("Photoshop", &*PHOTOSHOP),
+                    ("Camera raw", &*RAW),
+                ] {
+                    ui.label(RichText::new(kind).strong());
+                    ui.label(format_extensions(extensions));
+                }
+            });
+        });
+    }
+}
+
+fn format_extensions(extensions: &HashSet<&'static str>) -> String {
+    let mut extensions: Vec<_> = extensions.iter().copied().collect();
+    extensions.sort_unstable();
+    extensions.join(", ")
+}
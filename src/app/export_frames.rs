@@ -0,0 +1,127 @@
+use std::{
+    borrow::Cow,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    thread,
+};
+
+use glium::Display;
+use image::{DynamicImage, ImageFormat};
+
+use super::{
+    image_view::{oriented, ViewState},
+    op_queue::{Output, Progress},
+};
+use crate::util::{Proxy, UserEvent};
+
+/// What frames of the image `filename` are named after.
+pub fn frame_stem(filename: &OsStr) -> OsString {
+    Path::new(filename)
+        .file_stem()
+        .map_or_else(|| OsString::from("frame"), OsStr::to_os_string)
+}
+
+/// The file name of frame `index` of `count`, counted from one and padded with zeros so the
+/// files sort in the order they play.
+fn frame_name(stem: &OsStr, index: usize, count: usize) -> OsString {
+    let width = count.to_string().len();
+    let mut name = stem.to_os_string();
+    name.push(format!("_frame{:0width$}.png", index + 1, width = width));
+    name
+}
+
+/// PNG has no floating point samples, those frames are written with 16 bits per channel.
+fn png_compatible(buffer: &DynamicImage) -> Option<DynamicImage> {
+    match buffer {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            Some(DynamicImage::ImageRgba16(buffer.to_rgba16()))
+        }
+        _ => None,
+    }
+}
+
+pub fn folder_dialog(directory: Option<PathBuf>, proxy: Proxy, display: &Display) {
+    let mut dialog = rfd::AsyncFileDialog::new().set_parent(display.gl_window().window());
+    if let Some(directory) = &directory {
+        dialog = dialog.set_directory(directory);
+    }
+
+    thread::spawn(move || {
+        if let Some(folder) = futures_executor::block_on(dialog.pick_folder()) {
+            let _ = proxy.send_event(UserEvent::ExportFrames(folder.path().to_path_buf()));
+        }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
+    });
+}
+
+impl ViewState {
+    /// Writes every frame as a numbered PNG into `dir`, turned and flipped like it is shown.
+    /// Stops at the first frame that can not be written.
+    pub fn export_frames(
+        &self,
+        dir: PathBuf,
+        stem: OsString,
+        proxy: Proxy,
+        sender: Sender<Output>,
+        progress: Progress,
+    ) {
+        let image_data = self.image_data.clone();
+        let (rotation, horizontal_flip, vertical_flip) =
+            (self.rotation, self.horizontal_flip, self.vertical_flip);
+        thread::spawn(move || {
+            let guard = image_data.read().unwrap();
+            let count = guard.frames.len();
+            let mut result = Ok(());
+            for (index, frame) in guard.frames.iter().enumerate() {
+                let path = dir.join(frame_name(&stem, index, count));
+                let buffer = oriented(frame.buffer(), rotation, horizontal_flip, vertical_flip);
+                let buffer = png_compatible(&buffer).map_or(buffer, Cow::Owned);
+                result = buffer
+                    .save_with_format(&path, ImageFormat::Png)
+                    .map_err(|error| {
+                        format!("Could not write {}: {}", path.to_string_lossy(), error)
+                    });
+                if result.is_err() {
+                    break;
+                }
+                progress.set(index + 1, count);
+            }
+            drop(guard);
+
+            let _ = sender.send(Output::Done);
+            let event = match result {
+                Ok(()) => UserEvent::RevealToast(
+                    format!("Exported {} frames to {}", count, dir.to_string_lossy()),
+                    dir,
+                ),
+                Err(error) => UserEvent::ErrorMessage(error),
+            };
+            let _ = proxy.send_event(event);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_names_sort_in_order() {
+        let cat = OsStr::new("cat");
+        assert_eq!(frame_name(cat, 0, 9), "cat_frame1.png");
+        assert_eq!(frame_name(cat, 0, 10), "cat_frame01.png");
+        assert_eq!(frame_name(cat, 99, 120), "cat_frame100.png");
+        assert_eq!(frame_name(cat, 6, 120), "cat_frame007.png");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn frames_keep_names_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let stem = frame_stem(OsStr::from_bytes(b"caf\xe9.gif"));
+        assert_eq!(stem.as_bytes(), b"caf\xe9");
+        assert_eq!(frame_name(&stem, 1, 2).as_bytes(), b"caf\xe9_frame2.png");
+    }
+}
@@ -0,0 +1,75 @@
+use std::borrow::Cow;
+
+use glium::{
+    backend::glutin::Display,
+    framebuffer::SimpleFrameBuffer,
+    texture::{MipmapsOption, RawImage2d, SrgbFormat, SrgbTexture2d},
+    Surface,
+};
+use image::{
+    imageops::{flip_vertical_in_place, FilterType},
+    DynamicImage, GenericImageView, ImageBuffer, Rgba,
+};
+
+use super::App;
+use crate::{
+    util::{Image, ImageData},
+    vec2::Vec2,
+};
+
+impl App {
+    /// Renders the image exactly as it is framed in the window, without any ui on top.
+    pub fn render_view(&self, display: &Display) -> Option<ImageData> {
+        let view = self.image_view.as_ref()?;
+        let (width, height) = display.get_framebuffer_dimensions();
+        let factor = if self.export_supersample { 2 } else { 1 };
+
+        let texture = SrgbTexture2d::empty_with_format(
+            display,
+            SrgbFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+            width * factor,
+            height * factor,
+        )
+        .ok()?;
+
+        {
+            let mut target = SimpleFrameBuffer::new(display, &texture).ok()?;
+            let (r, g, b, a) = self.settings.background.color();
+            target.clear_color_srgb(r, g, b, a);
+            view.render(
+                &mut target,
+                Vec2::new(width as f32, height as f32),
+                self.settings.checks(),
+                false,
+            );
+        }
+
+        let raw: RawImage2d<'_, u8> = texture.read();
+        let data = match raw.data {
+            Cow::Borrowed(data) => data.to_vec(),
+            Cow::Owned(data) => data,
+        };
+        let mut buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(raw.width, raw.height, data)?;
+        // opengl stores the bottom row first
+        flip_vertical_in_place(&mut buffer);
+
+        let top = (self.top_bar_pixels().round() as u32 * factor).min(buffer.height());
+        let bottom = (self.bottom_bar_pixels().round() as u32 * factor).min(buffer.height() - top);
+        let image = DynamicImage::ImageRgba8(buffer).crop_imm(
+            0,
+            top,
+            width * factor,
+            height * factor - top - bottom,
+        );
+
+        let image = if factor > 1 {
+            let (w, h) = image.dimensions();
+            image.resize_exact(w / factor, h / factor, FilterType::Triangle)
+        } else {
+            image
+        };
+
+        Some(ImageData::from(vec![Image::new(image)]))
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use egui::{vec2, Align, Rect, ScrollArea, Sense, TopBottomPanel};
+
+use super::{
+    op_queue::Op,
+    thumbnails::{paint_cell, ThumbnailTextures},
+    App,
+};
+use crate::util::display_name;
+
+/// The side of a thumbnail cell in points.
+const CELL: f32 = 64.0;
+const GAP: f32 = 4.0;
+/// The height of the panel, with room for the scroll bar under the cells.
+pub const FILMSTRIP_SIZE: f32 = CELL + 2.0 * GAP + 12.0;
+
+/// Thumbnails of the folder along the bottom of the window.
+#[derive(Default)]
+pub struct Filmstrip {
+    textures: ThumbnailTextures,
+    /// The image the strip was last scrolled to, it follows when another one is shown.
+    scrolled_to: Option<usize>,
+    /// The thumbnail that is being dragged to another place in the list.
+    dragging: Option<usize>,
+}
+
+impl App {
+    pub fn filmstrip_size(&self) -> f32 {
+        if self.settings.filmstrip
+            && !self.fullscreen
+            && !self.gallery.visible
+            && self.op_queue.image_list.position().is_some()
+        {
+            FILMSTRIP_SIZE
+        } else {
+            0.0
+        }
+    }
+
+    pub fn toggle_filmstrip(&mut self) {
+        let fitted = self.fitted();
+        self.settings.filmstrip = !self.settings.filmstrip;
+        // the image gets more or less room, keep it fit if it was
+        if fitted {
+            self.best_fit();
+        }
+    }
+
+    pub fn filmstrip_ui(&mut self, ctx: &egui::Context) {
+        if self.filmstrip_size() == 0.0 {
+            self.filmstrip.textures.clear();
+            self.filmstrip.dragging = None;
+            return;
+        }
+        let (paths, current) = match (
+            self.op_queue.image_list.paths(),
+            self.op_queue.image_list.position(),
+        ) {
+            (Some(paths), Some((current, _))) => (paths, current),
+            _ => return,
+        };
+        // a scratch entry is shown, it is not in the folder
+        let current = self.scratch.position().is_none().then_some(current);
+
+        let mut clicked: Option<PathBuf> = None;
+        let mut moved: Option<(usize, usize)> = None;
+        TopBottomPanel::bottom("filmstrip")
+            .height_range(FILMSTRIP_SIZE..=FILMSTRIP_SIZE)
+            .show(ctx, |ui| {
+                ui.set_enabled(self.view_available() && !self.crop.cropping);
+                let step = CELL + GAP;
+                ScrollArea::horizontal()
+                    .auto_shrink([false, false])
+                    .show_viewport(ui, |ui, viewport| {
+                        ui.set_width(step * paths.len() as f32);
+                        let origin = ui.max_rect().min + vec2(GAP / 2.0, GAP);
+                        let cell = |index: usize| {
+                            Rect::from_min_size(
+                                origin + vec2(index as f32 * step, 0.0),
+                                vec2(CELL, CELL),
+                            )
+                        };
+
+                        if current != self.filmstrip.scrolled_to {
+                            if let Some(current) = current {
+                                ui.scroll_to_rect(cell(current), Some(Align::Center));
+                            }
+                            self.filmstrip.scrolled_to = current;
+                        }
+
+                        // the cell under the pointer while a thumbnail is dragged
+                        let drop_target = self.filmstrip.dragging.and_then(|_| {
+                            let x = ui.input().pointer.interact_pos()?.x - origin.x;
+                            let last = paths.len().saturating_sub(1);
+                            Some(((x / step).floor().max(0.0) as usize).min(last))
+                        });
+
+                        let first = (viewport.min.x / step).floor().max(0.0) as usize;
+                        let last = ((viewport.max.x / step).ceil() as usize + 1).min(paths.len());
+                        for (index, path) in paths.iter().enumerate().take(last).skip(first) {
+                            let rect = cell(index);
+                            let response = ui
+                                .allocate_rect(rect, Sense::click_and_drag())
+                                .on_hover_text(display_name(path));
+                            if response.drag_started() {
+                                self.filmstrip.dragging = Some(index);
+                            }
+                            let texture =
+                                self.filmstrip
+                                    .textures
+                                    .get(ctx, &self.op_queue.thumbnails, path);
+                            paint_cell(
+                                ui,
+                                rect,
+                                texture.as_ref(),
+                                Some(index) == current,
+                                response.hovered() || Some(index) == drop_target,
+                            );
+                            if response.clicked() && Some(index) != current {
+                                clicked = Some(path.clone());
+                            }
+                        }
+
+                        if ui.input().pointer.any_released() {
+                            if let Some(from) = self.filmstrip.dragging.take() {
+                                moved = drop_target.map(|to| (from, to));
+                            }
+                        }
+                    });
+            });
+        self.filmstrip.textures.end_frame();
+
+        if let Some((from, to)) = moved {
+            self.move_entry(from, to);
+        }
+        if let Some(path) = clicked {
+            self.queue(Op::LoadPath(path, true));
+        }
+    }
+}
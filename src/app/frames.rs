@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use egui::{DragValue, RichText, ScrollArea};
+use glium::Display;
+
+use super::{i18n::tr, op_queue::Op, App};
+use crate::util::Image;
+
+/// Shortest and longest frame delay the panel offers, in milliseconds.
+const DELAY_RANGE: std::ops::RangeInclusive<u32> = 10..=60_000;
+
+/// A change to the frames of an animation, undone like any other edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEdit {
+    Delete(usize),
+    /// Takes the frame out at `from` and puts it back in at `to`.
+    Move {
+        from: usize,
+        to: usize,
+    },
+    Delay(usize, Duration),
+}
+
+impl FrameEdit {
+    /// Whether the edit changes an animation of `count` frames. Edits that point past the
+    /// end do not, and neither does deleting the only frame since an animation keeps at
+    /// least one.
+    pub fn applies_to(self, count: usize) -> bool {
+        match self {
+            FrameEdit::Delete(index) => index < count && count > 1,
+            FrameEdit::Move { from, to } => from < count && to < count,
+            FrameEdit::Delay(index, _) => index < count,
+        }
+    }
+
+    /// Applies the edit to `frames`, edits that don't apply to them change nothing.
+    pub fn apply(self, frames: &mut Vec<Image>) {
+        if !self.applies_to(frames.len()) {
+            return;
+        }
+        match self {
+            FrameEdit::Delete(index) => {
+                frames.remove(index);
+            }
+            FrameEdit::Move { from, to } => {
+                let frame = frames.remove(from);
+                frames.insert(to, frame);
+            }
+            FrameEdit::Delay(index, delay) => frames[index].delay = delay,
+        }
+    }
+}
+
+/// The panel that lists the frames of an animation.
+#[derive(Default)]
+pub struct FramesPanel {
+    pub visible: bool,
+    /// The frame whose delay is being dragged or typed, with the delay in milliseconds. It is
+    /// only applied once the field is let go so a drag is a single edit.
+    editing: Option<(usize, u32)>,
+}
+
+impl App {
+    pub fn frames_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        if !self.frames.visible || !self.animated() {
+            self.frames.editing = None;
+            return;
+        }
+
+        let view = self.image_view.as_mut().unwrap();
+        let delays: Vec<Duration> = view
+            .image_data
+            .read()
+            .unwrap()
+            .frames
+            .iter()
+            .map(|frame| frame.delay)
+            .collect();
+        let count = delays.len();
+        let enabled = !self.op_queue.working();
+
+        let mut edit = None;
+        let mut show = None;
+        let mut open = true;
+        egui::Window::new(tr("Frames"))
+            .id(egui::Id::new("frames window"))
+            .collapsible(false)
+            .default_width(260.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let total: Duration = delays.iter().sum();
+                ui.label(format!(
+                    "{} frames, {:.2} s per loop",
+                    count,
+                    total.as_secs_f32()
+                ));
+                ui.separator();
+
+                let row_height = ui.spacing().interact_size.y;
+                ScrollArea::vertical().auto_shrink([false, true]).show_rows(
+                    ui,
+                    row_height,
+                    count,
+                    |ui, rows| {
+                        for index in rows {
+                            ui.horizontal(|ui| {
+                                let text = RichText::new(format!("{:>4}", index + 1)).monospace();
+                                if ui
+                                    .selectable_label(index == view.index, text)
+                                    .on_hover_text("Show this frame")
+                                    .clicked()
+                                {
+                                    show = Some(index);
+                                }
+
+                                ui.add_enabled_ui(enabled, |ui| {
+                                    let mut delay = match self.frames.editing {
+                                        Some((editing, delay)) if editing == index => delay,
+                                        _ => delays[index].as_millis() as u32,
+                                    };
+                                    let response = ui.add(
+                                        DragValue::new(&mut delay)
+                                            .clamp_range(DELAY_RANGE)
+                                            .suffix(" ms"),
+                                    );
+                                    if response.changed() {
+                                        self.frames.editing = Some((index, delay));
+                                    }
+                                    if response.drag_released() || response.lost_focus() {
+                                        if let Some((index, delay)) = self.frames.editing.take() {
+                                            let delay = Duration::from_millis(delay as u64);
+                                            edit = Some(FrameEdit::Delay(index, delay));
+                                        }
+                                    }
+
+                                    if ui
+                                        .add_enabled(index > 0, egui::Button::new("⏶").small())
+                                        .on_hover_text("Move earlier")
+                                        .clicked()
+                                    {
+                                        edit = Some(FrameEdit::Move {
+                                            from: index,
+                                            to: index - 1,
+                                        });
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            index + 1 < count,
+                                            egui::Button::new("⏷").small(),
+                                        )
+                                        .on_hover_text("Move later")
+                                        .clicked()
+                                    {
+                                        edit = Some(FrameEdit::Move {
+                                            from: index,
+                                            to: index + 1,
+                                        });
+                                    }
+                                    if ui
+                                        .add_enabled(count > 1, egui::Button::new("🗑").small())
+                                        .on_hover_text("Delete this frame")
+                                        .clicked()
+                                    {
+                                        edit = Some(FrameEdit::Delete(index));
+                                    }
+                                });
+                            });
+                        }
+                    },
+                );
+            });
+
+        if let Some(index) = show {
+            view.step_frame(display, index as isize - view.index as isize);
+        }
+        if !open {
+            self.frames.visible = false;
+        }
+        if let Some(edit) = edit.filter(|edit| edit.applies_to(count)) {
+            self.queue(Op::EditFrames(edit));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::*;
+
+    fn frames(count: u32) -> Vec<Image> {
+        (1..=count)
+            .map(|width| {
+                Image::with_delay(
+                    DynamicImage::new_rgba8(width, 1),
+                    Duration::from_millis(100),
+                )
+            })
+            .collect()
+    }
+
+    fn widths(frames: &[Image]) -> Vec<u32> {
+        frames.iter().map(|frame| frame.image.width()).collect()
+    }
+
+    #[test]
+    fn frames_are_deleted_moved_and_retimed() {
+        let mut edited = frames(4);
+        FrameEdit::Move { from: 0, to: 2 }.apply(&mut edited);
+        assert_eq!(widths(&edited), [2, 3, 1, 4]);
+        FrameEdit::Move { from: 3, to: 0 }.apply(&mut edited);
+        assert_eq!(widths(&edited), [4, 2, 3, 1]);
+
+        FrameEdit::Delete(1).apply(&mut edited);
+        assert_eq!(widths(&edited), [4, 3, 1]);
+
+        FrameEdit::Delay(2, Duration::from_millis(40)).apply(&mut edited);
+        assert_eq!(edited[2].delay, Duration::from_millis(40));
+        assert_eq!(edited[0].delay, Duration::from_millis(100));
+
+        // out of range edits and deleting the last frame change nothing
+        FrameEdit::Move { from: 1, to: 3 }.apply(&mut edited);
+        FrameEdit::Delete(3).apply(&mut edited);
+        assert_eq!(widths(&edited), [4, 3, 1]);
+        let mut single = frames(1);
+        assert!(!FrameEdit::Delete(0).applies_to(single.len()));
+        FrameEdit::Delete(0).apply(&mut single);
+        assert_eq!(single.len(), 1);
+    }
+}
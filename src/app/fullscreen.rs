@@ -0,0 +1,90 @@
+use glium::{
+    backend::glutin::Display,
+    glutin::{
+        dpi::{PhysicalPosition, PhysicalSize},
+        monitor::MonitorHandle,
+        window::{Fullscreen, Window},
+    },
+};
+
+use super::{App, BOTTOM_BAR_SIZE, TOP_BAR_SIZE};
+
+impl App {
+    pub fn toggle_fullscreen(&mut self, display: &Display) {
+        if self.fullscreen {
+            self.exit_fullscreen(display);
+        } else {
+            let window_context = display.gl_window();
+            let monitor = containing_monitor(window_context.window());
+            drop(window_context);
+            self.enter_fullscreen(display, monitor);
+        }
+    }
+
+    pub fn enter_fullscreen(&mut self, display: &Display, monitor: Option<MonitorHandle>) {
+        let window_context = display.gl_window();
+        let window = window_context.window();
+
+        // only remember the geometry when coming from windowed mode
+        // so switching monitors while fullscreen does not overwrite it
+        if window.fullscreen().is_none() {
+            if let Ok(position) = window.outer_position() {
+                self.windowed_geometry = Some((position, window.inner_size()));
+            }
+        }
+
+        window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+        self.fullscreen = true;
+        self.top_bar_size = 0.0;
+        self.bottom_bar_size = 0.0;
+    }
+
+    pub fn exit_fullscreen(&mut self, display: &Display) {
+        let window_context = display.gl_window();
+        let window = window_context.window();
+        window.set_fullscreen(None);
+        if let Some((position, size)) = self.windowed_geometry.take() {
+            window.set_inner_size(size);
+            window.set_outer_position(position);
+        }
+
+        self.fullscreen = false;
+        self.top_bar_size = TOP_BAR_SIZE;
+        self.bottom_bar_size = BOTTOM_BAR_SIZE;
+    }
+}
+
+/// Finds the monitor that contains the center of the window.
+fn containing_monitor(window: &Window) -> Option<MonitorHandle> {
+    let position = window.outer_position().ok()?;
+    let size = window.outer_size();
+    let center = PhysicalPosition::new(
+        position.x + size.width as i32 / 2,
+        position.y + size.height as i32 / 2,
+    );
+
+    window
+        .available_monitors()
+        .find(|monitor| contains(monitor.position(), monitor.size(), center))
+        .or_else(|| window.current_monitor())
+}
+
+fn contains(
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    point: PhysicalPosition<i32>,
+) -> bool {
+    point.x >= position.x
+        && point.y >= position.y
+        && point.x < position.x + size.width as i32
+        && point.y < position.y + size.height as i32
+}
+
+/// A human readable label for a monitor like "DP-1 (2560 x 1440)".
+pub fn monitor_label(monitor: &MonitorHandle) -> String {
+    let size = monitor.size();
+    let name = monitor
+        .name()
+        .unwrap_or_else(|| String::from("Unknown monitor"));
+    format!("{} ({} x {})", name, size.width, size.height)
+}
@@ -0,0 +1,165 @@
+use std::path::PathBuf;
+
+use egui::{vec2, Align, Align2, Rect, ScrollArea, Sense, TextStyle};
+
+use super::{
+    op_queue::Op,
+    thumbnails::{paint_cell, ThumbnailTextures},
+    App,
+};
+use crate::util::display_name;
+
+/// The side of a thumbnail in points.
+const CELL: f32 = 128.0;
+const GAP: f32 = 8.0;
+/// Room for the file name under a thumbnail.
+const LABEL: f32 = 18.0;
+
+/// A grid of thumbnails of the folder in place of the image.
+#[derive(Default)]
+pub struct Gallery {
+    pub visible: bool,
+    textures: ThumbnailTextures,
+    /// The image the grid was last scrolled to, it follows when another one is shown.
+    scrolled_to: Option<usize>,
+}
+
+/// How many cells of `step` fit next to each other in `width`, at least one.
+fn columns(width: f32, step: f32) -> usize {
+    ((width / step).floor() as usize).max(1)
+}
+
+impl App {
+    pub fn gallery_visible(&self) -> bool {
+        self.gallery.visible
+    }
+
+    pub fn toggle_gallery(&mut self) {
+        if self.gallery.visible {
+            self.close_gallery();
+        } else if self.op_queue.image_list.position().is_some() && !self.crop.cropping {
+            self.gallery.visible = true;
+            self.gallery.scrolled_to = None;
+        }
+    }
+
+    fn close_gallery(&mut self) {
+        let fitted = self.fitted();
+        self.gallery.visible = false;
+        // the filmstrip is back and takes room from the image
+        if fitted {
+            self.best_fit();
+        }
+    }
+
+    /// Draws the grid in the central panel. Returns false when there is no folder to show,
+    /// the image is shown then.
+    pub fn gallery_ui(&mut self, ctx: &egui::Context) -> bool {
+        let (paths, current) = match (
+            self.op_queue.image_list.paths(),
+            self.op_queue.image_list.position(),
+        ) {
+            (Some(paths), Some((current, _))) if self.gallery.visible => (paths, current),
+            _ => {
+                self.gallery.visible = false;
+                self.gallery.textures.clear();
+                return false;
+            }
+        };
+        let current = self.scratch.position().is_none().then_some(current);
+
+        // the path and whether to go back to the image
+        let mut open: Option<(PathBuf, bool)> = None;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let step = vec2(CELL + GAP, CELL + LABEL + GAP);
+            let columns = columns(ui.available_width(), step.x);
+            let rows = paths.len().div_ceil(columns);
+            ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .show_viewport(ui, |ui, viewport| {
+                    ui.set_height(step.y * rows as f32);
+                    let origin = ui.max_rect().min + vec2(GAP / 2.0, GAP / 2.0);
+                    let cell = |index: usize| {
+                        let (row, column) = (index / columns, index % columns);
+                        Rect::from_min_size(
+                            origin + vec2(column as f32 * step.x, row as f32 * step.y),
+                            vec2(CELL, CELL),
+                        )
+                    };
+
+                    if current != self.gallery.scrolled_to {
+                        if let Some(current) = current {
+                            ui.scroll_to_rect(cell(current), Some(Align::Center));
+                        }
+                        self.gallery.scrolled_to = current;
+                    }
+
+                    let first_row = (viewport.min.y / step.y).floor().max(0.0) as usize;
+                    let last_row = (viewport.max.y / step.y).ceil() as usize + 1;
+                    let visible = (first_row * columns)..(last_row * columns).min(paths.len());
+                    for index in visible {
+                        let path = &paths[index];
+                        let rect = cell(index);
+                        let name = display_name(path);
+                        let response = ui
+                            .allocate_rect(
+                                rect.union(rect.translate(vec2(0.0, LABEL))),
+                                Sense::click(),
+                            )
+                            .on_hover_text(&name);
+                        let texture =
+                            self.gallery
+                                .textures
+                                .get(ctx, &self.op_queue.thumbnails, path);
+                        paint_cell(
+                            ui,
+                            rect,
+                            texture.as_ref(),
+                            Some(index) == current,
+                            response.hovered(),
+                        );
+                        let label = Rect::from_min_size(rect.left_bottom(), vec2(CELL, LABEL));
+                        ui.painter().sub_region(label).text(
+                            label.center(),
+                            Align2::CENTER_CENTER,
+                            name,
+                            TextStyle::Small.resolve(ui.style()),
+                            ui.visuals().text_color(),
+                        );
+
+                        if response.double_clicked() {
+                            open = Some((path.clone(), true));
+                        } else if response.clicked() {
+                            open = Some((path.clone(), false));
+                        }
+                    }
+                });
+        });
+        self.gallery.textures.end_frame();
+
+        if let Some((path, close)) = open {
+            let index = paths.iter().position(|other| *other == path);
+            // the first click of a double click may still be loading it
+            if index != current && !self.op_queue.working() {
+                self.queue(Op::LoadPath(path, true));
+            }
+            if close {
+                self.close_gallery();
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn at_least_one_column_fits() {
+        assert_eq!(columns(1000.0, 136.0), 7);
+        assert_eq!(columns(136.0, 136.0), 1);
+        assert_eq!(columns(50.0, 136.0), 1);
+        assert_eq!(columns(0.0, 136.0), 1);
+    }
+}
@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+
+use glium::{
+    backend::{glutin::Display, Facade},
+    framebuffer::SimpleFrameBuffer,
+    index::{NoIndices, PrimitiveType},
+    program::Program,
+    texture::{MipmapsOption, RawImage2d, SrgbFormat, SrgbTexture2d},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler},
+    CapabilitiesSource, Surface, VertexBuffer,
+};
+use image::{imageops::FilterType, DynamicImage, ImageBuffer, Rgba};
+
+use super::image_view::Vertex;
+use crate::vec2::Vec2;
+
+/// Resizes textures by drawing them into a framebuffer of the target size. Much faster than
+/// resizing on the cpu but not identical to it, so it is only used for previews.
+pub struct GpuResize {
+    shader: Program,
+    vertices: VertexBuffer<Vertex>,
+}
+
+impl GpuResize {
+    pub fn new(display: &Display) -> Option<Self> {
+        let shader = Program::from_source(
+            display,
+            include_str!("../shader/downscale.vert"),
+            include_str!("../shader/downscale.frag"),
+            None,
+        )
+        .ok()?;
+
+        let shape = [
+            Vertex::new(-1.0, -1.0, 0.0, 0.0),
+            Vertex::new(-1.0, 1.0, 0.0, 1.0),
+            Vertex::new(1.0, -1.0, 1.0, 0.0),
+            Vertex::new(1.0, 1.0, 1.0, 1.0),
+        ];
+        let vertices = VertexBuffer::new(display, &shape).ok()?;
+
+        Some(Self { shader, vertices })
+    }
+
+    /// The longest side a texture can have.
+    pub fn max_side(display: &Display) -> u32 {
+        display.get_context().get_capabilities().max_texture_size as u32
+    }
+
+    /// Whether a texture of this size can be created at all.
+    pub fn fits(display: &Display, size: Vec2<u32>) -> bool {
+        let max = Self::max_side(display);
+        size.x() > 0 && size.y() > 0 && size.x() <= max && size.y() <= max
+    }
+
+    /// Renders `texture` at `size` and reads the result back. Nearest and triangle use the
+    /// mipmaps, the other filters a cubic kernel. Returns `None` if anything on the gpu fails.
+    pub fn resize(
+        &self,
+        display: &Display,
+        texture: &SrgbTexture2d,
+        size: Vec2<u32>,
+        filter: FilterType,
+    ) -> Option<DynamicImage> {
+        if !Self::fits(display, size) {
+            return None;
+        }
+
+        let target = SrgbTexture2d::empty_with_format(
+            display,
+            SrgbFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+            size.x(),
+            size.y(),
+        )
+        .ok()?;
+
+        let ratio = (texture.width() as f32 / size.x() as f32)
+            .max(texture.height() as f32 / size.y() as f32);
+        let lod = ratio.log2().max(0.0);
+        let cubic = !matches!(filter, FilterType::Nearest | FilterType::Triangle);
+        let sampler = Sampler::new(texture)
+            .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+            .magnify_filter(MagnifySamplerFilter::Linear);
+
+        {
+            let mut framebuffer = SimpleFrameBuffer::new(display, &target).ok()?;
+            framebuffer.clear_color(0.0, 0.0, 0.0, 0.0);
+            framebuffer
+                .draw(
+                    &self.vertices,
+                    NoIndices(PrimitiveType::TriangleStrip),
+                    &self.shader,
+                    &uniform! { tex: sampler, lod: lod, cubic: cubic },
+                    &Default::default(),
+                )
+                .ok()?;
+        }
+
+        let raw: RawImage2d<'_, u8> = target.read();
+        let data = match raw.data {
+            Cow::Borrowed(data) => data.to_vec(),
+            Cow::Owned(data) => data,
+        };
+        // the rows come back in the same order they were uploaded in so no flip is needed
+        let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(raw.width, raw.height, data)?;
+        Some(DynamicImage::ImageRgba8(buffer))
+    }
+}
+
+/// `size` shrunk to have no side longer than `max`, keeping the aspect ratio.
+pub fn fit_within(size: Vec2<u32>, max: u32) -> Vec2<u32> {
+    let longest = size.x().max(size.y());
+    if longest <= max {
+        return size;
+    }
+    let scale = max as f64 / longest as f64;
+    Vec2::new(
+        ((size.x() as f64 * scale).round() as u32).clamp(1, max),
+        ((size.y() as f64 * scale).round() as u32).clamp(1, max),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_keep_their_aspect_ratio() {
+        assert_eq!(fit_within(Vec2::new(800, 600), 1024), Vec2::new(800, 600));
+        assert_eq!(
+            fit_within(Vec2::new(40000, 10000), 16384),
+            Vec2::new(16384, 4096)
+        );
+        assert_eq!(fit_within(Vec2::new(1, 100000), 1000), Vec2::new(1, 1000));
+    }
+}
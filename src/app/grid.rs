@@ -0,0 +1,88 @@
+use egui::{pos2, Color32, Stroke};
+
+use super::App;
+use crate::vec2::Vec2;
+
+/// Lines closer than this many points on screen are left out, they would hide the image.
+const MIN_GAP: f32 = 4.0;
+
+/// The multiples of `spacing` between `start` and `end` that lie on an image `limit` long.
+fn positions(start: f32, end: f32, limit: f32, spacing: f32) -> impl Iterator<Item = f32> {
+    let first = (start.max(0.0) / spacing).ceil() as u32;
+    let last = (end.min(limit) / spacing).floor() as u32;
+    (first..=last).map(move |index| index as f32 * spacing)
+}
+
+impl App {
+    /// Lines every `grid_spacing` pixels of the image, drawn over the part in view.
+    pub fn grid_ui(&self, ui: &egui::Ui) {
+        if !self.settings.grid {
+            return;
+        }
+        let view = match &self.image_view {
+            Some(view) => view,
+            None => return,
+        };
+        let ppp = self.pixels_per_point();
+        let size = view.true_size();
+        let spacing = self.settings.grid_spacing as f32;
+        let gap = spacing * view.scaled().x() / size.x() / ppp;
+        if gap < MIN_GAP {
+            return;
+        }
+
+        // the part of the image in view, turned back into image space
+        let area = ui.max_rect();
+        let corners = [
+            area.left_top(),
+            area.right_top(),
+            area.left_bottom(),
+            area.right_bottom(),
+        ]
+        .map(|corner| view.screen_to_image(Vec2::new(corner.x * ppp, corner.y * ppp)));
+        let min = corners
+            .iter()
+            .fold(Vec2::new(f32::MAX, f32::MAX), |min, corner| {
+                Vec2::new(min.x().min(corner.x()), min.y().min(corner.y()))
+            });
+        let max = corners
+            .iter()
+            .fold(Vec2::new(f32::MIN, f32::MIN), |max, corner| {
+                Vec2::new(max.x().max(corner.x()), max.y().max(corner.y()))
+            });
+
+        let [r, g, b] = self.settings.grid_color;
+        let stroke = Stroke::new(1.0, Color32::from_rgba_unmultiplied(r, g, b, 160));
+        let painter = ui.painter();
+        let line = |from: Vec2<f32>, to: Vec2<f32>| {
+            let (from, to) = (view.image_to_screen(from), view.image_to_screen(to));
+            painter.line_segment(
+                [
+                    pos2(from.x() / ppp, from.y() / ppp),
+                    pos2(to.x() / ppp, to.y() / ppp),
+                ],
+                stroke,
+            );
+        };
+        for x in positions(min.x(), max.x(), size.x(), spacing) {
+            line(Vec2::new(x, 0.0), Vec2::new(x, size.y()));
+        }
+        for y in positions(min.y(), max.y(), size.y(), spacing) {
+            line(Vec2::new(0.0, y), Vec2::new(size.x(), y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_lines_in_view() {
+        let lines: Vec<_> = positions(-5.0, 30.0, 100.0, 8.0).collect();
+        assert_eq!(lines, [0.0, 8.0, 16.0, 24.0]);
+        let lines: Vec<_> = positions(9.0, 500.0, 20.0, 10.0).collect();
+        assert_eq!(lines, [10.0, 20.0]);
+        assert_eq!(positions(3.0, 7.0, 100.0, 8.0).count(), 0);
+    }
+}
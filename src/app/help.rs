@@ -1,11 +1,11 @@
 use egui::RichText;
 
-use super::App;
+use super::{action::Action, i18n::tr, App};
 impl App {
     pub fn help_ui(&mut self, ctx: &egui::Context) {
         if self.help_visible {
             let mut open = true;
-            egui::Window::new("Help")
+            egui::Window::new(tr("Help"))
                 .id(egui::Id::new("help window"))
                 .collapsible(false)
                 .resizable(false)
@@ -15,37 +15,25 @@ impl App {
                         .striped(true)
                         .min_col_width(180.0)
                         .show(ui, |ui| {
-                            const HELP: &[(&str, &str)] = &[
-                                ("Open image", "Ctrl + O"),
-                                ("Save as", "Ctrl + S"),
-                                ("Reload image", "F5"),
-                                ("Close image", "Ctrl + F4"),
-                                ("New window", "Ctrl + N"),
-                                ("Undo", "Ctrl + Z"),
-                                ("Redo", "Ctrl + Y"),
-                                ("Copy", "Ctrl + C"),
-                                ("Paste", "Ctrl + V"),
-                                ("Resize", "Ctrl + R"),
-                                ("Rotate left", "Q"),
-                                ("Rotate right", "E"),
-                                ("Zoom in", "- or Mousewheel up"),
-                                ("Zoom out", "+ or Mousewheel down"),
-                                ("Best fit", "B"),
-                                ("Largest fit", "F"),
-                                ("Crop", "Ctrl + X"),
-                                ("F11", "Fullscreen"),
-                                ("Delete image", "Delete"),
-                                ("1 - 9", "100% - 900% Zoom"),
-                            ];
-
-                            ui.label(RichText::new("Action").strong());
-                            ui.label(RichText::new("Hotkey").strong());
+                            ui.label(RichText::new(tr("Action")).strong());
+                            ui.label(RichText::new(tr("Hotkey")).strong());
                             ui.end_row();
-                            for (action, hotkey) in HELP {
-                                ui.label(*action);
-                                ui.label(*hotkey);
-                                ui.end_row();
+                            for action in Action::ALL {
+                                if let Some(hotkey) = self.keymap.hotkey(*action) {
+                                    ui.label(tr(action.name()));
+                                    ui.label(hotkey);
+                                    ui.end_row();
+                                }
                             }
+                            ui.label(tr("100% - 900% Zoom"));
+                            ui.label("1 - 9");
+                            ui.end_row();
+                            ui.label(tr("Compare with original"));
+                            ui.label(tr("Hold \\"));
+                            ui.end_row();
+                            ui.label(tr("Rotate by dragging"));
+                            ui.label(tr("Hold R and drag, Esc cancels"));
+                            ui.end_row();
                         });
                 });
             self.help_visible = open;
@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+/// The language of the interface. The English text is what gets looked up, so anything a
+/// translation lacks is shown in English.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    /// Follows the language of the desktop.
+    #[default]
+    System,
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::System, Language::English, Language::German];
+
+    /// Each language is named in itself, so it can be found by someone who can not read the
+    /// current one.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::System => "System",
+            Language::English => "English",
+            Language::German => "Deutsch",
+        }
+    }
+
+    /// The language of a locale like `de_DE.UTF-8` or `de-AT`, English when there is no
+    /// translation for it.
+    fn from_locale(locale: &str) -> Language {
+        let code = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default();
+        match code.to_ascii_lowercase().as_str() {
+            "de" => Language::German,
+            _ => Language::English,
+        }
+    }
+
+    fn resolve(self) -> Language {
+        match self {
+            Language::System => *SYSTEM_LANGUAGE,
+            language => language,
+        }
+    }
+
+    fn table(self) -> Option<&'static HashMap<&'static str, &'static str>> {
+        match self {
+            Language::German => Some(&GERMAN),
+            _ => None,
+        }
+    }
+}
+
+lazy_static! {
+    /// Asked once, what the system language resolves to.
+    static ref SYSTEM_LANGUAGE: Language = system_locale()
+        .map(|locale| Language::from_locale(&locale))
+        .unwrap_or(Language::English);
+    static ref GERMAN: HashMap<&'static str, &'static str> =
+        parse(include_str!("../../locales/de.txt"));
+}
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Lines of `English text = translated text`, empty lines and lines starting with `#` are
+/// skipped.
+fn parse(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(english, translated)| (english.trim(), translated.trim()))
+        .collect()
+}
+
+/// The first locale set in the environment, unix desktops hand their language down this way.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Apps started from the Finder get no `LANG`, the locale is in the global defaults.
+#[cfg(target_os = "macos")]
+fn system_locale() -> Option<String> {
+    let output = std::process::Command::new("defaults")
+        .args(["read", "-g", "AppleLocale"])
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|locale| !locale.is_empty())
+}
+
+#[cfg(windows)]
+fn system_locale() -> Option<String> {
+    use winapi::um::{winnls::GetUserDefaultLocaleName, winnt::LOCALE_NAME_MAX_LENGTH};
+
+    let mut buffer = [0u16; LOCALE_NAME_MAX_LENGTH];
+    let len = unsafe { GetUserDefaultLocaleName(buffer.as_mut_ptr(), buffer.len() as i32) };
+    // the length counts the terminating null
+    (len > 1).then(|| String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+/// Sets the language `tr` translates to, cheap enough to call every frame.
+pub fn set_language(language: Language) {
+    let index = Language::ALL
+        .iter()
+        .position(|other| *other == language.resolve())
+        .unwrap();
+    CURRENT.store(index, Ordering::Relaxed);
+}
+
+fn lookup(language: Language, text: &'static str) -> &'static str {
+    language
+        .table()
+        .and_then(|table| table.get(text).copied())
+        .unwrap_or(text)
+}
+
+/// `text` in the current language.
+pub fn tr(text: &'static str) -> &'static str {
+    lookup(Language::ALL[CURRENT.load(Ordering::Relaxed)], text)
+}
+
+/// `text` in the current language with each `{}` replaced by the next of `args`.
+pub fn trf(text: &'static str, args: &[&dyn Display]) -> String {
+    fill(tr(text), args)
+}
+
+fn fill(text: &str, args: &[&dyn Display]) -> String {
+    let mut parts = text.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::action::Action;
+
+    #[test]
+    fn locales_pick_a_language() {
+        assert_eq!(Language::from_locale("de_DE.UTF-8"), Language::German);
+        assert_eq!(Language::from_locale("de-AT"), Language::German);
+        assert_eq!(Language::from_locale("DE"), Language::German);
+        assert_eq!(Language::from_locale("en_US.UTF-8"), Language::English);
+        assert_eq!(Language::from_locale("C"), Language::English);
+        assert_eq!(Language::from_locale(""), Language::English);
+    }
+
+    #[test]
+    fn missing_text_stays_english() {
+        assert_eq!(lookup(Language::German, "Open"), "Öffnen");
+        assert_eq!(lookup(Language::German, "not translated"), "not translated");
+        assert_eq!(lookup(Language::English, "Open"), "Open");
+        assert_eq!(
+            fill("Save as {} or {}", &[&"a.png", &2]),
+            "Save as a.png or 2"
+        );
+        assert_eq!(fill("{} MP", &[]), " MP");
+    }
+
+    #[test]
+    fn translations_are_complete() {
+        for (english, german) in GERMAN.iter() {
+            assert_eq!(
+                english.matches("{}").count(),
+                german.matches("{}").count(),
+                "{}",
+                english
+            );
+        }
+        for action in Action::ALL {
+            assert!(GERMAN.contains_key(action.name()), "{}", action.name());
+        }
+    }
+
+    #[test]
+    fn dialogs_have_no_untranslated_text() {
+        // every window, label and button text in these goes through `tr`
+        const SOURCES: &[(&str, &str)] = &[
+            ("batch.rs", include_str!("batch.rs")),
+            ("contact_sheet.rs", include_str!("contact_sheet.rs")),
+            ("crop.rs", include_str!("crop.rs")),
+            ("crop_preset.rs", include_str!("crop_preset.rs")),
+            ("help.rs", include_str!("help.rs")),
+            ("import_frames.rs", include_str!("import_frames.rs")),
+            ("keymap.rs", include_str!("keymap.rs")),
+            ("levels.rs", include_str!("levels.rs")),
+            ("log_window.rs", include_str!("log_window.rs")),
+            ("oversized.rs", include_str!("oversized.rs")),
+            ("preferences.rs", include_str!("preferences.rs")),
+            ("rotate.rs", include_str!("rotate.rs")),
+            ("save_check.rs", include_str!("save_check.rs")),
+            ("save_conflict.rs", include_str!("save_conflict.rs")),
+        ];
+        const CALLS: &[&str] = &[
+            "Window::new(",
+            ".label(",
+            ".button(",
+            ".small_button(",
+            "Button::new(",
+            ".on_hover_text(",
+            ".hint_text(",
+        ];
+        for (name, source) in SOURCES {
+            for call in CALLS {
+                for (start, _) in source.match_indices(call) {
+                    let argument = source[start + call.len()..].trim_start();
+                    // text without letters, like empty grid cells and key ranges, needs none
+                    let literal = argument
+                        .strip_prefix('"')
+                        .and_then(|rest| rest.split('"').next())
+                        .unwrap_or_default();
+                    assert!(
+                        !literal.chars().any(char::is_alphabetic),
+                        "{}: {}\"{}\"",
+                        name,
+                        call,
+                        literal
+                    );
+                }
+            }
+        }
+    }
+}
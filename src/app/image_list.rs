@@ -1,4 +1,5 @@
 use std::{
+    fs, io,
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -6,24 +7,64 @@ use std::{
         Arc, Mutex,
     },
     thread,
+    time::SystemTime,
 };
 
-use glium::glutin::event_loop::EventLoopProxy;
+use serde::{Deserialize, Serialize};
 
 use super::op_queue::{prefetch, LoadingInfo, Output};
 use crate::{
     app::cache::Cache,
-    util::{extensions::*, UserEvent},
+    image_io::{load::probe_file, video},
+    util::{extensions::*, Proxy},
 };
 
-type List = Arc<Mutex<Option<Vec<PathBuf>>>>;
+/// The paths are shared with whoever draws them, changing them copies them only while
+/// such a snapshot is still around.
+type List = Arc<Mutex<Option<Arc<Vec<PathBuf>>>>>;
+
+/// The order the images of a folder are browsed in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sort {
+    Name,
+    #[default]
+    NameReversed,
+    Newest,
+    Oldest,
+}
+
+impl Sort {
+    pub const ALL: [Sort; 4] = [Sort::Name, Sort::NameReversed, Sort::Newest, Sort::Oldest];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sort::Name => "Name, A to Z",
+            Sort::NameReversed => "Name, Z to A",
+            Sort::Newest => "Newest first",
+            Sort::Oldest => "Oldest first",
+        }
+    }
+}
+
+/// Which files are listed besides the ones with an image extension, and in which order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Listing {
+    /// Files without an extension, they are probed when reached.
+    pub extensionless: bool,
+    /// Video files, they are shown as a still. Needs the `video` feature.
+    pub videos: bool,
+    pub sort: Sort,
+}
 
 pub struct ImageList {
     list: List,
     index: Arc<AtomicUsize>,
     path: Option<PathBuf>,
+    /// The list was given explicitly or reordered by hand so the directory is not rescanned.
+    explicit: bool,
+    listing: Listing,
     cache: Arc<Cache>,
-    proxy: EventLoopProxy<UserEvent>,
+    proxy: Proxy,
     sender: Sender<Output>,
     loading_info: Arc<Mutex<LoadingInfo>>,
 }
@@ -31,7 +72,7 @@ pub struct ImageList {
 impl ImageList {
     pub fn new(
         cache: Arc<Cache>,
-        proxy: EventLoopProxy<UserEvent>,
+        proxy: Proxy,
         sender: Sender<Output>,
         loading_info: Arc<Mutex<LoadingInfo>>,
     ) -> Self {
@@ -39,6 +80,8 @@ impl ImageList {
             list: Arc::new(Mutex::new(None)),
             index: Arc::new(AtomicUsize::new(0)),
             path: None,
+            explicit: false,
+            listing: Listing::default(),
             proxy,
             cache,
             sender,
@@ -46,14 +89,145 @@ impl ImageList {
         }
     }
 
+    pub fn listing(&self) -> Listing {
+        self.listing
+    }
+
+    /// Takes effect the next time a directory is scanned.
+    pub fn set_listing(&mut self, listing: Listing) {
+        if self.listing != listing {
+            self.listing = listing;
+            // forget the directory so it is scanned again
+            self.path = None;
+        }
+    }
+
     pub fn clear(&mut self) {
         *self.list.lock().unwrap() = None;
         self.path = None;
+        self.explicit = false;
         self.index.store(0, Ordering::SeqCst)
     }
 
+    /// Replaces the directory listing with an exact list of images, for example from a playlist.
+    pub fn set_explicit(&mut self, list: Vec<PathBuf>) {
+        *self.list.lock().unwrap() = Some(Arc::new(list));
+        self.path = None;
+        self.explicit = true;
+        self.index.store(0, Ordering::SeqCst);
+    }
+
+    /// The position of the current image in the list and the length of the list.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        let lock = self.list.lock().unwrap();
+        lock.as_ref()
+            .map(|list| (self.index.load(Ordering::SeqCst), list.len()))
+    }
+
+    /// A snapshot of the list, cheap enough to take every frame.
+    pub fn paths(&self) -> Option<Arc<Vec<PathBuf>>> {
+        self.list.lock().unwrap().clone()
+    }
+
+    /// Moves the current image `offset` steps in the list, which makes the order manual.
+    /// Returns false if it is already at that end of the list.
+    pub fn move_current(&mut self, offset: isize) -> bool {
+        let index = self.index.load(Ordering::SeqCst);
+        let target = index as isize + offset;
+        target >= 0 && self.move_entry(index, target as usize)
+    }
+
+    /// Moves the image at `from` to `to`, which makes the order manual. The current image
+    /// stays the current one wherever it ends up. Returns false if nothing moved.
+    pub fn move_entry(&mut self, from: usize, to: usize) -> bool {
+        let mut lock = self.list.lock().unwrap();
+        let list = match *lock {
+            Some(ref mut list) if from != to && from.max(to) < list.len() => Arc::make_mut(list),
+            _ => return false,
+        };
+
+        let item = list.remove(from);
+        list.insert(to, item);
+        let index = self.index.load(Ordering::SeqCst);
+        let index = if index == from {
+            to
+        } else if from < index && index <= to {
+            index - 1
+        } else if to <= index && index < from {
+            index + 1
+        } else {
+            index
+        };
+        self.index.store(index, Ordering::SeqCst);
+        self.explicit = true;
+        true
+    }
+
+    /// Removes `path` from the list and returns the image that takes the place of the current one.
+    pub fn remove(&mut self, path: &Path) -> Option<PathBuf> {
+        let mut lock = self.list.lock().unwrap();
+        if lock.is_none() {
+            // the folder is still being read, read it again now that the file is gone
+            drop(lock);
+            return self.rescan_without(path);
+        }
+        let list = Arc::make_mut(lock.as_mut().unwrap());
+        let removed = list.iter().position(|entry| entry == path)?;
+        list.remove(removed);
+        if list.is_empty() {
+            return None;
+        }
+
+        let mut index = self.index.load(Ordering::SeqCst);
+        if removed < index {
+            index -= 1;
+        }
+        if index >= list.len() {
+            index = 0;
+        }
+        self.index.store(index, Ordering::SeqCst);
+
+        Some(list[index].clone())
+    }
+
+    /// Lists the folder of the deleted `path` and returns the image that took its place.
+    fn rescan_without(&mut self, path: &Path) -> Option<PathBuf> {
+        let dir = path.parent()?;
+        let list = scan_dir(dir, self.listing).ok()?;
+        // a deleted file has no time to sort by, only by name it has a place to go back to
+        let index = match self.listing.sort {
+            Sort::Name => list.partition_point(|entry| entry.as_path() < path),
+            Sort::NameReversed => list.partition_point(|entry| entry.as_path() > path),
+            Sort::Newest | Sort::Oldest => 0,
+        };
+        let index = if index < list.len() { index } else { 0 };
+        let next = list.get(index).cloned();
+
+        self.path = Some(dir.to_path_buf());
+        self.index.store(index, Ordering::SeqCst);
+        *self.list.lock().unwrap() = Some(Arc::new(list));
+        next
+    }
+
     pub fn change_dir(&mut self, path: impl AsRef<Path>) {
         let path_buf = path.as_ref().to_path_buf();
+
+        if self.explicit {
+            let lock = self.list.lock().unwrap();
+            if let Some(index) = lock
+                .iter()
+                .flat_map(|list| list.iter())
+                .position(|path| *path == path_buf)
+            {
+                self.index.store(index, Ordering::SeqCst);
+                return;
+            }
+            drop(lock);
+            // an image from outside the list was opened so go back to the directory
+            self.explicit = false;
+            self.path = None;
+        }
+
         let mut dir_path = path_buf.clone();
         dir_path.pop();
 
@@ -79,28 +253,9 @@ impl ImageList {
         let cache = self.cache.clone();
         let loading_info = self.loading_info.clone();
         let sender = self.sender.clone();
+        let listing = self.listing;
         thread::spawn(move || {
-            let mut list = Vec::new();
-            let dirs = std::fs::read_dir(dir_path).unwrap();
-
-            for dir in dirs.flatten() {
-                if let Ok(file_type) = dir.file_type() {
-                    if file_type.is_file() {
-                        let path = dir.path();
-                        match dir.path().extension() {
-                            Some(ext)
-                                if EXTENSIONS
-                                    .contains(&*ext.to_string_lossy().to_ascii_lowercase()) =>
-                            {
-                                list.push(path)
-                            }
-                            _ => (),
-                        }
-                    }
-                }
-            }
-
-            list.sort_by(|a, b| b.cmp(a));
+            let list = scan_dir(&dir_path, listing).unwrap();
 
             for (index, path) in list.iter().enumerate() {
                 if *path == path_buf {
@@ -126,49 +281,71 @@ impl ImageList {
                 loading_info.clone(),
             );
 
-            *t_list.lock().unwrap() = Some(list);
+            *t_list.lock().unwrap() = Some(Arc::new(list));
         });
     }
 
-    pub fn next(&mut self) -> Option<PathBuf> {
+    /// The image `next` or `prev` moves to, without probing files on the way.
+    pub fn neighbour(&self, forward: bool) -> Option<PathBuf> {
         let lock = self.list.lock().unwrap();
-        if let Some(ref list) = *lock {
-            self.index.fetch_add(1, Ordering::SeqCst);
-            if list.len() <= self.index.load(Ordering::SeqCst) {
-                self.index.store(0, Ordering::SeqCst);
-            }
-            prefetch(
-                list[next_index(self.index.load(Ordering::SeqCst), list.len())].clone(),
-                self.cache.clone(),
-                self.proxy.clone(),
-                self.sender.clone(),
-                self.loading_info.clone(),
-            );
-            Some(list[self.index.load(Ordering::SeqCst)].clone())
+        let list = lock.as_ref().filter(|list| !list.is_empty())?;
+        let index = self.index.load(Ordering::SeqCst);
+        let index = if forward {
+            next_index(index, list.len())
         } else {
-            None
-        }
+            prev_index(index, list.len())
+        };
+        list.get(index).cloned()
+    }
+
+    pub fn next(&mut self) -> Option<PathBuf> {
+        self.step(true)
     }
 
     pub fn prev(&mut self) -> Option<PathBuf> {
-        let lock = self.list.lock().unwrap();
-        if let Some(ref list) = *lock {
-            if self.index.load(Ordering::SeqCst) == 0 {
-                self.index.store(list.len() - 1, Ordering::SeqCst);
+        self.step(false)
+    }
+
+    /// Moves one image forward or back. Files without an extension that turn
+    /// out not to be images are dropped from the list on the way.
+    fn step(&mut self, forward: bool) -> Option<PathBuf> {
+        let advance = |index, len| {
+            if forward {
+                next_index(index, len)
             } else {
-                self.index.fetch_sub(1, Ordering::SeqCst);
+                prev_index(index, len)
             }
+        };
+
+        let mut lock = self.list.lock().unwrap();
+        let list = Arc::make_mut(lock.as_mut()?);
+        let mut index = advance(self.index.load(Ordering::SeqCst), list.len());
+        while !worth_loading(&list[index]) {
+            list.remove(index);
+            if list.is_empty() {
+                self.index.store(0, Ordering::SeqCst);
+                return None;
+            }
+            // going forward the next file slid into the removed spot
+            index = if forward {
+                index % list.len()
+            } else {
+                prev_index(index, list.len())
+            };
+        }
+        self.index.store(index, Ordering::SeqCst);
+
+        let neighbour = &list[advance(index, list.len())];
+        if worth_loading(neighbour) {
             prefetch(
-                list[prev_index(self.index.load(Ordering::SeqCst), list.len())].clone(),
+                neighbour.clone(),
                 self.cache.clone(),
                 self.proxy.clone(),
                 self.sender.clone(),
                 self.loading_info.clone(),
             );
-            Some(list[self.index.load(Ordering::SeqCst)].clone())
-        } else {
-            None
         }
+        Some(list[index].clone())
     }
 }
 
@@ -189,3 +366,232 @@ fn prev_index(index: usize, len: usize) -> usize {
         current - 1
     }
 }
+
+fn is_image(path: &Path, listing: Listing) -> bool {
+    match path.extension() {
+        Some(ext) => {
+            let ext = ext.to_string_lossy().to_ascii_lowercase();
+            EXTENSIONS.contains(&*ext)
+                || (listing.videos && video::SUPPORTED && VIDEO.contains(&*ext))
+        }
+        None => listing.extensionless,
+    }
+}
+
+/// Files with an image extension were already picked by name, the rest has to be probed.
+fn worth_loading(path: &Path) -> bool {
+    path.extension().is_some() || probe_file(path)
+}
+
+/// Lists the images in a directory in the order they are browsed. Files without an extension
+/// are listed without checking what they contain if the `listing` asks for them.
+pub fn scan_dir(dir_path: &Path, listing: Listing) -> io::Result<Vec<PathBuf>> {
+    let mut list = Vec::new();
+    for dir in fs::read_dir(dir_path)?.flatten() {
+        if let Ok(file_type) = dir.file_type() {
+            let path = dir.path();
+            if file_type.is_file() && is_image(&path, listing) {
+                // files whose time can not be read sort as the oldest
+                let modified = match listing.sort {
+                    Sort::Newest | Sort::Oldest => dir
+                        .metadata()
+                        .and_then(|metadata| metadata.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
+                    Sort::Name | Sort::NameReversed => SystemTime::UNIX_EPOCH,
+                };
+                list.push((modified, path));
+            }
+        }
+    }
+
+    // files changed at the same time keep the order of their names
+    match listing.sort {
+        Sort::Name => list.sort_by(|(_, a), (_, b)| a.cmp(b)),
+        Sort::NameReversed => list.sort_by(|(_, a), (_, b)| b.cmp(a)),
+        Sort::Newest => list.sort_by(|(a_time, a), (b_time, b)| b_time.cmp(a_time).then(a.cmp(b))),
+        Sort::Oldest => list.sort_by(|(a_time, a), (b_time, b)| a_time.cmp(b_time).then(a.cmp(b))),
+    }
+    Ok(list.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Finds the first image in the next or previous sibling of `dir_path` that contains images,
+/// wrapping around at the ends. This reads the whole parent so it should not run on the ui thread.
+pub fn sibling_dir_image(dir_path: &Path, forward: bool, listing: Listing) -> Option<PathBuf> {
+    let parent = dir_path.parent()?;
+    let mut dirs: Vec<PathBuf> = fs::read_dir(parent)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+
+    let current = dirs.iter().position(|dir| dir == dir_path)?;
+    let len = dirs.len();
+    (1..len)
+        .map(|offset| {
+            if forward {
+                (current + offset) % len
+            } else {
+                (current + len - offset) % len
+            }
+        })
+        .find_map(|index| {
+            scan_dir(&dirs[index], listing)
+                .ok()
+                .and_then(|list| list.into_iter().find(|path| worth_loading(path)))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_are_listed_loaded_and_shown() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        use image::{ImageBuffer, ImageOutputFormat, Rgba};
+
+        use crate::{
+            app::load_image::{load_uncached, LoadOptions},
+            image_io::save::save_with_format,
+            util::{display_name, Image},
+        };
+
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let invalid = dir.join(OsStr::from_bytes(b"caf\xe9.png"));
+        let control = dir.join("tab\there.png");
+        let image = Image::from(ImageBuffer::from_pixel(4, 4, Rgba([255u8, 0, 0, 255])));
+        for path in [&invalid, &control] {
+            save_with_format(path, &image, ImageOutputFormat::Png).unwrap();
+        }
+
+        let list = scan_dir(&dir, Listing::default()).unwrap();
+        assert_eq!(list.len(), 2);
+        let index = list.iter().position(|path| *path == invalid).unwrap();
+        let neighbour = &list[next_index(index, list.len())];
+        assert_eq!(*neighbour, control);
+        assert_eq!(
+            list[prev_index(next_index(index, list.len()), list.len())],
+            invalid
+        );
+
+        for path in &list {
+            let image_data = load_uncached(path, LoadOptions::default()).unwrap();
+            assert_eq!(image_data.frames[0].buffer().width(), 4);
+        }
+        assert_eq!(display_name(&invalid), "caf\u{fffd}.png");
+        assert_eq!(display_name(&control), "tab\u{fffd}here.png");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn images_can_be_moved_by_hand() {
+        let (proxy, _events) = Proxy::channel();
+        let (sender, _outputs) = std::sync::mpsc::channel();
+        let mut list = ImageList::new(
+            Arc::new(Cache::new(0)),
+            proxy,
+            sender,
+            Arc::new(Mutex::new(LoadingInfo::default())),
+        );
+        let names = |list: &ImageList| -> String {
+            list.paths()
+                .unwrap()
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        };
+        list.set_explicit(["a", "b", "c", "d"].map(PathBuf::from).to_vec());
+        let snapshot = list.paths().unwrap();
+
+        // a is current and stays current when it is moved
+        assert!(list.move_entry(0, 2));
+        assert_eq!(names(&list), "bcad");
+        assert_eq!(list.position(), Some((2, 4)));
+        // moving others past it shifts it
+        assert!(list.move_entry(3, 0));
+        assert_eq!(names(&list), "dbca");
+        assert_eq!(list.position(), Some((3, 4)));
+        assert!(list.move_current(-1));
+        assert_eq!(names(&list), "dbac");
+        assert_eq!(list.position(), Some((2, 4)));
+
+        assert!(!list.move_current(2));
+        assert!(!list.move_entry(1, 1));
+        assert!(!list.move_entry(0, 4));
+        // a snapshot taken before is left as it was
+        assert_eq!(snapshot.len(), 4);
+        assert_eq!(snapshot[0], PathBuf::from("a"));
+    }
+
+    #[test]
+    fn deleting_before_the_folder_is_listed_rescans_it() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let (proxy, _events) = Proxy::channel();
+        let (sender, _outputs) = std::sync::mpsc::channel();
+        let mut list = ImageList::new(
+            Arc::new(Cache::new(0)),
+            proxy,
+            sender,
+            Arc::new(Mutex::new(LoadingInfo::default())),
+        );
+        list.set_listing(Listing {
+            sort: Sort::Name,
+            ..Listing::default()
+        });
+
+        fs::remove_file(dir.join("b.png")).unwrap();
+        assert_eq!(list.remove(&dir.join("b.png")), Some(dir.join("c.png")));
+        assert_eq!(list.position(), Some((1, 2)));
+
+        // the last one wraps around to the first
+        fs::remove_file(dir.join("c.png")).unwrap();
+        list.clear();
+        assert_eq!(list.remove(&dir.join("c.png")), Some(dir.join("a.png")));
+        assert_eq!(list.position(), Some((0, 1)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn folders_are_sorted_by_name_or_time() {
+        use std::time::Duration;
+
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        // b is the newest, a and c were changed at the same time
+        for (name, offset) in [("a.png", 0), ("b.png", 60), ("c.png", 0)] {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(start + Duration::from_secs(offset))
+                .unwrap();
+        }
+
+        let names = |sort| -> Vec<String> {
+            let listing = Listing {
+                sort,
+                ..Listing::default()
+            };
+            scan_dir(&dir, listing)
+                .unwrap()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(names(Sort::Name), ["a.png", "b.png", "c.png"]);
+        assert_eq!(names(Sort::NameReversed), ["c.png", "b.png", "a.png"]);
+        assert_eq!(names(Sort::Newest), ["b.png", "a.png", "c.png"]);
+        assert_eq!(names(Sort::Oldest), ["a.png", "c.png", "b.png"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
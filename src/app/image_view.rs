@@ -2,6 +2,7 @@ use std::{
     borrow::Cow,
     mem,
     path::PathBuf,
+    rc::Rc,
     sync::{mpsc::Sender, Arc, RwLock},
     thread,
     time::{Duration, Instant},
@@ -11,22 +12,35 @@ use cgmath::{Matrix4, Ortho, Vector3, Vector4};
 use glium::{
     backend::glutin::Display,
     draw_parameters::DrawParameters,
-    glutin::event_loop::EventLoopProxy,
     implement_vertex,
     index::PrimitiveType,
     program::Program,
-    texture::{ClientFormat, MipmapsOption, RawImage2d, SrgbTexture2d},
+    texture::{
+        ClientFormat, MipmapsOption, PixelValue, RawImage2d, SrgbFormat, SrgbTexture2d, Texture2d,
+        UncompressedFloatFormat,
+    },
     uniform,
     uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerBehavior},
     Blend, IndexBuffer, Surface, VertexBuffer,
 };
-use image::{imageops::rotate180_in_place, DynamicImage, GenericImageView};
+use image::{
+    imageops::{flip_horizontal_in_place, flip_vertical_in_place, rotate180_in_place, FilterType},
+    DynamicImage, GenericImageView,
+};
 
-use super::op_queue::Output;
+use super::{
+    analyze::{AnalysisKey, ColorAnalysis},
+    color::{Tone, WhiteBalance},
+    gpu_resize::{self, GpuResize},
+    levels::Levels,
+    op_queue::Output,
+    playback::Playback,
+    undo_stack::EditTarget,
+};
 use crate::{
     max, min,
     rect::Rect,
-    util::{Image, ImageData, UserEvent},
+    util::{Image, ImageData, Proxy, UserEvent},
     vec2::Vec2,
 };
 
@@ -47,11 +61,49 @@ impl Vertex {
 
 implement_vertex!(Vertex, position, tex_coords);
 
+/// Display only ways of looking at the channels of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChannelMode {
+    Normal,
+    Alpha,
+    Opaque,
+    Translucent,
+    Red,
+    Green,
+    Blue,
+}
+
+impl ChannelMode {
+    pub const ALL: &'static [ChannelMode] = &[
+        ChannelMode::Normal,
+        ChannelMode::Alpha,
+        ChannelMode::Opaque,
+        ChannelMode::Translucent,
+        ChannelMode::Red,
+        ChannelMode::Green,
+        ChannelMode::Blue,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChannelMode::Normal => "Normal",
+            ChannelMode::Alpha => "Alpha as grayscale",
+            ChannelMode::Opaque => "Ignore alpha",
+            ChannelMode::Translucent => "Highlight translucent pixels",
+            ChannelMode::Red => "Red channel",
+            ChannelMode::Green => "Green channel",
+            ChannelMode::Blue => "Blue channel",
+        }
+    }
+}
+
 pub struct ImageView {
     pub size: Vec2<f32>,
     pub position: Vec2<f32>,
     pub scale: f32,
     pub rotation: i32,
+    /// Extra clockwise rotation in degrees that is only applied when rendering.
+    pub preview_angle: f32,
     pub path: Option<PathBuf>,
     pub image_data: Arc<RwLock<ImageData>>,
     pub last_frame: Instant,
@@ -62,11 +114,65 @@ pub struct ImageView {
     pub contrast: f32,
     pub lightness: f32,
     pub saturation: f32,
+    /// The exposure, brightness and gamma of the color window.
+    pub tone: Tone,
+    pub white_balance: WhiteBalance,
+    /// The levels of the levels window.
+    pub levels: Levels,
+    pub channel_mode: ChannelMode,
+    /// Stops the brightness of floating point images is scaled by, only for display.
+    pub exposure: f32,
+    /// Whether floating point images are tone mapped instead of clipped at white.
+    pub tone_map: bool,
+    /// Whether non square pixels are stretched to their real proportions.
+    pub aspect_correction: bool,
+    pixel_aspect: Vec2<f32>,
+    /// How much larger the real image is than the decoded frames, below 1 for an svg
+    /// rendered larger than it declares.
+    decode_scale: Vec2<f32>,
+    /// Set once the full resolution has been asked for so it is only loaded once.
+    pub full_resolution_requested: bool,
+    /// The largest scale an svg was asked to be rendered at.
+    pub vector_scale_requested: f32,
+    /// Frames from before the first pixel edit, only held while comparing against them.
+    original: Option<Vec<Image>>,
+    /// What is drawn instead of the edited image while comparing.
+    comparison: Option<Comparison>,
+    /// Whether an animation is stopped on the current frame.
+    paused: bool,
+    /// How many times the animation went through all of its frames.
+    plays: u32,
+    /// Set when the animation stopped on its last frame after playing as often as the file says.
+    finished: bool,
+    /// Whether the neighboring frames are ghosted over a paused animation.
+    onion_skin: bool,
+    pub onion_opacity: f32,
+    /// Textures of the previous and next frame while onion skinning.
+    onion: Option<Onion>,
+    /// The size and filter the resize window is previewing.
+    resize_preview_key: Option<(Vec2<u32>, FilterType)>,
+    /// What is drawn instead of the image while previewing a resize.
+    resize_preview: Option<Comparison>,
+    /// A second image of the same size drawn right of a divider.
+    wipe: Option<Wipe>,
+    /// Where the divider is, as a fraction of the window width.
+    pub wipe_split: f32,
+    /// Recently rendered previews so changing the size back and forth does not render again.
+    preview_cache: Vec<CachedPreview>,
+    /// Created the first time a preview is rendered.
+    gpu_resize: Option<GpuResize>,
+    /// A resize preview the gpu could not render, for the op queue to make on the cpu.
+    pub preview_request: Option<PreviewRequest>,
+    /// Counts changes to the frames so results computed from them can tell they are stale.
+    generation: u64,
+    pub color_analysis: Option<(AnalysisKey, ColorAnalysis)>,
     shader: Box<Program>,
     vertices: VertexBuffer<Vertex>,
     indices: IndexBuffer<u8>,
-    texture: SrgbTexture2d,
-    texture_cords: (Vec2<f32>, Vec2<f32>, Vec2<f32>, Vec2<f32>),
+    texture: Rc<FrameTexture>,
+    /// Every frame of an animation on the GPU, `None` for still images.
+    frame_textures: Option<FrameTextures>,
+    texture_cords: TextureCords,
     sampler: SamplerBehavior,
 }
 
@@ -80,26 +186,40 @@ impl ImageView {
         let frames = &guard.frames;
         let image = frames[0].buffer();
         let (width, height) = image.dimensions();
+        let pixel_aspect = pixel_aspect(guard.resolution);
+        let decode_scale = decode_scale(guard.full_size, width, height);
         let texture_cords = (
             Vec2::new(0.0, 0.0),
             Vec2::new(0.0, 1.0),
             Vec2::new(1.0, 0.0),
             Vec2::new(1.0, 1.0),
         );
+        let display_width = width as f32 * pixel_aspect.x() * decode_scale.x();
+        let display_height = height as f32 * pixel_aspect.y() * decode_scale.y();
         let shape = vec![
             Vertex::new(0.0, 0.0, texture_cords.0.x(), texture_cords.0.y()),
-            Vertex::new(0.0, height as f32, texture_cords.1.x(), texture_cords.1.y()),
-            Vertex::new(width as f32, 0.0, texture_cords.2.x(), texture_cords.2.y()),
             Vertex::new(
-                width as f32,
-                height as f32,
+                0.0,
+                display_height,
+                texture_cords.1.x(),
+                texture_cords.1.y(),
+            ),
+            Vertex::new(display_width, 0.0, texture_cords.2.x(), texture_cords.2.y()),
+            Vertex::new(
+                display_width,
+                display_height,
                 texture_cords.3.x(),
                 texture_cords.3.y(),
             ),
         ];
         let index_buffer = &[0, 1, 2, 2, 1, 3];
 
-        let texture = get_texture(image, display);
+        // playback starts with the first frame, the rest is uploaded over the next frames
+        let mut frame_textures = FrameTextures::new(frames);
+        let texture = match &mut frame_textures {
+            Some(textures) => textures.get_or_upload(frames, 0, display),
+            None => Rc::new(get_texture(image, display)),
+        };
 
         drop(guard);
 
@@ -115,6 +235,7 @@ impl ImageView {
             position: Vec2::default(),
             scale: 1.0,
             rotation: 0,
+            preview_angle: 0.0,
             image_data,
             last_frame: Instant::now(),
             index: 0,
@@ -132,6 +253,7 @@ impl ImageView {
             vertices: VertexBuffer::new(display, &shape).unwrap(),
             indices: IndexBuffer::new(display, PrimitiveType::TrianglesList, index_buffer).unwrap(),
             texture,
+            frame_textures,
             texture_cords,
             sampler,
             path,
@@ -139,10 +261,46 @@ impl ImageView {
             contrast: 0.0,
             lightness: 0.0,
             saturation: 0.0,
+            tone: Tone::default(),
+            white_balance: WhiteBalance::default(),
+            levels: Levels::default(),
+            channel_mode: ChannelMode::Normal,
+            exposure: 0.0,
+            tone_map: false,
+            aspect_correction: true,
+            pixel_aspect,
+            decode_scale,
+            full_resolution_requested: false,
+            vector_scale_requested: 1.0,
+            original: None,
+            comparison: None,
+            paused: false,
+            plays: 0,
+            finished: false,
+            onion_skin: false,
+            onion_opacity: 0.3,
+            onion: None,
+            resize_preview_key: None,
+            resize_preview: None,
+            wipe: None,
+            wipe_split: 0.5,
+            preview_cache: Vec::new(),
+            gpu_resize: None,
+            preview_request: None,
+            generation: 0,
+            color_analysis: None,
         }
     }
 
-    pub fn render(&self, target: &mut glium::Frame, size: Vec2<f32>) {
+    /// Draws the image with `checks` behind its transparent parts, see `Transparency::checks`.
+    /// With `pixel_grid` lines are drawn between the pixels once they are large enough.
+    pub fn render<S: Surface>(
+        &self,
+        target: &mut S,
+        size: Vec2<f32>,
+        checks: [[f32; 3]; 2],
+        pixel_grid: bool,
+    ) {
         let ortho: Matrix4<f32> = Ortho {
             left: 0.0,
             right: size.x(),
@@ -153,17 +311,51 @@ impl ImageView {
         }
         .into();
 
-        let position = self.position - self.scaled() / 2.0;
+        let (texture, vertices, image_size) = match (&self.comparison, &self.resize_preview) {
+            (Some(comparison), _) | (None, Some(comparison)) => {
+                (&comparison.texture, &comparison.vertices, comparison.size)
+            }
+            (None, None) => (&*self.texture, &self.vertices, self.display_size()),
+        };
+        // the original is shown as it is on disk without any color preview
+        let (hue, contrast, lightness, saturation, tone, white_balance, levels) =
+            match self.comparison {
+                Some(_) => (
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    Tone::default(),
+                    WhiteBalance::default(),
+                    Levels::default(),
+                ),
+                None => (
+                    self.hue,
+                    self.contrast,
+                    self.lightness,
+                    self.saturation,
+                    self.tone,
+                    self.white_balance,
+                    self.levels,
+                ),
+            };
+        let gains = white_balance.gains();
+        let [levels_black, levels_white, levels_gamma, levels_low, levels_high] = levels.uniforms();
+
+        let position = self.position - image_size * self.scale / 2.0;
         let scale = Matrix4::from_scale(self.scale);
         let translation = Matrix4::from_translation(Vector3::new(position.x(), position.y(), 0.0));
 
-        let rotation = get_rotation_matrix(degrees_to_radians((self.rotation * 90) as f32));
+        let rotation = get_rotation_matrix(self.angle());
 
-        let pre_rotation =
-            Matrix4::from_translation(Vector3::new(self.size.x() / 2.0, self.size.y() / 2.0, 0.0));
+        let pre_rotation = Matrix4::from_translation(Vector3::new(
+            image_size.x() / 2.0,
+            image_size.y() / 2.0,
+            0.0,
+        ));
         let post_rotation = Matrix4::from_translation(Vector3::new(
-            -self.size.x() / 2.0,
-            -self.size.y() / 2.0,
+            -image_size.x() / 2.0,
+            -image_size.y() / 2.0,
             0.0,
         ));
         let final_rotation = (pre_rotation * rotation) * post_rotation;
@@ -172,33 +364,335 @@ impl ImageView {
 
         let raw: [[f32; 4]; 4] = matrix.into();
 
-        target
-            .draw(
-                &self.vertices,
-                &self.indices,
-                &self.shader,
-                &uniform! { matrix: raw, tex: Sampler(&self.texture, self.sampler), size: size, hue: self.hue, contrast: self.contrast, lightness: self.lightness, saturation: self.saturation },
-                &DrawParameters {
-                    blend: Blend::alpha_blending(),
-                    ..DrawParameters::default()
-                },
-            )
+        let exposure = self.exposure.exp2();
+        let tone_map = self.tone_map as i32;
+        let pixel_grid = (pixel_grid && self.pixel_scale() >= PIXEL_GRID_SCALE) as i32;
+        let mut draw_in = |texture: &FrameTexture,
+                           vertices: &VertexBuffer<Vertex>,
+                           tint: [f32; 4],
+                           scissor: Option<glium::Rect>| {
+            let uniforms = uniform! { matrix: raw, size: size, hue: hue, contrast: contrast, lightness: lightness, saturation: saturation, brightness: tone.brightness, gamma: tone.gamma, stops: tone.exposure, gains: gains, levels_black: levels_black, levels_white: levels_white, levels_gamma: levels_gamma, levels_low: levels_low, levels_high: levels_high, channel_mode: self.channel_mode as i32, tint: tint, gray: texture.gray_mode(), exposure: exposure, tone_map: tone_map, check_light: checks[0], check_dark: checks[1], pixel_grid: pixel_grid };
+            let parameters = DrawParameters {
+                blend: Blend::alpha_blending(),
+                scissor,
+                ..DrawParameters::default()
+            };
+            match texture {
+                FrameTexture::Color(texture) => target.draw(
+                    vertices,
+                    &self.indices,
+                    &self.shader,
+                    &uniforms.add("tex", Sampler(texture, self.sampler)),
+                    &parameters,
+                ),
+                FrameTexture::Gray(texture, _) | FrameTexture::Linear(texture) => target.draw(
+                    vertices,
+                    &self.indices,
+                    &self.shader,
+                    &uniforms.add("tex", Sampler(texture, self.sampler)),
+                    &parameters,
+                ),
+            }
             .unwrap();
+        };
+        let mut draw =
+            |texture: &FrameTexture, tint: [f32; 4]| draw_in(texture, vertices, tint, None);
+
+        draw(texture, [0.0; 4]);
+
+        if let (Some(onion), None, None) = (&self.onion, &self.comparison, &self.resize_preview) {
+            if let Some(prev) = &onion.prev {
+                draw(prev, [1.0, 0.0, 0.0, self.onion_opacity]);
+            }
+            if let Some(next) = &onion.next {
+                draw(next, [0.0, 1.0, 0.0, self.onion_opacity]);
+            }
+        }
+
+        if let (Some(wipe), None, None) = (&self.wipe, &self.comparison, &self.resize_preview) {
+            let left = (self.wipe_split.clamp(0.0, 1.0) * size.x()).round() as u32;
+            let scissor = glium::Rect {
+                left,
+                bottom: 0,
+                width: (size.x() as u32).saturating_sub(left),
+                height: size.y() as u32,
+            };
+            draw_in(&wipe.texture, &wipe.vertices, [0.0; 4], Some(scissor));
+        }
+    }
+
+    pub fn wiping(&self) -> bool {
+        self.wipe.is_some()
+    }
+
+    /// The name of the image right of the divider.
+    pub fn wipe_name(&self) -> Option<&str> {
+        self.wipe.as_ref().map(|wipe| wipe.name.as_str())
+    }
+
+    /// Draws `image` over the part of this one right of the divider, it has to be the same
+    /// size. Returns false when it is not.
+    pub fn set_wipe(&mut self, display: &Display, image: &DynamicImage, name: String) -> bool {
+        let size = Vec2::new(image.width(), image.height());
+        let true_size = self.true_size();
+        if size != Vec2::new(true_size.x() as u32, true_size.y() as u32) {
+            return false;
+        }
+        self.wipe = Some(Wipe {
+            texture: get_texture(image, display),
+            vertices: build_vertices(display, self.display_size(), &self.texture_cords),
+            size,
+            name,
+        });
+        self.wipe_split = 0.5;
+        true
+    }
+
+    pub fn clear_wipe(&mut self) {
+        self.wipe = None;
+    }
+
+    /// The total displayed rotation in radians.
+    fn angle(&self) -> f32 {
+        degrees_to_radians((self.rotation * 90) as f32 + self.preview_angle)
+    }
+
+    /// How many screen pixels wide one pixel of the frames is drawn.
+    pub fn pixel_scale(&self) -> f32 {
+        self.scale * self.display_size().x() / self.size.x()
     }
 
     pub fn scaled(&self) -> Vec2<f32> {
-        self.size * self.scale
+        self.display_size() * self.scale
+    }
+
+    /// Whether the resolution tags of the image describe non square pixels.
+    pub fn non_square_pixels(&self) -> bool {
+        self.pixel_aspect != Vec2::new(1.0, 1.0)
+    }
+
+    /// Whether the image is currently stretched to correct for non square pixels.
+    pub fn aspect_corrected(&self) -> bool {
+        self.aspect_correction && self.non_square_pixels()
+    }
+
+    /// The pixel size the image would have with the aspect correction baked in.
+    pub fn corrected_size(&self) -> Vec2<u32> {
+        Vec2::new(
+            (self.size.x() * self.pixel_aspect.x()).round() as u32,
+            (self.size.y() * self.pixel_aspect.y()).round() as u32,
+        )
+    }
+
+    /// The size of the image in screen pixels at 100% zoom. This differs from `size`
+    /// when aspect correction is active or the frames were decoded at a reduced scale.
+    pub fn display_size(&self) -> Vec2<f32> {
+        let size = self.true_size();
+        if self.aspect_corrected() {
+            Vec2::new(
+                size.x() * self.pixel_aspect.x(),
+                size.y() * self.pixel_aspect.y(),
+            )
+        } else {
+            size
+        }
+    }
+
+    /// The size of the image on disk, even when the frames were decoded at a reduced scale.
+    pub fn true_size(&self) -> Vec2<f32> {
+        Vec2::new(
+            (self.size.x() * self.decode_scale.x()).round(),
+            (self.size.y() * self.decode_scale.y()).round(),
+        )
+    }
+
+    /// Whether the frames are not the size of the image, either decoded smaller or an svg
+    /// rendered larger. Pixel edits need them at the real size.
+    pub fn downscaled(&self) -> bool {
+        self.decode_scale != Vec2::new(1.0, 1.0) || self.raw_preview()
+    }
+
+    /// Whether the frame is the preview a camera raw file carries.
+    fn raw_preview(&self) -> bool {
+        self.image_data.read().unwrap().raw_preview
+    }
+
+    /// Whether the view is zoomed in far enough that the downscaled frames are being magnified.
+    pub fn needs_full_resolution(&self) -> bool {
+        (self.decode_scale.x() > 1.0 || self.raw_preview())
+            && !self.full_resolution_requested
+            && self.scale * self.decode_scale.x() > 1.0
+    }
+
+    /// The scale an svg should be rendered at again to stay sharp at the current zoom, the
+    /// next power of two above it. `None` while the rendering on screen is detailed enough
+    /// or was already asked for.
+    pub fn wanted_vector_scale(&self) -> Option<f32> {
+        if !self.image_data.read().unwrap().vector {
+            return None;
+        }
+        let size = self.true_size();
+        let limit = (MAX_VECTOR_PIXELS / (size.x() * size.y()))
+            .sqrt()
+            .min(MAX_VECTOR_SIDE / size.x().max(size.y()));
+        let wanted = self.scale.log2().ceil().exp2().max(1.0).min(limit);
+        let rendered = 1.0 / self.decode_scale.x();
+        (wanted > rendered * 1.01 && wanted > self.vector_scale_requested).then_some(wanted)
+    }
+
+    /// Replaces downscaled frames with the full resolution ones, or an svg with a new
+    /// rendering of the size `full_size` declares. Unlike an edit this is not tracked as a
+    /// change to the image.
+    pub fn set_full_resolution(
+        &mut self,
+        frames: Vec<Image>,
+        full_size: Option<(u32, u32)>,
+        display: &Display,
+    ) {
+        // a developed raw file is not always the size of its preview, it stays as large on screen
+        let shown_width = self.true_size().x();
+        let mut guard = self.image_data.write().unwrap();
+        guard.frames = frames;
+        guard.full_size = full_size;
+        guard.raw_preview = false;
+        self.frame_textures = FrameTextures::new(&guard.frames);
+        drop(guard);
+        self.preview_cache.clear();
+        self.generation += 1;
+        self.update_image_data(display);
+        self.scale *= shown_width / self.true_size().x();
+        self.update_vertex_data(display);
+    }
+
+    /// Uploads the next few frames of an animation that are not on the GPU yet, starting
+    /// with the ones about to play. Returns whether any are still left.
+    pub fn upload_frames(&mut self, display: &Display) -> bool {
+        let textures = match &mut self.frame_textures {
+            Some(textures) if textures.pending() => textures,
+            _ => return false,
+        };
+
+        let guard = self.image_data.read().unwrap();
+        let frames = &guard.frames;
+        let mut bytes = 0;
+        for offset in 0..frames.len() {
+            if bytes >= UPLOAD_BYTES_PER_FRAME {
+                break;
+            }
+            let index = (self.index + offset) % frames.len();
+            if textures.textures[index].is_none() {
+                textures.get_or_upload(frames, index, display);
+                bytes += frames[index].buffer().as_bytes().len();
+            }
+        }
+        textures.pending()
+    }
+
+    /// How many frames of an animation are on the GPU and how many there are,
+    /// `None` once all of them are.
+    pub fn upload_progress(&self) -> Option<(usize, usize)> {
+        let textures = self.frame_textures.as_ref()?;
+        textures
+            .pending()
+            .then_some((textures.uploaded, textures.textures.len()))
+    }
+
+    /// The pixel of the current frame drawn at `screen`, `None` outside of the image.
+    pub fn pixel_at(&self, screen: Vec2<f32>) -> Option<(u32, u32)> {
+        let offset = (screen - self.position) / self.scale;
+        let angle = -self.angle();
+        let (sin, cos) = angle.sin_cos();
+        let display = self.display_size();
+        let local = Vec2::new(
+            offset.x() * cos - offset.y() * sin + display.x() / 2.0,
+            offset.x() * sin + offset.y() * cos + display.y() / 2.0,
+        );
+        if local.x() < 0.0
+            || local.y() < 0.0
+            || local.x() >= display.x()
+            || local.y() >= display.y()
+        {
+            return None;
+        }
+
+        let mut u = local.x() / display.x();
+        let mut v = local.y() / display.y();
+        if self.horizontal_flip {
+            u = 1.0 - u;
+        }
+        if self.vertical_flip {
+            v = 1.0 - v;
+        }
+        Some(((u * self.size.x()) as u32, (v * self.size.y()) as u32))
+    }
+
+    /// Where a point of the image, in pixels of the image on disk, is on screen. Follows the
+    /// turns and flips, a free rotation as well.
+    pub fn image_to_screen(&self, pixel: Vec2<f32>) -> Vec2<f32> {
+        let true_size = self.true_size();
+        let display = self.display_size();
+        let mut u = pixel.x() / true_size.x();
+        let mut v = pixel.y() / true_size.y();
+        if self.horizontal_flip {
+            u = 1.0 - u;
+        }
+        if self.vertical_flip {
+            v = 1.0 - v;
+        }
+        let local = Vec2::new((u - 0.5) * display.x(), (v - 0.5) * display.y());
+        let (sin, cos) = self.angle().sin_cos();
+        let offset = Vec2::new(
+            local.x() * cos - local.y() * sin,
+            local.x() * sin + local.y() * cos,
+        );
+        self.position + offset * self.scale
+    }
+
+    /// The point of the image at `screen` in pixels of the image on disk, the reverse of
+    /// `image_to_screen`. Points beside the image give pixels outside of it.
+    pub fn screen_to_image(&self, screen: Vec2<f32>) -> Vec2<f32> {
+        let offset = (screen - self.position) / self.scale;
+        let (sin, cos) = (-self.angle()).sin_cos();
+        let display = self.display_size();
+        let mut u = (offset.x() * cos - offset.y() * sin) / display.x() + 0.5;
+        let mut v = (offset.x() * sin + offset.y() * cos) / display.y() + 0.5;
+        if self.horizontal_flip {
+            u = 1.0 - u;
+        }
+        if self.vertical_flip {
+            v = 1.0 - v;
+        }
+        let true_size = self.true_size();
+        Vec2::new(u * true_size.x(), v * true_size.y())
+    }
+
+    /// The pixel under `screen` in the coordinates of the image on disk, with its color.
+    /// `None` outside of the image or while an edit holds the frames.
+    pub fn inspect(&self, screen: Vec2<f32>) -> Option<((u32, u32), [u8; 4])> {
+        let (x, y) = self.pixel_at(screen)?;
+        let guard = self.image_data.try_read().ok()?;
+        let buffer = guard.frames.get(self.index)?.buffer();
+        if x >= buffer.width() || y >= buffer.height() {
+            return None;
+        }
+        let color = buffer.get_pixel(x, y).0;
+        let real = (
+            (x as f32 * self.decode_scale.x()) as u32,
+            (y as f32 * self.decode_scale.y()) as u32,
+        );
+        Some((real, color))
     }
 
     pub fn real_size(&self) -> Vec2<f32> {
+        let image_size = self.display_size();
         let mut vectors = vec![
             Vector4::new(0.0, 0.0, 0.0, 1.0),
-            Vector4::new(0.0, self.size.y(), 0.0, 1.0),
-            Vector4::new(self.size.x(), 0.0, 0.0, 1.0),
-            Vector4::new(self.size.x(), self.size.y(), 0.0, 1.0),
+            Vector4::new(0.0, image_size.y(), 0.0, 1.0),
+            Vector4::new(image_size.x(), 0.0, 0.0, 1.0),
+            Vector4::new(image_size.x(), image_size.y(), 0.0, 1.0),
         ];
 
-        let rot = degrees_to_radians((self.rotation * 90) as f32);
+        let rot = self.angle();
 
         #[rustfmt::skip]
         let rotation = Matrix4::new(
@@ -228,12 +722,55 @@ impl ImageView {
         size
     }
 
+    /// The size of the frames with the quarter turns applied.
+    pub fn oriented_size(&self) -> Vec2<u32> {
+        let (width, height) = (self.size.x() as u32, self.size.y() as u32);
+        if self.rotation % 2 == 1 {
+            Vec2::new(height, width)
+        } else {
+            Vec2::new(width, height)
+        }
+    }
+
+    /// The size of the image on disk with the quarter turns applied. Crop selections are
+    /// measured in it, so they still fit once downscaled frames are swapped for the full ones.
+    pub fn oriented_true_size(&self) -> Vec2<u32> {
+        let size = self.true_size();
+        let (width, height) = (size.x() as u32, size.y() as u32);
+        if self.rotation % 2 == 1 {
+            Vec2::new(height, width)
+        } else {
+            Vec2::new(width, height)
+        }
+    }
+
+    /// Converts a point on screen to pixels of the image on disk as it is shown, turned and
+    /// flipped. Only exact while the image is not rotated by a free angle.
+    pub fn screen_to_pixels(&self, screen: Vec2<f32>) -> Vec2<f32> {
+        let bounds = self.bounds();
+        let size = self.oriented_true_size();
+        Vec2::new(
+            (screen.x() - bounds.x()) / bounds.width() * size.x() as f32,
+            (screen.y() - bounds.y()) / bounds.height() * size.y() as f32,
+        )
+    }
+
+    pub fn pixels_to_screen(&self, pixels: Vec2<f32>) -> Vec2<f32> {
+        let bounds = self.bounds();
+        let size = self.oriented_true_size();
+        Vec2::new(
+            bounds.x() + pixels.x() / size.x() as f32 * bounds.width(),
+            bounds.y() + pixels.y() / size.y() as f32 * bounds.height(),
+        )
+    }
+
     pub fn bounds(&self) -> Rect {
+        let image_size = self.display_size();
         let mut vectors = [
             Vector4::new(0.0, 0.0, 0.0, 1.0),
-            Vector4::new(0.0, self.size.y(), 0.0, 1.0),
-            Vector4::new(self.size.x(), 0.0, 0.0, 1.0),
-            Vector4::new(self.size.x(), self.size.y(), 0.0, 1.0),
+            Vector4::new(0.0, image_size.y(), 0.0, 1.0),
+            Vector4::new(image_size.x(), 0.0, 0.0, 1.0),
+            Vector4::new(image_size.x(), image_size.y(), 0.0, 1.0),
         ];
 
         let position = self.position - self.scaled() / 2.0;
@@ -241,13 +778,16 @@ impl ImageView {
         let translation =
             Matrix4::from_translation(cgmath::Vector3::new(position.x(), position.y(), 0.0));
 
-        let rotation = get_rotation_matrix(degrees_to_radians((self.rotation * 90) as f32));
+        let rotation = get_rotation_matrix(self.angle());
 
-        let pre_rotation =
-            Matrix4::from_translation(Vector3::new(self.size.x() / 2.0, self.size.y() / 2.0, 0.0));
+        let pre_rotation = Matrix4::from_translation(Vector3::new(
+            image_size.x() / 2.0,
+            image_size.y() / 2.0,
+            0.0,
+        ));
         let post_rotation = Matrix4::from_translation(Vector3::new(
-            -self.size.x() / 2.0,
-            -self.size.y() / 2.0,
+            -image_size.x() / 2.0,
+            -image_size.y() / 2.0,
             0.0,
         ));
         let final_rotation = (pre_rotation * rotation) * post_rotation;
@@ -291,7 +831,7 @@ impl ImageView {
     pub fn animate(&mut self, display: &Display) -> Option<Duration> {
         let guard = self.image_data.read().unwrap();
         let frames = &guard.frames;
-        if frames.len() > 1 {
+        if frames.len() > 1 && !self.paused {
             let now = Instant::now();
             let time_passed = now.duration_since(self.last_frame);
             let delay = frames[self.index].delay;
@@ -299,11 +839,23 @@ impl ImageView {
             if time_passed > delay {
                 self.index += 1;
                 if self.index >= frames.len() {
+                    self.plays = self.plays.saturating_add(1);
+                    if guard.loop_count != 0 && self.plays >= guard.loop_count {
+                        // stay on the last frame like browsers do
+                        self.index = frames.len() - 1;
+                        drop(guard);
+                        self.set_paused(display, true);
+                        self.finished = true;
+                        return None;
+                    }
                     self.index = 0;
                 }
 
                 drop(guard);
                 self.update_image_data(display);
+                if self.comparison.is_some() {
+                    self.comparison = self.build_comparison(display);
+                }
 
                 self.last_frame = now;
 
@@ -316,36 +868,404 @@ impl ImageView {
         }
     }
 
-    pub fn crop(&self, cut: Rect, proxy: EventLoopProxy<UserEvent>, sender: Sender<Output>) {
-        let bounds = self.bounds();
-        if !bounds.intersects(&cut) {
-            let _ = sender.send(Output::Done);
-            let _ = proxy.send_event(UserEvent::Wake);
+    pub fn swap_frames(&mut self, frames: &mut Vec<Image>, display: &Display) {
+        let mut guard = self.image_data.write().unwrap();
+        mem::swap(&mut guard.frames, frames);
+        // the frames were edited, none of the uploaded ones still fit
+        self.index = self.index.min(guard.frames.len() - 1);
+        self.frame_textures = FrameTextures::new(&guard.frames);
+        drop(guard);
+        self.preview_cache.clear();
+        self.generation += 1;
+        self.update_image_data(display);
+        self.update_vertex_data(display);
+    }
+
+    fn update_image_data(&mut self, display: &Display) {
+        let guard = self.image_data.read().unwrap();
+        let frames = &guard.frames;
+        let image = frames[self.index].buffer();
+        self.size = Vec2::new(image.width() as f32, image.height() as f32);
+        self.pixel_aspect = pixel_aspect(guard.resolution);
+        self.decode_scale = decode_scale(guard.full_size, image.width(), image.height());
+        self.texture = match &mut self.frame_textures {
+            Some(textures) => textures.get_or_upload(frames, self.index, display),
+            None => Rc::new(get_texture(image, display)),
+        };
+        drop(guard);
+        self.onion = self.build_onion(display);
+        // a resize or crop leaves nothing to compare pixel for pixel
+        let true_size = self.true_size();
+        let true_size = Vec2::new(true_size.x() as u32, true_size.y() as u32);
+        if self
+            .wipe
+            .as_ref()
+            .is_some_and(|wipe| wipe.size != true_size)
+        {
+            self.wipe = None;
+        }
+        if self.resize_preview_key.is_some() {
+            self.resize_preview = self.build_resize_preview(display);
+        }
+    }
+
+    fn update_vertex_data(&mut self, display: &Display) {
+        self.vertices = build_vertices(display, self.display_size(), &self.texture_cords);
+        if self.comparison.is_some() {
+            self.comparison = self.build_comparison(display);
+        }
+        if let Some(preview) = &mut self.resize_preview {
+            preview.vertices = build_vertices(display, preview.size, &self.texture_cords);
+        }
+        let size = self.display_size();
+        if let Some(wipe) = &mut self.wipe {
+            wipe.vertices = build_vertices(display, size, &self.texture_cords);
+        }
+    }
+
+    /// Draws the current frame resized to `preview` instead of the image, or stops when `None`.
+    /// Only the displayed texture changes, the image data is left alone.
+    pub fn set_resize_preview(
+        &mut self,
+        display: &Display,
+        preview: Option<(Vec2<u32>, FilterType)>,
+    ) {
+        if preview == self.resize_preview_key {
             return;
         }
 
-        let left = max!(cut.left(), bounds.left()) - bounds.x();
-        let right = min!(cut.right(), bounds.right()) - bounds.x();
+        self.resize_preview_key = preview;
+        self.preview_request = None;
+        self.resize_preview = self.build_resize_preview(display);
+    }
 
-        let top = max!(cut.top(), bounds.top()) - bounds.y();
-        let bottom = min!(cut.bottom(), bounds.bottom()) - bounds.y();
+    fn build_resize_preview(&mut self, display: &Display) -> Option<Comparison> {
+        let (size, filter) = self.resize_preview_key?;
+        if size.x() == 0 || size.y() == 0 {
+            return None;
+        }
 
-        let width = right - left;
-        let height = bottom - top;
+        let index = self.index;
+        let cached = self
+            .preview_cache
+            .iter()
+            .position(|c| c.index == index && c.size == size && c.filter == filter);
+        let cached = match cached {
+            Some(cached) => cached,
+            None => {
+                if self.gpu_resize.is_none() {
+                    self.gpu_resize = GpuResize::new(display);
+                }
+                // sizes over the texture limit are previewed smaller and stretched when drawn
+                let render = gpu_resize::fit_within(size, GpuResize::max_side(display));
+                let image = self
+                    .gpu_resize
+                    .as_ref()
+                    .and_then(|gpu| gpu.resize(display, self.texture.color()?, render, filter));
+                match image {
+                    Some(image) => self.cache_preview(index, size, filter, image),
+                    None => {
+                        self.preview_request = Some(PreviewRequest {
+                            generation: self.generation,
+                            index,
+                            size,
+                            filter,
+                        });
+                        return None;
+                    }
+                }
+            }
+        };
 
-        let normalized_width = 1.0 / (bounds.width() / width);
-        let normalized_height = 1.0 / (bounds.height() / height);
+        let size = Vec2::new(size.x() as f32, size.y() as f32);
+        Some(Comparison {
+            texture: get_texture(&self.preview_cache[cached].image, display),
+            vertices: build_vertices(display, size, &self.texture_cords),
+            size,
+        })
+    }
 
-        let mut normalized_x = 1.0 / (bounds.width() / left);
-        let mut normalized_y = 1.0 / (bounds.height() / top);
+    /// Adds a rendered preview to the cache and returns where it went.
+    fn cache_preview(
+        &mut self,
+        index: usize,
+        size: Vec2<u32>,
+        filter: FilterType,
+        image: DynamicImage,
+    ) -> usize {
+        if self.preview_cache.len() >= PREVIEW_CACHE_SIZE {
+            self.preview_cache.remove(0);
+        }
+        self.preview_cache.push(CachedPreview {
+            index,
+            size,
+            filter,
+            image,
+        });
+        self.preview_cache.len() - 1
+    }
 
-        if self.horizontal_flip {
-            normalized_x = 1.0 - normalized_x - normalized_width;
+    /// Takes the preview the cpu made for `request`. It is dropped if the pixels changed
+    /// in the meantime and only shown if it is still the one being previewed.
+    pub fn add_resize_preview(
+        &mut self,
+        display: &Display,
+        request: PreviewRequest,
+        image: DynamicImage,
+    ) {
+        if request.generation != self.generation {
+            return;
         }
+        self.cache_preview(request.index, request.size, request.filter, image);
+        if self.resize_preview_key == Some((request.size, request.filter)) {
+            self.resize_preview = self.build_resize_preview(display);
+        }
+    }
 
-        if self.vertical_flip {
-            normalized_y = 1.0 - normalized_y - normalized_height;
+    /// Identifies the frame on screen and the state of its pixels.
+    pub fn analysis_key(&self) -> AnalysisKey {
+        (self.generation, self.index)
+    }
+
+    /// Whether the frame on screen has values past white, which the exposure brings into view.
+    pub fn high_dynamic_range(&self) -> bool {
+        self.texture.high_dynamic_range()
+    }
+
+    pub fn comparing(&self) -> bool {
+        self.comparison.is_some()
+    }
+
+    /// Shows `original` instead of the edited frames, `None` goes back to them. This only
+    /// swaps what is drawn so it never touches the image data or the undo stack.
+    pub fn set_comparing(&mut self, display: &Display, original: Option<Vec<Image>>) {
+        if original.is_some() && self.comparing() {
+            return;
+        }
+
+        self.original = original;
+        self.comparison = self.build_comparison(display);
+    }
+
+    fn build_comparison(&self, display: &Display) -> Option<Comparison> {
+        let original = self.original.as_ref()?;
+        let image = original[self.index.min(original.len() - 1)].buffer();
+        let size = Vec2::new(image.width() as f32, image.height() as f32);
+        let size = if self.aspect_corrected() {
+            Vec2::new(
+                size.x() * self.pixel_aspect.x(),
+                size.y() * self.pixel_aspect.y(),
+            )
+        } else {
+            size
+        };
+
+        Some(Comparison {
+            texture: get_texture(image, display),
+            vertices: build_vertices(display, size, &self.texture_cords),
+            size,
+        })
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, display: &Display, paused: bool) {
+        self.paused = paused;
+        self.finished = false;
+        self.last_frame = Instant::now();
+        self.onion = self.build_onion(display);
+    }
+
+    /// Whether the animation stopped because it played as many times as the file asks for.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Plays a finished animation again from the first frame.
+    pub fn replay(&mut self, display: &Display) {
+        self.index = 0;
+        self.plays = 0;
+        self.set_paused(display, false);
+        self.update_image_data(display);
+        if self.comparison.is_some() {
+            self.comparison = self.build_comparison(display);
+        }
+    }
+
+    /// Shows another frame of a paused animation, wrapping around at the ends.
+    pub fn step_frame(&mut self, display: &Display, offset: isize) {
+        let len = self.image_data.read().unwrap().frames.len() as isize;
+        if len < 2 {
+            return;
+        }
+        self.paused = true;
+        self.finished = false;
+        self.index = (self.index as isize + offset).rem_euclid(len) as usize;
+        self.update_image_data(display);
+        if self.comparison.is_some() {
+            self.comparison = self.build_comparison(display);
+        }
+    }
+
+    /// Where an animation is, `None` for still images.
+    pub fn playback(&self) -> Option<Playback> {
+        let frames = self.image_data.read().unwrap().frames.len();
+        if frames < 2 {
+            return None;
+        }
+
+        Some(Playback {
+            index: self.index,
+            elapsed: self.last_frame.elapsed(),
+            paused: self.paused,
+            plays: self.plays,
+            finished: self.finished,
+            frames,
+        })
+    }
+
+    /// Continues an animation from an earlier position if the frames are still the same.
+    pub fn restore_playback(&mut self, display: &Display, playback: Playback) {
+        let frames = self.image_data.read().unwrap().frames.len();
+        if playback.frames != frames || playback.index >= frames {
+            return;
+        }
+
+        self.index = playback.index;
+        self.paused = playback.paused;
+        self.plays = playback.plays;
+        self.finished = playback.finished;
+        self.last_frame = Instant::now()
+            .checked_sub(playback.elapsed)
+            .unwrap_or_else(Instant::now);
+        self.update_image_data(display);
+    }
+
+    pub fn onion_skin(&self) -> bool {
+        self.onion_skin
+    }
+
+    pub fn set_onion_skin(&mut self, display: &Display, onion_skin: bool) {
+        self.onion_skin = onion_skin;
+        self.onion = self.build_onion(display);
+    }
+
+    /// The neighbors of the current frame. Only built while paused and never
+    /// part of the image data so copying and saving are not affected.
+    fn build_onion(&self, display: &Display) -> Option<Onion> {
+        if !self.onion_skin || !self.paused {
+            return None;
+        }
+
+        let guard = self.image_data.read().unwrap();
+        let frames = &guard.frames;
+        if frames.len() < 2 {
+            return None;
+        }
+
+        let prev = self
+            .index
+            .checked_sub(1)
+            .map(|index| get_texture(frames[index].buffer(), display));
+        let next = frames
+            .get(self.index + 1)
+            .map(|frame| get_texture(frame.buffer(), display));
+        Some(Onion { prev, next })
+    }
+
+    pub fn toggle_aspect_correction(&mut self, display: &Display) {
+        self.aspect_correction = !self.aspect_correction;
+        self.update_vertex_data(display);
+    }
+
+    /// Replaces the stored resolution, used when baking the aspect correction into the pixels.
+    pub fn swap_resolution(&mut self, resolution: &mut Option<(f32, f32)>) {
+        let mut guard = self.image_data.write().unwrap();
+        mem::swap(&mut guard.resolution, resolution);
+        self.pixel_aspect = pixel_aspect(guard.resolution);
+    }
+
+    pub fn rotate(&mut self, rot: i32) {
+        self.rotation += rot;
+        if self.rotation > 3 {
+            self.rotation -= 4;
+        } else if self.rotation < 0 {
+            self.rotation += 4;
+        }
+    }
+
+    /// What an op needs to know about the view.
+    pub fn state(&self) -> ViewState {
+        ViewState {
+            image_data: self.image_data.clone(),
+            path: self.path.clone(),
+            size: self.oriented_size(),
+            true_size: self.oriented_true_size(),
+            corrected_size: self.corrected_size(),
+            rotation: self.rotation,
+            horizontal_flip: self.horizontal_flip,
+            vertical_flip: self.vertical_flip,
+            analysis_key: self.analysis_key(),
         }
+    }
+}
+
+/// The parts of the view an op reads, taken when it is queued so the op never holds on to the
+/// view itself and can run without a display.
+#[derive(Clone)]
+pub struct ViewState {
+    pub image_data: Arc<RwLock<ImageData>>,
+    pub path: Option<PathBuf>,
+    /// The size as shown, with width and height swapped by a quarter turn.
+    pub size: Vec2<u32>,
+    /// The size of the image on disk as shown, see `ImageView::oriented_true_size`.
+    pub true_size: Vec2<u32>,
+    /// See `ImageView::corrected_size`.
+    pub corrected_size: Vec2<u32>,
+    pub rotation: i32,
+    pub horizontal_flip: bool,
+    pub vertical_flip: bool,
+    pub analysis_key: AnalysisKey,
+}
+
+impl ViewState {
+    /// `cut` from pixels of the image on disk to pixels of the frames, which are fewer
+    /// while they are decoded at a reduced scale.
+    pub fn frame_rect(&self, cut: Rect) -> Rect {
+        if self.true_size.x() == 0 || self.true_size.y() == 0 {
+            return cut;
+        }
+        cut.scaled(Vec2::new(
+            self.size.x() as f32 / self.true_size.x() as f32,
+            self.size.y() as f32 / self.true_size.y() as f32,
+        ))
+    }
+
+    /// Cuts `cut` out of every frame and resizes the result to `resize` if there is one.
+    /// `cut` is in pixels of the image on disk as it is shown, see `screen_to_pixels`.
+    pub fn crop(&self, cut: Rect, resize: Option<Vec2<u32>>, proxy: Proxy, sender: Sender<Output>) {
+        let size = self.size;
+        let [left, top, width, height] = match pixel_bounds(self.frame_rect(cut), size) {
+            Some(bounds) => bounds,
+            None => {
+                let _ = sender.send(Output::Done);
+                let _ = proxy.send_event(UserEvent::Wake);
+                return;
+            }
+        };
+
+        let x = if self.horizontal_flip {
+            size.x() - left - width
+        } else {
+            left
+        };
+        let y = if self.vertical_flip {
+            size.y() - top - height
+        } else {
+            top
+        };
 
         let rotation = self.rotation;
         let image_data = self.image_data.clone();
@@ -371,72 +1291,216 @@ impl ImageView {
                     _ => unreachable!(),
                 }
 
-                let real_width = (normalized_width * frame.buffer().width() as f32) as u32;
-                let real_height = (normalized_height * frame.buffer().height() as f32) as u32;
-
-                let real_x = (normalized_x * frame.buffer().width() as f32) as u32;
-                let real_y = (normalized_y * frame.buffer().height() as f32) as u32;
-
-                let image = frame
-                    .buffer()
-                    .crop_imm(real_x, real_y, real_width, real_height);
-
-                new_frames.push(Image::with_delay(image, frame.delay));
+                let image = frame.buffer().crop_imm(x, y, width, height);
+                new_frames.push(match resize {
+                    Some(size) => Image::with_delay(
+                        image.resize_exact(size.x(), size.y(), FilterType::Lanczos3),
+                        frame.delay,
+                    ),
+                    None => frame.derive(image),
+                });
             }
 
             let _ = sender.send(Output::Crop(new_frames, old_rotation));
             let _ = proxy.send_event(UserEvent::Wake);
         });
     }
+}
 
-    pub fn swap_frames(&mut self, frames: &mut Vec<Image>, display: &Display) {
-        let mut guard = self.image_data.write().unwrap();
-        mem::swap(&mut guard.frames, frames);
-        drop(guard);
-        self.update_image_data(display);
-        self.update_vertex_data(display);
+/// The view together with the display its textures are uploaded to.
+pub struct GpuView<'a> {
+    view: &'a mut ImageView,
+    display: &'a Display,
+}
+
+impl<'a> GpuView<'a> {
+    pub fn new(view: &'a mut ImageView, display: &'a Display) -> Self {
+        Self { view, display }
     }
+}
 
-    fn update_image_data(&mut self, display: &Display) {
-        let guard = self.image_data.read().unwrap();
-        let frames = &guard.frames;
-        let image = frames[self.index].buffer();
-        self.size = Vec2::new(image.width() as f32, image.height() as f32);
-        self.texture = get_texture(image, display);
+impl EditTarget for GpuView<'_> {
+    fn rotate(&mut self, rotation: i32) {
+        self.view.rotate(rotation);
     }
 
-    fn update_vertex_data(&mut self, display: &Display) {
-        let shape = vec![
-            Vertex::new(0.0, 0.0, self.texture_cords.0.x(), self.texture_cords.0.y()),
-            Vertex::new(
-                0.0,
-                self.size.y(),
-                self.texture_cords.1.x(),
-                self.texture_cords.1.y(),
-            ),
-            Vertex::new(
-                self.size.x(),
-                0.0,
-                self.texture_cords.2.x(),
-                self.texture_cords.2.y(),
-            ),
-            Vertex::new(
-                self.size.x(),
-                self.size.y(),
-                self.texture_cords.3.x(),
-                self.texture_cords.3.y(),
-            ),
-        ];
-        self.vertices = VertexBuffer::new(display, &shape).unwrap();
+    fn flip_horizontal(&mut self) {
+        self.view.flip_horizontal(self.display);
     }
 
-    pub fn rotate(&mut self, rot: i32) {
-        self.rotation += rot;
-        if self.rotation > 3 {
-            self.rotation -= 4;
-        } else if self.rotation < 0 {
-            self.rotation += 4;
+    fn flip_vertical(&mut self) {
+        self.view.flip_vertical(self.display);
+    }
+
+    fn swap_frames(&mut self, frames: &mut Vec<Image>) {
+        self.view.swap_frames(frames, self.display);
+    }
+
+    fn swap_resolution(&mut self, resolution: &mut Option<(f32, f32)>) {
+        self.view.swap_resolution(resolution);
+    }
+
+    fn swap_rotation(&mut self, rotation: &mut i32) {
+        mem::swap(&mut self.view.rotation, rotation);
+    }
+
+    fn clear_angle_preview(&mut self) {
+        self.view.preview_angle = 0.0;
+    }
+
+    fn clear_color_preview(&mut self) {
+        self.view.hue = 0.0;
+        self.view.contrast = 0.0;
+        self.view.saturation = 0.0;
+        self.view.lightness = 0.0;
+        self.view.tone = Tone::default();
+        self.view.white_balance = WhiteBalance::default();
+    }
+
+    fn clear_levels_preview(&mut self) {
+        self.view.levels = Levels::default();
+    }
+}
+
+/// The second image of a wipe compare.
+struct Wipe {
+    texture: FrameTexture,
+    vertices: VertexBuffer<Vertex>,
+    /// Its size in pixels, the wipe ends once the image is no longer that size.
+    size: Vec2<u32>,
+    name: String,
+}
+
+struct Comparison {
+    texture: FrameTexture,
+    vertices: VertexBuffer<Vertex>,
+    size: Vec2<f32>,
+}
+
+struct CachedPreview {
+    index: usize,
+    size: Vec2<u32>,
+    filter: FilterType,
+    image: DynamicImage,
+}
+
+const PREVIEW_CACHE_SIZE: usize = 8;
+
+/// A resize preview for the cpu to render, of the frame at `index` as it was at `generation`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreviewRequest {
+    generation: u64,
+    pub index: usize,
+    pub size: Vec2<u32>,
+    pub filter: FilterType,
+}
+
+impl PreviewRequest {
+    /// Renders the preview from `image`. The cpu is much slower than the gpu so the
+    /// preview is rendered smaller and stretched when drawn.
+    pub fn render(&self, image: &DynamicImage) -> DynamicImage {
+        let size = gpu_resize::fit_within(self.size, CPU_PREVIEW_SIDE);
+        image.thumbnail_exact(size.x(), size.y())
+    }
+}
+
+/// The longest side of a resize preview rendered on the cpu.
+const CPU_PREVIEW_SIDE: u32 = 2048;
+
+/// How many screen pixels a pixel has to cover before the pixel grid shows, 800% zoom.
+const PIXEL_GRID_SCALE: f32 = 8.0;
+
+/// Pixels and side length an svg is rendered at most, zooming further magnifies that.
+const MAX_VECTOR_PIXELS: f32 = 64_000_000.0;
+const MAX_VECTOR_SIDE: f32 = 16384.0;
+
+/// Animations larger than this are uploaded again every frame instead of being kept on the GPU.
+const FRAME_TEXTURE_BYTES: usize = 512 * 1024 * 1024;
+/// How much of an animation is uploaded per rendered frame, at least one frame is.
+const UPLOAD_BYTES_PER_FRAME: usize = 16 * 1024 * 1024;
+
+/// Textures of every frame of an animation so playing it does not upload each frame again.
+struct FrameTextures {
+    textures: Vec<Option<Rc<FrameTexture>>>,
+    uploaded: usize,
+}
+
+impl FrameTextures {
+    /// `None` for still images and animations too large to keep on the GPU.
+    fn new(frames: &[Image]) -> Option<Self> {
+        let bytes: usize = frames
+            .iter()
+            .map(|frame| frame.buffer().as_bytes().len())
+            .sum();
+        (frames.len() > 1 && bytes <= FRAME_TEXTURE_BYTES).then(|| Self {
+            textures: vec![None; frames.len()],
+            uploaded: 0,
+        })
+    }
+
+    fn pending(&self) -> bool {
+        self.uploaded < self.textures.len()
+    }
+
+    fn get_or_upload(
+        &mut self,
+        frames: &[Image],
+        index: usize,
+        display: &Display,
+    ) -> Rc<FrameTexture> {
+        if let Some(texture) = &self.textures[index] {
+            return texture.clone();
         }
+        let texture = Rc::new(get_texture(frames[index].buffer(), display));
+        self.textures[index] = Some(texture.clone());
+        self.uploaded += 1;
+        texture
+    }
+}
+
+struct Onion {
+    prev: Option<FrameTexture>,
+    next: Option<FrameTexture>,
+}
+
+type TextureCords = (Vec2<f32>, Vec2<f32>, Vec2<f32>, Vec2<f32>);
+
+fn build_vertices(
+    display: &Display,
+    size: Vec2<f32>,
+    texture_cords: &TextureCords,
+) -> VertexBuffer<Vertex> {
+    let shape = vec![
+        Vertex::new(0.0, 0.0, texture_cords.0.x(), texture_cords.0.y()),
+        Vertex::new(0.0, size.y(), texture_cords.1.x(), texture_cords.1.y()),
+        Vertex::new(size.x(), 0.0, texture_cords.2.x(), texture_cords.2.y()),
+        Vertex::new(size.x(), size.y(), texture_cords.3.x(), texture_cords.3.y()),
+    ];
+    VertexBuffer::new(display, &shape).unwrap()
+}
+
+fn decode_scale(full_size: Option<(u32, u32)>, width: u32, height: u32) -> Vec2<f32> {
+    match full_size {
+        Some((full_width, full_height)) => Vec2::new(
+            full_width as f32 / width as f32,
+            full_height as f32 / height as f32,
+        ),
+        None => Vec2::new(1.0, 1.0),
+    }
+}
+
+/// How much each axis has to be stretched for the pixels to appear square.
+/// Only ever stretches, so no pixels are lost to downscaling.
+fn pixel_aspect(resolution: Option<(f32, f32)>) -> Vec2<f32> {
+    match resolution {
+        Some((x, y)) if (x / y - 1.0).abs() > 0.001 => {
+            if x > y {
+                Vec2::new(1.0, x / y)
+            } else {
+                Vec2::new(y / x, 1.0)
+            }
+        }
+        _ => Vec2::new(1.0, 1.0),
     }
 }
 
@@ -449,39 +1513,199 @@ fn get_rotation_matrix(rad: f32) -> Matrix4<f32> {
     Matrix4::from_angle_z(cgmath::Rad(rad))
 }
 
-fn get_texture(image: &DynamicImage, display: &Display) -> SrgbTexture2d {
-    let (width, height) = image.dimensions();
+/// A frame on the GPU in the format of its color type. Gray frames keep one or two channels
+/// and are spread to rgb in the shader.
+pub enum FrameTexture {
+    Color(SrgbTexture2d),
+    /// Gray values, with alpha in the second channel when the bool is set.
+    Gray(Texture2d, bool),
+    /// Linear light as floating point, which can go past white.
+    Linear(Texture2d),
+}
+
+impl FrameTexture {
+    /// The texture if it holds srgb color, which the gpu resize needs.
+    pub fn color(&self) -> Option<&SrgbTexture2d> {
+        match self {
+            FrameTexture::Color(texture) => Some(texture),
+            FrameTexture::Gray(..) | FrameTexture::Linear(_) => None,
+        }
+    }
+
+    /// Whether the texture holds values past white that exposure brings into view.
+    pub fn high_dynamic_range(&self) -> bool {
+        matches!(self, FrameTexture::Linear(_))
+    }
+
+    /// How the shader reads the texture.
+    fn gray_mode(&self) -> i32 {
+        match self {
+            FrameTexture::Color(_) => 0,
+            FrameTexture::Gray(_, false) => 1,
+            FrameTexture::Gray(_, true) => 2,
+            FrameTexture::Linear(_) => 3,
+        }
+    }
+}
+
+fn raw_image<P: PixelValue + Clone>(
+    data: &[P],
+    (width, height): (u32, u32),
+    format: ClientFormat,
+) -> RawImage2d<'_, P> {
+    RawImage2d {
+        data: Cow::Borrowed(data),
+        width,
+        height,
+        format,
+    }
+}
+
+fn color_texture<P: PixelValue + Clone>(
+    display: &Display,
+    raw: RawImage2d<'_, P>,
+    format: SrgbFormat,
+) -> FrameTexture {
+    FrameTexture::Color(
+        SrgbTexture2d::with_format(display, raw, format, MipmapsOption::AutoGeneratedMipmaps)
+            .unwrap(),
+    )
+}
+
+fn gray_texture<P: PixelValue + Clone>(
+    display: &Display,
+    raw: RawImage2d<'_, P>,
+    format: UncompressedFloatFormat,
+    alpha: bool,
+) -> FrameTexture {
+    FrameTexture::Gray(
+        Texture2d::with_format(display, raw, format, MipmapsOption::AutoGeneratedMipmaps).unwrap(),
+        alpha,
+    )
+}
+
+/// Half floats keep the range of hdr images at half the memory.
+fn linear_texture(
+    display: &Display,
+    raw: RawImage2d<'_, f32>,
+    format: UncompressedFloatFormat,
+) -> FrameTexture {
+    FrameTexture::Linear(
+        Texture2d::with_format(display, raw, format, MipmapsOption::AutoGeneratedMipmaps).unwrap(),
+    )
+}
+
+fn get_texture(image: &DynamicImage, display: &Display) -> FrameTexture {
+    let size = image.dimensions();
 
     match image {
-        DynamicImage::ImageRgba8(buffer) => {
-            let data = Cow::Borrowed(&buffer.as_raw()[..]);
-            let raw = RawImage2d {
-                data,
-                width,
-                height,
-                format: ClientFormat::U8U8U8U8,
-            };
-            SrgbTexture2d::with_mipmaps(display, raw, MipmapsOption::AutoGeneratedMipmaps).unwrap()
-        }
-        DynamicImage::ImageRgba16(buffer) => {
-            let data = Cow::Borrowed(&buffer.as_raw()[..]);
-            let raw = RawImage2d {
-                data,
-                width,
-                height,
-                format: ClientFormat::U16U16U16U16,
-            };
-            SrgbTexture2d::with_mipmaps(display, raw, MipmapsOption::AutoGeneratedMipmaps).unwrap()
-        }
-        _ => {
-            let data = Cow::Owned(image.to_rgba8().into_raw());
-            let raw = RawImage2d {
-                data,
-                width,
-                height,
-                format: ClientFormat::U8U8U8U8,
-            };
-            SrgbTexture2d::with_mipmaps(display, raw, MipmapsOption::AutoGeneratedMipmaps).unwrap()
+        DynamicImage::ImageLuma8(buffer) => gray_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::U8),
+            UncompressedFloatFormat::U8,
+            false,
+        ),
+        DynamicImage::ImageLumaA8(buffer) => gray_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::U8U8),
+            UncompressedFloatFormat::U8U8,
+            true,
+        ),
+        DynamicImage::ImageLuma16(buffer) => gray_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::U16),
+            UncompressedFloatFormat::U16,
+            false,
+        ),
+        DynamicImage::ImageLumaA16(buffer) => gray_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::U16U16),
+            UncompressedFloatFormat::U16U16,
+            true,
+        ),
+        DynamicImage::ImageRgb8(buffer) => color_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::U8U8U8),
+            SrgbFormat::U8U8U8,
+        ),
+        DynamicImage::ImageRgba8(buffer) => color_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::U8U8U8U8),
+            SrgbFormat::U8U8U8U8,
+        ),
+        DynamicImage::ImageRgba16(buffer) => color_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::U16U16U16U16),
+            SrgbFormat::U8U8U8U8,
+        ),
+        DynamicImage::ImageRgb32F(buffer) => linear_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::F32F32F32),
+            UncompressedFloatFormat::F16F16F16,
+        ),
+        DynamicImage::ImageRgba32F(buffer) => linear_texture(
+            display,
+            raw_image(buffer.as_raw(), size, ClientFormat::F32F32F32F32),
+            UncompressedFloatFormat::F16F16F16F16,
+        ),
+        _ => color_texture(
+            display,
+            raw_image(&image.to_rgba8().into_raw(), size, ClientFormat::U8U8U8U8),
+            SrgbFormat::U8U8U8U8,
+        ),
+    }
+}
+
+/// The whole pixels `cut` covers of an image of `size` as `[x, y, width, height]`, `None`
+/// when that is nothing.
+pub fn pixel_bounds(cut: Rect, size: Vec2<u32>) -> Option<[u32; 4]> {
+    let left = (cut.left().round().max(0.0) as u32).min(size.x());
+    let right = (cut.right().round().max(0.0) as u32).min(size.x());
+    let top = (cut.top().round().max(0.0) as u32).min(size.y());
+    let bottom = (cut.bottom().round().max(0.0) as u32).min(size.y());
+    (right > left && bottom > top).then(|| [left, top, right - left, bottom - top])
+}
+
+/// Applies the view rotation and flips to a frame so it matches what is on screen.
+pub fn oriented(
+    buffer: &DynamicImage,
+    rotation: i32,
+    horizontal_flip: bool,
+    vertical_flip: bool,
+) -> Cow<'_, DynamicImage> {
+    let buffer = match rotation {
+        0 => Cow::Borrowed(buffer),
+        1 => Cow::Owned(buffer.rotate270()),
+        2 => Cow::Owned(buffer.rotate180()),
+        3 => Cow::Owned(buffer.rotate90()),
+        _ => unreachable!("image is rotated more then 360 degrees"),
+    };
+
+    match buffer {
+        Cow::Owned(mut buffer) => {
+            if horizontal_flip {
+                flip_horizontal_in_place(&mut buffer);
+            }
+
+            if vertical_flip {
+                flip_vertical_in_place(&mut buffer);
+            }
+            Cow::Owned(buffer)
+        }
+        Cow::Borrowed(buffer) => {
+            if !horizontal_flip && !vertical_flip {
+                Cow::Borrowed(buffer)
+            } else {
+                let mut buffer = buffer.clone();
+                if horizontal_flip {
+                    flip_horizontal_in_place(&mut buffer);
+                }
+
+                if vertical_flip {
+                    flip_vertical_in_place(&mut buffer);
+                }
+                Cow::Owned(buffer)
+            }
         }
     }
 }
@@ -0,0 +1,184 @@
+use std::{
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc, RwLock},
+    thread,
+    time::Duration,
+};
+
+use egui::DragValue;
+use glium::Display;
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, RgbaImage,
+};
+
+use super::{
+    i18n::{tr, trf},
+    load_image::{load_uncached, LoadOptions},
+    op_queue::{Op, Output, Progress},
+    App,
+};
+use crate::util::{display_name, Image, ImageData, Proxy, UserEvent};
+
+/// The delay between frames, in milliseconds, that is offered first.
+const DEFAULT_DELAY: u32 = 100;
+
+/// Still images picked to be put together into an animation, waiting for the delay.
+pub struct ImportFrames {
+    paths: Vec<PathBuf>,
+    /// The delay of each frame in milliseconds.
+    delay: u32,
+}
+
+pub fn open_dialog(proxy: Proxy, display: &Display) {
+    let dialog = rfd::AsyncFileDialog::new().set_parent(display.gl_window().window());
+    thread::spawn(move || {
+        if let Some(files) = futures_executor::block_on(dialog.pick_files()) {
+            let paths = files.iter().map(|file| file.path().to_path_buf()).collect();
+            let _ = proxy.send_event(UserEvent::ImportFrames(paths));
+        }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
+    });
+}
+
+/// `image` at `size`, scaled to fit and centered on a transparent canvas when it is another
+/// size, since every frame of an animation has the size of the first.
+fn fit_frame(image: DynamicImage, size: (u32, u32)) -> DynamicImage {
+    if (image.width(), image.height()) == size {
+        return image;
+    }
+    let scaled = image
+        .resize(size.0, size.1, FilterType::Triangle)
+        .into_rgba8();
+    let mut canvas = RgbaImage::new(size.0, size.1);
+    let x = (size.0 - scaled.width()) / 2;
+    let y = (size.1 - scaled.height()) / 2;
+    imageops::overlay(&mut canvas, &scaled, x as i64, y as i64);
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// Loads the first frame of each image in order and shows them as one unsaved animation.
+pub fn build(
+    paths: Vec<PathBuf>,
+    delay: Duration,
+    proxy: Proxy,
+    sender: Sender<Output>,
+    progress: Progress,
+) {
+    thread::spawn(move || {
+        let mut frames: Vec<Image> = Vec::new();
+        for (index, path) in paths.iter().enumerate() {
+            let image = match load_uncached(path, LoadOptions::default()) {
+                Ok(mut image_data) => image_data.frames.swap_remove(0).image,
+                Err(error) => {
+                    let _ = sender.send(Output::Done);
+                    let _ = proxy.send_event(UserEvent::ErrorMessage(format!(
+                        "Could not import {}: {}",
+                        display_name(path),
+                        error
+                    )));
+                    return;
+                }
+            };
+            let image = match frames.first() {
+                Some(first) => fit_frame(image, (first.image.width(), first.image.height())),
+                None => image,
+            };
+            frames.push(Image::with_delay(image, delay));
+            progress.set(index + 1, paths.len());
+        }
+
+        let image_data = Arc::new(RwLock::new(ImageData::from(frames)));
+        let _ = sender.send(Output::ImageLoaded(image_data, None));
+        let _ = proxy.send_event(UserEvent::Wake);
+    });
+}
+
+impl App {
+    pub fn pick_frames(&mut self, mut paths: Vec<PathBuf>) {
+        if paths.len() < 2 {
+            self.toasts.push(String::from(
+                "Pick at least two images to make an animation",
+            ));
+            return;
+        }
+        // dialogs hand them back in no particular order, numbered names sort into place
+        paths.sort();
+        self.import_frames = Some(ImportFrames {
+            paths,
+            delay: DEFAULT_DELAY,
+        });
+    }
+
+    pub fn import_frames_ui(&mut self, ctx: &egui::Context) {
+        let import = match &mut self.import_frames {
+            Some(import) => import,
+            None => return,
+        };
+
+        let mut done = false;
+        let mut confirmed = false;
+        let mut open = true;
+        egui::Window::new(tr("Import frames as animation"))
+            .id(egui::Id::new("import frames window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(trf(
+                    "{} images, in the order of their names",
+                    &[&import.paths.len()],
+                ));
+                ui.horizontal(|ui| {
+                    ui.label(tr("Frame delay:"));
+                    ui.add(
+                        DragValue::new(&mut import.delay)
+                            .clamp_range(10..=10_000)
+                            .suffix(" ms"),
+                    );
+                });
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Import")).clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        done = true;
+                    }
+                });
+            });
+
+        if !open || ctx.input().key_pressed(egui::Key::Escape) {
+            done = true;
+        }
+
+        if confirmed {
+            let import = self.import_frames.take().unwrap();
+            let delay = Duration::from_millis(import.delay as u64);
+            self.queue(Op::ImportFrames(import.paths, delay));
+        } else if done {
+            self.import_frames = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_take_the_first_size() {
+        let same = fit_frame(DynamicImage::new_rgba8(40, 30), (40, 30));
+        assert_eq!((same.width(), same.height()), (40, 30));
+
+        // scaled to fit and centered, the rest stays transparent
+        let wide =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(80, 30, image::Rgba([255, 0, 0, 255])));
+        let fitted = fit_frame(wide, (40, 30)).into_rgba8();
+        assert_eq!(fitted.dimensions(), (40, 30));
+        assert_eq!(fitted.get_pixel(20, 0)[3], 0);
+        assert_eq!(fitted.get_pixel(20, 15), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(fitted.get_pixel(20, 29)[3], 0);
+    }
+}
@@ -0,0 +1,433 @@
+use std::{collections::BTreeMap, fmt};
+
+use egui::{Color32, RichText, ScrollArea};
+use glium::glutin::event::{ModifiersState, VirtualKeyCode};
+
+use super::{
+    action::Action,
+    i18n::{tr, trf},
+    App,
+};
+
+/// The keys that can be bound, with the names they are written as in the config file.
+const KEYS: &[(VirtualKeyCode, &str)] = &[
+    (VirtualKeyCode::A, "A"),
+    (VirtualKeyCode::B, "B"),
+    (VirtualKeyCode::C, "C"),
+    (VirtualKeyCode::D, "D"),
+    (VirtualKeyCode::E, "E"),
+    (VirtualKeyCode::F, "F"),
+    (VirtualKeyCode::G, "G"),
+    (VirtualKeyCode::H, "H"),
+    (VirtualKeyCode::I, "I"),
+    (VirtualKeyCode::J, "J"),
+    (VirtualKeyCode::K, "K"),
+    (VirtualKeyCode::L, "L"),
+    (VirtualKeyCode::M, "M"),
+    (VirtualKeyCode::N, "N"),
+    (VirtualKeyCode::O, "O"),
+    (VirtualKeyCode::P, "P"),
+    (VirtualKeyCode::Q, "Q"),
+    (VirtualKeyCode::R, "R"),
+    (VirtualKeyCode::S, "S"),
+    (VirtualKeyCode::T, "T"),
+    (VirtualKeyCode::U, "U"),
+    (VirtualKeyCode::V, "V"),
+    (VirtualKeyCode::W, "W"),
+    (VirtualKeyCode::X, "X"),
+    (VirtualKeyCode::Y, "Y"),
+    (VirtualKeyCode::Z, "Z"),
+    (VirtualKeyCode::Key0, "0"),
+    (VirtualKeyCode::Key1, "1"),
+    (VirtualKeyCode::Key2, "2"),
+    (VirtualKeyCode::Key3, "3"),
+    (VirtualKeyCode::Key4, "4"),
+    (VirtualKeyCode::Key5, "5"),
+    (VirtualKeyCode::Key6, "6"),
+    (VirtualKeyCode::Key7, "7"),
+    (VirtualKeyCode::Key8, "8"),
+    (VirtualKeyCode::Key9, "9"),
+    (VirtualKeyCode::F1, "F1"),
+    (VirtualKeyCode::F2, "F2"),
+    (VirtualKeyCode::F3, "F3"),
+    (VirtualKeyCode::F4, "F4"),
+    (VirtualKeyCode::F5, "F5"),
+    (VirtualKeyCode::F6, "F6"),
+    (VirtualKeyCode::F7, "F7"),
+    (VirtualKeyCode::F8, "F8"),
+    (VirtualKeyCode::F9, "F9"),
+    (VirtualKeyCode::F10, "F10"),
+    (VirtualKeyCode::F11, "F11"),
+    (VirtualKeyCode::F12, "F12"),
+    (VirtualKeyCode::Left, "Left"),
+    (VirtualKeyCode::Right, "Right"),
+    (VirtualKeyCode::Up, "Up"),
+    (VirtualKeyCode::Down, "Down"),
+    (VirtualKeyCode::Home, "Home"),
+    (VirtualKeyCode::End, "End"),
+    (VirtualKeyCode::PageUp, "Page Up"),
+    (VirtualKeyCode::PageDown, "Page Down"),
+    (VirtualKeyCode::Insert, "Insert"),
+    (VirtualKeyCode::Delete, "Delete"),
+    (VirtualKeyCode::Back, "Backspace"),
+    (VirtualKeyCode::Return, "Enter"),
+    (VirtualKeyCode::Space, "Space"),
+    (VirtualKeyCode::Tab, "Tab"),
+    (VirtualKeyCode::Comma, ","),
+    (VirtualKeyCode::Period, "."),
+    (VirtualKeyCode::Slash, "/"),
+    (VirtualKeyCode::Semicolon, ";"),
+    (VirtualKeyCode::Apostrophe, "'"),
+    (VirtualKeyCode::LBracket, "["),
+    (VirtualKeyCode::RBracket, "]"),
+    (VirtualKeyCode::Grave, "`"),
+];
+
+/// A key together with the modifiers that have to be held for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Binding {
+    /// `None` for keys that can not be bound, like the modifiers themselves.
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Self> {
+        KEYS.iter()
+            .any(|(bindable, _)| *bindable == key)
+            .then(|| Self {
+                key,
+                ctrl: modifiers.ctrl(),
+                shift: modifiers.shift(),
+                alt: modifiers.alt(),
+            })
+    }
+
+    /// Reads what `Display` writes, like `Ctrl + Shift + P`, ignoring case.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split(" + ").map(str::trim).collect();
+        let name = parts.pop()?;
+        let key = KEYS
+            .iter()
+            .find(|(_, key_name)| key_name.eq_ignore_ascii_case(name))?
+            .0;
+        let mut binding = Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" => binding.alt = true,
+                _ => return None,
+            }
+        }
+        Some(binding)
+    }
+
+    fn matches(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.ctrl()
+            && self.shift == modifiers.shift()
+            && self.alt == modifiers.alt()
+    }
+
+    fn modifiers(&self) -> ModifiersState {
+        let mut modifiers = ModifiersState::empty();
+        modifiers.set(ModifiersState::CTRL, self.ctrl);
+        modifiers.set(ModifiersState::SHIFT, self.shift);
+        modifiers.set(ModifiersState::ALT, self.alt);
+        modifiers
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = KEYS
+            .iter()
+            .find(|(key, _)| *key == self.key)
+            .map_or("?", |(_, name)| name);
+        for (held, modifier) in [
+            (self.ctrl, "Ctrl"),
+            (self.shift, "Shift"),
+            (self.alt, "Alt"),
+        ] {
+            if held {
+                write!(f, "{} + ", modifier)?;
+            }
+        }
+        write!(f, "{}", name)
+    }
+}
+
+/// The name an action is stored under, stable as long as the variant is not renamed.
+fn action_id(action: Action) -> String {
+    format!("{:?}", action)
+}
+
+/// The keys of the actions the user rebound, every other action keeps its built-in keys
+/// from `Action::from_key`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Keymap {
+    /// An empty list leaves the action without a key.
+    custom: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Keymap {
+    /// Reads the `[keys]` table of the config, entries that can not be read are skipped.
+    pub fn from_config(keys: &BTreeMap<String, Vec<String>>) -> Self {
+        let mut custom = BTreeMap::new();
+        for (id, bindings) in keys {
+            let action = match Action::ALL.iter().find(|action| action_id(**action) == *id) {
+                Some(action) => *action,
+                None => continue,
+            };
+            let bindings = bindings.iter().filter_map(|text| Binding::parse(text));
+            custom.insert(action, bindings.collect());
+        }
+        Self { custom }
+    }
+
+    pub fn to_config(&self) -> BTreeMap<String, Vec<String>> {
+        self.custom
+            .iter()
+            .map(|(action, bindings)| {
+                let bindings = bindings.iter().map(Binding::to_string).collect();
+                (action_id(*action), bindings)
+            })
+            .collect()
+    }
+
+    /// The action bound to the key, rebound keys first and then the built-in ones of
+    /// actions that were not rebound.
+    pub fn action(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Action> {
+        let custom = self.custom.iter().find(|(_, bindings)| {
+            bindings
+                .iter()
+                .any(|binding| binding.matches(key, modifiers))
+        });
+        if let Some((action, _)) = custom {
+            return Some(*action);
+        }
+        Action::from_key(key, modifiers).filter(|action| !self.custom.contains_key(action))
+    }
+
+    /// The keys of `action` as the help and the command palette show them.
+    pub fn hotkey(&self, action: Action) -> Option<String> {
+        match self.custom.get(&action) {
+            Some(bindings) if bindings.is_empty() => None,
+            Some(bindings) => {
+                let names: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+                Some(names.join(" or "))
+            }
+            None => action.hotkey().map(String::from),
+        }
+    }
+
+    pub fn rebound(&self, action: Action) -> bool {
+        self.custom.contains_key(&action)
+    }
+
+    /// Makes `binding` the only key of `action`.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        self.custom.insert(action, vec![binding]);
+    }
+
+    pub fn unbind(&mut self, action: Action) {
+        self.custom.insert(action, Vec::new());
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.custom.remove(&action);
+    }
+
+    /// Another action that `binding` is bound to, either rebound to it as well or an
+    /// action that was not rebound and has it built in.
+    fn conflict(&self, action: Action, binding: Binding) -> Option<Action> {
+        let custom = self
+            .custom
+            .iter()
+            .find(|(other, bindings)| **other != action && bindings.contains(&binding));
+        if let Some((other, _)) = custom {
+            return Some(*other);
+        }
+        Action::from_key(binding.key, binding.modifiers())
+            .filter(|other| *other != action && !self.custom.contains_key(other))
+    }
+}
+
+#[derive(Default)]
+pub struct ShortcutEditor {
+    pub visible: bool,
+    /// The action whose new key is awaited.
+    pub capturing: Option<Action>,
+}
+
+impl App {
+    /// Takes the next key press as the new key of the action being edited, Escape cancels.
+    pub fn capture_shortcut(&mut self, key: VirtualKeyCode) {
+        let action = match self.shortcuts.capturing {
+            Some(action) => action,
+            None => return,
+        };
+        if key == VirtualKeyCode::Escape {
+            self.shortcuts.capturing = None;
+        } else if let Some(binding) = Binding::new(key, self.modifiers) {
+            self.keymap.bind(action, binding);
+            self.shortcuts.capturing = None;
+        }
+    }
+
+    pub fn shortcuts_ui(&mut self, ctx: &egui::Context) {
+        if !self.shortcuts.visible {
+            return;
+        }
+
+        let mut open = true;
+        let keymap = &mut self.keymap;
+        let editor = &mut self.shortcuts;
+        egui::Window::new(tr("Keyboard shortcuts"))
+            .id(egui::Id::new("shortcuts window"))
+            .collapsible(false)
+            .default_size([460.0, 480.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(tr(
+                    "Click a shortcut and press the new keys, Escape cancels.",
+                ));
+                ui.separator();
+                ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("shortcuts grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for action in Action::ALL.iter().filter(|action| action.supported()) {
+                                let action = *action;
+                                ui.label(tr(action.name()));
+
+                                let text = if editor.capturing == Some(action) {
+                                    String::from(tr("Press keys…"))
+                                } else {
+                                    keymap
+                                        .hotkey(action)
+                                        .unwrap_or_else(|| String::from(tr("None")))
+                                };
+                                if ui.button(text).clicked() {
+                                    editor.capturing = Some(action);
+                                }
+
+                                ui.horizontal(|ui| {
+                                    if ui.small_button(tr("Clear")).clicked() {
+                                        keymap.unbind(action);
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            keymap.rebound(action),
+                                            egui::Button::new(tr("Reset")).small(),
+                                        )
+                                        .clicked()
+                                    {
+                                        keymap.reset(action);
+                                    }
+                                    let conflict =
+                                        keymap.custom.get(&action).and_then(|bindings| {
+                                            bindings.iter().find_map(|binding| {
+                                                keymap.conflict(action, *binding)
+                                            })
+                                        });
+                                    if let Some(other) = conflict {
+                                        ui.label(
+                                            RichText::new("⚠")
+                                                .color(Color32::from_rgb(230, 190, 80)),
+                                        )
+                                        .on_hover_text(
+                                            trf("Also bound to {}", &[&tr(other.name())]),
+                                        );
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        if !open {
+            self.shortcuts.visible = false;
+            self.shortcuts.capturing = None;
+            if let Err(error) = self.save_config() {
+                self.toasts.push(error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_read_what_they_write() {
+        let binding = Binding::parse("Ctrl + Shift + P").unwrap();
+        assert_eq!(binding.key, VirtualKeyCode::P);
+        assert!(binding.ctrl && binding.shift && !binding.alt);
+        assert_eq!(binding.to_string(), "Ctrl + Shift + P");
+        assert_eq!(
+            Binding::parse("alt + page up").unwrap().to_string(),
+            "Alt + Page Up"
+        );
+        assert_eq!(Binding::parse(",").unwrap().key, VirtualKeyCode::Comma);
+        assert_eq!(Binding::parse("Hyper + P"), None);
+        assert_eq!(Binding::parse("Ctrl + "), None);
+        assert_eq!(
+            Binding::new(VirtualKeyCode::LControl, ModifiersState::CTRL),
+            None
+        );
+    }
+
+    #[test]
+    fn rebinding_replaces_the_built_in_keys() {
+        let none = ModifiersState::empty();
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(VirtualKeyCode::Q, none),
+            Some(Action::RotateLeft)
+        );
+
+        keymap.bind(Action::RotateLeft, Binding::parse("J").unwrap());
+        assert_eq!(
+            keymap.action(VirtualKeyCode::J, none),
+            Some(Action::RotateLeft)
+        );
+        assert_eq!(keymap.action(VirtualKeyCode::Q, none), None);
+        assert_eq!(keymap.hotkey(Action::RotateLeft).as_deref(), Some("J"));
+
+        // a rebound key wins over the action that had it built in
+        keymap.bind(Action::Help, Binding::parse("E").unwrap());
+        assert_eq!(keymap.action(VirtualKeyCode::E, none), Some(Action::Help));
+        // but the rebound action warns about it
+        let e = Binding::parse("E").unwrap();
+        assert_eq!(keymap.conflict(Action::Help, e), Some(Action::RotateRight));
+        keymap.bind(Action::Crop, e);
+        assert_eq!(keymap.conflict(Action::Crop, e), Some(Action::Help));
+        keymap.reset(Action::Crop);
+        let j = Binding::parse("J").unwrap();
+        assert_eq!(keymap.conflict(Action::RotateLeft, j), None);
+
+        keymap.unbind(Action::Fullscreen);
+        assert_eq!(keymap.action(VirtualKeyCode::F11, none), None);
+        assert_eq!(keymap.hotkey(Action::Fullscreen), None);
+
+        let reloaded = Keymap::from_config(&keymap.to_config());
+        assert_eq!(reloaded, keymap);
+
+        keymap.reset(Action::RotateLeft);
+        assert_eq!(
+            keymap.action(VirtualKeyCode::Q, none),
+            Some(Action::RotateLeft)
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use egui::{pos2, vec2, Color32, Sense, Slider, Stroke};
+use image::DynamicImage;
+
+use super::{color::map_colors, i18n::tr, op_queue::Op, App};
+
+const CHANNELS: [&str; 4] = ["RGB", "Red", "Green", "Blue"];
+const HISTOGRAM_HEIGHT: f32 = 100.0;
+
+/// Input black and white points, the gamma between them and the output range they are
+/// stretched to, all but the gamma from 0 to 255.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Level {
+    pub black: u8,
+    pub white: u8,
+    pub gamma: f32,
+    pub output_black: u8,
+    pub output_white: u8,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            black: 0,
+            white: 255,
+            gamma: 1.0,
+            output_black: 0,
+            output_white: 255,
+        }
+    }
+}
+
+impl Level {
+    fn apply(&self, value: f32) -> f32 {
+        let black = self.black as f32 / 255.0;
+        let range = (self.white as f32 / 255.0 - black).max(1.0 / 255.0);
+        let value = ((value - black) / range)
+            .clamp(0.0, 1.0)
+            .powf(1.0 / self.gamma);
+        let (low, high) = (
+            self.output_black as f32 / 255.0,
+            self.output_white as f32 / 255.0,
+        );
+        low + value * (high - low)
+    }
+}
+
+/// The levels of every channel together, then those of red, green and blue which are applied
+/// first. Gray images only have the first.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Levels(pub [Level; 4]);
+
+impl Levels {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(&self, channel: Option<usize>, value: f32) -> f32 {
+        let value = match channel {
+            Some(channel) => self.0[channel + 1].apply(value),
+            None => value,
+        };
+        self.0[0].apply(value)
+    }
+
+    /// `buffer` with the levels applied, alpha is left alone.
+    pub fn adjust(&self, buffer: &DynamicImage) -> DynamicImage {
+        if self.is_identity() {
+            return buffer.clone();
+        }
+        map_colors(buffer, |channel, value| self.apply(channel, value))
+    }
+
+    /// The black points, white points, gammas, output blacks and output whites of the
+    /// channels in order, from zero to one, for the shader.
+    pub fn uniforms(&self) -> [[f32; 4]; 5] {
+        let channels = |f: fn(&Level) -> f32| [0, 1, 2, 3].map(|index| f(&self.0[index]));
+        [
+            channels(|level| level.black as f32 / 255.0),
+            channels(|level| level.white as f32 / 255.0),
+            channels(|level| level.gamma),
+            channels(|level| level.output_black as f32 / 255.0),
+            channels(|level| level.output_white as f32 / 255.0),
+        ]
+    }
+}
+
+#[derive(Default)]
+pub struct LevelsWindow {
+    pub visible: bool,
+    /// The index of the channel being edited, 0 is all of them.
+    channel: usize,
+}
+
+/// Draws the histogram of `counts` with the input points of `level` over it.
+fn histogram_ui(ui: &mut egui::Ui, counts: Option<&[u32; 256]>, level: &Level, color: Color32) {
+    let (rect, _) = ui.allocate_exact_size(vec2(256.0, HISTOGRAM_HEIGHT), Sense::hover());
+    let painter = ui.painter().sub_region(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+    match counts {
+        Some(counts) => {
+            let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+            for (value, count) in counts.iter().enumerate() {
+                if *count == 0 {
+                    continue;
+                }
+                let x = rect.left() + value as f32 + 0.5;
+                let height = *count as f32 / max * rect.height();
+                painter.line_segment(
+                    [pos2(x, rect.bottom()), pos2(x, rect.bottom() - height)],
+                    Stroke::new(1.0, color),
+                );
+            }
+        }
+        None => {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                tr("Analyzing…"),
+                egui::TextStyle::Body.resolve(ui.style()),
+                ui.visuals().weak_text_color(),
+            );
+        }
+    }
+
+    // where the input black and white points are, and the middle gray between them
+    let stroke = Stroke::new(1.0, ui.visuals().strong_text_color());
+    let x = |value: f32| rect.left() + value + 0.5;
+    let middle =
+        level.black as f32 + (level.white as f32 - level.black as f32) * 0.5f32.powf(level.gamma);
+    for value in [level.black as f32, middle, level.white as f32] {
+        painter.line_segment(
+            [pos2(x(value), rect.top()), pos2(x(value), rect.bottom())],
+            stroke,
+        );
+    }
+    painter.rect_stroke(rect, 0.0, stroke);
+}
+
+impl App {
+    pub fn levels_ui(&mut self, ctx: &egui::Context) {
+        if !self.levels.visible {
+            return;
+        }
+
+        let histogram = self
+            .current_analysis()
+            .map(|analysis| analysis.histogram[self.levels.channel]);
+        let available = self.view_available();
+        let mut open = self.image_view.is_some();
+        let mut closed = false;
+        let mut apply = None;
+        if let Some(view) = self.image_view.as_mut() {
+            let channel = &mut self.levels.channel;
+            egui::Window::new(tr("Levels"))
+                .id(egui::Id::new("levels window"))
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        for (index, name) in CHANNELS.iter().enumerate() {
+                            ui.selectable_value(channel, index, tr(name));
+                        }
+                    });
+
+                    let level = &mut view.levels.0[*channel];
+                    let color = match *channel {
+                        1 => Color32::from_rgb(230, 80, 80),
+                        2 => Color32::from_rgb(80, 200, 80),
+                        3 => Color32::from_rgb(90, 130, 240),
+                        _ => ui.visuals().text_color(),
+                    };
+                    histogram_ui(ui, histogram.as_ref(), level, color);
+
+                    egui::Grid::new("levels grid").show(ui, |ui| {
+                        ui.label(tr("Input black:"));
+                        ui.add(Slider::new(
+                            &mut level.black,
+                            0..=level.white.saturating_sub(1),
+                        ));
+                        ui.end_row();
+                        ui.label(tr("Gamma:"));
+                        ui.add(Slider::new(&mut level.gamma, 0.1..=10.0).logarithmic(true));
+                        ui.end_row();
+                        ui.label(tr("Input white:"));
+                        ui.add(Slider::new(
+                            &mut level.white,
+                            level.black.saturating_add(1)..=255,
+                        ));
+                        ui.end_row();
+                        ui.label(tr("Output black:"));
+                        ui.add(Slider::new(&mut level.output_black, 0..=255));
+                        ui.end_row();
+                        ui.label(tr("Output white:"));
+                        ui.add(Slider::new(&mut level.output_white, 0..=255));
+                        ui.end_row();
+                    });
+
+                    let mut reset = false;
+                    ui.horizontal(|ui| {
+                        reset = ui.button(tr("Reset")).clicked();
+                        if ui
+                            .add_enabled(available, egui::Button::new(tr("Cancel")))
+                            .clicked()
+                        {
+                            closed = true;
+                        }
+                        if ui
+                            .add_enabled(
+                                available && !view.levels.is_identity(),
+                                egui::Button::new(tr("Apply")),
+                            )
+                            .clicked()
+                        {
+                            apply = Some(view.levels);
+                        }
+                    });
+                    if reset {
+                        view.levels.0[*channel] = Level::default();
+                    }
+                });
+        }
+
+        // the frame or the pixels changed while the window was open
+        if open && histogram.is_none() && !self.op_queue.working() {
+            self.queue(Op::AnalyzeColors);
+        }
+        if let Some(levels) = apply {
+            self.queue(Op::Levels(levels));
+        }
+        self.levels.visible = open && !closed;
+        if !self.levels.visible {
+            if let Some(view) = self.image_view.as_mut() {
+                view.levels = Levels::default();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Luma, LumaA, Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn points_stretch_and_gamma_bends() {
+        let level = Level {
+            black: 64,
+            white: 192,
+            ..Level::default()
+        };
+        assert_eq!(level.apply(32.0 / 255.0), 0.0);
+        assert!((level.apply(128.0 / 255.0) - 0.5).abs() < 1e-6);
+        assert_eq!(level.apply(1.0), 1.0);
+
+        let level = Level {
+            gamma: 2.0,
+            output_black: 51,
+            output_white: 204,
+            ..Level::default()
+        };
+        assert!((level.apply(0.25) - 0.5).abs() < 1e-6);
+        assert!((level.apply(0.0) - 0.2).abs() < 1e-6);
+        assert!((level.apply(1.0) - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn channels_come_before_all_of_them() {
+        let mut levels = Levels::default();
+        levels.0[1].output_white = 127;
+        levels.0[0].black = 127;
+
+        let color = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([255, 255, 0])));
+        let adjusted = levels.adjust(&color).into_rgb8();
+        assert_eq!(adjusted.get_pixel(0, 0), &Rgb([0, 255, 0]));
+
+        // gray images only take the levels of every channel, alpha is left alone
+        let gray = DynamicImage::ImageLumaA8(image::ImageBuffer::from_pixel(1, 1, LumaA([191, 7])));
+        let adjusted = levels.adjust(&gray).into_luma_alpha8();
+        assert_eq!(adjusted.get_pixel(0, 0), &LumaA([128, 7]));
+
+        let untouched = DynamicImage::ImageLuma8(image::ImageBuffer::from_pixel(1, 1, Luma([33])));
+        assert_eq!(Levels::default().adjust(&untouched), untouched);
+    }
+}
@@ -4,18 +4,29 @@ use std::{
     thread,
 };
 
-use glium::{glutin::event_loop::EventLoopProxy, Display};
-use rexif::ExifTag;
+use glium::Display;
+use image::{io::Limits, ImageFormat};
+use rexif::{ExifEntry, ExifTag};
 
 use crate::{
-    image_io::load::*,
-    util::{extensions::*, ImageData, UserEvent},
+    image_io::{avif, jxl, load::*, video},
+    util::{extensions::*, log, Image, ImageData, Proxy, UserEvent},
 };
 
+/// Images with more pixels than this are not decoded without asking, see `LoadOptions`.
+pub const DEFAULT_MAX_PIXELS: u64 = 512_000_000;
+/// Worst case memory per pixel while decoding, four channels of 32 bit floats.
+const MAX_BYTES_PER_PIXEL: u64 = 16;
+
 #[derive(Debug)]
 pub enum LoadError {
     Io(std::io::Error),
     Decoding(PathBuf),
+    /// The image is larger than the limit. Holds its size and whether
+    /// its format can be decoded at a reduced size instead.
+    TooLarge((u32, u32), bool),
+    /// The format is recognized but this build has no decoder for it.
+    Unsupported(&'static str),
 }
 
 impl fmt::Display for LoadError {
@@ -25,6 +36,14 @@ impl fmt::Display for LoadError {
             LoadError::Decoding(ref path_buf) => {
                 write!(f, "error decoding image: {:?}", path_buf.to_string_lossy())
             }
+            LoadError::TooLarge((width, height), _) => write!(
+                f,
+                "the image is {} x {} pixels, more than the decode limit allows",
+                width, height
+            ),
+            LoadError::Unsupported(format) => {
+                write!(f, "this build of simp can not open {} images", format)
+            }
         }
     }
 }
@@ -33,7 +52,7 @@ impl error::Error for LoadError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             LoadError::Io(ref e) => Some(e),
-            LoadError::Decoding(_) => None,
+            LoadError::Decoding(_) | LoadError::TooLarge(..) | LoadError::Unsupported(_) => None,
         }
     }
 }
@@ -44,45 +63,216 @@ impl From<std::io::Error> for LoadError {
     }
 }
 
-pub fn open(proxy: EventLoopProxy<UserEvent>, display: &Display) {
-    let dialog = rfd::FileDialog::new().set_parent(display.gl_window().window());
+pub fn open(proxy: Proxy, display: &Display) {
+    let dialog = rfd::AsyncFileDialog::new().set_parent(display.gl_window().window());
     thread::spawn(move || {
-        if let Some(file) = dialog.pick_file() {
-            let _ = proxy.send_event(UserEvent::QueueLoad(file));
+        if let Some(files) = futures_executor::block_on(dialog.pick_files()) {
+            let mut paths: Vec<_> = files.iter().map(|file| file.path().to_path_buf()).collect();
+            let event = if paths.len() == 1 {
+                UserEvent::QueueLoad(paths.remove(0))
+            } else {
+                UserEvent::OpenTabs(paths)
+            };
+            let _ = proxy.send_event(event);
         }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
     });
 }
 
-pub fn load_uncached(path: impl AsRef<Path>) -> Result<ImageData, LoadError> {
+type Loader<'a> = dyn Fn(&[u8]) -> Option<Vec<Image>> + 'a;
+
+/// What to do with an image over the size limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oversized {
+    Refuse,
+    /// Decode it anyway, for those that really have the memory.
+    Full,
+    /// Decode it at a reduced size if the format allows that, refuse otherwise.
+    Downsampled,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    /// When set large jpegs are decoded at a reduced scale that is still at least that
+    /// large, the full image can be loaded later without it.
+    pub fit: Option<(u32, u32)>,
+    pub max_pixels: Option<u64>,
+    pub oversized: Oversized,
+    /// How many times the size it declares an svg is rendered at, to stay sharp when zoomed in.
+    pub vector_scale: f32,
+    /// Whether camera raw files show the jpeg preview they carry instead of being developed.
+    pub raw_preview: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            fit: None,
+            max_pixels: Some(DEFAULT_MAX_PIXELS),
+            oversized: Oversized::Refuse,
+            vector_scale: 1.0,
+            raw_preview: false,
+        }
+    }
+}
+
+/// The largest size with the same aspect ratio as `size` that has at most `max_pixels`.
+fn downsampled_size(size: (u32, u32), max_pixels: u64) -> (u32, u32) {
+    let scale = (max_pixels as f64 / (size.0 as f64 * size.1 as f64))
+        .sqrt()
+        .min(1.0);
+    (
+        ((size.0 as f64 * scale) as u32).max(1),
+        ((size.1 as f64 * scale) as u32).max(1),
+    )
+}
+
+/// Loads an image from disk. Images over the pixel limit fail with `LoadError::TooLarge`
+/// before anything is allocated for them, unless `options` say otherwise.
+pub fn load_uncached(path: impl AsRef<Path>, options: LoadOptions) -> Result<ImageData, LoadError> {
     let path_buf = path.as_ref().to_path_buf();
+    let extension = path_buf
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+
+    // videos can be huge, ffmpeg reads only what it needs of them
+    if video::SUPPORTED && VIDEO.contains(&*extension) {
+        log::debug!("taking a still from {}", path_buf.to_string_lossy());
+        return match video::first_frame(&path_buf) {
+            Some((frame, duration)) => {
+                let mut image_data = ImageData::new(vec![frame], Vec::new());
+                image_data.video = true;
+                image_data.duration = duration;
+                Ok(image_data)
+            }
+            None => Err(LoadError::Decoding(path_buf)),
+        };
+    }
+
     let bytes = fs::read(&path_buf)?;
+    load_bytes(&path_buf, &bytes, options)
+}
 
-    let extension = path_buf
+/// Decodes the `bytes` read from `path` like `load_uncached` does, for callers that
+/// need the bytes for something else too. The path gives the extension and error messages.
+pub fn load_bytes(path: &Path, bytes: &[u8], options: LoadOptions) -> Result<ImageData, LoadError> {
+    let path_buf = path.to_path_buf();
+    let extension = path
         .extension()
         .unwrap_or_default()
         .to_string_lossy()
         .to_lowercase();
 
-    let mut loaders = [
-        load_raw,
-        load_svg,
-        load_psd,
-        load_raster,
-        load_un_detectable_raster,
+    // the content decides first, plenty of downloaded files carry the wrong extension
+    let content = sniff(bytes);
+    log::debug!(
+        "decoding {}, {} bytes of {:?}",
+        path_buf.to_string_lossy(),
+        bytes.len(),
+        content
+    );
+
+    if !avif::SUPPORTED && content == Some(Content::Raster(ImageFormat::Avif)) {
+        log::warn!("built without the avif feature");
+        return Err(LoadError::Unsupported("AVIF"));
+    }
+
+    let is_jxl = jxl::is_jxl(bytes);
+    if is_jxl && !jxl::SUPPORTED {
+        log::warn!("built without the jxl feature");
+        return Err(LoadError::Unsupported("JPEG XL"));
+    }
+
+    let mut fit = options.fit;
+    let mut limits = Limits::default();
+    limits.max_alloc = options
+        .max_pixels
+        .map(|max_pixels| max_pixels.saturating_mul(MAX_BYTES_PER_PIXEL));
+    let size = if is_jxl {
+        jxl::dimensions(bytes)
+    } else {
+        dimensions(bytes, content)
+    }
+    .unwrap_or_default();
+    // every frame of an animation is decoded to a full image
+    let pixels = size.0 as u64 * size.1 as u64 * frame_count(bytes);
+    let mut downsample = None;
+    match options.max_pixels {
+        Some(max_pixels) if pixels > max_pixels => {
+            let can_downsample = matches!(
+                content,
+                Some(Content::Raster(ImageFormat::Jpeg) | Content::Vector)
+            );
+            match options.oversized {
+                Oversized::Full => limits = Limits::no_limits(),
+                Oversized::Downsampled if can_downsample => {
+                    // the jpeg decoder picks the next larger scale so aim for half of that
+                    let (width, height) = downsampled_size(size, max_pixels);
+                    fit = Some(((width / 2).max(1), (height / 2).max(1)));
+                    downsample = Some(max_pixels);
+                }
+                Oversized::Refuse | Oversized::Downsampled => {
+                    log::debug!("refusing {} x {} pixels", size.0, size.1);
+                    return Err(LoadError::TooLarge(size, can_downsample));
+                }
+            }
+        }
+        _ => (),
+    }
+
+    // jpeg xl has no reduced scale to decode at, so it was refused above when too large
+    if is_jxl {
+        return match jxl::decode(bytes) {
+            Some(frames) => Ok(ImageData::new(frames, Vec::new())),
+            None => Err(LoadError::Decoding(path_buf)),
+        };
+    }
+
+    if content == Some(Content::Vector) && (downsample.is_some() || options.vector_scale != 1.0) {
+        let max_pixels = downsample.or(options.max_pixels);
+        if let Some((frames, full_size)) = load_svg_scaled(bytes, options.vector_scale, max_pixels)
+        {
+            let mut image_data = ImageData::new(frames, Vec::new());
+            image_data.full_size = Some(full_size);
+            image_data.vector = true;
+            return Ok(image_data);
+        }
+    }
+
+    let load_raster = |bytes: &[u8]| load_raster_with_limits(bytes, limits.clone());
+    let mut loaders: [&Loader<'_>; 5] = [
+        &load_raw,
+        &load_svg,
+        &load_psd,
+        &load_raster,
+        &load_un_detectable_raster,
     ];
 
-    if RASTER.contains(&*extension) {
-        loaders.swap(0, 3);
-    } else if VECTOR.contains(&*extension) {
-        loaders.swap(0, 1);
-    } else if PHOTOSHOP.contains(&*extension) {
-        loaders.swap(0, 2);
-    } else if UNDETECTABLE_RASTER.contains(&*extension) {
-        loaders.swap(0, 4);
+    match content {
+        Some(Content::Raster(_)) => loaders.swap(0, 3),
+        Some(Content::Vector) => loaders.swap(0, 1),
+        Some(Content::Photoshop) => loaders.swap(0, 2),
+        None if RASTER.contains(&*extension) => loaders.swap(0, 3),
+        None if VECTOR.contains(&*extension) => loaders.swap(0, 1),
+        None if PHOTOSHOP.contains(&*extension) => loaders.swap(0, 2),
+        None if UNDETECTABLE_RASTER.contains(&*extension) => loaders.swap(0, 4),
+        None => (),
     }
+    let mismatch = extension_mismatch(content, &extension);
+    let vector = match content {
+        Some(content) => content == Content::Vector,
+        None => VECTOR.contains(&*extension),
+    };
 
     let mut metadata = Vec::new();
-    if let Ok(exif) = rexif::parse_buffer_quiet(&bytes).0 {
+    // the exif tags win over the density in the jpeg or png header
+    let mut resolution = jfif_resolution(bytes).or_else(|| png_resolution(bytes));
+    let mut orientation = None;
+    if let Ok(exif) = rexif::parse_buffer_quiet(bytes).0 {
+        resolution = read_resolution(&exif.entries).or(resolution);
+        orientation = read_orientation(&exif.entries);
         for entry in exif.entries {
             use ExifTag::*;
             const HIDDEN_TAGS: &[ExifTag] = &[MakerNote];
@@ -94,10 +284,373 @@ pub fn load_uncached(path: impl AsRef<Path>) -> Result<ImageData, LoadError> {
         }
     }
 
+    let raw = content.is_none() && RAW.contains(&*extension);
+    if let Some(preview) = (options.raw_preview && raw)
+        .then(|| load_raw_preview(bytes))
+        .flatten()
+    {
+        log::debug!("showing the embedded preview");
+        let preview = orient(preview, orientation.unwrap_or(1));
+        let mut image_data = ImageData::new(vec![Image::new(preview)], metadata);
+        image_data.resolution = resolution;
+        image_data.raw_preview = true;
+        // measured against the sensor, so zoom, info and crops are in developed pixels
+        image_data.full_size = raw_dimensions(bytes);
+        return Ok(image_data);
+    }
+
+    if let Some((frames, full_size)) = fit.and_then(|fit| load_jpeg_scaled(bytes, fit)) {
+        log::debug!(
+            "decoded at a reduced scale, {} x {} in full",
+            full_size.0,
+            full_size.1
+        );
+        let mut image_data = ImageData::new(frames, metadata);
+        image_data.resolution = resolution;
+        image_data.warnings = jpeg_warnings(bytes);
+        image_data.warnings.extend(mismatch.clone());
+        image_data.full_size = Some(full_size);
+        return Ok(image_data);
+    }
+    // decoding at full size is exactly what was declined
+    if downsample.is_some() {
+        return Err(LoadError::TooLarge(size, false));
+    }
+
     for loader in loaders {
-        if let Some(image) = loader(&bytes) {
-            return Ok(ImageData::new(image, metadata));
+        if let Some(image) = loader(bytes) {
+            let mut image_data = ImageData::new(image, metadata);
+            image_data.resolution = resolution;
+            image_data.warnings = jpeg_warnings(bytes);
+            image_data.warnings.extend(mismatch);
+            image_data.vector = vector;
+            if image_data.frames.len() > 1 {
+                image_data.loop_count = loop_count(bytes);
+            }
+            return Ok(image_data);
         }
     }
+    log::warn!("no decoder could read {}", path_buf.to_string_lossy());
     Err(LoadError::Decoding(path_buf))
 }
+
+/// Describes how the content differs from what the extension says, if it does.
+pub fn extension_mismatch(content: Option<Content>, extension: &str) -> Option<String> {
+    let content_name = match content? {
+        Content::Raster(format) => {
+            let named = match extension {
+                "jpe" | "jif" | "jfif" => Some(ImageFormat::Jpeg),
+                "pnm" => Some(ImageFormat::Pnm),
+                extension => ImageFormat::from_extension(extension),
+            };
+            if named == Some(format) {
+                return None;
+            }
+            format!("{:?}", format).to_uppercase()
+        }
+        Content::Vector if VECTOR.contains(extension) => return None,
+        Content::Vector => String::from("SVG"),
+        Content::Photoshop if PHOTOSHOP.contains(extension) => return None,
+        Content::Photoshop => String::from("PSD"),
+    };
+
+    Some(if extension.is_empty() {
+        format!(
+            "The file has no extension but contains a {} image",
+            content_name
+        )
+    } else {
+        format!(
+            "The file is named .{} but contains a {} image",
+            extension, content_name
+        )
+    })
+}
+
+/// Reads the x and y resolution tags and converts them to dots per inch.
+fn read_resolution(entries: &[ExifEntry]) -> Option<(f32, f32)> {
+    let value = |tag: ExifTag| {
+        entries
+            .iter()
+            .find(|entry| entry.tag == tag)
+            .and_then(|entry| entry.value.to_f64(0))
+    };
+
+    let x = value(ExifTag::XResolution)?;
+    let y = value(ExifTag::YResolution)?;
+    if x <= 0.0 || y <= 0.0 {
+        return None;
+    }
+
+    let scale = match value(ExifTag::ResolutionUnit) {
+        Some(unit) if unit as u16 == 3 => 2.54,
+        _ => 1.0,
+    };
+    Some(((x * scale) as f32, (y * scale) as f32))
+}
+
+/// Reads the density in the JFIF header of a jpeg as dots per inch. A density without a unit
+/// only gives the aspect ratio so it is left out.
+fn jfif_resolution(bytes: &[u8]) -> Option<(f32, f32)> {
+    // the APP0 segment comes right after the start of image marker
+    let header = bytes.get(..18)?;
+    if header[..4] != [0xFF, 0xD8, 0xFF, 0xE0] || &header[6..11] != b"JFIF\0" {
+        return None;
+    }
+    let scale = match header[13] {
+        1 => 1.0,
+        2 => 2.54,
+        _ => return None,
+    };
+    let x = u16::from_be_bytes([header[14], header[15]]) as f32;
+    let y = u16::from_be_bytes([header[16], header[17]]) as f32;
+    (x > 0.0 && y > 0.0).then_some((x * scale, y * scale))
+}
+
+/// Reads the pHYs chunk of a png as dots per inch, it has to come before the image data.
+fn png_resolution(bytes: &[u8]) -> Option<(f32, f32)> {
+    if !bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return None;
+    }
+    let number = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+
+    let mut offset = 8;
+    loop {
+        let length = number(offset)? as usize;
+        match bytes.get(offset + 4..offset + 8)? {
+            b"pHYs" if length == 9 => {
+                // only pixels per meter give a size, otherwise it is just the aspect ratio
+                if *bytes.get(offset + 16)? != 1 {
+                    return None;
+                }
+                let dpi = |per_meter: u32| per_meter as f32 * 0.0254;
+                let (x, y) = (number(offset + 8)?, number(offset + 12)?);
+                return (x > 0 && y > 0).then(|| (dpi(x), dpi(y)));
+            }
+            b"IDAT" => return None,
+            _ => offset = offset.checked_add(length + 12)?,
+        }
+    }
+}
+
+fn read_orientation(entries: &[ExifEntry]) -> Option<u16> {
+    entries
+        .iter()
+        .find(|entry| entry.tag == ExifTag::Orientation)
+        .and_then(|entry| entry.value.to_i64(0))
+        .map(|orientation| orientation as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView};
+
+    use super::*;
+
+    const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn content_wins_over_extension() {
+        let content = sniff(PNG_MAGIC);
+        assert_eq!(content, Some(Content::Raster(ImageFormat::Png)));
+        assert_eq!(extension_mismatch(content, "png"), None);
+        assert_eq!(
+            extension_mismatch(content, "jpg").as_deref(),
+            Some("The file is named .jpg but contains a PNG image")
+        );
+        assert!(extension_mismatch(content, "").is_some());
+        assert_eq!(
+            sniff(b"\xef\xbb\xbf  <?xml version=\"1.0\"?>\n<svg>"),
+            Some(Content::Vector)
+        );
+        // tiff and raw files can not be told apart by their header
+        assert_eq!(sniff(b"II*\0\x08\0\0\0"), None);
+        assert_eq!(extension_mismatch(None, "jpg"), None);
+    }
+
+    #[test]
+    fn images_over_the_limit_need_permission() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("large.png");
+        image::RgbaImage::new(100, 50).save(&path).unwrap();
+
+        let limited = LoadOptions {
+            max_pixels: Some(1000),
+            ..LoadOptions::default()
+        };
+        assert!(matches!(
+            load_uncached(&path, limited),
+            Err(LoadError::TooLarge((100, 50), false))
+        ));
+        // pngs can not be decoded smaller so downsampling is refused too
+        let downsampled = LoadOptions {
+            oversized: Oversized::Downsampled,
+            ..limited
+        };
+        assert!(load_uncached(&path, downsampled).is_err());
+        let full = LoadOptions {
+            oversized: Oversized::Full,
+            ..limited
+        };
+        assert_eq!(load_uncached(&path, full).unwrap().frames.len(), 1);
+        assert_eq!(downsampled_size((4000, 2000), 2_000_000), (2000, 1000));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tiffs_and_animations_count_against_the_limit() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let limited = LoadOptions {
+            max_pixels: Some(1000),
+            ..LoadOptions::default()
+        };
+
+        // tiffs sniff as nothing since raw files share their header
+        let tiff = dir.join("large.tiff");
+        image::RgbImage::new(100, 50).save(&tiff).unwrap();
+        assert!(matches!(
+            load_uncached(&tiff, limited),
+            Err(LoadError::TooLarge((100, 50), false))
+        ));
+
+        let gif = dir.join("animated.gif");
+        let frame = image::Frame::new(image::RgbaImage::from_pixel(20, 20, image::Rgba([9; 4])));
+        image::codecs::gif::GifEncoder::new(fs::File::create(&gif).unwrap())
+            .encode_frames(vec![frame; 3])
+            .unwrap();
+        assert_eq!(frame_count(&fs::read(&gif).unwrap()), 3);
+        // one frame fits, all three do not
+        assert!(matches!(
+            load_uncached(&gif, limited),
+            Err(LoadError::TooLarge((20, 20), false))
+        ));
+        let frames = load_uncached(&gif, LoadOptions::default()).unwrap().frames;
+        assert_eq!(frames.len(), 3);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn raw_files_can_show_their_largest_preview() {
+        let jpeg = |width, height| {
+            let mut bytes = Vec::new();
+            image::codecs::jpeg::JpegEncoder::new(&mut bytes)
+                .encode(
+                    &vec![128; width as usize * height as usize * 3],
+                    width,
+                    height,
+                    image::ColorType::Rgb8,
+                )
+                .unwrap();
+            bytes
+        };
+        // a raw file has its sensor data around a thumbnail and a larger preview
+        let mut bytes = b"II*\0\x08\0\0\0".to_vec();
+        bytes.extend(jpeg(16, 8));
+        bytes.extend([0xFF; 64]);
+        bytes.extend(jpeg(64, 32));
+        bytes.extend([0xFF, 0xD8, 0xFF, 0xC4, 0, 0]);
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("shot.cr2");
+        fs::write(&path, bytes).unwrap();
+
+        let options = LoadOptions {
+            raw_preview: true,
+            ..LoadOptions::default()
+        };
+        let image_data = load_uncached(&path, options).unwrap();
+        assert!(image_data.raw_preview);
+        assert_eq!(image_data.frames[0].buffer().dimensions(), (64, 32));
+        // without previews the raw data has to be developed, which this file has none of
+        assert!(load_uncached(&path, LoadOptions::default()).is_err());
+
+        let wide = DynamicImage::new_rgb8(4, 2);
+        assert_eq!(orient(wide.clone(), 1).dimensions(), (4, 2));
+        assert_eq!(orient(wide.clone(), 6).dimensions(), (2, 4));
+        assert_eq!(orient(wide, 7).dimensions(), (2, 4));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn hdr_images_keep_values_past_white() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("render.hdr");
+        let pixels = [image::Rgb([4.0, 0.5, 0.0]), image::Rgb([0.0, 1.0, 16.0])];
+        image::codecs::hdr::HdrEncoder::new(fs::File::create(&path).unwrap())
+            .encode(&pixels, 2, 1)
+            .unwrap();
+        assert!(RASTER.contains("hdr") && RASTER.contains("exr"));
+
+        let image_data = load_uncached(&path, LoadOptions::default()).unwrap();
+        let buffer = image_data.frames[0].buffer().to_rgb32f();
+        assert_eq!(buffer.get_pixel(0, 0).0[0], 4.0);
+        assert_eq!(buffer.get_pixel(1, 0).0[2], 16.0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn svgs_are_rendered_larger_on_request() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("shape.svg");
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">
+            <rect width="40" height="20" fill="red"/></svg>"#;
+        fs::write(&path, svg).unwrap();
+        let size = |image_data: &ImageData| image_data.frames[0].buffer().dimensions();
+
+        let declared = load_uncached(&path, LoadOptions::default()).unwrap();
+        assert!(declared.vector);
+        assert_eq!(size(&declared), (40, 20));
+        assert_eq!(declared.full_size, None);
+
+        let sharp = LoadOptions {
+            vector_scale: 4.0,
+            ..LoadOptions::default()
+        };
+        let zoomed = load_uncached(&path, sharp).unwrap();
+        assert!(zoomed.vector);
+        assert_eq!(size(&zoomed), (160, 80));
+        assert_eq!(zoomed.full_size, Some((40, 20)));
+
+        // the pixel limit holds for the larger rendering too
+        let limited = LoadOptions {
+            max_pixels: Some(800),
+            ..sharp
+        };
+        assert_eq!(size(&load_uncached(&path, limited).unwrap()), (40, 20));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn jpeg_and_png_keep_their_resolution() {
+        use crate::image_io::save::{jpeg_with_resolution, png_with_resolution};
+
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = Image::new(DynamicImage::ImageRgb8(image::RgbImage::new(8, 4)));
+
+        let jpeg = dir.join("density.jpg");
+        jpeg_with_resolution(&jpeg, &image, 90, (300.0, 150.0)).unwrap();
+        let loaded = load_uncached(&jpeg, LoadOptions::default()).unwrap();
+        assert_eq!(loaded.resolution, Some((300.0, 150.0)));
+
+        let png = dir.join("density.png");
+        png_with_resolution(&png, &image, (300.0, 150.0)).unwrap();
+        let (x, y) = load_uncached(&png, LoadOptions::default())
+            .unwrap()
+            .resolution
+            .unwrap();
+        // pixels per meter round to a little off the dots per inch
+        assert!((x - 300.0).abs() < 0.1 && (y - 150.0).abs() < 0.1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
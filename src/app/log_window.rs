@@ -0,0 +1,115 @@
+use egui::{Color32, ComboBox, RichText, ScrollArea, TextEdit, TextStyle};
+
+use super::{i18n::tr, App};
+use crate::util::log::{self, Level, Record};
+
+const LEVELS: [Level; 5] = [
+    Level::Error,
+    Level::Warn,
+    Level::Info,
+    Level::Debug,
+    Level::Trace,
+];
+
+#[derive(Default)]
+pub struct LogWindow {
+    pub open: bool,
+    search: String,
+}
+
+/// Whether `record` is at least as severe as `level` and contains `search`, ignoring case.
+fn matches(record: &Record, level: Level, search: &str) -> bool {
+    record.level <= level
+        && (search.is_empty()
+            || record.message.to_lowercase().contains(search)
+            || record.target.to_lowercase().contains(search))
+}
+
+fn level_color(level: Level) -> Option<Color32> {
+    match level {
+        Level::Error => Some(Color32::from_rgb(230, 90, 90)),
+        Level::Warn => Some(Color32::from_rgb(230, 190, 80)),
+        _ => None,
+    }
+}
+
+impl App {
+    pub fn log_ui(&mut self, ctx: &egui::Context) {
+        // the level can also change in the window and with the other settings
+        log::set_level(self.settings.log_level);
+        if !self.log_window.open {
+            return;
+        }
+
+        let window = &mut self.log_window;
+        let settings = &mut self.settings;
+        let search = window.search.to_lowercase();
+        // borrowed for the whole frame, nothing in here may log
+        let all = log::records();
+        let records: Vec<&Record> = all
+            .iter()
+            .filter(|record| matches(record, settings.log_level, &search))
+            .collect();
+
+        egui::Window::new(tr("Log"))
+            .id(egui::Id::new("log window"))
+            .collapsible(false)
+            .default_size([640.0, 360.0])
+            .open(&mut window.open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_source("log level")
+                        .selected_text(settings.log_level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in LEVELS {
+                                ui.selectable_value(&mut settings.log_level, level, level.as_str());
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("Less severe records are not kept"));
+                    ui.add(TextEdit::singleline(&mut window.search).hint_text(tr("Search")));
+                    if ui.button(tr("Copy all")).clicked() {
+                        let lines: Vec<String> =
+                            records.iter().map(|record| record.line()).collect();
+                        ctx.output().copied_text = lines.join("\n");
+                    }
+                });
+                ui.separator();
+
+                let row_height = ui.text_style_height(&TextStyle::Monospace);
+                ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom()
+                    .show_rows(ui, row_height, records.len(), |ui, rows| {
+                        for record in &records[rows] {
+                            let text = RichText::new(record.line()).monospace();
+                            let text = match level_color(record.level) {
+                                Some(color) => text.color(color),
+                                None => text,
+                            };
+                            ui.label(text);
+                        }
+                    });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_level_and_text() {
+        let record = Record {
+            time: 1.0,
+            level: Level::Debug,
+            target: String::from("app::op_queue"),
+            message: String::from("op load photo.JPG"),
+        };
+        assert!(matches(&record, Level::Debug, ""));
+        assert!(matches(&record, Level::Trace, "photo.jpg"));
+        assert!(matches(&record, Level::Debug, "op_queue"));
+        assert!(!matches(&record, Level::Info, ""));
+        assert!(!matches(&record, Level::Debug, "save"));
+    }
+}
@@ -1,99 +1,273 @@
 use std::thread;
 
-use egui::{menu, Button, TopBottomPanel};
+use egui::{menu, Button, SelectableLabel, Slider, TopBottomPanel};
 use glium::Display;
 
-use super::{delete, load_image, new_window, op_queue::Op, save_image, App};
+use super::{
+    acquire,
+    action::Action,
+    associations,
+    color::Filter,
+    fullscreen::monitor_label,
+    i18n::{tr, trf},
+    image_view::ChannelMode,
+    preferences::accent_ui,
+    settings::Theme,
+    App,
+};
 
 impl App {
     pub fn menu_bar(&mut self, display: &Display, ctx: &egui::Context) {
         TopBottomPanel::top("top").show(ctx, |ui| {
             menu::bar(ui, |ui| {
-                menu::menu_button(ui, "File", |ui| {
-                    if ui.button("Open").clicked() {
-                        load_image::open(self.proxy.clone(), display);
+                menu::menu_button(ui, tr("File"), |ui| {
+                    if ui.button(tr("Open")).clicked() {
+                        self.dispatch(display, Action::Open);
+                        ui.close_menu();
+                    }
+
+                    if ui.button(tr("Import frames as animation…")).clicked() {
+                        self.dispatch(display, Action::ImportFrames);
+                        ui.close_menu();
+                    }
+
+                    if acquire::SUPPORTED
+                        && ui
+                            .add_enabled(
+                                !self.op_queue.working(),
+                                Button::new(tr("Acquire from scanner/camera…")),
+                            )
+                            .clicked()
+                    {
+                        self.dispatch(display, Action::Acquire);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Save as"))
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Save as")))
                         .clicked()
                     {
-                        save_image::open(
-                            self.current_filename.clone(),
-                            self.proxy.clone(),
-                            display,
-                        );
+                        self.dispatch(display, Action::SaveAs);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.image_view.is_some(),
+                            Button::new(tr("Export view as image…")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::ExportView);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.view_available() && self.animated(),
+                            Button::new(tr("Export current frame…")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::ExportFrame);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.view_available() && self.animated(),
+                            Button::new(tr("Export frames…")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::ExportFrames);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.op_queue.image_list.position().is_some(),
+                            Button::new(tr("Save list as playlist…")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::SavePlaylist);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.op_queue.image_list.position().is_some(),
+                            Button::new(tr("Export folder report…")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::FolderReport);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.op_queue.image_list.position().is_some(),
+                            Button::new(tr("Export contact sheet…")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::ContactSheet);
                         ui.close_menu();
                     }
 
+                    ui.checkbox(&mut self.export_supersample, tr("2× supersampled export"));
+
                     ui.separator();
 
-                    if ui.button("New Window").clicked() {
-                        new_window();
+                    if ui.button(tr("New Window")).clicked() {
+                        self.dispatch(display, Action::NewWindow);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Refresh"))
+                        .add_enabled(!self.op_queue.working(), Button::new(tr("New Tab")))
                         .clicked()
                     {
-                        if let Some(ref path) = self.image_view.as_ref().unwrap().path {
-                            let buf = path.to_path_buf();
-                            self.queue(Op::LoadPath(buf, false));
-                        }
+                        self.dispatch(display, Action::NewTab);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(self.tabs.len() > 1, Button::new(tr("Close Tab")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::CloseTab);
+                        ui.close_menu();
+                    }
+
+                    let mut refresh = ui.add_enabled(
+                        self.image_view.is_some() && !self.pathless(),
+                        Button::new(tr("Refresh")),
+                    );
+                    if let Some(reason) = self.file_required(Action::Reload) {
+                        refresh = refresh.on_disabled_hover_text(reason);
+                    }
+                    if refresh.clicked() {
+                        self.dispatch(display, Action::Reload);
                         ui.close_menu();
                     }
 
+                    if associations::SUPPORTED {
+                        ui.separator();
+
+                        if ui.button(tr("Register file associations…")).clicked() {
+                            self.dispatch(display, Action::RegisterAssociations);
+                            ui.close_menu();
+                        }
+
+                        if ui.button(tr("Remove file associations…")).clicked() {
+                            self.dispatch(display, Action::UnregisterAssociations);
+                            ui.close_menu();
+                        }
+                    }
+
                     ui.separator();
 
-                    if ui.button("Exit").clicked() {
-                        self.exit = true;
+                    if ui.button(tr("Exit")).clicked() {
+                        self.dispatch(display, Action::Exit);
                     }
                 });
 
-                menu::menu_button(ui, "Edit", |ui| {
+                menu::menu_button(ui, tr("Edit"), |ui| {
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Undo"))
+                        .add_enabled(self.view_available(), Button::new(tr("Undo")))
                         .clicked()
                     {
-                        self.queue(Op::Undo);
+                        self.dispatch(display, Action::Undo);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Redo"))
+                        .add_enabled(self.view_available(), Button::new(tr("Redo")))
                         .clicked()
                     {
-                        self.queue(Op::Redo);
+                        self.dispatch(display, Action::Redo);
                         ui.close_menu();
                     }
 
                     ui.separator();
 
+                    for filter in Filter::ALL {
+                        if ui
+                            .add_enabled(self.view_available(), Button::new(tr(filter.name())))
+                            .clicked()
+                        {
+                            self.dispatch(display, Action::Filter(filter));
+                            ui.close_menu();
+                        }
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .add_enabled(self.view_available(), Button::new(tr("Copy")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Copy);
+                        ui.close_menu();
+                    }
+
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Copy"))
+                        .add_enabled(self.view_available(), Button::new(tr("Copy as data URI")))
                         .clicked()
                     {
-                        self.queue(Op::Copy);
+                        self.dispatch(display, Action::CopyDataUri);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(!self.op_queue.working(), Button::new("Paste"))
+                        .add_enabled(!self.op_queue.working(), Button::new(tr("Paste")))
                         .clicked()
                     {
-                        self.queue(Op::Paste);
+                        self.dispatch(display, Action::Paste);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            !self.op_queue.working(),
+                            Button::new(tr("Paste into clipboard list")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::PasteToScratch);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui.button(tr("Preferences…")).clicked() {
+                        self.dispatch(display, Action::Preferences);
+                        ui.close_menu();
+                    }
+
+                    if ui.button(tr("Keyboard shortcuts…")).clicked() {
+                        self.dispatch(display, Action::Shortcuts);
                         ui.close_menu();
                     }
                 });
 
-                menu::menu_button(ui, "Image", |ui| {
+                menu::menu_button(ui, tr("Image"), |ui| {
+                    if ui
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Color")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Color);
+                        ui.close_menu();
+                    }
+
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Color"))
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Levels…")))
                         .clicked()
                     {
-                        self.color_visible = true;
+                        self.dispatch(display, Action::Levels);
                         ui.close_menu();
                     }
 
@@ -109,146 +283,433 @@ impl App {
                                     .unwrap()
                                     .metadata
                                     .is_empty(),
-                            Button::new("Metadata"),
+                            Button::new(tr("Metadata")),
                         )
                         .clicked()
                     {
-                        self.metadata_visible = true;
+                        self.dispatch(display, Action::Metadata);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Pick color")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::PickColor);
+                        ui.close_menu();
+                    }
+
+                    if ui.button(tr("Picked colors")).clicked() {
+                        self.dispatch(display, Action::ColorHistory);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(self.view_available(), Button::new(tr("Analyze colors")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::AnalyzeColors);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .add_enabled(self.view_available(), Button::new(tr("Rotate Left")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::RotateLeft);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(self.view_available(), Button::new(tr("Rotate Right")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::RotateRight);
                         ui.close_menu();
                     }
 
+                    if ui
+                        .add_enabled(self.view_available(), Button::new(tr("Free Rotate")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::FreeRotate);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(self.view_available(), Button::new(tr("Straighten…")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Straighten);
+                        ui.close_menu();
+                    }
+
+                    ui.checkbox(&mut self.rotate.snap, tr("Snap R-drag rotation to 90°"));
+
                     ui.separator();
 
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Rotate Left"))
+                        .add_enabled(self.view_available(), Button::new(tr("Flip Horizontal")))
                         .clicked()
                     {
-                        self.queue(Op::Rotate(-1));
+                        self.dispatch(display, Action::FlipHorizontal);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Rotate Right"))
+                        .add_enabled(self.view_available(), Button::new(tr("Flip Vertical")))
                         .clicked()
                     {
-                        self.queue(Op::Rotate(1));
+                        self.dispatch(display, Action::FlipVertical);
                         ui.close_menu();
                     }
 
                     ui.separator();
 
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Flip Horizontal"))
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Zoom in")))
                         .clicked()
                     {
-                        self.queue(Op::FlipHorizontal);
+                        self.dispatch(display, Action::ZoomIn);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Flip Vertical"))
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Zoom out")))
                         .clicked()
                     {
-                        self.queue(Op::FlipVertical);
+                        self.dispatch(display, Action::ZoomOut);
                         ui.close_menu();
                     }
 
                     ui.separator();
 
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Zoom in"))
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Best fit")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::BestFit);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Largest fit")))
                         .clicked()
                     {
-                        self.zoom(1.0, self.size / 2.0);
+                        self.dispatch(display, Action::LargestFit);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Zoom out"))
+                        .add_enabled(
+                            self.auto_scroll.active() || self.can_auto_scroll(),
+                            SelectableLabel::new(
+                                self.auto_scroll.active(),
+                                tr("Auto-scroll panorama"),
+                            ),
+                        )
                         .clicked()
                     {
-                        self.zoom(-1.0, self.size / 2.0);
+                        self.dispatch(display, Action::AutoScroll);
+                        ui.close_menu();
+                    }
+
+                    let non_square = self
+                        .image_view
+                        .as_ref()
+                        .is_some_and(|view| view.non_square_pixels());
+                    let corrected = self
+                        .image_view
+                        .as_ref()
+                        .is_some_and(|view| view.aspect_correction);
+                    if ui
+                        .add_enabled(
+                            non_square,
+                            SelectableLabel::new(corrected, tr("Correct pixel aspect ratio")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::ToggleAspectCorrection);
                         ui.close_menu();
                     }
 
                     ui.separator();
 
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Best fit"))
+                        .add_enabled(self.view_available(), Button::new(tr("Crop")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Crop);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.view_available(),
+                            Button::new(tr("Crop & resize to preset…")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::CropToPreset);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(self.image_view.is_some(), Button::new(tr("Resize")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Resize);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.view_available() && non_square,
+                            Button::new(tr("Bake aspect correction")),
+                        )
                         .clicked()
                     {
-                        self.best_fit();
+                        self.dispatch(display, Action::BakeAspect);
                         ui.close_menu();
                     }
 
+                    ui.separator();
+
+                    let reason = self.file_required(Action::Delete);
+                    let can_delete = self.image_view.is_some() && !self.pathless();
+                    let mut delete = ui.add_enabled(can_delete, Button::new(tr("Delete")));
+                    if let Some(reason) = reason {
+                        delete = delete.on_disabled_hover_text(reason);
+                    }
+                    if delete.clicked() {
+                        self.dispatch(display, Action::Delete);
+                        ui.close_menu();
+                    }
+
+                    let mut delete =
+                        ui.add_enabled(can_delete, Button::new(tr("Delete permanently")));
+                    if let Some(reason) = reason {
+                        delete = delete.on_disabled_hover_text(reason);
+                    }
+                    if delete.clicked() {
+                        self.dispatch(display, Action::DeletePermanently);
+                        ui.close_menu();
+                    }
+                });
+
+                menu::menu_button(ui, tr("View"), |ui| {
+                    if ui.button(tr("Fullscreen")).clicked() {
+                        self.dispatch(display, Action::Fullscreen);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.image_view.is_some(),
+                            SelectableLabel::new(self.slideshow.active(), tr("Slideshow")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Slideshow);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add(SelectableLabel::new(
+                            self.settings.filmstrip,
+                            tr("Filmstrip"),
+                        ))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Filmstrip);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add(SelectableLabel::new(self.gallery.visible, tr("Gallery")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Gallery);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add(SelectableLabel::new(self.settings.grid, tr("Grid")))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Grid);
+                        ui.close_menu();
+                    }
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Largest fit"))
+                        .add(SelectableLabel::new(self.lock_view, tr("Lock view")))
+                        .on_hover_text(tr("Keep zoom and position when browsing"))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::LockView);
+                        ui.close_menu();
+                    }
+                    let wiping = self.image_view.as_ref().is_some_and(|view| view.wiping());
+                    if ui
+                        .add_enabled(
+                            self.image_view.is_some(),
+                            SelectableLabel::new(wiping, tr("Wipe compare…")),
+                        )
                         .clicked()
                     {
-                        self.largest_fit();
+                        self.dispatch(display, Action::Wipe);
                         ui.close_menu();
                     }
 
                     ui.separator();
 
                     if ui
-                        .add_enabled(self.view_available(), Button::new("Crop"))
+                        .add_enabled(self.view_available(), Button::new(tr("Previous folder")))
                         .clicked()
                     {
-                        self.crop.cropping = true;
+                        self.dispatch(display, Action::PrevDir);
                         ui.close_menu();
                     }
 
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Resize"))
+                        .add_enabled(self.view_available(), Button::new(tr("Next folder")))
                         .clicked()
                     {
-                        self.resize.visible = true;
+                        self.dispatch(display, Action::NextDir);
                         ui.close_menu();
                     }
 
                     ui.separator();
 
+                    let animated = self.animated();
+                    let paused = self.image_view.as_ref().is_some_and(|view| view.paused());
                     if ui
-                        .add_enabled(self.image_view.is_some(), Button::new("Delete"))
+                        .add_enabled(
+                            animated,
+                            SelectableLabel::new(paused, tr("Pause animation")),
+                        )
                         .clicked()
                     {
-                        if let Some(ref view) = self.image_view {
-                            if let Some(ref path) = view.path {
-                                delete(path, self.proxy.clone());
-                            }
-                        }
+                        self.dispatch(display, Action::TogglePlayback);
+                        ui.close_menu();
+                    }
+
+                    let onion_skin = self
+                        .image_view
+                        .as_ref()
+                        .is_some_and(|view| view.onion_skin());
+                    if ui
+                        .add_enabled(
+                            animated && paused,
+                            SelectableLabel::new(onion_skin, tr("Onion skin")),
+                        )
+                        .on_disabled_hover_text(tr("Pause the animation to compare frames"))
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::OnionSkin);
                         ui.close_menu();
                     }
+
+                    if ui
+                        .add_enabled(
+                            animated,
+                            SelectableLabel::new(self.frames.visible, tr("Frames")),
+                        )
+                        .clicked()
+                    {
+                        self.dispatch(display, Action::Frames);
+                        ui.close_menu();
+                    }
+
+                    if let Some(view) = self.image_view.as_mut() {
+                        if onion_skin {
+                            ui.add(
+                                Slider::new(&mut view.onion_opacity, 0.05..=1.0)
+                                    .text(tr("Onion opacity")),
+                            );
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.menu_button(tr("Channels"), |ui| {
+                        let current = self.image_view.as_ref().map(|view| view.channel_mode);
+                        for mode in ChannelMode::ALL {
+                            if ui
+                                .add_enabled(
+                                    current.is_some(),
+                                    SelectableLabel::new(current == Some(*mode), tr(mode.name())),
+                                )
+                                .clicked()
+                            {
+                                self.dispatch(display, Action::Channel(*mode));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    ui.menu_button(tr("Fullscreen on monitor"), |ui| {
+                        let monitors: Vec<_> =
+                            display.gl_window().window().available_monitors().collect();
+                        for monitor in monitors {
+                            if ui.button(monitor_label(&monitor)).clicked() {
+                                self.enter_fullscreen(display, Some(monitor));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    // written to the config with the other settings when the window closes
+                    ui.menu_button(tr("Theme"), |ui| {
+                        for theme in Theme::ALL {
+                            ui.radio_value(&mut self.settings.theme, theme, tr(theme.name()));
+                        }
+                        ui.separator();
+                        accent_ui(ui, &mut self.settings.accent);
+                    });
                 });
 
-                menu::menu_button(ui, "Help", |ui| {
-                    if ui.button("Repository").clicked() {
+                menu::menu_button(ui, tr("Help"), |ui| {
+                    if ui.button(tr("Repository")).clicked() {
                         webbrowser::open("https://github.com/Kl4rry/simp").unwrap();
                         ui.close_menu();
                     }
 
-                    if ui.button("Report Bug").clicked() {
+                    if ui.button(tr("Report Bug")).clicked() {
                         webbrowser::open("https://github.com/Kl4rry/simp/issues").unwrap();
                         ui.close_menu();
                     }
 
                     ui.separator();
 
-                    if ui.button("Help").clicked() {
-                        self.help_visible = true;
+                    if ui.button(tr("Help")).clicked() {
+                        self.dispatch(display, Action::Help);
+                        ui.close_menu();
+                    }
+
+                    if ui.button(tr("Command Palette")).clicked() {
+                        self.dispatch(display, Action::CommandPalette);
+                        ui.close_menu();
+                    }
+
+                    if ui.button(tr("Show log")).clicked() {
+                        self.dispatch(display, Action::ShowLog);
+                        ui.close_menu();
+                    }
+
+                    if ui.button(tr("Export diagnostic bundle")).clicked() {
+                        self.dispatch(display, Action::ExportDiagnostics);
+                        ui.close_menu();
                     }
 
-                    if ui.button("About").clicked() {
+                    if ui.button(tr("About")).clicked() {
                         let about = format!(
                             "{}\n{}\n{}\n{}",
                             env!("CARGO_PKG_NAME"),
                             env!("CARGO_PKG_DESCRIPTION"),
-                            &format!("Version: {}", env!("CARGO_PKG_VERSION")),
-                            &format!("Commit: {}", env!("GIT_HASH")),
+                            &trf("Version: {}", &[&env!("CARGO_PKG_VERSION")]),
+                            &trf("Commit: {}", &[&env!("GIT_HASH")]),
                         );
                         thread::spawn(move || {
-                            msgbox::create("About", &about, msgbox::IconType::Info).unwrap()
+                            msgbox::create(tr("About"), &about, msgbox::IconType::Info).unwrap()
                         });
                         ui.close_menu();
                     }
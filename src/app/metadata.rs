@@ -12,7 +12,29 @@ impl App {
                 .open(&mut open)
                 .show(ctx, |ui| {
                     ScrollArea::vertical().show(ui, |ui| {
-                        let guard = self.image_view.as_ref().unwrap().image_data.read().unwrap();
+                        let view = self.image_view.as_ref().unwrap();
+                        let guard = view.image_data.read().unwrap();
+                        if !guard.warnings.is_empty() {
+                            ui.label(RichText::new("Loaded with warnings:").strong());
+                            for warning in &guard.warnings {
+                                ui.label(format!("• {}", warning));
+                            }
+                            ui.separator();
+                        }
+
+                        // edits that add colors make the image true color, this follows them
+                        let frame = &guard.frames[view.index.min(guard.frames.len() - 1)];
+                        ui.label(format!("color type: {}", frame.color_name()));
+
+                        if guard.frames.len() > 1 {
+                            let loops = match guard.loop_count {
+                                0 => String::from("infinite"),
+                                count => count.to_string(),
+                            };
+                            ui.label(format!("loops: {}", loops));
+                        }
+                        ui.separator();
+
                         if guard.metadata.is_empty() {
                             ui.centered_and_justified(|ui| {
                                 ui.label(RichText::new("Could not find any metadata.").size(20.0));
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use egui::{pos2, vec2, ColorImage, Image, Rect, Sense, Stroke, TextureHandle};
+
+use super::{image_view::oriented, App};
+use crate::vec2::Vec2;
+
+/// The longest side of the minimap in points.
+const SIZE: f32 = 160.0;
+const MARGIN: f32 = 12.0;
+
+/// What the texture was made from: the image data, its edit count, the quarter turns and
+/// flips.
+type Key = (usize, u64, i32, bool, bool);
+
+/// A small copy of an image larger than the window with the part in view marked.
+#[derive(Default)]
+pub struct Minimap {
+    texture: Option<(Key, TextureHandle)>,
+}
+
+/// Where the minimap goes in `area`, in its bottom right corner with the shape of `bounds`.
+fn placement(area: Rect, bounds: egui::Vec2) -> Rect {
+    let scale = SIZE / bounds.x.max(bounds.y);
+    let size = bounds * scale;
+    Rect::from_min_size(area.right_bottom() - size - vec2(MARGIN, MARGIN), size)
+}
+
+impl App {
+    /// Shows the minimap while the image does not fit, a click or drag in it moves the view
+    /// there. It has to take the drag before the image does.
+    pub fn minimap_ui(&mut self, ui: &mut egui::Ui) {
+        if !self.settings.minimap || self.crop.cropping {
+            return;
+        }
+        let ppp = self.pixels_per_point();
+        let view = match self.image_view.as_mut() {
+            Some(view) => view,
+            None => return,
+        };
+        let area = ui.max_rect();
+        let room = Rect::from_min_max(
+            pos2(area.min.x * ppp, area.min.y * ppp),
+            pos2(area.max.x * ppp, area.max.y * ppp),
+        );
+        let bounds = view.bounds();
+        let bounds = Rect::from_min_size(
+            pos2(bounds.x(), bounds.y()),
+            vec2(bounds.width(), bounds.height()),
+        );
+        if bounds.width() <= room.width() + 1.0 && bounds.height() <= room.height() + 1.0 {
+            return;
+        }
+
+        let key = (
+            Arc::as_ptr(&view.image_data) as usize,
+            view.analysis_key().0,
+            view.rotation,
+            view.horizontal_flip,
+            view.vertical_flip,
+        );
+        if self.minimap.texture.as_ref().map(|(made, _)| *made) != Some(key) {
+            let guard = match view.image_data.try_read() {
+                Ok(guard) => guard,
+                Err(_) => return,
+            };
+            let frame = guard.frames[view.index.min(guard.frames.len() - 1)].buffer();
+            let small = frame.thumbnail(SIZE as u32 * 2, SIZE as u32 * 2);
+            let small = oriented(
+                &small,
+                view.rotation,
+                view.horizontal_flip,
+                view.vertical_flip,
+            )
+            .to_rgba8();
+            let size = [small.width() as usize, small.height() as usize];
+            let texture = ui.ctx().load_texture(
+                "minimap",
+                ColorImage::from_rgba_unmultiplied(size, small.as_raw()),
+            );
+            drop(guard);
+            self.minimap.texture = Some((key, texture));
+        }
+        let texture = match &self.minimap.texture {
+            Some((_, texture)) => texture,
+            None => return,
+        };
+
+        let rect = placement(area, bounds.size());
+        let response = ui.interact(rect, ui.id().with("minimap"), Sense::click_and_drag());
+        if response.is_pointer_button_down_on() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                // the point of the image under the pointer goes to the middle of the window
+                let fraction = (pointer - rect.min) / rect.size();
+                let point = bounds.min + fraction * bounds.size();
+                let center = room.center();
+                view.position += Vec2::new(center.x - point.x, center.y - point.y);
+            }
+        }
+
+        let visuals = ui.visuals().clone();
+        ui.painter()
+            .rect_filled(rect.expand(2.0), 2.0, visuals.extreme_bg_color);
+        Image::new(texture.id(), rect.size()).paint_at(ui, rect);
+        let visible = room.intersect(bounds);
+        let to_map =
+            |point: egui::Pos2| rect.min + (point - bounds.min) / bounds.size() * rect.size();
+        ui.painter().rect_stroke(
+            Rect::from_min_max(to_map(visible.min), to_map(visible.max)),
+            0.0,
+            Stroke::new(1.5, visuals.selection.stroke.color),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimap_keeps_the_shape_in_the_corner() {
+        let area = Rect::from_min_size(pos2(0.0, 20.0), vec2(800.0, 600.0));
+        let wide = placement(area, vec2(4000.0, 1000.0));
+        assert_eq!(wide.size(), vec2(SIZE, SIZE / 4.0));
+        assert_eq!(wide.right_bottom(), pos2(800.0 - MARGIN, 620.0 - MARGIN));
+        let tall = placement(area, vec2(500.0, 1000.0));
+        assert_eq!(tall.size(), vec2(SIZE / 2.0, SIZE));
+    }
+}
@@ -1,58 +1,255 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    ffi::OsString,
     path::{Path, PathBuf},
     sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
         mpsc::{self, Receiver, Sender},
-        Arc, Mutex, RwLock,
+        Arc, Condvar, Mutex, PoisonError, RwLock,
     },
     thread,
+    time::Duration,
 };
 
-use glium::glutin::event_loop::EventLoopProxy;
-use image::imageops::FilterType;
+use image::{imageops::FilterType, DynamicImage};
 
 use super::{
-    cache::Cache, clipboard, image_list::ImageList, image_view::ImageView,
-    load_image::load_uncached, save_image,
+    acquire,
+    analyze::{self, AnalysisKey, ColorAnalysis},
+    cache::Cache,
+    clipboard,
+    color::{Filter, Tone, WhiteBalance},
+    download,
+    frames::FrameEdit,
+    image_list::{sibling_dir_image, ImageList},
+    image_view::{PreviewRequest, ViewState},
+    import_frames,
+    levels::Levels,
+    load_image::{load_uncached, LoadError, LoadOptions, Oversized},
+    resize::resized,
+    rotate,
+    save_image::{self, Fixes},
+    thumbnails::Thumbnails,
 };
 use crate::{
-    app::undo_stack::UndoStack,
+    app::{
+        settings::Settings,
+        undo_stack::{EditTarget, UndoFrame, UndoStack},
+    },
+    image_io::save::Encoding,
     rect::Rect,
-    util::{Image, ImageData, UserEvent},
+    util::{display_name, log, Image, ImageData, Proxy, UserEvent},
     vec2::Vec2,
 };
 
 #[derive(Debug)]
 pub enum Op {
     LoadPath(PathBuf, bool),
+    /// Puts the first frame of each image together into an unsaved animation.
+    ImportFrames(Vec<PathBuf>, Duration),
+    /// Downloads the image at a web address and opens it unsaved.
+    Download(String),
     Next,
     Prev,
-    Save(PathBuf),
+    NextDir,
+    PrevDir,
+    Save(PathBuf, Encoding, Fixes),
+    Export(PathBuf, Arc<RwLock<ImageData>>, Encoding, Fixes),
+    /// Writes each frame into the folder, named after the stem.
+    ExportFrames(PathBuf, OsString),
     Resize(Vec2<u32>, FilterType),
     Color {
         hue: f32,
         saturation: f32,
         contrast: f32,
         lightness: f32,
+        tone: Tone,
+        white_balance: WhiteBalance,
     },
-    Crop(Rect),
+    Levels(Levels),
+    Filter(Filter),
+    /// Crops to the rectangle, in pixels of the image on disk as it is shown, and resizes
+    /// the result to the size if there is one, as a single step so one undo brings back the original.
+    Crop(Rect, Option<Vec2<u32>>),
     FlipHorizontal,
     FlipVertical,
     Rotate(i32),
+    RotateArbitrary(f32),
+    BakeAspect,
+    /// Deletes, moves or retimes a frame of an animation.
+    EditFrames(FrameEdit),
     Undo,
     Redo,
     Close,
-    Copy,
+    /// Copies the image as it is shown, only the part in the crop selection if there is one.
+    Copy(Option<Rect>),
+    CopyDataUri(Option<Rect>),
     Paste,
+    /// Pastes into the scratch list instead of replacing the image.
+    PasteToScratch,
+    /// Shows image data that is already in memory, like an entry of the scratch list.
+    Show(Arc<RwLock<ImageData>>),
+    Acquire,
+    FullResolution,
+    /// Renders the svg on screen again at this many times the size it declares.
+    Rasterize(f32),
+    AnalyzeColors,
+    /// Renders a resize preview on the cpu when the gpu could not.
+    ResizePreview(PreviewRequest),
+}
+
+impl Op {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::LoadPath(..) => "load",
+            Op::ImportFrames(..) => "import frames",
+            Op::Download(_) => "download",
+            Op::Next => "next",
+            Op::Prev => "prev",
+            Op::NextDir => "next dir",
+            Op::PrevDir => "prev dir",
+            Op::Save(..) => "save",
+            Op::Export(..) => "export",
+            Op::ExportFrames(..) => "export frames",
+            Op::Resize(..) => "resize",
+            Op::Color { .. } => "color",
+            Op::Levels(_) => "levels",
+            Op::Filter(Filter::Grayscale) => "grayscale",
+            Op::Filter(Filter::Sepia) => "sepia",
+            Op::Filter(Filter::Invert) => "invert",
+            Op::Crop(..) => "crop",
+            Op::FlipHorizontal => "flip horizontal",
+            Op::FlipVertical => "flip vertical",
+            Op::Rotate(_) => "rotate",
+            Op::RotateArbitrary(_) => "rotate arbitrary",
+            Op::BakeAspect => "bake aspect",
+            Op::EditFrames(_) => "edit frames",
+            Op::Undo => "undo",
+            Op::Redo => "redo",
+            Op::Close => "close",
+            Op::Copy(_) => "copy",
+            Op::CopyDataUri(_) => "copy data uri",
+            Op::Paste => "paste",
+            Op::PasteToScratch => "paste to scratch",
+            Op::Show(_) => "show",
+            Op::Acquire => "acquire",
+            Op::FullResolution => "full resolution",
+            Op::Rasterize(_) => "rasterize",
+            Op::AnalyzeColors => "analyze colors",
+            Op::ResizePreview(_) => "resize preview",
+        }
+    }
+
+    /// Moves the pixels the op points at from an image of size `from` to one of size `to`,
+    /// for an op that waited for the full resolution frames of an image that turned out
+    /// another size than the preview said, like a developed camera raw file.
+    pub fn rescaled(self, from: Vec2<u32>, to: Vec2<u32>) -> Op {
+        if from == to || from.x() == 0 || from.y() == 0 {
+            return self;
+        }
+        let factor = Vec2::new(
+            to.x() as f32 / from.x() as f32,
+            to.y() as f32 / from.y() as f32,
+        );
+        match self {
+            Op::Crop(cut, resize) => Op::Crop(cut.scaled(factor), resize),
+            Op::Copy(cut) => Op::Copy(cut.map(|cut| cut.scaled(factor))),
+            Op::CopyDataUri(cut) => Op::CopyDataUri(cut.map(|cut| cut.scaled(factor))),
+            op => op,
+        }
+    }
+
+    /// Ops that read or write the pixels and so can not run on a downscaled decode.
+    pub fn needs_full_resolution(&self) -> bool {
+        matches!(
+            self,
+            Op::Save(..)
+                | Op::ExportFrames(..)
+                | Op::Resize(..)
+                | Op::Color { .. }
+                | Op::Levels(_)
+                | Op::Filter(_)
+                | Op::Crop(..)
+                | Op::RotateArbitrary(_)
+                | Op::BakeAspect
+                | Op::EditFrames(_)
+                | Op::Copy(_)
+                | Op::CopyDataUri(_)
+        )
+    }
+
+    /// Ops that work on the image on screen and end right away when there is none.
+    fn needs_view(&self) -> bool {
+        matches!(
+            self,
+            Op::Save(..)
+                | Op::ExportFrames(..)
+                | Op::Resize(..)
+                | Op::Color { .. }
+                | Op::Levels(_)
+                | Op::Filter(_)
+                | Op::Crop(..)
+                | Op::FlipHorizontal
+                | Op::FlipVertical
+                | Op::Rotate(_)
+                | Op::RotateArbitrary(_)
+                | Op::BakeAspect
+                | Op::EditFrames(_)
+                | Op::Undo
+                | Op::Redo
+                | Op::Copy(_)
+                | Op::CopyDataUri(_)
+                | Op::AnalyzeColors
+                | Op::ResizePreview(_)
+        )
+    }
+
+    /// What the user is told when the op is refused because `running` is not done yet.
+    /// Browsing is refused quietly since it repeats while the key is held.
+    fn refusal(&self, running: &str) -> Option<String> {
+        let what = match self {
+            Op::Next
+            | Op::Prev
+            | Op::NextDir
+            | Op::PrevDir
+            | Op::Show(_)
+            | Op::FullResolution
+            | Op::Rasterize(_)
+            | Op::AnalyzeColors
+            | Op::ResizePreview(_) => return None,
+            Op::LoadPath(path, _) => format!("open {}", display_name(path)),
+            Op::Download(url) => format!("open {}", url),
+            Op::Save(path, ..) | Op::Export(path, ..) => format!("save {}", display_name(path)),
+            op => op.name().to_string(),
+        };
+        Some(format!("Could not {}, {} is still running", what, running))
+    }
 }
 
 pub enum Output {
     ImageLoaded(Arc<RwLock<ImageData>>, Option<PathBuf>),
+    /// A pasted image to add to the scratch list and show.
+    ScratchPasted(Arc<RwLock<ImageData>>),
     Rotate(i32),
     FlipHorizontal,
     FlipVertical,
     Resize(Vec<Image>),
+    RotateArbitrary(Vec<Image>),
     Color(Vec<Image>),
+    /// Frames with the levels baked in, only the levels preview is dropped.
+    Levels(Vec<Image>),
+    /// Filtered frames, undone like the color adjustments but without dropping their preview.
+    Filter(Vec<Image>),
+    BakeAspect(Vec<Image>, Option<(f32, f32)>),
+    /// The frames of an animation after one was deleted, moved or retimed.
+    Frames(Vec<Image>),
+    /// Frames to show instead of the reduced or svg ones, with the size the image declares
+    /// if they are rendered larger.
+    FullResolution(Vec<Image>, Option<(u32, u32)>),
+    ColorAnalysis(AnalysisKey, ColorAnalysis),
+    ResizePreview(PreviewRequest, DynamicImage),
+    /// The first image of a sibling directory, if one with images was found.
+    SiblingDir(Option<PathBuf>),
     Crop(Vec<Image>, i32),
     Undo,
     Redo,
@@ -62,55 +259,323 @@ pub enum Output {
     Done,
 }
 
+impl Output {
+    /// Outputs that change the image on screen and go on the undo stack.
+    fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Output::Rotate(_)
+                | Output::FlipHorizontal
+                | Output::FlipVertical
+                | Output::Resize(_)
+                | Output::RotateArbitrary(_)
+                | Output::Color(_)
+                | Output::Levels(_)
+                | Output::Filter(_)
+                | Output::BakeAspect(..)
+                | Output::Frames(_)
+                | Output::Crop(..)
+                | Output::Undo
+                | Output::Redo
+        )
+    }
+}
+
+/// Shows an edit that finished on `target` and records it for undo, or undoes and redoes one.
+/// Edits that arrive after the image was closed are dropped. Anything else is handed back.
+pub fn apply_edit(
+    output: Output,
+    target: Option<&mut impl EditTarget>,
+    stack: &mut UndoStack,
+) -> Option<Output> {
+    if !output.is_edit() {
+        return Some(output);
+    }
+    let target = match target {
+        Some(target) => target,
+        None => {
+            log::debug!("dropped an edit that finished after the image was closed");
+            return None;
+        }
+    };
+
+    match output {
+        Output::FlipHorizontal => {
+            target.flip_horizontal();
+            stack.push(UndoFrame::FlipHorizontal);
+        }
+        Output::FlipVertical => {
+            target.flip_vertical();
+            stack.push(UndoFrame::FlipVertical);
+        }
+        Output::Rotate(dir) => {
+            target.rotate(dir);
+            stack.push(UndoFrame::Rotate(dir));
+        }
+        Output::Resize(mut frames) => {
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::Resize(frames));
+        }
+        Output::BakeAspect(mut frames, mut resolution) => {
+            target.swap_resolution(&mut resolution);
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::BakeAspect { frames, resolution });
+        }
+        Output::RotateArbitrary(mut frames) => {
+            target.clear_angle_preview();
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::RotateArbitrary(frames));
+        }
+        Output::Frames(mut frames) => {
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::Frames(frames));
+        }
+        Output::Color(mut frames) => {
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::Color(frames));
+            target.clear_color_preview();
+        }
+        Output::Levels(mut frames) => {
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::Color(frames));
+            target.clear_levels_preview();
+        }
+        Output::Filter(mut frames) => {
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::Color(frames));
+        }
+        Output::Crop(mut frames, rotation) => {
+            // the frames were cut as they are shown, so they are not turned anymore
+            target.swap_rotation(&mut 0);
+            target.swap_frames(&mut frames);
+            stack.push(UndoFrame::Crop { frames, rotation });
+        }
+        Output::Undo => stack.undo(target),
+        Output::Redo => stack.redo(target),
+        _ => unreachable!("only edits get here"),
+    }
+    None
+}
+
 #[derive(Default)]
 pub struct LoadingInfo {
     target_file: Option<PathBuf>,
     loading: HashSet<PathBuf>,
+    saving: HashSet<PathBuf>,
+    save_done: Arc<Condvar>,
+    /// Space available for the image when it is best fit, used to decode large jpegs smaller.
+    fit_size: Option<(u32, u32)>,
+    max_pixels: Option<u64>,
+    raw_previews: bool,
+    /// Images over the limit the user chose to open anyway.
+    oversized: HashMap<PathBuf, Oversized>,
+}
+
+fn load_options(loading_info: &Mutex<LoadingInfo>, path: &Path) -> LoadOptions {
+    let guard = loading_info.lock().unwrap();
+    LoadOptions {
+        fit: guard.fit_size,
+        max_pixels: guard.max_pixels,
+        oversized: guard
+            .oversized
+            .get(path)
+            .copied()
+            .unwrap_or(Oversized::Refuse),
+        vector_scale: 1.0,
+        raw_preview: guard.raw_previews,
+    }
+}
+
+/// Keeps a path marked as being saved until it is dropped, which also happens when the
+/// thread doing the save panics.
+pub struct SaveLock {
+    loading_info: Arc<Mutex<LoadingInfo>>,
+    path: PathBuf,
+    /// Where the save op is ended if the thread panics, see `ends_op`.
+    op: Option<(Sender<Output>, Proxy)>,
+}
+
+impl SaveLock {
+    /// Has the lock end the op if the thread holding it panics, the queue would otherwise
+    /// wait for its output forever and refuse every op after it.
+    pub fn ends_op(mut self, sender: Sender<Output>, proxy: Proxy) -> Self {
+        self.op = Some((sender, proxy));
+        self
+    }
+}
+
+impl Drop for SaveLock {
+    fn drop(&mut self) {
+        // a save that panicked may have poisoned the lock, the path is freed regardless
+        let mut guard = self
+            .loading_info
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        guard.saving.remove(&self.path);
+        guard.save_done.notify_all();
+        drop(guard);
+
+        if let Some((sender, proxy)) = self.op.take().filter(|_| thread::panicking()) {
+            let _ = sender.send(Output::Done);
+            let _ = proxy.send_event(UserEvent::ErrorMessage(format!(
+                "Saving {} failed",
+                self.path.to_string_lossy()
+            )));
+        }
+    }
 }
 
+/// Marks `path` as being saved until the returned lock is dropped. `None` if a save to it
+/// is already running.
+pub fn begin_save(loading_info: &Arc<Mutex<LoadingInfo>>, path: &Path) -> Option<SaveLock> {
+    let inserted = loading_info
+        .lock()
+        .unwrap()
+        .saving
+        .insert(path.to_path_buf());
+    inserted.then(|| SaveLock {
+        loading_info: loading_info.clone(),
+        path: path.to_path_buf(),
+        op: None,
+    })
+}
+
+/// Blocks until no save to `path` is in flight so a load never races the rename.
+pub fn wait_for_save(loading_info: &Mutex<LoadingInfo>, path: &Path) {
+    let mut guard = loading_info.lock().unwrap();
+    while guard.saving.contains(path) {
+        let save_done = guard.save_done.clone();
+        guard = save_done.wait(guard).unwrap();
+    }
+}
+
+const UNKNOWN_PROGRESS: u32 = u32::MAX;
+
+/// How far along the running op is. Written by the worker thread and read by the ui.
+#[derive(Clone)]
+pub struct Progress(Arc<AtomicU32>);
+
+impl Progress {
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicU32::new(UNKNOWN_PROGRESS)))
+    }
+
+    pub fn set(&self, done: usize, total: usize) {
+        let fraction = done as f32 / total.max(1) as f32;
+        self.0.store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.0.store(UNKNOWN_PROGRESS, Ordering::Relaxed);
+    }
+
+    /// The fraction that is done, if the op reports it.
+    pub fn get(&self) -> Option<f32> {
+        match self.0.load(Ordering::Relaxed) {
+            UNKNOWN_PROGRESS => None,
+            bits => Some(f32::from_bits(bits)),
+        }
+    }
+}
+
+/// Runs one op at a time. Ops queued while another one runs are refused, with a toast for
+/// the ones the user would miss, rather than waiting in line behind it.
 pub struct OpQueue {
-    working: bool,
+    /// The name of the op that has not sent its output yet.
+    running: Option<&'static str>,
     loading_info: Arc<Mutex<LoadingInfo>>,
     sender: Sender<Output>,
     receiver: Receiver<Output>,
-    proxy: EventLoopProxy<UserEvent>,
+    proxy: Proxy,
     stack: UndoStack,
+    progress: Progress,
+    /// Set to stop the running download.
+    cancel_download: Arc<AtomicBool>,
     pub cache: Arc<Cache>,
     pub image_list: ImageList,
+    pub thumbnails: Thumbnails,
 }
 
 impl OpQueue {
-    pub fn new(proxy: EventLoopProxy<UserEvent>) -> Self {
+    pub fn new(proxy: Proxy, settings: &Settings) -> Self {
         let (sender, receiver) = mpsc::channel();
 
-        const CACHE_SIZE: usize = 1_000_000_000;
-        let cache = Arc::new(Cache::new(CACHE_SIZE));
-        let loading_info = Arc::new(Mutex::new(LoadingInfo::default()));
+        let cache = Arc::new(Cache::new(settings.cache_size()));
+        let loading_info = Arc::new(Mutex::new(LoadingInfo {
+            max_pixels: Some(settings.max_pixels()),
+            raw_previews: settings.raw_previews,
+            ..Default::default()
+        }));
+
+        let mut image_list = ImageList::new(
+            cache.clone(),
+            proxy.clone(),
+            sender.clone(),
+            loading_info.clone(),
+        );
+        image_list.set_listing(settings.listing());
 
         Self {
-            working: false,
-            image_list: ImageList::new(
-                cache.clone(),
-                proxy.clone(),
-                sender.clone(),
-                loading_info.clone(),
-            ),
+            running: None,
+            image_list,
+            thumbnails: Thumbnails::new(cache.clone(), proxy.clone()),
             loading_info,
             sender,
             receiver,
             stack: UndoStack::new(),
+            progress: Progress::new(),
+            cancel_download: Arc::new(AtomicBool::new(false)),
             proxy,
             cache,
         }
     }
 
-    pub fn queue(&mut self, op: Op, view: Option<&ImageView>) {
-        if !self.working {
-            self.working = true;
+    pub fn queue(&mut self, op: Op, view: Option<&ViewState>) {
+        let description = match &op {
+            Op::LoadPath(path, _)
+            | Op::Save(path, ..)
+            | Op::Export(path, ..)
+            | Op::ExportFrames(path, _) => {
+                format!("{} {}", op.name(), path.to_string_lossy())
+            }
+            Op::Download(url) => format!("{} {}", op.name(), url),
+            op => op.name().to_string(),
+        };
+        if let Some(running) = self.running {
+            log::warn!("op {} refused, {} is running", description, running);
+            if let Some(message) = op.refusal(running) {
+                let _ = self.proxy.send_event(UserEvent::Toast(message));
+            }
+        } else if view.is_none() && op.needs_view() {
+            // the ops below can count on the view being there
+            log::debug!("op {} skipped, no image is open", description);
+        } else {
+            log::info!("op {}", description);
+            self.running = Some(op.name());
+            self.progress.reset();
             match op {
                 Op::LoadPath(path, use_cache) => {
                     self.load(path, use_cache);
                 }
+                Op::ImportFrames(paths, delay) => {
+                    import_frames::build(
+                        paths,
+                        delay,
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                        self.progress.clone(),
+                    );
+                }
+                Op::Download(url) => {
+                    download::open(
+                        url,
+                        self.download_options(),
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                        self.progress.clone(),
+                        self.download_cancel(),
+                    );
+                }
                 Op::Next => match self.image_list.next() {
                     Some(path) => {
                         self.load(path, true);
@@ -129,19 +594,57 @@ impl OpQueue {
                         let _ = self.proxy.send_event(UserEvent::Wake);
                     }
                 },
-                Op::Save(path) => {
-                    if let Some(view) = view {
-                        save_image::save(
-                            self.proxy.clone(),
-                            self.sender.clone(),
-                            path,
-                            view.image_data.clone(),
-                            view.rotation,
-                            view.horizontal_flip,
-                            view.vertical_flip,
-                        )
-                    }
+                Op::NextDir | Op::PrevDir => {
+                    let forward = matches!(op, Op::NextDir);
+                    let dir = view
+                        .and_then(|view| view.path.as_ref())
+                        .and_then(|path| path.parent())
+                        .map(Path::to_path_buf);
+                    let sender = self.sender.clone();
+                    let proxy = self.proxy.clone();
+                    let listing = self.image_list.listing();
+                    thread::spawn(move || {
+                        let image = dir.and_then(|dir| sibling_dir_image(&dir, forward, listing));
+                        let _ = sender.send(Output::SiblingDir(image));
+                        let _ = proxy.send_event(UserEvent::Wake);
+                    });
+                }
+                Op::Save(path, encoding, fixes) => {
+                    let view = view.unwrap();
+                    save_image::save(
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                        self.loading_info.clone(),
+                        self.cache.clone(),
+                        path,
+                        view.image_data.clone(),
+                        view.rotation,
+                        view.horizontal_flip,
+                        view.vertical_flip,
+                        encoding,
+                        fixes,
+                    )
                 }
+                Op::Export(path, image_data, encoding, fixes) => save_image::save(
+                    self.proxy.clone(),
+                    self.sender.clone(),
+                    self.loading_info.clone(),
+                    self.cache.clone(),
+                    path,
+                    image_data,
+                    0,
+                    false,
+                    false,
+                    encoding,
+                    fixes,
+                ),
+                Op::ExportFrames(dir, stem) => view.unwrap().export_frames(
+                    dir,
+                    stem,
+                    self.proxy.clone(),
+                    self.sender.clone(),
+                    self.progress.clone(),
+                ),
                 Op::Rotate(dir) => {
                     let _ = self.sender.send(Output::Rotate(dir));
                     let _ = self.proxy.send_event(UserEvent::Wake);
@@ -154,6 +657,17 @@ impl OpQueue {
                     let _ = self.sender.send(Output::FlipVertical);
                     let _ = self.proxy.send_event(UserEvent::Wake);
                 }
+                Op::EditFrames(edit) => {
+                    let image_data = view.unwrap().image_data.clone();
+                    let proxy = self.proxy.clone();
+                    let sender = self.sender.clone();
+                    thread::spawn(move || {
+                        let mut frames = image_data.read().unwrap().frames.clone();
+                        edit.apply(&mut frames);
+                        let _ = sender.send(Output::Frames(frames));
+                        let _ = proxy.send_event(UserEvent::Wake);
+                    });
+                }
                 Op::Undo => {
                     let _ = self.sender.send(Output::Undo);
                     let _ = self.proxy.send_event(UserEvent::Wake);
@@ -170,49 +684,199 @@ impl OpQueue {
                     let image_data = view.as_ref().unwrap().image_data.clone();
                     let proxy = self.proxy.clone();
                     let sender = self.sender.clone();
+                    let progress = self.progress.clone();
                     thread::spawn(move || {
                         let guard = image_data.read().unwrap();
                         let mut new = Vec::new();
-                        for image in guard.frames.iter() {
-                            let buffer = image.buffer().resize_exact(size.x(), size.y(), resample);
-                            new.push(Image::with_delay(buffer, image.delay));
+                        for (index, image) in guard.frames.iter().enumerate() {
+                            new.push(resized(image, size, resample));
+                            progress.set(index + 1, guard.frames.len());
                         }
                         let _ = sender.send(Output::Resize(new));
                         let _ = proxy.send_event(UserEvent::Wake);
                     });
                 }
-                Op::Color {
-                    hue,
-                    saturation: _,
-                    contrast,
-                    lightness: _,
-                } => {
-                    let image_data = view.as_ref().unwrap().image_data.clone();
+                Op::FullResolution | Op::Rasterize(_) => {
+                    let vector_scale = match op {
+                        Op::Rasterize(scale) => scale,
+                        _ => 1.0,
+                    };
+                    let path = view.and_then(|view| view.path.clone());
                     let proxy = self.proxy.clone();
                     let sender = self.sender.clone();
+                    let loading_info = self.loading_info.clone();
+                    thread::spawn(move || {
+                        let res = match path {
+                            Some(path) => {
+                                wait_for_save(&loading_info, &path);
+                                let options = LoadOptions {
+                                    fit: None,
+                                    vector_scale,
+                                    raw_preview: false,
+                                    ..load_options(&loading_info, &path)
+                                };
+                                load_uncached(&path, options)
+                            }
+                            None => {
+                                let _ = sender.send(Output::Done);
+                                let _ = proxy.send_event(UserEvent::Wake);
+                                return;
+                            }
+                        };
+                        match res {
+                            Ok(image_data) => {
+                                let _ = sender.send(Output::FullResolution(
+                                    image_data.frames,
+                                    image_data.full_size,
+                                ));
+                                let _ = proxy.send_event(UserEvent::Wake);
+                            }
+                            Err(error) => {
+                                let _ = sender.send(Output::Done);
+                                let _ =
+                                    proxy.send_event(UserEvent::ErrorMessage(error.to_string()));
+                            }
+                        }
+                    });
+                }
+                Op::BakeAspect => {
+                    let view = view.unwrap();
+                    let image_data = view.image_data.clone();
+                    let size = view.corrected_size;
+                    let proxy = self.proxy.clone();
+                    let sender = self.sender.clone();
+                    let progress = self.progress.clone();
                     thread::spawn(move || {
                         let guard = image_data.read().unwrap();
+                        // after stretching the pixels are square at the higher resolution
+                        let resolution = guard.resolution.map(|(x, y)| (x.max(y), x.max(y)));
                         let mut new = Vec::new();
-                        for image in guard.frames.iter() {
-                            let buffer = image
-                                .buffer()
-                                .huerotate(hue as i32)
-                                .adjust_contrast(contrast);
+                        for (index, image) in guard.frames.iter().enumerate() {
+                            let buffer = image.buffer().resize_exact(
+                                size.x(),
+                                size.y(),
+                                FilterType::CatmullRom,
+                            );
                             new.push(Image::with_delay(buffer, image.delay));
+                            progress.set(index + 1, guard.frames.len());
                         }
-                        let _ = sender.send(Output::Color(new));
+                        let _ = sender.send(Output::BakeAspect(new, resolution));
                         let _ = proxy.send_event(UserEvent::Wake);
                     });
                 }
-                Op::Crop(rect) => {
+                Op::RotateArbitrary(angle) => {
+                    let view = view.unwrap();
+                    // the angle is how it looks on screen so a mirrored view turns the other way
+                    let angle = if view.horizontal_flip != view.vertical_flip {
+                        -angle
+                    } else {
+                        angle
+                    };
+                    self.map_frames(
+                        view,
+                        move |buffer| rotate::rotate(buffer, angle),
+                        Output::RotateArbitrary,
+                    );
+                }
+                Op::Color {
+                    hue,
+                    saturation: _,
+                    contrast,
+                    lightness: _,
+                    tone,
+                    white_balance,
+                } => {
+                    self.map_frames(
+                        view.unwrap(),
+                        move |buffer| {
+                            tone.adjust(&white_balance.adjust(buffer))
+                                .huerotate(hue as i32)
+                                .adjust_contrast(contrast)
+                        },
+                        Output::Color,
+                    );
+                }
+                Op::Levels(levels) => {
+                    self.map_frames(
+                        view.unwrap(),
+                        move |buffer| levels.adjust(buffer),
+                        Output::Levels,
+                    );
+                }
+                Op::Filter(filter) => {
+                    self.map_frames(
+                        view.unwrap(),
+                        move |buffer| filter.apply(buffer),
+                        Output::Filter,
+                    );
+                }
+                Op::Crop(rect, resize) => {
                     view.unwrap()
-                        .crop(rect, self.proxy.clone(), self.sender.clone());
+                        .crop(rect, resize, self.proxy.clone(), self.sender.clone());
                 }
-                Op::Copy => {
-                    clipboard::copy(view.unwrap(), self.proxy.clone(), self.sender.clone());
+                Op::Copy(selection) => {
+                    clipboard::copy(
+                        view.unwrap(),
+                        selection,
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                    );
+                }
+                Op::CopyDataUri(selection) => {
+                    clipboard::copy_data_uri(
+                        view.unwrap(),
+                        selection,
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                    );
                 }
                 Op::Paste => {
-                    clipboard::paste(self.proxy.clone(), self.sender.clone());
+                    clipboard::paste(
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                        self.progress.clone(),
+                        self.download_options(),
+                        self.download_cancel(),
+                        false,
+                    );
+                }
+                Op::PasteToScratch => {
+                    clipboard::paste(
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                        self.progress.clone(),
+                        self.download_options(),
+                        self.download_cancel(),
+                        true,
+                    );
+                }
+                Op::Show(image_data) => {
+                    let _ = self.sender.send(Output::ImageLoaded(image_data, None));
+                    let _ = self.proxy.send_event(UserEvent::Wake);
+                }
+                Op::Acquire => {
+                    acquire::acquire(self.proxy.clone(), self.sender.clone());
+                }
+                Op::AnalyzeColors => {
+                    let view = view.unwrap();
+                    analyze::analyze(
+                        view.image_data.clone(),
+                        view.analysis_key,
+                        self.proxy.clone(),
+                        self.sender.clone(),
+                    );
+                }
+                Op::ResizePreview(request) => {
+                    let image_data = view.unwrap().image_data.clone();
+                    let sender = self.sender.clone();
+                    let proxy = self.proxy.clone();
+                    thread::spawn(move || {
+                        let guard = image_data.read().unwrap();
+                        let index = request.index.min(guard.frames.len() - 1);
+                        let image = request.render(guard.frames[index].buffer());
+                        let _ = sender.send(Output::ResizePreview(request, image));
+                        let _ = proxy.send_event(UserEvent::Wake);
+                    });
                 }
             }
         }
@@ -221,13 +885,37 @@ impl OpQueue {
     pub fn poll(&mut self) -> Option<(Output, &mut UndoStack)> {
         match self.receiver.try_recv() {
             Ok(output) => {
-                self.working = false;
+                self.running = None;
                 Some((output, &mut self.stack))
             }
             Err(_) => None,
         }
     }
 
+    /// Maps every frame of the image on screen with `map` on another thread and hands the new
+    /// frames back wrapped in `output`.
+    fn map_frames(
+        &self,
+        view: &ViewState,
+        map: impl Fn(&DynamicImage) -> DynamicImage + Send + 'static,
+        output: fn(Vec<Image>) -> Output,
+    ) {
+        let image_data = view.image_data.clone();
+        let proxy = self.proxy.clone();
+        let sender = self.sender.clone();
+        let progress = self.progress.clone();
+        thread::spawn(move || {
+            let guard = image_data.read().unwrap();
+            let mut new = Vec::new();
+            for (index, image) in guard.frames.iter().enumerate() {
+                new.push(Image::with_delay(map(image.buffer()), image.delay));
+                progress.set(index + 1, guard.frames.len());
+            }
+            let _ = sender.send(output(new));
+            let _ = proxy.send_event(UserEvent::Wake);
+        });
+    }
+
     fn load(&self, path_buf: PathBuf, use_cache: bool) {
         {
             let mut guard = self.loading_info.lock().unwrap();
@@ -259,7 +947,8 @@ impl OpQueue {
         let proxy = self.proxy.clone();
         let loading_info = self.loading_info.clone();
         thread::spawn(move || {
-            let res = load_uncached(&path_buf);
+            wait_for_save(&loading_info, &path_buf);
+            let res = load_uncached(&path_buf, load_options(&loading_info, &path_buf));
             let mut guard = loading_info.lock().unwrap();
             guard.loading.remove(&path_buf);
             guard.target_file = None;
@@ -273,23 +962,121 @@ impl OpQueue {
                         .unwrap();
                     let _ = proxy.send_event(UserEvent::Wake);
                 }
-                Err(error) => {
-                    let _ = sender.send(Output::Done);
-                    let _ = proxy.send_event(UserEvent::ErrorMessage(error.to_string()));
-                }
+                Err(error) => load_failed(path_buf, error, &sender, &proxy),
             };
         });
     }
 
     pub fn working(&self) -> bool {
-        self.working
+        self.running.is_some()
+    }
+
+    /// Whether the running op is a download, or a paste that may be downloading a web address.
+    pub fn downloading(&self) -> bool {
+        matches!(
+            self.running,
+            Some("download" | "paste" | "paste to scratch")
+        )
+    }
+
+    /// Downloaded images are decoded with the limits of the settings. The bytes are gone
+    /// afterwards so they are never decoded smaller to fit the window.
+    fn download_options(&self) -> LoadOptions {
+        let guard = self.loading_info.lock().unwrap();
+        LoadOptions {
+            max_pixels: guard.max_pixels,
+            raw_preview: guard.raw_previews,
+            ..LoadOptions::default()
+        }
+    }
+
+    /// A cleared cancel flag for the next download.
+    fn download_cancel(&self) -> Arc<AtomicBool> {
+        self.cancel_download.store(false, Ordering::SeqCst);
+        self.cancel_download.clone()
+    }
+
+    /// Stops the download that is running, returns whether there was one.
+    pub fn cancel_download(&self) -> bool {
+        let downloading = self.downloading();
+        if downloading {
+            self.cancel_download.store(true, Ordering::SeqCst);
+        }
+        downloading
+    }
+
+    /// Every tab has its own undo history, the one of the tab on screen lives here.
+    pub fn swap_stack(&mut self, stack: &mut UndoStack) {
+        std::mem::swap(&mut self.stack, stack);
+    }
+
+    /// Whether the image on screen has edits that are not saved.
+    pub fn edited(&self) -> bool {
+        self.stack.edited()
+    }
+
+    /// The frames of the image on screen from before its pixels were edited.
+    pub fn original(&self) -> Option<&[Image]> {
+        self.stack.original()
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.stack.mark_saved();
+    }
+
+    /// Whether the image `Op::Next` or `Op::Prev` goes to is decoded already.
+    pub fn neighbour_cached(&self, forward: bool) -> bool {
+        self.image_list
+            .neighbour(forward)
+            .is_some_and(|path| self.cache.contains(&path))
+    }
+
+    pub fn progress(&self) -> Option<f32> {
+        self.progress.get()
+    }
+
+    pub fn set_fit_size(&self, size: Option<(u32, u32)>) {
+        self.loading_info.lock().unwrap().fit_size = size;
+    }
+
+    pub fn set_max_pixels(&self, max_pixels: u64) {
+        self.loading_info.lock().unwrap().max_pixels = Some(max_pixels);
+    }
+
+    pub fn set_raw_previews(&self, raw_previews: bool) {
+        self.loading_info.lock().unwrap().raw_previews = raw_previews;
+    }
+
+    /// Lets `path` be loaded even though it is over the limit.
+    pub fn allow_oversized(&self, path: PathBuf, oversized: Oversized) {
+        self.loading_info
+            .lock()
+            .unwrap()
+            .oversized
+            .insert(path, oversized);
     }
 }
 
+/// Ends a failed load so the queue moves on, large images get asked about instead.
+fn load_failed(path: PathBuf, error: LoadError, sender: &Sender<Output>, proxy: &Proxy) {
+    let _ = sender.send(Output::Done);
+    let event = match error {
+        LoadError::TooLarge(size, can_downsample) => {
+            UserEvent::TooLarge(path, size, can_downsample)
+        }
+        LoadError::Decoding(_) => {
+            UserEvent::LoadFailed(path, String::from("unsupported or damaged file"))
+        }
+        LoadError::Io(error) => UserEvent::LoadFailed(path, error.to_string()),
+        error @ LoadError::Unsupported(_) => UserEvent::LoadFailed(path, error.to_string()),
+    };
+    let _ = proxy.send_event(event);
+}
+
 pub fn prefetch(
     path: impl AsRef<Path>,
     cache: Arc<Cache>,
-    proxy: EventLoopProxy<UserEvent>,
+    proxy: Proxy,
     sender: Sender<Output>,
     loading_info: Arc<Mutex<LoadingInfo>>,
 ) {
@@ -309,7 +1096,8 @@ pub fn prefetch(
     }
 
     thread::spawn(move || {
-        let res = load_uncached(&path_buf);
+        wait_for_save(&loading_info, &path_buf);
+        let res = load_uncached(&path_buf, load_options(&loading_info, &path_buf));
         let mut guard = loading_info.lock().unwrap();
         guard.loading.remove(&path_buf);
 
@@ -321,9 +1109,16 @@ pub fn prefetch(
                     sender
                         .send(Output::ImageLoaded(images, Some(path_buf.clone())))
                         .unwrap();
-                    let _ = proxy.send_event(UserEvent::Wake);
                 }
+                // also repaints the indicators of cached neighbours
+                let _ = proxy.send_event(UserEvent::Wake);
             }
+            // the load of the target waits for this one, so it has to fail in its place
+            Err(error) if guard.target_file.as_ref() == Some(&path_buf) => {
+                load_failed(path_buf.clone(), error, &sender, &proxy)
+            }
+            // the user is asked about large images once they actually open them
+            Err(LoadError::TooLarge(..)) => (),
             Err(error) => {
                 let _ = proxy.send_event(UserEvent::ErrorMessage(error.to_string()));
             }
@@ -334,3 +1129,417 @@ pub fn prefetch(
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs, mem,
+        path::PathBuf,
+        sync::{mpsc::Receiver, Arc, Mutex, RwLock},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use image::{ImageBuffer, ImageOutputFormat, Rgba};
+
+    use super::*;
+    use crate::{
+        app::{
+            export_frames::frame_stem,
+            load_image::{load_uncached, LoadOptions},
+            untitled::save_name,
+        },
+        image_io::save::save_with_format,
+        util::Image,
+    };
+
+    /// Stands in for the view, keeping the frames in memory instead of uploading them.
+    impl EditTarget for ViewState {
+        fn rotate(&mut self, rotation: i32) {
+            self.rotation = (self.rotation + rotation).rem_euclid(4);
+        }
+
+        fn flip_horizontal(&mut self) {
+            self.horizontal_flip = !self.horizontal_flip;
+        }
+
+        fn flip_vertical(&mut self) {
+            self.vertical_flip = !self.vertical_flip;
+        }
+
+        fn swap_frames(&mut self, frames: &mut Vec<Image>) {
+            let mut guard = self.image_data.write().unwrap();
+            mem::swap(&mut guard.frames, frames);
+            let (width, height) = (
+                guard.frames[0].buffer().width(),
+                guard.frames[0].buffer().height(),
+            );
+            self.size = Vec2::new(width, height);
+            self.true_size = self.size;
+            self.corrected_size = self.size;
+        }
+
+        fn swap_resolution(&mut self, resolution: &mut Option<(f32, f32)>) {
+            mem::swap(&mut self.image_data.write().unwrap().resolution, resolution);
+        }
+
+        fn swap_rotation(&mut self, rotation: &mut i32) {
+            mem::swap(&mut self.rotation, rotation);
+        }
+
+        fn clear_angle_preview(&mut self) {}
+
+        fn clear_color_preview(&mut self) {}
+
+        fn clear_levels_preview(&mut self) {}
+    }
+
+    fn view_state(image_data: Arc<RwLock<ImageData>>, path: Option<PathBuf>) -> ViewState {
+        let buffer = image_data.read().unwrap().frames[0].buffer().clone();
+        let size = Vec2::new(buffer.width(), buffer.height());
+        ViewState {
+            image_data,
+            path,
+            size,
+            true_size: size,
+            corrected_size: size,
+            rotation: 0,
+            horizontal_flip: false,
+            vertical_flip: false,
+            analysis_key: (0, 0),
+        }
+    }
+
+    fn harness() -> (OpQueue, Receiver<UserEvent>) {
+        let (proxy, events) = Proxy::channel();
+        (OpQueue::new(proxy, &Settings::default()), events)
+    }
+
+    /// Waits for the running op and handles its output the way `App::poll` does.
+    fn settle(queue: &mut OpQueue, view: &mut Option<ViewState>) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some((output, stack)) = queue.poll() {
+                match apply_edit(output, view.as_mut(), stack) {
+                    Some(Output::ImageLoaded(image_data, path)) => {
+                        stack.clear();
+                        *view = Some(view_state(image_data, path));
+                    }
+                    Some(Output::Close) => {
+                        stack.clear();
+                        *view = None;
+                    }
+                    _ => (),
+                }
+                return;
+            }
+            assert!(Instant::now() < deadline, "the op never sent its output");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn toasts(events: &Receiver<UserEvent>) -> Vec<String> {
+        events
+            .try_iter()
+            .filter_map(|event| match event {
+                UserEvent::Toast(text) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn frame_size(view: &ViewState) -> (u32, u32) {
+        let guard = view.image_data.read().unwrap();
+        (
+            guard.frames[0].buffer().width(),
+            guard.frames[0].buffer().height(),
+        )
+    }
+
+    #[test]
+    fn edits_undo_and_close_in_the_order_they_were_queued() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("queue.png");
+        let frame = Image::from(ImageBuffer::from_pixel(40, 20, Rgba([9, 9, 9, 255])));
+        save_with_format(&path, &frame, ImageOutputFormat::Png).unwrap();
+
+        let (mut queue, events) = harness();
+        let mut view = None;
+        queue.queue(Op::LoadPath(path.clone(), true), view.as_ref());
+        settle(&mut queue, &mut view);
+        assert_eq!(view.as_ref().unwrap().path.as_ref(), Some(&path));
+
+        queue.queue(Op::Rotate(1), view.as_ref());
+        settle(&mut queue, &mut view);
+        assert_eq!(view.as_ref().unwrap().rotation, 1);
+        queue.queue(Op::Undo, view.as_ref());
+        settle(&mut queue, &mut view);
+        assert_eq!(view.as_ref().unwrap().rotation, 0);
+        queue.queue(Op::Redo, view.as_ref());
+        settle(&mut queue, &mut view);
+        assert_eq!(view.as_ref().unwrap().rotation, 1);
+
+        // the queue stays busy until the output is polled, so this is never a race
+        queue.queue(
+            Op::Resize(Vec2::new(10, 5), FilterType::Nearest),
+            view.as_ref(),
+        );
+        queue.queue(Op::Undo, view.as_ref());
+        queue.queue(Op::Close, view.as_ref());
+        queue.queue(Op::Next, view.as_ref());
+        assert_eq!(
+            toasts(&events),
+            [
+                "Could not undo, resize is still running",
+                "Could not close, resize is still running",
+            ]
+        );
+        settle(&mut queue, &mut view);
+        assert_eq!(frame_size(view.as_ref().unwrap()), (10, 5));
+
+        queue.queue(Op::Undo, view.as_ref());
+        settle(&mut queue, &mut view);
+        assert_eq!(frame_size(view.as_ref().unwrap()), (40, 20));
+        assert_eq!(view.as_ref().unwrap().rotation, 1);
+
+        queue.queue(Op::Close, view.as_ref());
+        settle(&mut queue, &mut view);
+        assert!(view.is_none());
+        assert!(!queue.working());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pasted_images_edit_save_and_export_like_files() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let frames = vec![
+            Image::from(ImageBuffer::from_pixel(40, 20, Rgba([9, 9, 9, 255]))),
+            Image::from(ImageBuffer::from_pixel(40, 20, Rgba([90, 9, 9, 255]))),
+        ];
+        let image_data = Arc::new(RwLock::new(ImageData::from(frames)));
+        let mut view = Some(view_state(image_data, None));
+
+        let (mut queue, events) = harness();
+        queue.queue(Op::Rotate(1), view.as_ref());
+        settle(&mut queue, &mut view);
+        queue.queue(Op::FlipHorizontal, view.as_ref());
+        settle(&mut queue, &mut view);
+        queue.queue(
+            Op::Resize(Vec2::new(10, 5), FilterType::Nearest),
+            view.as_ref(),
+        );
+        settle(&mut queue, &mut view);
+        let state = view.as_ref().unwrap();
+        assert_eq!((state.rotation, state.horizontal_flip), (1, true));
+        assert_eq!(frame_size(state), (10, 5));
+
+        queue.queue(Op::Undo, view.as_ref());
+        settle(&mut queue, &mut view);
+        assert_eq!(frame_size(view.as_ref().unwrap()), (40, 20));
+        queue.queue(Op::Undo, view.as_ref());
+        settle(&mut queue, &mut view);
+        assert!(!view.as_ref().unwrap().horizontal_flip);
+        assert!(view.as_ref().unwrap().path.is_none());
+
+        let stem = frame_stem(&save_name(None));
+        queue.queue(Op::ExportFrames(dir.clone(), stem), view.as_ref());
+        settle(&mut queue, &mut view);
+        assert!(dir.join("untitled_frame1.png").exists());
+        assert!(dir.join("untitled_frame2.png").exists());
+
+        let path = dir.join(save_name(None));
+        queue.queue(
+            Op::Save(path.clone(), Encoding::default(), Fixes::default()),
+            view.as_ref(),
+        );
+        settle(&mut queue, &mut view);
+        // the event comes after the output, wait for it instead of racing the thread
+        let saved = std::iter::from_fn(|| events.recv_timeout(Duration::from_secs(10)).ok())
+            .find_map(|event| match event {
+                UserEvent::Saved(saved, _) => Some(saved),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(saved, path);
+        assert!(path.exists());
+        assert!(toasts(&events).is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn crops_cut_the_same_part_at_any_decode_scale() {
+        // every pixel holds its position in the image on disk
+        let frame = |scale: f32| {
+            let size = |side: f32| (side * scale) as u32;
+            Image::from(ImageBuffer::from_fn(size(100.0), size(50.0), |x, y| {
+                Rgba([(x as f32 / scale) as u8, (y as f32 / scale) as u8, 0, 255])
+            }))
+        };
+        let cut = Rect::new(Vec2::new(20.0, 10.0), Vec2::new(40.0, 20.0));
+
+        let (mut queue, _events) = harness();
+        // a jpeg decoded at half the size, the full one and an svg rendered at 4x
+        for scale in [0.5, 1.0, 4.0] {
+            let image_data = Arc::new(RwLock::new(ImageData::from(vec![frame(scale)])));
+            let mut view = Some(ViewState {
+                true_size: Vec2::new(100, 50),
+                ..view_state(image_data, None)
+            });
+            queue.queue(Op::Crop(cut, None), view.as_ref());
+            settle(&mut queue, &mut view);
+
+            let guard = view.as_ref().unwrap().image_data.read().unwrap();
+            let cropped = guard.frames[0].buffer().to_rgba8();
+            let expected = ((40.0 * scale) as u32, (20.0 * scale) as u32);
+            assert_eq!(cropped.dimensions(), expected);
+            assert_eq!(cropped.get_pixel(0, 0), &Rgba([20, 10, 0, 255]));
+        }
+
+        // a preview that turns out half the size of the developed image
+        let from = Vec2::new(100, 50);
+        let to = Vec2::new(200, 100);
+        match Op::Crop(cut, None).rescaled(from, to) {
+            Op::Crop(rescaled, None) => assert_eq!(
+                rescaled,
+                Rect::new(Vec2::new(40.0, 20.0), Vec2::new(80.0, 40.0))
+            ),
+            op => panic!("{:?}", op),
+        }
+    }
+
+    #[test]
+    fn edits_that_arrive_after_close_are_dropped() {
+        let frame = Image::from(ImageBuffer::from_pixel(4, 4, Rgba([0, 0, 0, 255])));
+        let mut stack = UndoStack::new();
+        let late = Output::Resize(vec![frame.clone()]);
+        assert!(apply_edit(late, None::<&mut ViewState>, &mut stack).is_none());
+        assert!(apply_edit(Output::Undo, None::<&mut ViewState>, &mut stack).is_none());
+
+        // nothing was recorded, so undoing on the next image changes nothing
+        let image_data = Arc::new(RwLock::new(ImageData::from(vec![frame])));
+        let mut view = view_state(image_data, None);
+        stack.undo(&mut view);
+        assert_eq!(frame_size(&view), (4, 4));
+        assert!(matches!(
+            apply_edit(Output::Done, Some(&mut view), &mut stack),
+            Some(Output::Done)
+        ));
+    }
+
+    #[test]
+    fn ops_on_the_image_are_skipped_without_one() {
+        let (mut queue, events) = harness();
+        for op in [
+            Op::Rotate(1),
+            Op::Undo,
+            Op::Resize(Vec2::new(2, 2), FilterType::Nearest),
+            Op::Crop(Rect::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0)), None),
+            Op::Copy(None),
+            Op::AnalyzeColors,
+            Op::Save(
+                PathBuf::from("unused.png"),
+                Encoding::default(),
+                Fixes::default(),
+            ),
+        ] {
+            queue.queue(op, None);
+            assert!(!queue.working());
+        }
+        assert!(toasts(&events).is_empty());
+    }
+
+    #[test]
+    fn second_save_to_same_path_is_rejected() {
+        let loading_info = Arc::new(Mutex::new(LoadingInfo::default()));
+        let path = std::env::temp_dir().join("simp-save-lock.png");
+        let lock = begin_save(&loading_info, &path);
+        assert!(lock.is_some());
+        assert!(begin_save(&loading_info, &path).is_none());
+        drop(lock);
+        assert!(begin_save(&loading_info, &path).is_some());
+    }
+
+    #[test]
+    fn panicking_save_frees_the_path() {
+        let loading_info = Arc::new(Mutex::new(LoadingInfo::default()));
+        let path = std::env::temp_dir().join("simp-save-panic.png");
+        let lock = begin_save(&loading_info, &path).unwrap();
+        let save = thread::spawn(move || {
+            let _lock = lock;
+            panic!("encoder panicked");
+        });
+        wait_for_save(&loading_info, &path);
+        assert!(save.join().is_err());
+        assert!(begin_save(&loading_info, &path).is_some());
+    }
+
+    #[test]
+    fn panicking_save_ends_the_op() {
+        let frame = Image::from(ImageBuffer::from_pixel(4, 4, Rgba([0, 0, 0, 255])));
+        let image_data = Arc::new(RwLock::new(ImageData::from(vec![frame])));
+        // no view is ever turned this far, so the save thread panics
+        let mut view = Some(ViewState {
+            rotation: 7,
+            ..view_state(image_data, None)
+        });
+        let path = std::env::temp_dir().join(format!("simp-test-{}.png", nanoid::nanoid!()));
+
+        let (mut queue, events) = harness();
+        queue.queue(
+            Op::Save(path.clone(), Encoding::default(), Fixes::default()),
+            view.as_ref(),
+        );
+        assert!(queue.working());
+        settle(&mut queue, &mut view);
+        assert!(!queue.working());
+        assert!(!path.exists());
+
+        view.as_mut().unwrap().rotation = 0;
+        queue.queue(Op::Rotate(1), view.as_ref());
+        settle(&mut queue, &mut view);
+        assert_eq!(view.as_ref().unwrap().rotation, 1);
+        assert!(toasts(&events).is_empty());
+    }
+
+    #[test]
+    fn alternating_save_and_reload() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stress.png");
+
+        let frames: Vec<Image> = [0u8, 255]
+            .iter()
+            .map(|v| Image::from(ImageBuffer::from_pixel(64, 64, Rgba([*v, *v, *v, 255]))))
+            .collect();
+        save_with_format(&path, &frames[0], ImageOutputFormat::Png).unwrap();
+
+        let loading_info = Arc::new(Mutex::new(LoadingInfo::default()));
+        let writer = {
+            let loading_info = loading_info.clone();
+            let path = path.clone();
+            thread::spawn(move || {
+                for i in 0..100 {
+                    let lock = begin_save(&loading_info, &path).unwrap();
+                    save_with_format(&path, &frames[i % 2], ImageOutputFormat::Png).unwrap();
+                    drop(lock);
+                }
+            })
+        };
+
+        for _ in 0..100 {
+            wait_for_save(&loading_info, &path);
+            let image_data = load_uncached(&path, LoadOptions::default()).unwrap();
+            let buffer = image_data.frames[0].buffer().to_rgba8();
+            assert_eq!(buffer.dimensions(), (64, 64));
+            let first = *buffer.get_pixel(0, 0);
+            assert!(buffer.pixels().all(|pixel| *pixel == first));
+        }
+
+        writer.join().unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Color32, Frame};
+
+use super::App;
+
+const SHOW_DURATION: Duration = Duration::from_secs(2);
+const FADE_DURATION: Duration = Duration::from_millis(500);
+/// How often to repaint while fading out.
+const FADE_STEP: Duration = Duration::from_millis(16);
+
+/// File information shown in a corner of the view while fullscreen hides the bars.
+#[derive(Default)]
+pub struct InfoOverlay {
+    pinned: bool,
+    shown: Option<Instant>,
+}
+
+impl InfoOverlay {
+    pub fn toggle(&mut self) {
+        self.pinned = !self.pinned;
+        self.shown = None;
+    }
+
+    /// Shows the overlay for a moment before it fades out.
+    pub fn show_briefly(&mut self) {
+        self.shown = Some(Instant::now());
+    }
+
+    fn opacity(&self) -> f32 {
+        if self.pinned {
+            return 1.0;
+        }
+
+        match self.shown {
+            Some(shown) => {
+                let elapsed = shown.elapsed();
+                if elapsed < SHOW_DURATION {
+                    1.0
+                } else {
+                    let fading = (elapsed - SHOW_DURATION).as_secs_f32();
+                    1.0 - (fading / FADE_DURATION.as_secs_f32()).min(1.0)
+                }
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Time until the overlay needs to be drawn again to fade out.
+    pub fn next_update(&self) -> Option<Duration> {
+        if self.pinned {
+            return None;
+        }
+
+        let elapsed = self.shown?.elapsed();
+        if elapsed < SHOW_DURATION {
+            Some(SHOW_DURATION - elapsed)
+        } else if elapsed < SHOW_DURATION + FADE_DURATION {
+            Some(FADE_STEP)
+        } else {
+            None
+        }
+    }
+}
+
+impl App {
+    pub fn info_overlay_ui(&mut self, ctx: &egui::Context) {
+        if !self.fullscreen {
+            return;
+        }
+
+        let opacity = self.info_overlay.opacity();
+        if opacity <= 0.0 {
+            return;
+        }
+
+        let view = match self.image_view.as_ref() {
+            Some(view) => view,
+            None => return,
+        };
+
+        let mut lines = vec![self.current_filename.clone()];
+        if let Some((index, len)) = self.scratch.position() {
+            lines.push(format!("clipboard {}/{}", index + 1, len));
+        } else if let Some((index, len)) = self.op_queue.image_list.position() {
+            lines.push(format!("{} of {}", index + 1, len));
+        }
+        let size = view.true_size();
+        lines.push(format!(
+            "{} x {}, zoom {}%",
+            size.x(),
+            size.y(),
+            (view.scale * 100.0).round()
+        ));
+        let frames = view.image_data.read().unwrap().frames.len();
+        if frames > 1 {
+            // the frame number only changes while paused so playback does not redraw the text
+            if view.finished() {
+                lines.push(String::from("Finished, press Space to replay"));
+            } else if view.paused() {
+                lines.push(format!("Paused, frame {} of {}", view.index + 1, frames));
+            } else {
+                lines.push(format!("Playing, {} frames", frames));
+            }
+        }
+
+        egui::Area::new("info overlay")
+            .anchor(Align2::LEFT_BOTTOM, [10.0, -10.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                let text_color = ui.visuals().text_color();
+                ui.visuals_mut().override_text_color = Some(text_color.linear_multiply(opacity));
+                Frame::popup(ui.style())
+                    .fill(Color32::from_black_alpha(160).linear_multiply(opacity))
+                    .multiply_with_opacity(opacity)
+                    .show(ui, |ui| {
+                        for line in &lines {
+                            ui.label(line);
+                        }
+                    });
+            });
+    }
+}
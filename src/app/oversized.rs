@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use egui::RichText;
+
+use super::{
+    i18n::{tr, trf},
+    load_image::Oversized,
+    op_queue::Op,
+    App,
+};
+use crate::util::display_name;
+
+/// An image that was not opened because it is over the decode limit.
+pub struct OversizedPrompt {
+    pub path: PathBuf,
+    pub size: (u32, u32),
+    pub can_downsample: bool,
+}
+
+impl App {
+    pub fn oversized_ui(&mut self, ctx: &egui::Context) {
+        let prompt = match &self.oversized {
+            Some(prompt) => prompt,
+            None => return,
+        };
+
+        let (width, height) = prompt.size;
+        let megapixels = width as f64 * height as f64 / 1_000_000.0;
+        // four bytes per pixel is the least any decoder needs
+        let gigabytes = megapixels * 4.0 / 1000.0;
+        let name = display_name(&prompt.path);
+
+        let mut choice = None;
+        let mut open = true;
+        egui::Window::new(tr("Image too large"))
+            .id(egui::Id::new("oversized window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(name).strong());
+                ui.label(trf(
+                    "{} x {} pixels ({} MP) is more than the limit of {} MP.",
+                    &[
+                        &width,
+                        &height,
+                        &format!("{:.0}", megapixels),
+                        &self.settings.max_megapixels,
+                    ],
+                ));
+                ui.label(trf(
+                    "Opening it takes at least {} GB of memory.",
+                    &[&format!("{:.1}", gigabytes)],
+                ));
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Open anyway")).clicked() {
+                        choice = Some(Oversized::Full);
+                    }
+                    if prompt.can_downsample && ui.button(tr("Open downsampled")).clicked() {
+                        choice = Some(Oversized::Downsampled);
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        choice = Some(Oversized::Refuse);
+                    }
+                });
+            });
+
+        if !open || ctx.input().key_pressed(egui::Key::Escape) {
+            choice = Some(Oversized::Refuse);
+        }
+
+        if let Some(choice) = choice {
+            let prompt = self.oversized.take().unwrap();
+            if choice != Oversized::Refuse {
+                self.op_queue.allow_oversized(prompt.path.clone(), choice);
+                self.queue(Op::LoadPath(prompt.path, false));
+            }
+        }
+    }
+}
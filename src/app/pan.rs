@@ -0,0 +1,55 @@
+use glium::glutin::event::VirtualKeyCode;
+
+use super::App;
+use crate::vec2::Vec2;
+
+/// The side an arrow key brings more of the image in from.
+pub fn direction(key: VirtualKeyCode) -> Option<Vec2<f32>> {
+    match key {
+        VirtualKeyCode::Left => Some(Vec2::new(-1.0, 0.0)),
+        VirtualKeyCode::Right => Some(Vec2::new(1.0, 0.0)),
+        VirtualKeyCode::Up => Some(Vec2::new(0.0, -1.0)),
+        VirtualKeyCode::Down => Some(Vec2::new(0.0, 1.0)),
+        _ => None,
+    }
+}
+
+impl App {
+    /// Whether the image is wider and taller than the room it has, it only pans that way.
+    fn overflow(&self) -> (bool, bool) {
+        let view = match &self.image_view {
+            Some(view) => view,
+            None => return (false, false),
+        };
+        let size = view.real_size();
+        let room = self.size.y() - self.top_bar_pixels() - self.bottom_bar_pixels();
+        (size.x() > self.size.x(), size.y() > room)
+    }
+
+    /// Moves the view one pan step towards `direction`, `update` keeps it on the image.
+    pub fn pan(&mut self, direction: Vec2<f32>) {
+        let step = self.settings.pan_step * self.pixels_per_point();
+        if let Some(view) = self.image_view.as_mut() {
+            view.position -= direction * step;
+        }
+    }
+
+    /// With the setting on, the arrow keys pan an image that does not fit instead of
+    /// browsing. They still browse along a side that fits.
+    pub fn pan_key(&mut self, key: VirtualKeyCode) -> bool {
+        if !self.settings.arrow_keys_pan || !self.modifiers.is_empty() {
+            return false;
+        }
+        let direction = match direction(key) {
+            Some(direction) => direction,
+            None => return false,
+        };
+        let (wide, tall) = self.overflow();
+        if (direction.x() != 0.0 && wide) || (direction.y() != 0.0 && tall) {
+            self.pan(direction);
+            true
+        } else {
+            false
+        }
+    }
+}
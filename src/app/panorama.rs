@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use glium::glutin::event::VirtualKeyCode;
+
+use super::App;
+use crate::vec2::Vec2;
+
+/// Time between two auto-scroll steps, about one frame at 60 Hz.
+const SCROLL_INTERVAL: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// The long side of an image whose sides differ by more than `threshold` to one.
+pub fn long_axis(size: Vec2<f32>, threshold: f32) -> Option<Axis> {
+    if size.x() <= 0.0 || size.y() <= 0.0 {
+        None
+    } else if size.x() / size.y() > threshold {
+        Some(Axis::Horizontal)
+    } else if size.y() / size.x() > threshold {
+        Some(Axis::Vertical)
+    } else {
+        None
+    }
+}
+
+/// Slowly pans along a panorama until its end is reached, while a key is held or the
+/// slideshow shows it.
+#[derive(Default)]
+pub struct AutoScroll {
+    /// When the view was moved last, `None` while not scrolling.
+    last_step: Option<Instant>,
+    /// The key that has to stay down, `None` for a scroll that runs to the end by itself.
+    held: Option<VirtualKeyCode>,
+}
+
+impl AutoScroll {
+    pub fn active(&self) -> bool {
+        self.last_step.is_some()
+    }
+
+    pub fn stop(&mut self) {
+        self.last_step = None;
+        self.held = None;
+    }
+}
+
+impl App {
+    /// The axis to pan along when the image is a panorama.
+    pub fn panorama(&self) -> Option<Axis> {
+        let view = self.image_view.as_ref()?;
+        long_axis(view.display_size(), self.settings.panorama_ratio)
+    }
+
+    /// Scrolling is motion, so it stops with autoplay for those that asked for less of it.
+    pub fn can_auto_scroll(&self) -> bool {
+        self.settings.autoplay() && self.panorama().is_some()
+    }
+
+    /// Scrolls until the end of the panorama, or until `held` is released if there is one.
+    /// Does nothing while already scrolling, so key repeats do not start over.
+    pub fn start_auto_scroll(&mut self, held: Option<VirtualKeyCode>) {
+        if !self.auto_scroll.active() && self.can_auto_scroll() {
+            self.auto_scroll.last_step = Some(Instant::now());
+            self.auto_scroll.held = held;
+        }
+    }
+
+    /// Stops a scroll that runs while `key` is held.
+    pub fn release_auto_scroll(&mut self, key: VirtualKeyCode) {
+        if self.auto_scroll.held == Some(key) {
+            self.auto_scroll.stop();
+        }
+    }
+
+    /// Moves the view along the long side of the image by the time since the last step,
+    /// returns when the next step is due.
+    pub fn step_auto_scroll(&mut self) -> Option<Duration> {
+        let last_step = self.auto_scroll.last_step?;
+        let speed = self.settings.scroll_speed * self.pixels_per_point();
+        let (view, axis) = match (self.image_view.as_ref(), self.panorama()) {
+            (Some(view), Some(axis)) if self.settings.autoplay() => (view, axis),
+            _ => {
+                self.auto_scroll.stop();
+                return None;
+            }
+        };
+
+        let now = Instant::now();
+        let distance = speed * now.duration_since(last_step).as_secs_f32();
+        let size = view.real_size();
+        let bottom = self.size.y() - self.bottom_bar_pixels();
+        let mut position = view.position;
+        // the far edge of the image stops at the far edge of the window
+        let done = match axis {
+            Axis::Horizontal => {
+                let end = self.size.x() - size.x() / 2.0;
+                position.set_x((position.x() - distance).max(end));
+                position.x() <= end
+            }
+            Axis::Vertical => {
+                let end = bottom - size.y() / 2.0;
+                position.set_y((position.y() - distance).max(end));
+                position.y() <= end
+            }
+        };
+        self.image_view.as_mut().unwrap().position = position;
+
+        if done {
+            self.auto_scroll.stop();
+            None
+        } else {
+            self.auto_scroll.last_step = Some(now);
+            Some(SCROLL_INTERVAL)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_extreme_ratios_are_panoramas() {
+        assert_eq!(
+            long_axis(Vec2::new(20000.0, 1000.0), 3.0),
+            Some(Axis::Horizontal)
+        );
+        assert_eq!(
+            long_axis(Vec2::new(500.0, 2000.0), 3.0),
+            Some(Axis::Vertical)
+        );
+        assert_eq!(long_axis(Vec2::new(1920.0, 1080.0), 3.0), None);
+        assert_eq!(long_axis(Vec2::new(0.0, 1080.0), 3.0), None);
+    }
+}
@@ -0,0 +1,138 @@
+use glium::glutin::{
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    monitor::MonitorHandle,
+    window::Window,
+};
+
+use super::settings::Config;
+use crate::{rect::Rect, vec2::Vec2};
+
+/// How much of the window has to be on a monitor, in physical pixels, so it can still be
+/// grabbed and moved back.
+const MIN_VISIBLE: f32 = 64.0;
+
+/// Whether a window at `window` shows at least a grabbable part on one of `monitors`.
+/// Everything is in physical pixels of the desktop, monitors left of or above the
+/// primary one have negative positions.
+pub fn visible_on(window: Rect, monitors: &[Rect]) -> bool {
+    let needed = Vec2::new(
+        MIN_VISIBLE.min(window.width()),
+        MIN_VISIBLE.min(window.height()),
+    );
+    monitors.iter().any(|monitor| {
+        let overlap = Vec2::new(
+            (window.x() + window.width()).min(monitor.x() + monitor.width())
+                - window.x().max(monitor.x()),
+            (window.y() + window.height()).min(monitor.y() + monitor.height())
+                - window.y().max(monitor.y()),
+        );
+        overlap.x() >= needed.x() && overlap.y() >= needed.y()
+    })
+}
+
+/// Where a window of `size` is centered on `monitor`.
+pub fn centered(size: Vec2<f32>, monitor: Rect) -> Vec2<f32> {
+    monitor.position + (monitor.size - size) / 2.0
+}
+
+fn monitor_rect(monitor: &MonitorHandle) -> Rect {
+    let position = monitor.position();
+    let size = monitor.size();
+    Rect::new(
+        Vec2::new(position.x as f32, position.y as f32),
+        Vec2::new(size.width as f32, size.height as f32),
+    )
+}
+
+/// Puts the window where it was when simp last closed, or in the middle of the primary
+/// monitor when that place is not on any connected monitor anymore.
+pub fn restore(window: &Window, config: &Config) {
+    let position = match (config.x, config.y) {
+        (Some(x), Some(y)) => Vec2::new(x as f32, y as f32),
+        _ => return,
+    };
+    let monitors: Vec<MonitorHandle> = window.available_monitors().collect();
+    let rects: Vec<Rect> = monitors.iter().map(monitor_rect).collect();
+
+    // the size is stored in logical pixels, the monitor the window lands on decides the scale
+    let scale_factor = monitors
+        .iter()
+        .zip(&rects)
+        .find(|(_, rect)| visible_on(Rect::new(position, Vec2::new(1.0, 1.0)), &[**rect]))
+        .map(|(monitor, _)| monitor.scale_factor())
+        .unwrap_or_else(|| window.scale_factor());
+    let size: PhysicalSize<f32> =
+        LogicalSize::new(config.width, config.height).to_physical(scale_factor);
+    let size = Vec2::new(size.width, size.height);
+
+    let position = if visible_on(Rect::new(position, size), &rects) {
+        position
+    } else {
+        match window
+            .primary_monitor()
+            .or_else(|| monitors.first().cloned())
+        {
+            Some(monitor) => centered(size, monitor_rect(&monitor)),
+            None => return,
+        }
+    };
+    window.set_outer_position(PhysicalPosition::new(
+        position.x().round() as i32,
+        position.y().round() as i32,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect::new(Vec2::new(x, y), Vec2::new(width, height))
+    }
+
+    /// A 1080p primary monitor with a 1440p one to the left of it and a portrait
+    /// one above the primary, the left one sits lower than the primary.
+    fn layout() -> Vec<Rect> {
+        vec![
+            rect(0.0, 0.0, 1920.0, 1080.0),
+            rect(-2560.0, 200.0, 2560.0, 1440.0),
+            rect(240.0, -1920.0, 1080.0, 1920.0),
+        ]
+    }
+
+    #[test]
+    fn windows_on_any_monitor_are_visible() {
+        let monitors = layout();
+        assert!(visible_on(rect(100.0, 100.0, 1100.0, 720.0), &monitors));
+        assert!(visible_on(rect(-2000.0, 500.0, 1100.0, 720.0), &monitors));
+        assert!(visible_on(rect(300.0, -1500.0, 1100.0, 720.0), &monitors));
+        // mostly off the right edge of the primary, the left part still shows
+        assert!(visible_on(rect(1800.0, 100.0, 1100.0, 720.0), &monitors));
+    }
+
+    #[test]
+    fn windows_in_gaps_or_on_removed_monitors_are_not() {
+        let monitors = layout();
+        // the corner left of the portrait monitor and above the left one is not covered
+        assert!(!visible_on(rect(-1500.0, -800.0, 1100.0, 720.0), &monitors));
+        // only a sliver above the left monitor's top edge
+        assert!(!visible_on(rect(-2000.0, -500.0, 1100.0, 720.0), &monitors));
+        // the left monitor was unplugged
+        assert!(!visible_on(
+            rect(-2000.0, 500.0, 1100.0, 720.0),
+            &monitors[..1]
+        ));
+        assert!(!visible_on(rect(5000.0, 5000.0, 1100.0, 720.0), &monitors));
+    }
+
+    #[test]
+    fn fallback_is_centered_on_the_monitor() {
+        let monitor = rect(-2560.0, 200.0, 2560.0, 1440.0);
+        let position = centered(Vec2::new(1100.0, 720.0), monitor);
+        assert_eq!(position, Vec2::new(-1830.0, 560.0));
+        assert!(visible_on(
+            Rect::new(position, Vec2::new(1100.0, 720.0)),
+            &[monitor]
+        ));
+    }
+}
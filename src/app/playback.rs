@@ -0,0 +1,60 @@
+use std::{path::PathBuf, time::Duration};
+
+use lru::LruCache;
+
+use super::image_view::ImageView;
+
+/// How many animations to remember the position of.
+const CAPACITY: usize = 50;
+
+/// How far into an animation playback was.
+#[derive(Clone, Copy)]
+pub struct Playback {
+    pub index: usize,
+    /// Time already spent on the frame at `index`.
+    pub elapsed: Duration,
+    pub paused: bool,
+    /// Completed plays, for animations that stop after a number of loops.
+    pub plays: u32,
+    pub finished: bool,
+    /// Length of the frame list, a different length means the frames changed.
+    pub frames: usize,
+}
+
+/// Playback positions of animations navigated away from during this session.
+pub struct PlaybackPositions {
+    lru: LruCache<PathBuf, Playback>,
+}
+
+impl Default for PlaybackPositions {
+    fn default() -> Self {
+        Self {
+            lru: LruCache::new(CAPACITY),
+        }
+    }
+}
+
+impl PlaybackPositions {
+    /// Stores where `view` is before it is replaced, `frames_edited` when its pixels no
+    /// longer are the ones of the file.
+    pub fn remember(&mut self, view: &ImageView, frames_edited: bool) {
+        let path = match &view.path {
+            Some(path) => path.clone(),
+            None => return,
+        };
+
+        // an edited frame list does not line up with the file anymore
+        match view.playback() {
+            Some(playback) if !frames_edited => {
+                self.lru.put(path, playback);
+            }
+            _ => {
+                self.lru.pop(&path);
+            }
+        }
+    }
+
+    pub fn take(&mut self, view: &ImageView) -> Option<Playback> {
+        self.lru.pop(view.path.as_ref()?)
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use glium::Display;
+
+use super::{download, op_queue::Op, App};
+use crate::util::{write_atomic, Proxy, UserEvent};
+
+pub fn is_playlist(path: &Path) -> bool {
+    matches!(
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .as_deref(),
+        Some("m3u" | "m3u8")
+    )
+}
+
+/// Reads an m3u style playlist, one path per line. Relative paths are resolved from the
+/// directory of the playlist and lines starting with `#` are ignored.
+/// Returns the paths that exist followed by the ones that could not be found.
+pub fn read(path: &Path) -> io::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let text = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = dir.join(line);
+        if entry.is_file() {
+            found.push(entry);
+        } else {
+            missing.push(entry);
+        }
+    }
+    Ok((found, missing))
+}
+
+/// Writes `paths` as an m3u playlist, replacing the file only once all of it is written.
+pub fn write(path: &Path, paths: &[PathBuf]) -> io::Result<()> {
+    let mut text = String::from("#EXTM3U\n");
+    for entry in paths {
+        text.push_str(&entry.to_string_lossy());
+        text.push('\n');
+    }
+    write_atomic(path, text.as_bytes())
+}
+
+pub fn save_dialog(proxy: Proxy, display: &Display) {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_file_name("playlist.m3u")
+        .set_parent(display.gl_window().window())
+        .add_filter("Playlist", &["m3u", "m3u8"]);
+
+    thread::spawn(move || {
+        if let Some(file) = futures_executor::block_on(dialog.save_file()) {
+            let _ = proxy.send_event(UserEvent::SavePlaylist(file.path().to_path_buf()));
+        }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
+    });
+}
+
+impl App {
+    /// Opens an image or, for playlist files, the first image of the playlist.
+    pub fn open_path(&mut self, path: PathBuf, use_cache: bool) {
+        if let Some(url) = path.to_str().and_then(download::url) {
+            self.queue(Op::Download(url.to_string()));
+            if self.op_queue.downloading() {
+                self.toasts.push("Downloading, press Escape to cancel");
+            }
+            return;
+        }
+        if !is_playlist(&path) {
+            self.queue(Op::LoadPath(path, use_cache));
+            return;
+        }
+
+        match read(&path) {
+            Ok((found, missing)) => {
+                if !missing.is_empty() {
+                    let missing: Vec<_> = missing
+                        .iter()
+                        .map(|path| path.to_string_lossy().to_string())
+                        .collect();
+                    self.toasts
+                        .push(format!("Skipped missing files:\n{}", missing.join("\n")));
+                }
+
+                if let Some(first) = found.first().cloned() {
+                    self.op_queue.image_list.set_explicit(found);
+                    self.queue(Op::LoadPath(first, use_cache));
+                } else {
+                    self.toasts.push("The playlist does not contain any images");
+                }
+            }
+            Err(error) => {
+                let _ = self
+                    .proxy
+                    .send_event(UserEvent::ErrorMessage(error.to_string()));
+            }
+        }
+    }
+
+    pub fn move_current(&mut self, offset: isize) {
+        let list = &mut self.op_queue.image_list;
+        if list.move_current(offset) {
+            if let Some((index, len)) = list.position() {
+                self.toasts
+                    .push(format!("Moved to position {} of {}", index + 1, len));
+            }
+        }
+    }
+
+    /// Moves the image at `from` to `to`, for thumbnails dragged to another place.
+    pub fn move_entry(&mut self, from: usize, to: usize) {
+        let list = &mut self.op_queue.image_list;
+        if list.move_entry(from, to) {
+            if let Some((_, len)) = list.position() {
+                self.toasts
+                    .push(format!("Moved to position {} of {}", to + 1, len));
+            }
+        }
+    }
+
+    pub fn save_playlist(&mut self, path: &Path) {
+        let paths = match self.op_queue.image_list.paths() {
+            Some(paths) => paths,
+            None => return,
+        };
+
+        match write(path, &paths) {
+            Ok(_) => self.toasts.push("Saved playlist"),
+            Err(error) => {
+                let _ = self
+                    .proxy
+                    .send_event(UserEvent::ErrorMessage(error.to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playlists_survive_a_round_trip() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let (first, second) = (dir.join("sub").join("b.png"), dir.join("a.png"));
+        for path in [&first, &second] {
+            fs::write(path, b"").unwrap();
+        }
+        let gone = dir.join("gone.png");
+
+        // the manual order is kept, not sorted
+        let playlist = dir.join("list.m3u");
+        write(&playlist, &[first.clone(), gone.clone(), second.clone()]).unwrap();
+        assert_eq!(read(&playlist).unwrap(), (vec![first, second], vec![gone]));
+        // nothing is left besides the images and the playlist
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        // relative entries are resolved from the playlist and comments are skipped
+        fs::write(&playlist, "#EXTM3U\n# a comment\n\nsub/b.png\n  a.png  \n").unwrap();
+        let (found, missing) = read(&playlist).unwrap();
+        assert_eq!(found, [dir.join("sub/b.png"), dir.join("a.png")]);
+        assert!(missing.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
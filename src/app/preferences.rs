@@ -0,0 +1,356 @@
+use egui::{color_picker::color_edit_button_srgb, ComboBox, Key, RichText, Slider};
+
+use super::{
+    i18n::{tr, Language},
+    image_list::Sort,
+    settings::{Background, Theme, Transparency, Zoom, ZoomAnchor},
+    App, Settings,
+};
+use crate::{image_io::video, single_instance};
+
+#[derive(Default)]
+pub struct Preferences {
+    pub visible: bool,
+    /// The theme, the accent and whether that is dark that egui was last set to, `None`
+    /// until the first frame. A system theme can turn out dark or light after that.
+    applied_theme: Option<(Theme, Option<[u8; 3]>, bool)>,
+}
+
+fn section(ui: &mut egui::Ui, title: &str) {
+    ui.add_space(4.0);
+    ui.label(RichText::new(title).strong());
+}
+
+/// A color button for the accent and a way back to the default one.
+pub fn accent_ui(ui: &mut egui::Ui, accent: &mut Option<[u8; 3]>) {
+    ui.horizontal(|ui| {
+        let mut color = accent.unwrap_or_else(|| {
+            let default = ui.visuals().selection.bg_fill;
+            [default.r(), default.g(), default.b()]
+        });
+        if color_edit_button_srgb(ui, &mut color).changed() {
+            *accent = Some(color);
+        }
+        if ui
+            .add_enabled(accent.is_some(), egui::Button::new(tr("Default")))
+            .clicked()
+        {
+            *accent = None;
+        }
+    });
+}
+
+impl App {
+    pub fn apply_theme(&mut self, ctx: &egui::Context) {
+        let theme = (
+            self.settings.theme,
+            self.settings.accent,
+            self.settings.theme.dark(),
+        );
+        if self.preferences.applied_theme != Some(theme) {
+            ctx.set_visuals(theme.0.visuals(theme.1));
+            self.preferences.applied_theme = Some(theme);
+        }
+    }
+
+    /// Egui starts out at the monitor scale, the interface scale is applied on top of it.
+    pub fn apply_scale(&mut self, ctx: &egui::Context) {
+        let pixels_per_point = self.pixels_per_point();
+        if (ctx.pixels_per_point() - pixels_per_point).abs() > 0.001 {
+            ctx.set_pixels_per_point(pixels_per_point);
+        }
+    }
+
+    /// Interface animations stop together with autoplay.
+    pub fn apply_motion(&mut self, ctx: &egui::Context) {
+        let animation_time = if self.settings.autoplay() {
+            egui::Style::default().animation_time
+        } else {
+            0.0
+        };
+        if ctx.style().animation_time != animation_time {
+            let mut style = (*ctx.style()).clone();
+            style.animation_time = animation_time;
+            ctx.set_style(style);
+        }
+    }
+
+    /// Changes take effect right away, the file is written when the window closes.
+    pub fn preferences_ui(&mut self, ctx: &egui::Context) {
+        if !self.preferences.visible {
+            return;
+        }
+
+        let mut open = true;
+        let old_cache_size = self.settings.cache_size_mb;
+        let settings = &mut self.settings;
+        egui::Window::new(tr("Preferences"))
+            .id(egui::Id::new("preferences window"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                section(ui, tr("Appearance"));
+                egui::Grid::new("appearance grid").show(ui, |ui| {
+                    ui.label(tr("Language:"));
+                    ComboBox::from_id_source("language")
+                        .selected_text(settings.language.name())
+                        .show_ui(ui, |ui| {
+                            for language in Language::ALL {
+                                ui.selectable_value(
+                                    &mut settings.language,
+                                    language,
+                                    language.name(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("Theme:"));
+                    ui.horizontal(|ui| {
+                        for theme in Theme::ALL {
+                            ui.radio_value(&mut settings.theme, theme, tr(theme.name()));
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(tr("Accent color:"));
+                    accent_ui(ui, &mut settings.accent);
+                    ui.end_row();
+
+                    ui.label(tr("Background:"));
+                    ui.horizontal(|ui| {
+                        for background in Background::ALL {
+                            ui.radio_value(&mut settings.background, background, tr(background.name()));
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(tr("Transparency:"));
+                    ui.horizontal(|ui| {
+                        for transparency in Transparency::ALL {
+                            ui.radio_value(
+                                &mut settings.transparency,
+                                transparency,
+                                tr(transparency.name()),
+                            );
+                        }
+                        if settings.transparency == Transparency::Custom {
+                            color_edit_button_srgb(ui, &mut settings.transparency_color);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(tr("Animations:"));
+                    let mut autoplay = settings.autoplay();
+                    if ui
+                        .checkbox(&mut autoplay, tr("Autoplay and animate the interface"))
+                        .changed()
+                    {
+                        settings.autoplay = Some(autoplay);
+                    }
+                    ui.end_row();
+
+                    ui.label(tr("Interface scale:"));
+                    ui.add(
+                        Slider::new(&mut settings.interface_scale, Settings::INTERFACE_SCALE_RANGE)
+                            .suffix("%")
+                            .step_by(10.0),
+                    );
+                    ui.end_row();
+                });
+
+                ui.separator();
+                section(ui, tr("Zoom"));
+                egui::Grid::new("zoom grid").show(ui, |ui| {
+                    ui.label(tr("Open images at:"));
+                    ComboBox::from_id_source("open zoom")
+                        .selected_text(settings.zoom.name())
+                        .show_ui(ui, |ui| {
+                            for zoom in [Zoom::Fit, Zoom::Largest, Zoom::Scale(1.0)] {
+                                ui.selectable_value(&mut settings.zoom, zoom, zoom.name());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("Zoom step:"));
+                    ui.add(
+                        Slider::new(&mut settings.zoom_step, Settings::ZOOM_STEP_RANGE)
+                            .suffix("%")
+                            .step_by(1.0),
+                    );
+                    ui.end_row();
+
+                    ui.label(tr("Zoom towards:"));
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut settings.zoom_anchor, ZoomAnchor::Cursor, tr("Cursor"));
+                        ui.radio_value(
+                            &mut settings.zoom_anchor,
+                            ZoomAnchor::Center,
+                            tr("Window center"),
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.label(tr("Pan step:"));
+                    ui.add(
+                        Slider::new(&mut settings.pan_step, Settings::PAN_STEP_RANGE)
+                            .suffix(" px")
+                            .step_by(1.0),
+                    );
+                    ui.end_row();
+
+                    ui.label(tr("Arrow keys:"));
+                    ui.checkbox(
+                        &mut settings.arrow_keys_pan,
+                        tr("Pan an image larger than the window"),
+                    )
+                    .on_hover_text(tr("Shift and the arrow keys always pan"));
+                    ui.end_row();
+
+                    ui.label(tr("Image edges:"));
+                    ui.checkbox(&mut settings.rubber_band, tr("Stretch when panned past"));
+                    ui.end_row();
+
+                    ui.label(tr("Grid:"));
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            Slider::new(&mut settings.grid_spacing, Settings::GRID_SPACING_RANGE)
+                                .logarithmic(true)
+                                .suffix(" px"),
+                        );
+                        color_edit_button_srgb(ui, &mut settings.grid_color);
+                    });
+                    ui.end_row();
+
+                    ui.label(tr("Pixel grid:"));
+                    ui.checkbox(&mut settings.pixel_grid, tr("Between pixels from 800% zoom"));
+                    ui.end_row();
+
+                    ui.label(tr("Minimap:"));
+                    ui.checkbox(&mut settings.minimap, tr("Show it for images larger than the window"));
+                    ui.end_row();
+
+                    ui.label(tr("Panoramas:"));
+                    ui.checkbox(&mut settings.smart_fit, tr("Fit the short side"));
+                    ui.end_row();
+
+                    ui.label(tr("Panorama from:"));
+                    ui.add_enabled(
+                        settings.smart_fit,
+                        Slider::new(&mut settings.panorama_ratio, Settings::PANORAMA_RATIO_RANGE)
+                            .prefix("1 : ")
+                            .step_by(0.5),
+                    );
+                    ui.end_row();
+
+                    ui.label(tr("Auto-scroll speed:"));
+                    ui.add(
+                        Slider::new(&mut settings.scroll_speed, Settings::SCROLL_SPEED_RANGE)
+                            .suffix(" pt/s")
+                            .logarithmic(true),
+                    );
+                    ui.end_row();
+                });
+
+                ui.separator();
+                section(ui, tr("Memory"));
+                egui::Grid::new("memory grid").show(ui, |ui| {
+                    ui.label(tr("Image cache:"));
+                    ui.add(
+                        Slider::new(&mut settings.cache_size_mb, Settings::CACHE_SIZE_RANGE)
+                            .suffix(" MB")
+                            .logarithmic(true),
+                    );
+                    ui.end_row();
+
+                    ui.label(tr("Decode limit:"));
+                    ui.add(
+                        Slider::new(&mut settings.max_megapixels, Settings::MAX_MEGAPIXELS_RANGE)
+                            .suffix(" MP")
+                            .logarithmic(true),
+                    )
+                    .on_hover_text(tr("Larger images are only opened after asking"));
+                    ui.end_row();
+                });
+
+                ui.separator();
+                section(ui, tr("Browsing"));
+                egui::Grid::new("browsing grid").show(ui, |ui| {
+                    ui.label(tr("Order:"));
+                    ComboBox::from_id_source("sort order")
+                        .selected_text(tr(settings.sort.name()))
+                        .show_ui(ui, |ui| {
+                            for sort in Sort::ALL {
+                                ui.selectable_value(&mut settings.sort, sort, tr(sort.name()));
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("Slideshow:"));
+                    ui.add(
+                        Slider::new(&mut settings.slideshow_seconds, Settings::SLIDESHOW_RANGE)
+                            .suffix(format!(" {}", tr("s per image")))
+                            .step_by(0.5),
+                    );
+                    ui.end_row();
+
+                    ui.label(tr("Ken Burns:"));
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut settings.ken_burns, "")
+                            .on_hover_text(tr("Slowly zooms out of a corner of each still image"));
+                        ui.add_enabled(
+                            settings.ken_burns,
+                            Slider::new(
+                                &mut settings.ken_burns_intensity,
+                                Settings::KEN_BURNS_RANGE,
+                            )
+                            .suffix(format!(" {}", tr("% zoom"))),
+                        );
+                    });
+                    ui.end_row();
+                });
+
+                ui.separator();
+                section(ui, tr("Files"));
+                ui.checkbox(
+                    &mut settings.extensionless,
+                    tr("Browse files without an extension"),
+                )
+                .on_hover_text(
+                    tr("They are checked for image data when reached, which is slower in large folders"),
+                );
+                ui.checkbox(&mut settings.raw_previews, tr("Show previews of camera raw files"))
+                    .on_hover_text(tr("The preview the camera stored is much faster to show, the raw data is developed when zooming past 100% or editing"));
+                if video::SUPPORTED {
+                    ui.checkbox(&mut settings.videos, tr("Browse video files"))
+                        .on_hover_text(tr("They are shown as a still near the start and Enter plays them in the system player, this needs ffmpeg"));
+                }
+                if single_instance::SUPPORTED {
+                    ui.checkbox(&mut settings.single_instance, tr("Open images in the running window"))
+                        .on_hover_text(tr("Opening another image shows it here instead of in a new window, from the next start on"));
+                }
+
+                ui.separator();
+                if ui.button(tr("Restore defaults")).clicked() {
+                    *settings = Settings::default();
+                }
+            });
+
+        self.op_queue
+            .image_list
+            .set_listing(self.settings.listing());
+        self.op_queue.set_max_pixels(self.settings.max_pixels());
+        self.op_queue.set_raw_previews(self.settings.raw_previews);
+        if self.settings.cache_size_mb != old_cache_size {
+            self.op_queue.cache.set_max_size(self.settings.cache_size());
+        }
+
+        if !open || ctx.input().key_pressed(Key::Escape) {
+            self.preferences.visible = false;
+            if let Err(error) = self.save_config() {
+                self.toasts.push(error);
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+use glium::Display;
+
+use super::App;
+use crate::util::taskbar::{self, TaskbarProgress};
+
+/// Ops that finish faster than this never touch the title so it does not flicker.
+const PROGRESS_DELAY: Duration = Duration::from_millis(500);
+/// How often the progress is refreshed while a long op runs.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+pub struct Busy {
+    started: Option<Instant>,
+    shown: Option<TaskbarProgress>,
+    failed: bool,
+}
+
+impl Busy {
+    /// Marks the last op as failed until the next one starts.
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+}
+
+impl App {
+    pub fn window_title(&self) -> String {
+        if self.current_filename.is_empty() {
+            String::from("Simp")
+        } else {
+            self.current_filename.clone()
+        }
+    }
+
+    /// Shows the progress of long ops in the window title and on the taskbar.
+    pub fn update_progress(&mut self, display: &Display) -> Option<Duration> {
+        let working = self.op_queue.working();
+        let progress = if working {
+            let started = *self.busy.started.get_or_insert_with(Instant::now);
+            self.busy.failed = false;
+            let elapsed = started.elapsed();
+            if elapsed < PROGRESS_DELAY {
+                return Some(PROGRESS_DELAY - elapsed);
+            }
+
+            match self.op_queue.progress() {
+                Some(fraction) => TaskbarProgress::Normal((fraction * 100.0).floor() / 100.0),
+                None => TaskbarProgress::Indeterminate,
+            }
+        } else {
+            self.busy.started = None;
+            if self.busy.failed {
+                TaskbarProgress::Error
+            } else {
+                TaskbarProgress::None
+            }
+        };
+
+        if self.busy.shown.unwrap_or(TaskbarProgress::None) != progress {
+            let title = match progress {
+                TaskbarProgress::Normal(fraction) => {
+                    format!("{} ({}%)", self.window_title(), (fraction * 100.0) as u32)
+                }
+                _ => self.window_title(),
+            };
+
+            let window_context = display.gl_window();
+            let window = window_context.window();
+            window.set_title(&title);
+            taskbar::set_progress(window, progress);
+            self.busy.shown = Some(progress);
+        }
+
+        working.then_some(PROGRESS_INTERVAL)
+    }
+}
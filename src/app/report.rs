@@ -0,0 +1,241 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use glium::Display;
+use rexif::ExifTag;
+
+use super::{batch::BatchJob, App};
+use crate::{
+    info,
+    util::{json, Proxy, UserEvent},
+};
+
+const COLUMNS: &[&str] = &[
+    "file",
+    "width",
+    "height",
+    "format",
+    "file_size",
+    "frames",
+    "date_taken",
+    "camera",
+    "iso",
+    "exposure",
+    "error",
+];
+
+/// One line of the report. Files that could not be read only have the path and the error.
+#[derive(Default)]
+struct Row {
+    file: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    format: String,
+    file_size: Option<u64>,
+    frames: Option<usize>,
+    date_taken: String,
+    camera: String,
+    iso: String,
+    exposure: String,
+    error: String,
+}
+
+impl Row {
+    fn fields(&self) -> [String; 11] {
+        let number = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        [
+            self.file.clone(),
+            number(self.width.map(u64::from)),
+            number(self.height.map(u64::from)),
+            self.format.clone(),
+            number(self.file_size),
+            number(self.frames.map(|f| f as u64)),
+            self.date_taken.clone(),
+            self.camera.clone(),
+            self.iso.clone(),
+            self.exposure.clone(),
+            self.error.clone(),
+        ]
+    }
+}
+
+/// A report that is being written in the background.
+#[derive(Default)]
+pub struct Report {
+    job: Option<BatchJob>,
+}
+
+pub fn save_dialog(proxy: Proxy, display: &Display) {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_file_name("report.csv")
+        .set_parent(display.gl_window().window())
+        .add_filter("CSV", &["csv"])
+        .add_filter("JSON", &["json"]);
+
+    thread::spawn(move || {
+        if let Some(file) = futures_executor::block_on(dialog.save_file()) {
+            let _ = proxy.send_event(UserEvent::ExportReport(file.path().to_path_buf()));
+        }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
+    });
+}
+
+fn read_row(path: &Path) -> Row {
+    let mut row = Row {
+        file: path.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    match info::read(path) {
+        Ok(info) => {
+            row.width = Some(info.width);
+            row.height = Some(info.height);
+            row.file_size = Some(info.file_size);
+            row.frames = Some(info.frames);
+            row.date_taken = info.exif(ExifTag::DateTimeOriginal).to_string();
+            row.camera = format!("{} {}", info.exif(ExifTag::Make), info.exif(ExifTag::Model))
+                .trim()
+                .to_string();
+            row.iso = info.exif(ExifTag::ISOSpeedRatings).to_string();
+            row.exposure = info.exif(ExifTag::ExposureTime).to_string();
+            row.format = info.format;
+        }
+        Err(error) => row.error = error,
+    }
+    row
+}
+
+/// Quotes a field if it contains anything a spreadsheet would split on.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_csv(rows: &[Row]) -> String {
+    let mut out = COLUMNS.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let fields: Vec<_> = row.fields().iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn to_json(rows: &[Row]) -> String {
+    let mut out = String::from("[\n");
+    for (index, row) in rows.iter().enumerate() {
+        let fields: Vec<_> = COLUMNS
+            .iter()
+            .zip(row.fields())
+            .map(|(column, value)| format!("{}: {}", json::string(column), json::string(&value)))
+            .collect();
+        out.push_str("  {");
+        out.push_str(&fields.join(", "));
+        out.push('}');
+        if index + 1 < rows.len() {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+    out.push(']');
+    out
+}
+
+impl App {
+    pub fn export_report(&mut self, path: PathBuf) {
+        if self.report.job.is_some() {
+            return;
+        }
+
+        let paths = match self.op_queue.image_list.paths() {
+            Some(paths) if !paths.is_empty() => paths,
+            _ => return,
+        };
+
+        let job = BatchJob::start(paths.len(), self.proxy.clone(), move |batch| {
+            let rows = batch.map(&paths, read_row);
+            if batch.canceled() {
+                return UserEvent::Toast(String::from("Canceled the folder report"));
+            }
+
+            let rows: Vec<Row> = rows.into_iter().flatten().collect();
+            let as_json = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+            let text = if as_json {
+                to_json(&rows)
+            } else {
+                to_csv(&rows)
+            };
+            match fs::write(&path, text) {
+                Ok(_) => UserEvent::Toast(format!(
+                    "Wrote a report of {} images to {}",
+                    rows.len(),
+                    path.to_string_lossy()
+                )),
+                Err(error) => UserEvent::ErrorMessage(error.to_string()),
+            }
+        });
+        self.report.job = Some(job);
+    }
+
+    pub fn report_ui(&mut self, ctx: &egui::Context) {
+        if let Some(job) = &self.report.job {
+            if job.finished() {
+                self.report.job = None;
+            } else {
+                job.progress_ui(ctx, "Folder report", "report window");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_only_when_needed() {
+        assert_eq!(csv_field("plain.png"), "plain.png");
+        assert_eq!(csv_field("a,b.png"), "\"a,b.png\"");
+        assert_eq!(csv_field("say \"hi\".png"), "\"say \"\"hi\"\".png\"");
+    }
+
+    #[test]
+    fn failed_rows_keep_their_error() {
+        let row = read_row(Path::new("does/not/exist.png"));
+        assert!(!row.error.is_empty());
+        assert_eq!(row.width, None);
+
+        let csv = to_csv(&[row]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), COLUMNS.join(","));
+        assert!(lines.next().unwrap().starts_with("does/not/exist.png,,,,,"));
+    }
+
+    #[test]
+    fn the_format_is_read_from_the_content() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("misnamed.jpg");
+        image::RgbaImage::new(4, 2)
+            .save_with_format(&path, image::ImageFormat::Png)
+            .unwrap();
+
+        let row = read_row(&path);
+        assert_eq!(row.format, "PNG");
+        assert_eq!(
+            (row.width, row.height, row.frames),
+            (Some(4), Some(2), Some(1))
+        );
+        assert!(row.error.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
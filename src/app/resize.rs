@@ -1,6 +1,6 @@
 use image::imageops::FilterType;
 
-use crate::vec2::Vec2;
+use crate::{util::Image, vec2::Vec2};
 
 #[derive(Clone)]
 pub struct Resize {
@@ -9,6 +9,8 @@ pub struct Resize {
     pub width: String,
     pub height: String,
     pub maintain_aspect_ratio: bool,
+    /// Shows the image at the new size before it is resized.
+    pub preview: bool,
 }
 
 impl Default for Resize {
@@ -19,6 +21,7 @@ impl Default for Resize {
             width: String::from("0"),
             height: String::from("0"),
             maintain_aspect_ratio: true,
+            preview: false,
         }
     }
 }
@@ -29,3 +32,9 @@ impl Resize {
         self.height = size.y().to_string();
     }
 }
+
+/// `image` stretched to exactly `size`, keeping how long the frame is shown.
+pub fn resized(image: &Image, size: Vec2<u32>, resample: FilterType) -> Image {
+    let buffer = image.buffer().resize_exact(size.x(), size.y(), resample);
+    Image::with_delay(buffer, image.delay)
+}
@@ -0,0 +1,371 @@
+use egui::{Button, CursorIcon, DragValue, Key, Pos2, Sense, Stroke};
+use image::{DynamicImage, ImageBuffer, Pixel};
+
+use super::{i18n::tr, op_queue::Op, App};
+
+/// Degrees the view turns per pixel of horizontal mouse movement while dragging with R held.
+pub const DRAG_DEGREES_PER_PIXEL: f32 = 0.5;
+
+/// Lines shorter than this, in points, are too unsteady to take an angle from.
+const MIN_LINE_LENGTH: f32 = 8.0;
+
+pub struct Rotate {
+    pub visible: bool,
+    /// Whether R is held to rotate the view by dragging.
+    pub dragging: bool,
+    /// Whether a drag rotation snaps to the nearest quarter turn or is applied as a free rotation.
+    pub snap: bool,
+    /// Whether dragging over the image draws a line to straighten it by.
+    pub straightening: bool,
+    /// The line being drawn, in screen points.
+    line: Option<(Pos2, Pos2)>,
+}
+
+impl Default for Rotate {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            dragging: false,
+            snap: true,
+            straightening: false,
+            line: None,
+        }
+    }
+}
+
+/// The rotation, clockwise in degrees, that makes the line from `start` to `end` level, or
+/// upright when it is closer to upright. `None` for lines too short to tell.
+pub fn straighten_angle(start: Pos2, end: Pos2) -> Option<f32> {
+    let delta = end - start;
+    if delta.length() < MIN_LINE_LENGTH {
+        return None;
+    }
+    // screen y points down, so a positive angle is a line turned clockwise
+    let angle = delta.y.atan2(delta.x).to_degrees();
+    let off = angle - (angle / 90.0).round() * 90.0;
+    Some(-off)
+}
+
+impl App {
+    pub fn begin_rotate_drag(&mut self) {
+        if self.rotate.visible || self.rotate.dragging || self.crop.cropping {
+            return;
+        }
+
+        if let Some(view) = self.image_view.as_mut() {
+            view.preview_angle = 0.0;
+            self.rotate.dragging = true;
+        }
+    }
+
+    /// Applies the previewed rotation, or throws it away if `cancel` is set.
+    pub fn end_rotate_drag(&mut self, cancel: bool) {
+        if !self.rotate.dragging {
+            return;
+        }
+        self.rotate.dragging = false;
+
+        let view = match self.image_view.as_mut() {
+            Some(view) => view,
+            None => return,
+        };
+        let angle = view.preview_angle;
+        if cancel || self.op_queue.working() {
+            view.preview_angle = 0.0;
+            return;
+        }
+
+        if self.rotate.snap {
+            view.preview_angle = 0.0;
+            let quarters = ((angle / 90.0).round() as i32).rem_euclid(4);
+            match quarters {
+                0 => (),
+                3 => self.queue(Op::Rotate(-1)),
+                quarters => self.queue(Op::Rotate(quarters)),
+            }
+        } else if angle.abs() >= 0.1 {
+            // the preview stays until the rotated frames replace it
+            self.queue(Op::RotateArbitrary(round_angle(angle)));
+        } else {
+            view.preview_angle = 0.0;
+        }
+    }
+
+    /// Draws the straighten line over the image and turns the preview once it is let go.
+    pub fn straighten_ui(&mut self, ui: &mut egui::Ui) {
+        if !(self.rotate.visible && self.rotate.straightening) || self.image_view.is_none() {
+            self.rotate.line = None;
+            return;
+        }
+
+        let response = ui
+            .interact(ui.max_rect(), ui.id().with("straighten"), Sense::drag())
+            .on_hover_cursor(CursorIcon::Crosshair);
+        if let Some(pointer) = response.interact_pointer_pos() {
+            if response.drag_started() {
+                self.rotate.line = Some((pointer, pointer));
+            } else if let Some((_, end)) = self.rotate.line.as_mut() {
+                *end = pointer;
+            }
+        }
+        if response.drag_released() {
+            if let Some((start, end)) = self.rotate.line.take() {
+                if let Some(correction) = straighten_angle(start, end) {
+                    let view = self.image_view.as_mut().unwrap();
+                    view.preview_angle = round_angle(view.preview_angle + correction);
+                }
+            }
+        }
+
+        if let Some((start, end)) = self.rotate.line {
+            let painter = ui.painter();
+            let color = ui.visuals().selection.bg_fill;
+            painter.line_segment([start, end], Stroke::new(3.0, egui::Color32::BLACK));
+            painter.line_segment([start, end], Stroke::new(1.5, color));
+            for point in [start, end] {
+                painter.circle_filled(point, 3.0, color);
+            }
+        }
+    }
+
+    pub fn rotate_ui(&mut self, ctx: &egui::Context) {
+        if self.rotate.visible && self.image_view.is_some() {
+            if !ctx.wants_keyboard_input() {
+                let input = ctx.input();
+                let step = if input.modifiers.shift { 1.0 } else { 0.1 };
+                let mut nudge = 0.0;
+                if input.key_pressed(Key::ArrowLeft) {
+                    nudge -= step;
+                }
+                if input.key_pressed(Key::ArrowRight) {
+                    nudge += step;
+                }
+                drop(input);
+                if nudge != 0.0 {
+                    let view = self.image_view.as_mut().unwrap();
+                    view.preview_angle = round_angle(view.preview_angle + nudge);
+                }
+            }
+
+            let mut open = true;
+            let mut closed = false;
+            egui::Window::new(tr("Rotate"))
+                .id(egui::Id::new("rotate window"))
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    egui::Grid::new("rotate grid").show(ui, |ui| {
+                        ui.with_layout(egui::Layout::right_to_left(), |ui| {
+                            ui.label(tr("Angle:"));
+                        });
+                        let view = self.image_view.as_mut().unwrap();
+                        ui.add(
+                            DragValue::new(&mut view.preview_angle)
+                                .speed(0.1)
+                                .clamp_range(-180.0..=180.0)
+                                .max_decimals(1)
+                                .suffix("°"),
+                        );
+                        ui.end_row();
+                        ui.label("");
+                        ui.label(tr("Arrow keys nudge by 0.1°, hold Shift for 1°"));
+                        ui.end_row();
+                        ui.label("");
+                        if ui
+                            .selectable_label(self.rotate.straightening, tr("Straighten"))
+                            .on_hover_text(tr(
+                                "Drag along something in the image that should be level or upright",
+                            ))
+                            .clicked()
+                        {
+                            self.rotate.straightening = !self.rotate.straightening;
+                        }
+                        ui.end_row();
+                        ui.end_row();
+
+                        ui.with_layout(
+                            egui::Layout::top_down_justified(egui::Align::Center),
+                            |ui| {
+                                if ui
+                                    .add_enabled(self.view_available(), Button::new(tr("Cancel")))
+                                    .clicked()
+                                {
+                                    closed = true;
+                                }
+                            },
+                        );
+
+                        ui.with_layout(
+                            egui::Layout::top_down_justified(egui::Align::Center),
+                            |ui| {
+                                if ui
+                                    .add_enabled(self.view_available(), Button::new(tr("Apply")))
+                                    .clicked()
+                                {
+                                    let angle = self.image_view.as_ref().unwrap().preview_angle;
+                                    if angle != 0.0 {
+                                        self.queue(Op::RotateArbitrary(angle));
+                                    }
+                                    self.rotate.visible = false;
+                                    self.rotate.straightening = false;
+                                }
+                            },
+                        );
+                    });
+                });
+
+            if !open || closed {
+                self.rotate.visible = false;
+                self.rotate.straightening = false;
+                if let Some(view) = self.image_view.as_mut() {
+                    view.preview_angle = 0.0;
+                }
+            }
+        }
+    }
+}
+
+fn round_angle(angle: f32) -> f32 {
+    // wrap first so a long drag keeps turning the same way
+    let angle = (angle + 180.0).rem_euclid(360.0) - 180.0;
+    ((angle * 10.0).round() / 10.0).clamp(-180.0, 180.0)
+}
+
+/// Rotates an image clockwise by `degrees` using bilinear sampling.
+/// The canvas grows to fit the rotated image and uncovered pixels are transparent, so images
+/// without alpha get an alpha channel of the same depth.
+pub fn rotate(image: &DynamicImage, degrees: f32) -> DynamicImage {
+    let small = |value: f32| value.round().clamp(0.0, u8::MAX as f32) as u8;
+    let large = |value: f32| value.round().clamp(0.0, u16::MAX as f32) as u16;
+    let float = |value: f32| value;
+    match image {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageLumaA8(_) => DynamicImage::ImageLumaA8(
+            rotated(&image.to_luma_alpha8(), degrees, |v| v as f32, small),
+        ),
+        DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) => DynamicImage::ImageLumaA16(
+            rotated(&image.to_luma_alpha16(), degrees, |v| v as f32, large),
+        ),
+        DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => {
+            DynamicImage::ImageRgba16(rotated(&image.to_rgba16(), degrees, |v| v as f32, large))
+        }
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            DynamicImage::ImageRgba32F(rotated(&image.to_rgba32f(), degrees, |v| v, float))
+        }
+        _ => DynamicImage::ImageRgba8(rotated(&image.to_rgba8(), degrees, |v| v as f32, small)),
+    }
+}
+
+fn rotated<P: Pixel>(
+    source: &ImageBuffer<P, Vec<P::Subpixel>>,
+    degrees: f32,
+    to_f32: impl Fn(P::Subpixel) -> f32,
+    from_f32: impl Fn(f32) -> P::Subpixel,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let (width, height) = source.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+
+    let new_width = (width as f32 * cos.abs() + height as f32 * sin.abs()).round() as u32;
+    let new_height = (width as f32 * sin.abs() + height as f32 * cos.abs()).round() as u32;
+
+    let src_center = (width as f32 / 2.0, height as f32 / 2.0);
+    let dst_center = (new_width as f32 / 2.0, new_height as f32 / 2.0);
+
+    ImageBuffer::from_fn(new_width, new_height, |x, y| {
+        let dx = x as f32 + 0.5 - dst_center.0;
+        let dy = y as f32 + 0.5 - dst_center.1;
+        let sx = dx * cos + dy * sin + src_center.0 - 0.5;
+        let sy = -dx * sin + dy * cos + src_center.1 - 0.5;
+        sample_bilinear(source, sx, sy, &to_f32, &from_f32)
+    })
+}
+
+fn sample_bilinear<P: Pixel>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    x: f32,
+    y: f32,
+    to_f32: impl Fn(P::Subpixel) -> f32,
+    from_f32: impl Fn(f32) -> P::Subpixel,
+) -> P {
+    let channels = P::CHANNEL_COUNT as usize;
+    let mut out = [from_f32(0.0); 4];
+    let (width, height) = image.dimensions();
+    if x <= -1.0 || y <= -1.0 || x >= width as f32 || y >= height as f32 {
+        return *P::from_slice(&out[..channels]);
+    }
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let pixel = |px: f32, py: f32| -> [f32; 4] {
+        let mut values = [0.0; 4];
+        if px >= 0.0 && py >= 0.0 && px < width as f32 && py < height as f32 {
+            let channels = image.get_pixel(px as u32, py as u32).channels();
+            for (value, &channel) in values.iter_mut().zip(channels) {
+                *value = to_f32(channel);
+            }
+        }
+        values
+    };
+
+    let tl = pixel(x0, y0);
+    let tr = pixel(x0 + 1.0, y0);
+    let bl = pixel(x0, y0 + 1.0);
+    let br = pixel(x0 + 1.0, y0 + 1.0);
+
+    for i in 0..channels {
+        let top = tl[i] + (tr[i] - tl[i]) * fx;
+        let bottom = bl[i] + (br[i] - bl[i]) * fx;
+        out[i] = from_f32(top + (bottom - top) * fy);
+    }
+    *P::from_slice(&out[..channels])
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::pos2;
+
+    use super::*;
+
+    #[test]
+    fn lines_are_turned_level_or_upright() {
+        let origin = pos2(100.0, 100.0);
+        let turned = |degrees: f32| {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            straighten_angle(origin, origin + egui::vec2(cos, sin) * 50.0).unwrap()
+        };
+        assert!(turned(0.0).abs() < 1e-4);
+        // a line sloping down to the right is turned back counterclockwise
+        assert!((turned(5.0) + 5.0).abs() < 1e-4);
+        assert!((turned(-3.0) - 3.0).abs() < 1e-4);
+        // closer to upright than level, it is made upright
+        assert!((turned(80.0) - 10.0).abs() < 1e-4);
+        assert!((turned(185.0) + 5.0).abs() < 1e-4);
+        assert_eq!(
+            straighten_angle(origin, origin + egui::vec2(3.0, 2.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn rotating_keeps_the_bit_depth() {
+        let gray = DynamicImage::ImageLuma16(ImageBuffer::from_pixel(4, 2, image::Luma([40_000])));
+        let turned = rotate(&gray, 90.0);
+        let DynamicImage::ImageLumaA16(turned) = turned else {
+            panic!("a 16 bit gray image came back as {:?}", turned.color());
+        };
+        assert_eq!(turned.dimensions(), (2, 4));
+        assert_eq!(turned.get_pixel(1, 1).0, [40_000, u16::MAX]);
+
+        let float = DynamicImage::ImageRgb32F(ImageBuffer::from_pixel(3, 3, image::Rgb([4.5; 3])));
+        let DynamicImage::ImageRgba32F(turned) = rotate(&float, 45.0) else {
+            panic!("a float image lost its precision");
+        };
+        let center = turned.get_pixel(turned.width() / 2, turned.height() / 2);
+        assert!((center[0] - 4.5).abs() < 1e-4 && center[3] == 1.0);
+        // the corners are outside the turned image
+        assert_eq!(turned.get_pixel(0, 0).0, [0.0; 4]);
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+use crate::vec2::Vec2;
+
+/// The furthest the image can be pulled past its edges, in points.
+pub const MAX_OVERSHOOT: f32 = 80.0;
+/// How hard the image resists being pulled, smaller is stiffer.
+const RESISTANCE: f32 = 0.55;
+/// How long the image takes to spring back after the drag ends.
+const SPRING_BACK: Duration = Duration::from_millis(200);
+/// Time between two frames of the spring back.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// How far an image pulled `distance` past its edge is shown past it. Grows slower and
+/// slower the further it is pulled and never reaches `max`.
+fn resist(distance: f32, max: f32) -> f32 {
+    if max <= 0.0 {
+        return 0.0;
+    }
+    let pulled = (1.0 - 1.0 / (distance.abs() * RESISTANCE / max + 1.0)) * max;
+    pulled.copysign(distance)
+}
+
+/// Lets a panned image overshoot its edges a little and springs it back when the drag ends,
+/// so it is clear the image is at its limit. The clamped position is always worked out from
+/// scratch, the overshoot is only ever added on top of it so it can not stay behind.
+#[derive(Default)]
+pub struct RubberBand {
+    /// Where the drag would put the image without any clamping, `Some` while dragging.
+    raw: Option<Vec2<f32>>,
+    /// How far the image is shown past the clamped position right now.
+    offset: Vec2<f32>,
+    /// When the drag ended and how far past the edge the image was then.
+    release: Option<(Instant, Vec2<f32>)>,
+}
+
+impl RubberBand {
+    /// Moves the image along with the pointer, `position` is where it is shown now.
+    pub fn drag(&mut self, position: Vec2<f32>, delta: Vec2<f32>) {
+        self.release = None;
+        self.raw = Some(self.raw.unwrap_or(position) + delta);
+    }
+
+    /// Starts the spring back if the drag left the image past an edge.
+    pub fn release(&mut self) {
+        if self.raw.take().is_some() && self.offset != Vec2::default() {
+            self.release = Some((Instant::now(), self.offset));
+        }
+    }
+
+    /// Where the image would be without the overshoot, `shown` is where it is now.
+    pub fn position(&self, shown: Vec2<f32>) -> Vec2<f32> {
+        self.raw.unwrap_or(shown - self.offset)
+    }
+
+    /// The overshoot to show on top of the clamped position. `excess` is how far the
+    /// position is past the clamped one and `max` the furthest it can go, in pixels.
+    /// Without `animate` the image is back in place right away.
+    pub fn overshoot(&mut self, excess: Vec2<f32>, max: f32, animate: bool) -> Vec2<f32> {
+        self.offset = if self.raw.is_some() {
+            Vec2::new(resist(excess.x(), max), resist(excess.y(), max))
+        } else if let Some((released, offset)) = self.release {
+            let t = released.elapsed().as_secs_f32() / SPRING_BACK.as_secs_f32();
+            if t >= 1.0 || !animate {
+                self.release = None;
+                Vec2::default()
+            } else {
+                // ease out so it settles gently
+                offset * (1.0 - t).powi(3)
+            }
+        } else {
+            Vec2::default()
+        };
+        self.offset
+    }
+
+    /// When the spring back wants the next frame.
+    pub fn next_frame(&self) -> Option<Duration> {
+        self.release.map(|_| FRAME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overshoot_is_bounded_and_keeps_its_direction() {
+        assert_eq!(resist(0.0, 80.0), 0.0);
+        assert!(resist(50.0, 80.0) < 50.0);
+        assert!(resist(10_000.0, 80.0) < 80.0);
+        assert!(resist(-10_000.0, 80.0) > -80.0);
+        assert!(resist(-20.0, 80.0) < 0.0);
+        assert!(resist(100.0, 80.0) > resist(50.0, 80.0));
+    }
+
+    #[test]
+    fn nothing_is_left_behind_after_release() {
+        let mut band = RubberBand::default();
+        band.drag(Vec2::new(100.0, 100.0), Vec2::new(300.0, 0.0));
+        assert_eq!(band.position(Vec2::default()), Vec2::new(400.0, 100.0));
+        let offset = band.overshoot(Vec2::new(300.0, 0.0), 80.0, true);
+        assert!(offset.x() > 0.0 && offset.x() < 80.0);
+
+        band.release();
+        assert!(band.next_frame().is_some());
+        assert_eq!(
+            band.overshoot(Vec2::default(), 80.0, false),
+            Vec2::default()
+        );
+        assert!(band.next_frame().is_none());
+        assert_eq!(band.position(Vec2::new(5.0, 5.0)), Vec2::new(5.0, 5.0));
+    }
+}
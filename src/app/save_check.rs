@@ -0,0 +1,121 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use egui::RichText;
+use glium::Display;
+
+use super::{
+    i18n::{tr, trf},
+    save_image::{self, Fixes, Problem, SaveFormat},
+    App,
+};
+use crate::util::{display_name, ImageData, UserEvent};
+
+/// A save that was held back because the format can not store the image as it is.
+pub struct SaveProblems {
+    pub path: PathBuf,
+    /// The image to export, `None` when saving the current image.
+    pub export: Option<Arc<RwLock<ImageData>>>,
+    pub format: &'static SaveFormat,
+    pub problems: Vec<Problem>,
+}
+
+enum Choice {
+    Save,
+    OtherFormat,
+    Cancel,
+}
+
+impl App {
+    pub fn save_check_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        let check = match &self.save_check {
+            Some(check) => check,
+            None => return,
+        };
+
+        let format = check.format;
+        let too_large = check
+            .problems
+            .iter()
+            .any(|problem| matches!(problem, Problem::TooLarge { .. }));
+        let animated = check
+            .problems
+            .iter()
+            .any(|problem| matches!(problem, Problem::Animation { .. }));
+        let fixes = Fixes {
+            max_side: too_large.then_some(format.max_side),
+            first_frame: animated,
+        };
+        let save_label = match (too_large, animated) {
+            (true, true) => tr("Scale down and save the first frame"),
+            (true, false) => tr("Scale down to fit"),
+            (false, true) => tr("Save the first frame only"),
+            (false, false) => tr("Save anyway"),
+        };
+        let name = display_name(&check.path);
+
+        let mut choice = None;
+        let mut open = true;
+        egui::Window::new(trf("Saving as {}", &[&format.name]))
+            .id(egui::Id::new("save check window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(name).strong());
+                for problem in &check.problems {
+                    ui.label(format!("• {}", problem.describe(format)));
+                }
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui.button(save_label).clicked() {
+                        choice = Some(Choice::Save);
+                    }
+                    if ui.button(tr("Pick another format…")).clicked() {
+                        choice = Some(Choice::OtherFormat);
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        choice = Some(Choice::Cancel);
+                    }
+                });
+            });
+
+        if !open || ctx.input().key_pressed(egui::Key::Escape) {
+            choice = Some(Choice::Cancel);
+        }
+
+        let choice = match choice {
+            Some(choice) => choice,
+            None => return,
+        };
+        let check = self.save_check.take().unwrap();
+        match choice {
+            Choice::Save => self.request_save_with(check.path, check.export, fixes),
+            Choice::OtherFormat if !self.dialog_open => {
+                self.dialog_open = true;
+                let name = check.path.file_name().unwrap_or_default().to_os_string();
+                let event: fn(PathBuf) -> UserEvent = match check.export {
+                    Some(image_data) => {
+                        self.pending_export = Some(image_data);
+                        UserEvent::QueueExport
+                    }
+                    None => UserEvent::QueueSave,
+                };
+                let directory = check.path.parent().map(Path::to_path_buf);
+                // png stores everything the other formats might not
+                save_image::open(
+                    name,
+                    Some("png"),
+                    directory,
+                    self.proxy.clone(),
+                    display,
+                    event,
+                );
+            }
+            Choice::OtherFormat | Choice::Cancel => (),
+        }
+    }
+}
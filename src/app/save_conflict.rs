@@ -0,0 +1,235 @@
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use egui::RichText;
+
+use super::{
+    i18n::{tr, trf},
+    save_image::target_path,
+    App,
+};
+use crate::util::{display_name, ImageData};
+
+/// A save to a path that is already taken, held back until the user picks what happens.
+pub struct SaveConflict {
+    pub path: PathBuf,
+    /// The image to export, `None` when saving the current image.
+    pub export: Option<Arc<RwLock<ImageData>>>,
+}
+
+enum Choice {
+    Overwrite,
+    Rename(PathBuf),
+    Cancel,
+}
+
+/// `photo` for `photo (2)`, names without a number in brackets are returned whole.
+fn base_name(stem: &str) -> &str {
+    stem.strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" ("))
+        .filter(|(_, number)| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+        .map_or(stem, |(base, _)| base)
+}
+
+/// `path` itself if nothing is there, otherwise the first of `name (1).ext`, `name (2).ext` …
+/// that is free. A number in brackets the name already has is counted up from 1 again.
+pub fn free_path(path: &Path, exists: impl Fn(&Path) -> bool) -> PathBuf {
+    if !exists(path) {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default();
+    // only a valid UTF-8 name can end in a number someone typed, the rest keep their bytes
+    let base = stem
+        .to_str()
+        .map_or(stem, |stem| OsStr::new(base_name(stem)));
+    (1..)
+        .map(|number| {
+            let mut name = base.to_os_string();
+            name.push(format!(" ({})", number));
+            if let Some(extension) = path.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+            path.with_file_name(name)
+        })
+        .find(|candidate| !exists(candidate))
+        .unwrap()
+}
+
+/// The name the save dialog starts with, the stem of `name` with `extension` or, without
+/// one, the extension of `name` if it can be written and png if not. When that changes the
+/// extension the name is numbered so it does not land on a file already next to the image.
+pub fn suggested_name(name: &OsStr, extension: Option<&str>, directory: Option<&Path>) -> OsString {
+    let mut target = PathBuf::from(name);
+    if let Some(extension) = extension {
+        target.set_extension(extension);
+    }
+    let target = target_path(&target);
+    let same_extension = |path: &Path| path.extension().map(|ext| ext.to_ascii_lowercase());
+    if same_extension(&target) == same_extension(Path::new(name)) {
+        return name.to_os_string();
+    }
+    let target = match directory {
+        Some(directory) => free_path(&directory.join(&target), Path::exists),
+        None => target,
+    };
+    target.file_name().unwrap_or_default().to_os_string()
+}
+
+impl App {
+    pub fn save_conflict_ui(&mut self, ctx: &egui::Context) {
+        let conflict = match &self.save_conflict {
+            Some(conflict) => conflict,
+            None => return,
+        };
+
+        let renamed = free_path(&conflict.path, Path::exists);
+        let directory = conflict
+            .path
+            .parent()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut choice = None;
+        let mut open = true;
+        egui::Window::new(tr("File already exists"))
+            .id(egui::Id::new("save conflict window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(display_name(&conflict.path)).strong());
+                ui.label(trf("is already in {}", &[&directory]));
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Overwrite")).clicked() {
+                        choice = Some(Choice::Overwrite);
+                    }
+                    if ui
+                        .button(tr("Auto-rename"))
+                        .on_hover_text(trf("Save as {}", &[&display_name(&renamed)]))
+                        .clicked()
+                    {
+                        choice = Some(Choice::Rename(renamed.clone()));
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        choice = Some(Choice::Cancel);
+                    }
+                });
+            });
+
+        if !open || ctx.input().key_pressed(egui::Key::Escape) {
+            choice = Some(Choice::Cancel);
+        }
+
+        let choice = match choice {
+            Some(choice) => choice,
+            None => return,
+        };
+        let conflict = self.save_conflict.take().unwrap();
+        match choice {
+            Choice::Overwrite => self.request_save_checked(conflict.path, conflict.export),
+            Choice::Rename(path) => self.request_save_checked(path, conflict.export),
+            Choice::Cancel => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn taken(names: &[&str]) -> HashSet<PathBuf> {
+        names
+            .iter()
+            .map(|name| Path::new("/pictures").join(name))
+            .collect()
+    }
+
+    fn free(name: &str, existing: &HashSet<PathBuf>) -> String {
+        let path = free_path(&Path::new("/pictures").join(name), |path| {
+            existing.contains(path)
+        });
+        display_name(&path)
+    }
+
+    #[test]
+    fn the_first_free_number_is_used() {
+        let existing = taken(&["photo.png", "photo (1).png", "photo (3).png"]);
+        assert_eq!(free("other.png", &existing), "other.png");
+        assert_eq!(free("photo.png", &existing), "photo (2).png");
+        assert_eq!(free("photo (3).png", &existing), "photo (2).png");
+
+        let existing = taken(&["photo.png", "photo (1).png", "photo (2).png"]);
+        assert_eq!(free("photo.png", &existing), "photo (3).png");
+    }
+
+    #[test]
+    fn numbers_that_belong_to_the_name_are_kept() {
+        let existing = taken(&[
+            "scan 2.png",
+            "IMG_0012.jpg",
+            "notes",
+            "a (b).png",
+            "x ().png",
+        ]);
+        assert_eq!(free("scan 2.png", &existing), "scan 2 (1).png");
+        assert_eq!(free("IMG_0012.jpg", &existing), "IMG_0012 (1).jpg");
+        assert_eq!(free("notes", &existing), "notes (1)");
+        assert_eq!(free("a (b).png", &existing), "a (b) (1).png");
+        assert_eq!(free("x ().png", &existing), "x () (1).png");
+        assert_eq!(base_name("photo (12)"), "photo");
+        assert_eq!(base_name("photo(12)"), "photo(12)");
+    }
+
+    #[test]
+    fn suggestions_with_another_extension_are_numbered() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("photo.png"), b"").unwrap();
+
+        assert_eq!(
+            suggested_name(OsStr::new("photo.jpg"), None, Some(&dir)),
+            "photo.jpg"
+        );
+        assert_eq!(
+            suggested_name(OsStr::new("photo.PNG"), None, Some(&dir)),
+            "photo.PNG"
+        );
+        assert_eq!(
+            suggested_name(OsStr::new("photo.cr2"), None, Some(&dir)),
+            "photo (1).png"
+        );
+        assert_eq!(
+            suggested_name(OsStr::new("other.cr2"), None, Some(&dir)),
+            "other.png"
+        );
+        assert_eq!(
+            suggested_name(OsStr::new("photo.cr2"), None, None),
+            "photo.png"
+        );
+
+        // saving as another format
+        let png = Some("png");
+        assert_eq!(
+            suggested_name(OsStr::new("photo.jpg"), png, Some(&dir)),
+            "photo (1).png"
+        );
+        assert_eq!(
+            suggested_name(OsStr::new("other.jpg"), png, Some(&dir)),
+            "other.png"
+        );
+        assert_eq!(
+            suggested_name(OsStr::new("photo.png"), png, Some(&dir)),
+            "photo.png"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,63 +1,407 @@
 use std::{
-    path::PathBuf,
-    sync::{mpsc::Sender, Arc, RwLock},
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc, Mutex, RwLock},
     thread,
 };
 
-use glium::{glutin::event_loop::EventLoopProxy, Display};
+use glium::Display;
 use image::{
-    imageops::{flip_horizontal_in_place, flip_vertical_in_place},
-    ImageOutputFormat,
+    imageops::{flip_horizontal_in_place, flip_vertical_in_place, FilterType},
+    DynamicImage, GenericImageView, ImageOutputFormat,
 };
 
-use super::op_queue::Output;
+use super::{
+    cache::Cache,
+    i18n::trf,
+    op_queue::{begin_save, LoadingInfo, Output},
+    save_conflict::suggested_name,
+};
 use crate::{
-    image_io::save::{farbfeld, gif, save_with_format, tiff, webp, webp_animation},
-    util::{Image, ImageData, UserEvent},
+    image_io::save::{
+        farbfeld, gif, jpeg, jpeg_with_resolution, png, png_with_resolution, save_with_format,
+        tiff, tiff_with_resolution, webp, webp_animation, Encoding, SaveResult,
+    },
+    util::{log, Image, ImageData, Proxy, UserEvent},
 };
 
-pub fn open(name: String, proxy: EventLoopProxy<UserEvent>, display: &Display) {
-    let dialog = rfd::FileDialog::new()
-        .set_file_name(&name)
-        .set_parent(display.gl_window().window())
-        .add_filter("PNG", &["png"])
-        .add_filter("JPEG", &["jpg", "jpeg", "jpe", "jif", "jfif"])
-        .add_filter("GIF", &["gif"])
-        .add_filter("ICO", &["ico"])
-        .add_filter("BMP", &["bmp"])
-        .add_filter("TIFF", &["tiff", "tif"])
-        .add_filter("WEBP", &["webp"])
-        .add_filter("Targaformat", &["ff", "farbfeld"])
-        .add_filter("TGA", &["tga"]);
+/// The oriented frames and everything else an encoder needs.
+struct Target {
+    path: PathBuf,
+    frames: Vec<Image>,
+    encoding: Encoding,
+    loop_count: u32,
+    resolution: Option<(f32, f32)>,
+}
+
+/// A format that can be saved, what it is able to store and how it is written.
+pub struct SaveFormat {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    /// Longest width or height the format can store.
+    pub max_side: u32,
+    pub animation: bool,
+    pub alpha: bool,
+    /// Whether more than 8 bits per channel are kept.
+    pub high_bit_depth: bool,
+    write: fn(Target) -> SaveResult<()>,
+}
+
+/// Every format that can be saved. Unknown extensions are written as the first one.
+pub static FORMATS: &[SaveFormat] = &[
+    SaveFormat {
+        name: "PNG",
+        extensions: &["png"],
+        max_side: i32::MAX as u32,
+        animation: false,
+        alpha: true,
+        high_bit_depth: true,
+        write: |target| match target.resolution {
+            Some(resolution) => png_with_resolution(target.path, &target.frames[0], resolution),
+            None => png(target.path, &target.frames[0]),
+        },
+    },
+    SaveFormat {
+        name: "JPEG",
+        extensions: &["jpg", "jpeg", "jpe", "jif", "jfif"],
+        max_side: u16::MAX as u32,
+        animation: false,
+        alpha: false,
+        high_bit_depth: false,
+        write: |target| {
+            let image = &target.frames[0];
+            let quality = target.encoding.quality;
+            match target.resolution {
+                Some(resolution) => jpeg_with_resolution(target.path, image, quality, resolution),
+                None => jpeg(target.path, image, quality),
+            }
+        },
+    },
+    SaveFormat {
+        name: "GIF",
+        extensions: &["gif"],
+        max_side: u16::MAX as u32,
+        animation: true,
+        alpha: true,
+        high_bit_depth: false,
+        write: |target| gif(target.path, target.frames, target.loop_count),
+    },
+    SaveFormat {
+        name: "ICO",
+        extensions: &["ico"],
+        max_side: 256,
+        animation: false,
+        alpha: true,
+        high_bit_depth: false,
+        write: |target| save_with_format(target.path, &target.frames[0], ImageOutputFormat::Ico),
+    },
+    SaveFormat {
+        name: "BMP",
+        extensions: &["bmp"],
+        max_side: i32::MAX as u32,
+        animation: false,
+        alpha: true,
+        high_bit_depth: false,
+        write: |target| save_with_format(target.path, &target.frames[0], ImageOutputFormat::Bmp),
+    },
+    SaveFormat {
+        name: "TIFF",
+        extensions: &["tiff", "tif"],
+        max_side: u32::MAX,
+        animation: false,
+        alpha: true,
+        high_bit_depth: true,
+        write: |target| match target.resolution {
+            Some(resolution) => tiff_with_resolution(target.path, &target.frames[0], resolution),
+            None => tiff(target.path, &target.frames[0]),
+        },
+    },
+    SaveFormat {
+        name: "WEBP",
+        extensions: &["webp"],
+        max_side: 16383,
+        animation: true,
+        alpha: true,
+        high_bit_depth: false,
+        write: |target| {
+            if target.frames.len() > 1 {
+                webp_animation(
+                    target.path,
+                    target.frames,
+                    target.encoding,
+                    target.loop_count,
+                )
+            } else {
+                webp(target.path, &target.frames[0], target.encoding)
+            }
+        },
+    },
+    #[cfg(feature = "jxl")]
+    SaveFormat {
+        name: "JPEG XL",
+        extensions: &["jxl"],
+        max_side: 1 << 30,
+        animation: false,
+        alpha: true,
+        high_bit_depth: true,
+        write: |target| crate::image_io::save::jxl(target.path, &target.frames[0]),
+    },
+    SaveFormat {
+        name: "Targaformat",
+        extensions: &["ff", "farbfeld"],
+        max_side: u32::MAX,
+        animation: false,
+        alpha: true,
+        high_bit_depth: true,
+        write: |target| farbfeld(target.path, &target.frames[0]),
+    },
+    SaveFormat {
+        name: "TGA",
+        extensions: &["tga"],
+        max_side: u16::MAX as u32,
+        animation: false,
+        alpha: true,
+        high_bit_depth: false,
+        write: |target| save_with_format(target.path, &target.frames[0], ImageOutputFormat::Tga),
+    },
+];
+
+pub fn format_for(extension: &str) -> Option<&'static SaveFormat> {
+    FORMATS
+        .iter()
+        .find(|format| format.extensions.contains(&extension))
+}
+
+/// The format `path` is saved in, lowercasing its extension and switching it to png when it
+/// is not one simp can write.
+fn normalize_extension(path: &mut PathBuf) -> &'static SaveFormat {
+    let ext = match path.extension() {
+        Some(ext) => ext.to_string_lossy().to_lowercase(),
+        None => String::from("png"),
+    };
+    match format_for(&ext) {
+        Some(format) => {
+            path.set_extension(&ext);
+            format
+        }
+        None => {
+            path.set_extension("png");
+            &FORMATS[0]
+        }
+    }
+}
+
+/// The path an image saved to `path` is actually written to.
+pub fn target_path(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    normalize_extension(&mut path);
+    path
+}
+
+/// Something about an image that the format it is saved to can not hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    TooLarge { width: u32, height: u32 },
+    Animation { frames: usize },
+    Alpha,
+    BitDepth,
+}
+
+impl Problem {
+    pub fn describe(self, format: &SaveFormat) -> String {
+        match self {
+            Problem::TooLarge { width, height } => trf(
+                "{} stores at most {} pixels per side, the image is {} x {}.",
+                &[&format.name, &format.max_side, &width, &height],
+            ),
+            Problem::Animation { frames } => trf(
+                "{} can not store animations, only the first of {} frames would be saved.",
+                &[&format.name, &frames],
+            ),
+            Problem::Alpha => trf(
+                "{} has no transparency, transparent parts would lose it.",
+                &[&format.name],
+            ),
+            Problem::BitDepth => trf(
+                "{} stores 8 bits per channel, the image has more and would be reduced.",
+                &[&format.name],
+            ),
+        }
+    }
+}
+
+/// Changes that make an image fit its format, picked when the save is checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Fixes {
+    /// Scales the image down so neither side is longer.
+    pub max_side: Option<u32>,
+    pub first_frame: bool,
+}
+
+fn has_transparency(buffer: &DynamicImage) -> bool {
+    match buffer {
+        DynamicImage::ImageLumaA8(buffer) => buffer.pixels().any(|p| p[1] < u8::MAX),
+        DynamicImage::ImageRgba8(buffer) => buffer.pixels().any(|p| p[3] < u8::MAX),
+        DynamicImage::ImageLumaA16(buffer) => buffer.pixels().any(|p| p[1] < u16::MAX),
+        DynamicImage::ImageRgba16(buffer) => buffer.pixels().any(|p| p[3] < u16::MAX),
+        DynamicImage::ImageRgba32F(buffer) => buffer.pixels().any(|p| p[3] < 1.0),
+        _ => false,
+    }
+}
+
+fn high_bit_depth(buffer: &DynamicImage) -> bool {
+    let color = buffer.color();
+    color.bytes_per_pixel() > color.channel_count()
+}
+
+/// What would go wrong writing `image_data` with the format of `extension`, unknown
+/// extensions are checked as png since that is what they are written as.
+pub fn check(extension: &str, image_data: &ImageData) -> Vec<Problem> {
+    let format = format_for(extension).unwrap_or(&FORMATS[0]);
+    let frames = &image_data.frames;
+    // a preview is saved at the size of the image it stands for
+    let (width, height) = image_data
+        .full_size
+        .unwrap_or_else(|| frames[0].buffer().dimensions());
+
+    let mut problems = Vec::new();
+    if width.max(height) > format.max_side {
+        problems.push(Problem::TooLarge { width, height });
+    }
+    if frames.len() > 1 && !format.animation {
+        problems.push(Problem::Animation {
+            frames: frames.len(),
+        });
+    }
+    // this runs before the save is queued, so only the first frame that can be transparent
+    // is scanned rather than every pixel of every frame
+    let alpha_frame = frames
+        .iter()
+        .find(|frame| frame.buffer().color().has_alpha());
+    if !format.alpha && alpha_frame.is_some_and(|frame| has_transparency(frame.buffer())) {
+        problems.push(Problem::Alpha);
+    }
+    if !format.high_bit_depth && frames.iter().any(|frame| high_bit_depth(frame.buffer())) {
+        problems.push(Problem::BitDepth);
+    }
+    problems
+}
+
+/// Opens the save dialog, suggesting `name` or its stem with `extension`.
+pub fn open(
+    name: OsString,
+    extension: Option<&str>,
+    directory: Option<PathBuf>,
+    proxy: Proxy,
+    display: &Display,
+    event: fn(PathBuf) -> UserEvent,
+) {
+    let name = suggested_name(&name, extension, directory.as_deref());
+    let mut dialog = rfd::AsyncFileDialog::new()
+        // the dialogs only take UTF-8, this is the one place the name is converted
+        .set_file_name(&name.to_string_lossy())
+        .set_parent(display.gl_window().window());
+    if let Some(directory) = &directory {
+        dialog = dialog.set_directory(directory);
+    }
+    let dialog = FORMATS.iter().fold(dialog, |dialog, format| {
+        dialog.add_filter(format.name, format.extensions)
+    });
 
     thread::spawn(move || {
-        if let Some(path) = dialog.save_file() {
-            let _ = proxy.send_event(UserEvent::QueueSave(path));
+        if let Some(file) = futures_executor::block_on(dialog.save_file()) {
+            let _ = proxy.send_event(event(file.path().to_path_buf()));
         }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
     });
 }
 
+/// Encodes `frames` to `path` with `format`, the part of a save that needs no window.
+/// Frames deeper than the format can store are reduced to 8 bits first.
+pub fn write(
+    path: PathBuf,
+    format: &SaveFormat,
+    mut frames: Vec<Image>,
+    encoding: Encoding,
+    loop_count: u32,
+    resolution: Option<(f32, f32)>,
+) -> Result<(), String> {
+    // the encoders of 8 bit formats refuse anything deeper
+    for frame in frames.iter_mut() {
+        if !format.high_bit_depth && high_bit_depth(frame.buffer()) {
+            *frame.buffer_mut() = if frame.buffer().color().has_alpha() {
+                DynamicImage::ImageRgba8(frame.buffer().to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(frame.buffer().to_rgb8())
+            };
+        }
+    }
+
+    let (width, height) = frames[0].buffer().dimensions();
+    if width.max(height) > format.max_side {
+        return Err(format!(
+            "Could not save {}: {}",
+            path.to_string_lossy(),
+            Problem::TooLarge { width, height }.describe(format)
+        ));
+    }
+    (format.write)(Target {
+        path,
+        frames,
+        encoding,
+        loop_count,
+        resolution,
+    })
+    .map_err(|error| error.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn save(
-    proxy: EventLoopProxy<UserEvent>,
+    proxy: Proxy,
     sender: Sender<Output>,
+    loading_info: Arc<Mutex<LoadingInfo>>,
+    cache: Arc<Cache>,
     mut path: PathBuf,
     image_data: Arc<RwLock<ImageData>>,
     rotation: i32,
     horizontal_flip: bool,
     vertical_flip: bool,
+    encoding: Encoding,
+    fixes: Fixes,
 ) {
-    let os_str = path.extension();
-    let ext = match os_str {
-        Some(ext) => ext.to_string_lossy().to_string().to_lowercase(),
-        None => String::from("png"),
+    let format = normalize_extension(&mut path);
+
+    let lock = match begin_save(&loading_info, &path) {
+        Some(lock) => lock.ends_op(sender.clone(), proxy.clone()),
+        None => {
+            log::warn!("{} is already being saved", path.to_string_lossy());
+            let _ = sender.send(Output::Done);
+            let _ = proxy.send_event(UserEvent::Toast(format!(
+                "{} is already being saved",
+                path.to_string_lossy()
+            )));
+            return;
+        }
     };
-    path.set_extension(&ext);
 
     thread::spawn(move || {
+        let lock_path = path.clone();
+        let saved = image_data.clone();
         let guard = image_data.read().unwrap();
         let old_frames = &guard.frames;
+        let loop_count = encoding.loop_count.unwrap_or(guard.loop_count);
+        // a quarter turn swaps which axis each resolution belongs to
+        let resolution = guard.resolution.map(|(x, y)| match rotation {
+            1 | 3 => (y, x),
+            _ => (x, y),
+        });
+        let frame_count = if fixes.first_frame {
+            1
+        } else {
+            old_frames.len()
+        };
         let mut frames = Vec::new();
-        for frame in old_frames {
+        for frame in &old_frames[..frame_count] {
             let buffer = match rotation {
                 0 => frame.buffer().clone(),
                 1 => frame.buffer().rotate270(),
@@ -65,8 +409,9 @@ pub fn save(
                 3 => frame.buffer().rotate90(),
                 _ => unreachable!("image is rotated more then 360 degrees"),
             };
-            frames.push(Image::with_delay(buffer, frame.delay));
+            frames.push(frame.derive(buffer));
         }
+        drop(guard);
 
         for frame in frames.iter_mut() {
             if horizontal_flip {
@@ -76,35 +421,77 @@ pub fn save(
             if vertical_flip {
                 flip_vertical_in_place(frame.buffer_mut());
             }
-        }
 
-        let res = match ext.as_str() {
-            "png" => save_with_format(path, &frames[0], ImageOutputFormat::Png),
-            "jpg" | "jpeg" | "jpe" | "jif" | "jfif" => {
-                save_with_format(path, &frames[0], ImageOutputFormat::Jpeg(100))
-            }
-            "ico" => save_with_format(path, &frames[0], ImageOutputFormat::Ico),
-            "tga" => save_with_format(path, &frames[0], ImageOutputFormat::Tga),
-            "ff" | "farbfeld" => farbfeld(path, &frames[0]),
-            "tiff" | "tif" => tiff(path, &frames[0]),
-            "gif" => gif(path, frames),
-            "webp" => {
-                if frames.len() > 1 {
-                    webp_animation(path, frames)
-                } else {
-                    webp(path, &frames[0])
+            if let Some(max_side) = fixes.max_side {
+                let (width, height) = frame.buffer().dimensions();
+                if width.max(height) > max_side {
+                    let resized = frame
+                        .buffer()
+                        .resize(max_side, max_side, FilterType::Lanczos3);
+                    *frame.buffer_mut() = resized;
+                    // resampling blends colors that are not in the palette
+                    frame.palette = None;
                 }
             }
-            _ => {
-                path.set_extension("png");
-                save_with_format(path, &frames[0], ImageOutputFormat::Png)
-            }
-        };
+        }
+
+        let res = write(path, format, frames, encoding, loop_count, resolution);
+
+        // the file on disk changed so any cached decode of it is stale
+        cache.pop(&lock_path);
+        drop(lock);
 
         let _ = sender.send(Output::Done);
         let _ = match res {
-            Ok(_) => proxy.send_event(UserEvent::Wake),
-            Err(error) => proxy.send_event(UserEvent::ErrorMessage(error.to_string())),
+            Ok(_) => {
+                log::info!("saved {} as {}", lock_path.to_string_lossy(), format.name);
+                proxy.send_event(UserEvent::Saved(lock_path, saved))
+            }
+            Err(error) => {
+                log::warn!("saving {} failed: {}", lock_path.to_string_lossy(), error);
+                proxy.send_event(UserEvent::ErrorMessage(error))
+            }
         };
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    fn image_data(width: u32, height: u32, alpha: u8, frames: usize) -> ImageData {
+        let frame = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, alpha]));
+        ImageData::from(vec![Image::from(frame); frames])
+    }
+
+    #[test]
+    fn formats_report_what_they_can_not_store() {
+        assert_eq!(
+            check("webp", &image_data(20_000, 1, 255, 1)),
+            vec![Problem::TooLarge {
+                width: 20_000,
+                height: 1
+            }]
+        );
+        assert_eq!(
+            check("png", &image_data(2, 2, 255, 3)),
+            vec![Problem::Animation { frames: 3 }]
+        );
+        assert_eq!(check("jpg", &image_data(2, 2, 255, 1)), vec![]);
+        assert_eq!(check("jpg", &image_data(2, 2, 0, 1)), vec![Problem::Alpha]);
+        // unknown extensions are written as png
+        assert_eq!(check("xyz", &image_data(2, 2, 0, 1)), vec![]);
+
+        let mut preview = image_data(200, 100, 255, 1);
+        preview.full_size = Some((20_000, 10_000));
+        assert_eq!(
+            check("webp", &preview),
+            vec![Problem::TooLarge {
+                width: 20_000,
+                height: 10_000
+            }]
+        );
+    }
+}
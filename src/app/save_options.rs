@@ -0,0 +1,492 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use egui::{Color32, ColorImage, DragValue, Key, RichText, Slider, TextureHandle};
+use image::{DynamicImage, GenericImageView};
+
+use super::{
+    image_view::oriented,
+    load_image::extension_mismatch,
+    op_queue::Op,
+    save_check::SaveProblems,
+    save_conflict::SaveConflict,
+    save_image::{self, Fixes, FORMATS},
+    App,
+};
+use crate::{
+    image_io::{
+        load::{load_raster, sniff_file},
+        save::{encode_gif, encode_jpeg, encode_webp, Encoding},
+    },
+    util::{display_name, ImageData, Proxy, UserEvent},
+};
+
+/// Side of the square cut from the middle of the image that is encoded for the preview.
+const SAMPLE_SIZE: u32 = 256;
+/// How long the settings have to stay put before the sample is encoded again.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// The formats that have options to choose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Jpeg,
+    Webp,
+    /// Only asked about for animations, the loop count is its one option.
+    Gif,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Format> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "jpg" | "jpeg" | "jpe" | "jif" | "jfif" => Some(Format::Jpeg),
+            "webp" => Some(Format::Webp),
+            "gif" => Some(Format::Gif),
+            _ => None,
+        }
+    }
+
+    fn encode(self, buffer: &DynamicImage, encoding: Encoding) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Jpeg => encode_jpeg(buffer, encoding.quality),
+            Format::Webp => encode_webp(buffer, encoding),
+            Format::Gif => encode_gif(buffer),
+        }
+        .map_err(|error| error.to_string())
+    }
+}
+
+/// The encoded size of the sample and, for lossy encodings, how it looks decoded again.
+type Preview = (usize, Option<ColorImage>);
+
+struct Pending {
+    path: PathBuf,
+    /// The image to export, `None` when saving the current image.
+    export: Option<Arc<RwLock<ImageData>>>,
+    format: Format,
+    sample: Arc<DynamicImage>,
+    /// Pixels in everything that gets written, all frames of an animation included.
+    pixels: u64,
+    fixes: Fixes,
+}
+
+pub struct SaveOptions {
+    pending: Option<Pending>,
+    jpeg_quality: u8,
+    webp: Encoding,
+    /// How many times the animation plays, `None` for still images.
+    loop_count: Option<u32>,
+    /// When the settings last changed, `None` once the sample was encoded with them.
+    changed: Option<Instant>,
+    receiver: Option<Receiver<Result<Preview, String>>>,
+    preview: Option<(usize, Option<TextureHandle>)>,
+    error: Option<String>,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            jpeg_quality: Encoding::default().quality,
+            webp: Encoding::default(),
+            loop_count: None,
+            changed: None,
+            receiver: None,
+            preview: None,
+            error: None,
+        }
+    }
+}
+
+impl SaveOptions {
+    pub fn visible(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn encoding(&self, format: Format) -> Encoding {
+        match format {
+            Format::Jpeg => Encoding {
+                quality: self.jpeg_quality,
+                lossless: false,
+                loop_count: None,
+            },
+            Format::Webp => Encoding {
+                loop_count: self.loop_count,
+                ..self.webp
+            },
+            // the palette is what loses quality, the preview shows it
+            Format::Gif => Encoding {
+                quality: 100,
+                lossless: false,
+                loop_count: self.loop_count,
+            },
+        }
+    }
+
+    fn encode_sample(&mut self, proxy: Proxy) {
+        let pending = match &self.pending {
+            Some(pending) => pending,
+            None => return,
+        };
+        let format = pending.format;
+        let encoding = self.encoding(format);
+        let sample = pending.sample.clone();
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+        self.changed = None;
+
+        thread::spawn(move || {
+            let preview = format.encode(&sample, encoding).map(|bytes| {
+                // a lossless sample looks exactly like the original, only the size is news
+                let decoded = (!encoding.lossless)
+                    .then(|| load_raster(&bytes))
+                    .flatten()
+                    .map(|frames| {
+                        let buffer = frames[0].buffer().to_rgba8();
+                        ColorImage::from_rgba_unmultiplied(
+                            [buffer.width() as usize, buffer.height() as usize],
+                            buffer.as_raw(),
+                        )
+                    });
+                (bytes.len(), decoded)
+            });
+            let _ = sender.send(preview);
+            let _ = proxy.send_event(UserEvent::Wake);
+        });
+    }
+}
+
+/// The middle of the frame, at most `SAMPLE_SIZE` on each side.
+fn center_sample(buffer: &DynamicImage) -> DynamicImage {
+    let (width, height) = buffer.dimensions();
+    let sample_width = width.min(SAMPLE_SIZE);
+    let sample_height = height.min(SAMPLE_SIZE);
+    buffer.crop_imm(
+        (width - sample_width) / 2,
+        (height - sample_height) / 2,
+        sample_width,
+        sample_height,
+    )
+}
+
+/// The full file size from the size of the sample, assuming every part of
+/// the image compresses about as well as its middle.
+fn estimate_size(sample_bytes: usize, sample_pixels: u64, pixels: u64) -> f64 {
+    sample_bytes as f64 * pixels as f64 / sample_pixels.max(1) as f64
+}
+
+fn format_size(bytes: f64) -> String {
+    if bytes < 1024.0 {
+        format!("{:.0} bytes", bytes)
+    } else if bytes < 1024.0 * 1024.0 {
+        format!("{:.0} KB", bytes / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+    }
+}
+
+impl App {
+    /// Saves to `path` from the save dialog, then continues with `request_save_checked`.
+    /// The dialog already asked before replacing `path`, so this only asks when the image is
+    /// written under another name, like png for an unknown extension, and that one is taken.
+    pub fn request_save(&mut self, path: PathBuf, export: Option<Arc<RwLock<ImageData>>>) {
+        let target = save_image::target_path(&path);
+        // saving over the file the image came from is what saving in place means
+        let own_file = export.is_none()
+            && self
+                .image_view
+                .as_ref()
+                .is_some_and(|view| view.path.as_deref() == Some(&*target));
+        if target != path && !own_file && target.exists() {
+            self.save_conflict = Some(SaveConflict {
+                path: target,
+                export,
+            });
+        } else {
+            self.request_save_checked(path, export);
+        }
+    }
+
+    /// Checks that the format can hold the image before saving, see `request_save_with`.
+    pub fn request_save_checked(&mut self, path: PathBuf, export: Option<Arc<RwLock<ImageData>>>) {
+        let extension = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        let in_place = export.is_none()
+            && self
+                .image_view
+                .as_ref()
+                .is_some_and(|view| view.path.as_deref() == Some(&*path));
+        if in_place {
+            if let Some(mismatch) = extension_mismatch(sniff_file(&path), &extension) {
+                self.toasts
+                    .push(format!("{}, it is written as the extension says", mismatch));
+            }
+        }
+
+        let image_data = match (&export, &self.image_view) {
+            (Some(image_data), _) => image_data.clone(),
+            (None, Some(view)) => view.image_data.clone(),
+            (None, None) => return,
+        };
+        let problems = save_image::check(&extension, &image_data.read().unwrap());
+        if problems.is_empty() {
+            self.request_save_with(path, export, Fixes::default());
+        } else {
+            self.save_check = Some(SaveProblems {
+                path,
+                export,
+                format: save_image::format_for(&extension).unwrap_or(&FORMATS[0]),
+                problems,
+            });
+        }
+    }
+
+    /// Saves right away for formats without options, asks for them first otherwise.
+    pub fn request_save_with(
+        &mut self,
+        path: PathBuf,
+        export: Option<Arc<RwLock<ImageData>>>,
+        fixes: Fixes,
+    ) {
+        let (image_data, orientation) = match (&export, &self.image_view) {
+            (Some(image_data), _) => (image_data.clone(), None),
+            (None, Some(view)) => (
+                view.image_data.clone(),
+                Some((view.rotation, view.horizontal_flip, view.vertical_flip)),
+            ),
+            (None, None) => return,
+        };
+        let (frame_count, loop_count) = {
+            let guard = image_data.read().unwrap();
+            let frames = if fixes.first_frame {
+                1
+            } else {
+                guard.frames.len()
+            };
+            (frames, guard.loop_count)
+        };
+
+        let format = match Format::from_path(&path) {
+            Some(Format::Gif) if frame_count < 2 => None,
+            format => format,
+        };
+        let format = match format {
+            Some(format) => format,
+            None => {
+                match export {
+                    Some(image_data) => {
+                        self.queue(Op::Export(path, image_data, Encoding::default(), fixes))
+                    }
+                    None => self.queue(Op::Save(path, Encoding::default(), fixes)),
+                }
+                return;
+            }
+        };
+
+        let (sample, pixels) = {
+            let guard = image_data.read().unwrap();
+            let first = guard.frames[0].buffer();
+            let (width, height) = guard.full_size.unwrap_or((first.width(), first.height()));
+            let scale = match fixes.max_side {
+                Some(max_side) => (max_side as f64 / width.max(height) as f64).min(1.0),
+                None => 1.0,
+            };
+            let (width, height) = (width as f64 * scale, height as f64 * scale);
+            // jpeg only keeps the first frame
+            let frames = match format {
+                Format::Webp | Format::Gif => frame_count as u64,
+                Format::Jpeg => 1,
+            };
+            let sample = center_sample(first);
+            let sample = match orientation {
+                Some((rotation, horizontal_flip, vertical_flip)) => {
+                    oriented(&sample, rotation, horizontal_flip, vertical_flip).into_owned()
+                }
+                None => sample,
+            };
+            (sample, (width * height) as u64 * frames)
+        };
+
+        self.save_options.loop_count = (frame_count > 1).then_some(loop_count);
+        self.save_options.pending = Some(Pending {
+            path,
+            export,
+            format,
+            sample: Arc::new(sample),
+            pixels,
+            fixes,
+        });
+        self.save_options.preview = None;
+        self.save_options.error = None;
+        self.save_options.receiver = None;
+        self.save_options.encode_sample(self.proxy.clone());
+    }
+
+    pub fn save_options_ui(&mut self, ctx: &egui::Context) {
+        let options = &mut self.save_options;
+        let (format, sample_pixels, pixels, name) = match &options.pending {
+            Some(pending) => (
+                pending.format,
+                pending.sample.width() as u64 * pending.sample.height() as u64,
+                pending.pixels,
+                display_name(&pending.path),
+            ),
+            None => return,
+        };
+
+        if let Some(result) = options.receiver.as_ref().and_then(|r| r.try_recv().ok()) {
+            options.receiver = None;
+            match result {
+                Ok((bytes, image)) => {
+                    let texture = image.map(|image| ctx.load_texture("save preview", image));
+                    options.preview = Some((bytes, texture));
+                    options.error = None;
+                }
+                Err(error) => options.error = Some(error),
+            }
+        }
+
+        if let Some(changed) = options.changed {
+            if changed.elapsed() < DEBOUNCE {
+                ctx.request_repaint();
+            } else if options.receiver.is_none() {
+                options.encode_sample(self.proxy.clone());
+            }
+        }
+
+        let options = &mut self.save_options;
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+        let mut changed = false;
+        egui::Window::new("Save options")
+            .id(egui::Id::new("save options window"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(name).strong());
+                egui::Grid::new("save options grid").show(ui, |ui| {
+                    let lossy = !options.encoding(format).lossless;
+                    let quality = match format {
+                        Format::Jpeg => Some(&mut options.jpeg_quality),
+                        Format::Webp => {
+                            ui.label("Lossless:");
+                            changed |= ui.checkbox(&mut options.webp.lossless, "").changed();
+                            ui.end_row();
+                            Some(&mut options.webp.quality)
+                        }
+                        Format::Gif => None,
+                    };
+                    if let Some(quality) = quality {
+                        ui.label("Quality:");
+                        changed |= ui
+                            .add_enabled(lossy, Slider::new(quality, 1..=100))
+                            .changed();
+                        ui.end_row();
+                    }
+
+                    if format != Format::Jpeg {
+                        if let Some(loop_count) = &mut options.loop_count {
+                            ui.label("Loops:");
+                            ui.horizontal(|ui| {
+                                ui.add(DragValue::new(loop_count).clamp_range(0..=u16::MAX));
+                                ui.label(RichText::new("0 plays forever").weak());
+                            });
+                            ui.end_row();
+                        }
+                    }
+                });
+
+                ui.separator();
+                let lossy = !options.encoding(format).lossless;
+                match &options.preview {
+                    Some((bytes, texture)) => {
+                        if let (true, Some(texture)) = (lossy, texture) {
+                            ui.image(texture, texture.size_vec2())
+                                .on_hover_text("The middle of the image as it will be saved");
+                        }
+                        ui.label(format!(
+                            "Estimated size: about {}",
+                            format_size(estimate_size(*bytes, sample_pixels, pixels))
+                        ));
+                    }
+                    None => {
+                        ui.label("Estimating size…");
+                    }
+                }
+                if let Some(error) = &options.error {
+                    ui.label(RichText::new(error).color(Color32::from_rgb(255, 110, 110)));
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if changed {
+            options.changed = Some(Instant::now());
+        }
+        let input = ctx.input();
+        save |= input.key_pressed(Key::Enter);
+        open &= !cancel && !input.key_pressed(Key::Escape);
+        drop(input);
+
+        if save {
+            let encoding = options.encoding(format);
+            if let Some(pending) = options.pending.take() {
+                match pending.export {
+                    Some(image_data) => self.queue(Op::Export(
+                        pending.path,
+                        image_data,
+                        encoding,
+                        pending.fixes,
+                    )),
+                    None => self.queue(Op::Save(pending.path, encoding, pending.fixes)),
+                }
+            }
+        } else if !open {
+            options.pending = None;
+        }
+
+        if !self.save_options.visible() {
+            self.save_options.preview = None;
+            self.save_options.changed = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+
+    #[test]
+    fn sample_is_taken_from_the_middle() {
+        let mut image = RgbaImage::new(1000, 10);
+        image.put_pixel(500, 5, image::Rgba([255, 0, 0, 255]));
+        let sample = center_sample(&DynamicImage::ImageRgba8(image));
+        assert_eq!(sample.dimensions(), (SAMPLE_SIZE, 10));
+        assert_eq!(sample.get_pixel(128, 5).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn size_is_extrapolated_by_pixel_count() {
+        assert_eq!(estimate_size(1000, 256 * 256, 1024 * 1024), 16000.0);
+        assert_eq!(format_size(16000.0), "16 KB");
+        assert_eq!(format_size(3.5 * 1024.0 * 1024.0), "3.5 MB");
+    }
+}
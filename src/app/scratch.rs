@@ -0,0 +1,133 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use crate::util::ImageData;
+
+struct Entry {
+    image_data: Arc<RwLock<ImageData>>,
+    /// Where the entry was saved, `None` while it only exists in memory.
+    path: Option<PathBuf>,
+}
+
+/// Images pasted with Ctrl + Shift + V. They are browsed like a folder while one of them is
+/// shown, and edits land in the entry itself since the view shares its image data.
+#[derive(Default)]
+pub struct Scratch {
+    entries: Vec<Entry>,
+    /// The entry on screen, `None` while something else is shown.
+    index: Option<usize>,
+    /// Set once closing the window was refused because of unsaved entries or edits, the
+    /// next attempt closes.
+    pub warned: bool,
+}
+
+impl Scratch {
+    pub fn push(&mut self, image_data: Arc<RwLock<ImageData>>) {
+        self.entries.push(Entry {
+            image_data,
+            path: None,
+        });
+        self.warned = false;
+    }
+
+    /// Notes which image is on screen. Returns the path of the entry if it is one that was saved.
+    pub fn show(&mut self, image_data: &Arc<RwLock<ImageData>>) -> Option<PathBuf> {
+        self.index = self
+            .entries
+            .iter()
+            .position(|entry| Arc::ptr_eq(&entry.image_data, image_data));
+        self.entries[self.index?].path.clone()
+    }
+
+    /// Notes that no entry is on screen.
+    pub fn hide(&mut self) {
+        self.index = None;
+    }
+
+    /// The entry on screen and how many there are.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.index.map(|index| (index, self.entries.len()))
+    }
+
+    /// The entry after or before the one on screen, wrapping around at the ends.
+    pub fn step(&self, forward: bool) -> Option<Arc<RwLock<ImageData>>> {
+        let index = self.index?;
+        let len = self.entries.len();
+        let next = if forward {
+            (index + 1) % len
+        } else {
+            (index + len - 1) % len
+        };
+        Some(self.entries[next].image_data.clone())
+    }
+
+    /// Gives the entry with this image data its path, returns whether there was one.
+    pub fn saved(&mut self, image_data: &Arc<RwLock<ImageData>>, path: PathBuf) -> bool {
+        match self
+            .entries
+            .iter_mut()
+            .find(|entry| Arc::ptr_eq(&entry.image_data, image_data))
+        {
+            Some(entry) => {
+                entry.path = Some(path);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn unsaved(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.path.is_none())
+            .count()
+    }
+
+    /// The images of the entries, they count against the cache limit.
+    pub fn images(&self) -> impl Iterator<Item = &Arc<RwLock<ImageData>>> {
+        self.entries.iter().map(|entry| &entry.image_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::util::Image;
+
+    fn image() -> Arc<RwLock<ImageData>> {
+        let frame = Image::from(RgbaImage::new(2, 2));
+        Arc::new(RwLock::new(ImageData::new(vec![frame], Vec::new())))
+    }
+
+    #[test]
+    fn entries_are_browsed_and_saved_one_at_a_time() {
+        let mut scratch = Scratch::default();
+        let (first, second, other) = (image(), image(), image());
+        scratch.push(first.clone());
+        scratch.push(second.clone());
+        let bytes: usize = scratch
+            .images()
+            .map(|image| image.read().unwrap().bytes())
+            .sum();
+        assert_eq!(bytes, 32);
+
+        assert_eq!(scratch.show(&other), None);
+        assert_eq!(scratch.position(), None);
+        assert!(scratch.step(true).is_none());
+
+        scratch.show(&second);
+        assert_eq!(scratch.position(), Some((1, 2)));
+        assert!(Arc::ptr_eq(&scratch.step(true).unwrap(), &first));
+        assert!(Arc::ptr_eq(&scratch.step(false).unwrap(), &first));
+
+        assert_eq!(scratch.unsaved(), 2);
+        assert!(scratch.saved(&first, PathBuf::from("first.png")));
+        assert!(!scratch.saved(&other, PathBuf::from("other.png")));
+        assert_eq!(scratch.unsaved(), 1);
+        assert_eq!(scratch.show(&first), Some(PathBuf::from("first.png")));
+    }
+}
@@ -0,0 +1,718 @@
+use std::{collections::BTreeMap, sync::OnceLock, thread, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    color_picker::ColorFormat,
+    crop::{AspectRatio, Guides},
+    i18n::{tr, Language},
+    image_list::{Listing, Sort},
+    App,
+};
+use crate::{
+    image_io::video,
+    util::{log::Level, Proxy, UserEvent},
+    vec2::Vec2,
+};
+
+/// Name of the config file, see `confy`.
+const CONFIG_NAME: &str = "simp";
+
+/// What the OS asks apps for, found out once by `detect_system_preferences`.
+struct SystemPreferences {
+    dark: bool,
+    reduced_motion: bool,
+}
+
+static SYSTEM: OnceLock<SystemPreferences> = OnceLock::new();
+
+/// Asks the OS for its theme and whether it wants less motion. That can mean running a
+/// program, so it happens off the ui thread while the window is made. Until the answer is
+/// in simp is dark and animates, then the ui thread is woken to apply it.
+pub fn detect_system_preferences(proxy: Proxy) {
+    thread::spawn(move || {
+        let preferences = SystemPreferences {
+            dark: system_prefers_dark(),
+            reduced_motion: system_prefers_reduced_motion(),
+        };
+        if SYSTEM.set(preferences).is_ok() {
+            let _ = proxy.send_event(UserEvent::Wake);
+        }
+    });
+}
+
+/// Whether the OS is set to reduce animations. `false` where that can not be found out.
+#[cfg(windows)]
+fn system_prefers_reduced_motion() -> bool {
+    use winapi::{
+        shared::minwindef::{BOOL, TRUE},
+        um::winuser::{SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION},
+    };
+
+    let mut animations: BOOL = TRUE;
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            &mut animations as *mut BOOL as *mut _,
+            0,
+        )
+    };
+    ok != 0 && animations == 0
+}
+
+/// Whether apps are asked to use dark colors, read from the same key the settings app writes.
+#[cfg(windows)]
+fn system_prefers_dark() -> bool {
+    use std::{ffi::OsStr, os::windows::ffi::OsStrExt, ptr};
+
+    use winapi::{
+        shared::minwindef::DWORD,
+        um::winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+    };
+
+    let wide = |text: &str| -> Vec<u16> { OsStr::new(text).encode_wide().chain([0]).collect() };
+    let key = wide("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize");
+    let value = wide("AppsUseLightTheme");
+    let mut light: DWORD = 1;
+    let mut size = std::mem::size_of::<DWORD>() as DWORD;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_DWORD,
+            ptr::null_mut(),
+            &mut light as *mut DWORD as *mut _,
+            &mut size,
+        )
+    };
+    status == 0 && light == 0
+}
+
+/// Dark unless the desktop says otherwise, simp has always been dark.
+#[cfg(not(windows))]
+fn system_prefers_dark() -> bool {
+    use std::process::Command;
+
+    #[cfg(target_os = "macos")]
+    let (command, args) = ("defaults", ["read", "-g", "AppleInterfaceStyle"]);
+    #[cfg(not(target_os = "macos"))]
+    let (command, args) = (
+        "gsettings",
+        ["get", "org.gnome.desktop.interface", "color-scheme"],
+    );
+    match Command::new(command).args(args).output() {
+        // macos only has the key while dark
+        Ok(output) if cfg!(target_os = "macos") => output.status.success(),
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim() == "'prefer-dark'"
+        }
+        _ => true,
+    }
+}
+
+#[cfg(not(windows))]
+fn system_prefers_reduced_motion() -> bool {
+    use std::process::Command;
+
+    #[cfg(target_os = "macos")]
+    let (command, args, reduced) = (
+        "defaults",
+        ["read", "com.apple.universalaccess", "reduceMotion"],
+        "1",
+    );
+    #[cfg(not(target_os = "macos"))]
+    let (command, args, reduced) = (
+        "gsettings",
+        ["get", "org.gnome.desktop.interface", "enable-animations"],
+        "false",
+    );
+    Command::new(command)
+        .args(args)
+        .output()
+        .map(|output| {
+            output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == reduced
+        })
+        .unwrap_or(false)
+}
+
+/// What zooming with the wheel or the keys zooms towards.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomAnchor {
+    Cursor,
+    Center,
+}
+
+/// How an image is scaled when it is shown.
+/// Stored as the text `--zoom` takes since toml has no place for a variant with a value.
+/// Text that is not a zoom is read as a best fit.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(into = "String", from = "String")]
+pub enum Zoom {
+    /// Fit the window, never larger than the image.
+    #[default]
+    Fit,
+    /// Fit the window, small images are scaled up.
+    Largest,
+    /// A fixed scale, 1 shows every image pixel as one screen pixel.
+    Scale(f32),
+}
+
+impl Zoom {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "fit" => Some(Zoom::Fit),
+            "largest" | "fill" => Some(Zoom::Largest),
+            "actual" | "1:1" => Some(Zoom::Scale(1.0)),
+            text => {
+                let percent: f32 = text.strip_suffix('%')?.parse().ok()?;
+                let scale = percent / 100.0;
+                (scale.is_finite() && scale > 0.0).then_some(Zoom::Scale(scale))
+            }
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Zoom::Fit => String::from(tr("Best fit")),
+            Zoom::Largest => String::from(tr("Largest fit")),
+            Zoom::Scale(scale) => format!("{}%", (scale * 100.0).round()),
+        }
+    }
+}
+
+impl From<Zoom> for String {
+    fn from(zoom: Zoom) -> Self {
+        match zoom {
+            Zoom::Fit => String::from("fit"),
+            Zoom::Largest => String::from("largest"),
+            Zoom::Scale(scale) => format!("{}%", scale * 100.0),
+        }
+    }
+}
+
+impl From<String> for Zoom {
+    fn from(text: String) -> Self {
+        Zoom::parse(&text).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    /// Follows the dark or light setting of the desktop at startup.
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+
+    /// Whether the theme is dark, a system theme that is not known yet is.
+    pub fn dark(self) -> bool {
+        match self {
+            Theme::System => SYSTEM.get().is_none_or(|system| system.dark),
+            Theme::Dark => true,
+            Theme::Light => false,
+        }
+    }
+
+    /// The egui visuals, with selections and links in `accent` if one is picked.
+    pub fn visuals(self, accent: Option<[u8; 3]>) -> egui::Visuals {
+        let mut visuals = if self.dark() {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+        if let Some([r, g, b]) = accent {
+            let color = egui::Color32::from_rgb(r, g, b);
+            // text on the selection has to stay readable whatever the accent is
+            let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+            let text = if luminance > 140.0 {
+                egui::Color32::BLACK
+            } else {
+                egui::Color32::WHITE
+            };
+            visuals.selection.bg_fill = color;
+            visuals.selection.stroke = egui::Stroke::new(1.0, text);
+            visuals.hyperlink_color = color;
+        }
+        visuals
+    }
+}
+
+/// The color behind the image.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Dark,
+    Black,
+    Gray,
+    White,
+}
+
+impl Background {
+    pub const ALL: [Background; 4] = [
+        Background::Dark,
+        Background::Black,
+        Background::Gray,
+        Background::White,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Background::Dark => "Dark",
+            Background::Black => "Black",
+            Background::Gray => "Gray",
+            Background::White => "White",
+        }
+    }
+
+    /// The color in srgb.
+    pub fn color(self) -> (f32, f32, f32, f32) {
+        match self {
+            Background::Dark => (0.172, 0.172, 0.172, 1.0),
+            Background::Black => (0.0, 0.0, 0.0, 1.0),
+            Background::Gray => (0.5, 0.5, 0.5, 1.0),
+            Background::White => (1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// What shows through the transparent parts of an image.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transparency {
+    Checkerboard,
+    Black,
+    White,
+    /// The color picked in the preferences.
+    Custom,
+}
+
+impl Transparency {
+    pub const ALL: [Transparency; 4] = [
+        Transparency::Checkerboard,
+        Transparency::Black,
+        Transparency::White,
+        Transparency::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Transparency::Checkerboard => "Checkerboard",
+            Transparency::Black => "Black",
+            Transparency::White => "White",
+            Transparency::Custom => "Color",
+        }
+    }
+
+    /// The two colors of the checks in srgb, a solid backdrop is the same color twice.
+    pub fn checks(self, custom: [u8; 3]) -> [[f32; 3]; 2] {
+        let solid = |[r, g, b]: [u8; 3]| {
+            let color = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0];
+            [color, color]
+        };
+        match self {
+            Transparency::Checkerboard => [[64.0 / 255.0; 3], [48.0 / 255.0; 3]],
+            Transparency::Black => solid([0, 0, 0]),
+            Transparency::White => solid([255, 255, 255]),
+            Transparency::Custom => solid(custom),
+        }
+    }
+}
+
+/// Everything that can be changed in the preferences window.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub language: Language,
+    pub theme: Theme,
+    /// The color of selections and links, egui's own when `None`.
+    pub accent: Option<[u8; 3]>,
+    pub background: Background,
+    pub transparency: Transparency,
+    /// Behind transparent pixels when `transparency` is `Custom`.
+    pub transparency_color: [u8; 3],
+    /// How picked colors are copied.
+    pub color_format: ColorFormat,
+    /// How much one zoom step changes the scale, in percent.
+    pub zoom_step: f32,
+    pub zoom_anchor: ZoomAnchor,
+    /// How far one press of a pan key moves the view, in points.
+    pub pan_step: f32,
+    /// Whether the arrow keys pan an image larger than the window instead of browsing.
+    pub arrow_keys_pan: bool,
+    /// Whether a small copy of an image larger than the window shows the part in view.
+    pub minimap: bool,
+    /// Whether lines every `grid_spacing` image pixels are drawn over the image.
+    pub grid: bool,
+    pub grid_spacing: u32,
+    pub grid_color: [u8; 3],
+    /// Whether lines are drawn between the pixels from 800% zoom on.
+    pub pixel_grid: bool,
+    /// The lines drawn inside a crop selection.
+    pub crop_guides: Guides,
+    /// The shape a crop selection keeps while it is dragged.
+    pub crop_aspect: AspectRatio,
+    /// Width and height of `AspectRatio::Custom`.
+    pub crop_custom_aspect: [u32; 2],
+    /// Whether the aspect ratio is turned to be taller than wide.
+    pub crop_portrait: bool,
+    /// How much memory decoded images may keep in the cache, in megabytes.
+    pub cache_size_mb: u32,
+    /// Images with more megapixels are only decoded after asking.
+    pub max_megapixels: u32,
+    /// Whether browsing a folder includes files without an extension that contain images.
+    pub extensionless: bool,
+    /// Whether browsing a folder includes video files, shown as a still from the start.
+    pub videos: bool,
+    /// Whether camera raw files show the jpeg preview they carry until zoomed in or edited,
+    /// which is much faster than developing them.
+    pub raw_previews: bool,
+    /// Size of the menus and windows in percent, on top of the monitor scale.
+    pub interface_scale: f32,
+    /// Whether fitting a panorama fills the window with its short side.
+    pub smart_fit: bool,
+    /// From how many times longer than wide, or the other way around, an image is a panorama.
+    pub panorama_ratio: f32,
+    /// Whether animations start playing when they are opened and the interface animates.
+    /// `None` until it is changed, which follows the OS asking for reduced motion.
+    pub autoplay: Option<bool>,
+    /// Whether panning past the edges of the image gives a little and springs back,
+    /// otherwise the image stops dead at the edges.
+    pub rubber_band: bool,
+    /// Whether opening an image while simp runs shows it in the open window instead of a
+    /// new one.
+    pub single_instance: bool,
+    /// The order the images of a folder are browsed in.
+    pub sort: Sort,
+    /// How long the slideshow shows each image, in seconds.
+    pub slideshow_seconds: f32,
+    /// Whether the slideshow slowly zooms out of a corner of each still image, only while
+    /// `autoplay` is on.
+    pub ken_burns: bool,
+    /// How far in the Ken Burns pan starts, in percent over the opened size.
+    pub ken_burns_intensity: f32,
+    /// Whether thumbnails of the folder are shown along the bottom.
+    pub filmstrip: bool,
+    /// How fast auto-scroll pans along a panorama, in points per second.
+    pub scroll_speed: f32,
+    /// How images are scaled when they are opened, `--zoom` overrides it.
+    pub zoom: Zoom,
+    /// The least severe records that are kept for the log window and diagnostics.
+    pub log_level: Level,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            language: Language::System,
+            theme: Theme::Dark,
+            accent: None,
+            background: Background::Dark,
+            transparency: Transparency::Checkerboard,
+            transparency_color: [255, 0, 255],
+            color_format: ColorFormat::default(),
+            zoom_step: 10.0,
+            zoom_anchor: ZoomAnchor::Cursor,
+            pan_step: 64.0,
+            arrow_keys_pan: false,
+            minimap: true,
+            grid: false,
+            grid_spacing: 8,
+            grid_color: [255, 255, 255],
+            crop_guides: Guides::None,
+            crop_aspect: AspectRatio::Free,
+            crop_custom_aspect: [3, 2],
+            crop_portrait: false,
+            pixel_grid: true,
+            cache_size_mb: 1000,
+            max_megapixels: 512,
+            extensionless: false,
+            videos: false,
+            raw_previews: false,
+            interface_scale: 100.0,
+            smart_fit: true,
+            panorama_ratio: 3.0,
+            autoplay: None,
+            rubber_band: true,
+            single_instance: false,
+            sort: Sort::default(),
+            slideshow_seconds: 5.0,
+            ken_burns: false,
+            ken_burns_intensity: 20.0,
+            filmstrip: false,
+            scroll_speed: 120.0,
+            zoom: Zoom::Fit,
+            log_level: Level::Info,
+        }
+    }
+}
+
+impl Settings {
+    pub const ZOOM_STEP_RANGE: std::ops::RangeInclusive<f32> = 5.0..=50.0;
+    pub const PAN_STEP_RANGE: std::ops::RangeInclusive<f32> = 8.0..=512.0;
+    pub const GRID_SPACING_RANGE: std::ops::RangeInclusive<u32> = 1..=1024;
+    pub const CROP_ASPECT_RANGE: std::ops::RangeInclusive<u32> = 1..=100;
+    pub const INTERFACE_SCALE_RANGE: std::ops::RangeInclusive<f32> = 50.0..=200.0;
+    pub const PANORAMA_RATIO_RANGE: std::ops::RangeInclusive<f32> = 2.0..=20.0;
+    pub const CACHE_SIZE_RANGE: std::ops::RangeInclusive<u32> = 100..=8000;
+
+    pub const MAX_MEGAPIXELS_RANGE: std::ops::RangeInclusive<u32> = 16..=16000;
+    pub const SLIDESHOW_RANGE: std::ops::RangeInclusive<f32> = 1.0..=60.0;
+    pub const KEN_BURNS_RANGE: std::ops::RangeInclusive<f32> = 5.0..=50.0;
+    pub const SCROLL_SPEED_RANGE: std::ops::RangeInclusive<f32> = 20.0..=1000.0;
+
+    pub fn listing(&self) -> Listing {
+        Listing {
+            extensionless: self.extensionless,
+            videos: self.videos && video::SUPPORTED,
+            sort: self.sort,
+        }
+    }
+
+    pub fn slideshow_interval(&self) -> Duration {
+        Duration::from_secs_f32(self.slideshow_seconds)
+    }
+
+    pub fn max_pixels(&self) -> u64 {
+        self.max_megapixels as u64 * 1_000_000
+    }
+
+    pub fn cache_size(&self) -> usize {
+        self.cache_size_mb as usize * 1_000_000
+    }
+
+    /// Puts values edited by hand back in range.
+    /// Whether animations play and the interface animates, see `autoplay`.
+    pub fn autoplay(&self) -> bool {
+        self.autoplay
+            .unwrap_or_else(|| SYSTEM.get().is_none_or(|system| !system.reduced_motion))
+    }
+
+    fn sanitize(&mut self) {
+        if !self.zoom_step.is_finite() {
+            self.zoom_step = Settings::default().zoom_step;
+        }
+        self.zoom_step = self
+            .zoom_step
+            .clamp(*Self::ZOOM_STEP_RANGE.start(), *Self::ZOOM_STEP_RANGE.end());
+        if !self.pan_step.is_finite() {
+            self.pan_step = Settings::default().pan_step;
+        }
+        self.pan_step = self
+            .pan_step
+            .clamp(*Self::PAN_STEP_RANGE.start(), *Self::PAN_STEP_RANGE.end());
+        self.grid_spacing = self.grid_spacing.clamp(
+            *Self::GRID_SPACING_RANGE.start(),
+            *Self::GRID_SPACING_RANGE.end(),
+        );
+        for side in &mut self.crop_custom_aspect {
+            *side = (*side).clamp(
+                *Self::CROP_ASPECT_RANGE.start(),
+                *Self::CROP_ASPECT_RANGE.end(),
+            );
+        }
+        if !self.interface_scale.is_finite() {
+            self.interface_scale = Settings::default().interface_scale;
+        }
+        self.interface_scale = self.interface_scale.clamp(
+            *Self::INTERFACE_SCALE_RANGE.start(),
+            *Self::INTERFACE_SCALE_RANGE.end(),
+        );
+        if !self.panorama_ratio.is_finite() {
+            self.panorama_ratio = Settings::default().panorama_ratio;
+        }
+        self.panorama_ratio = self.panorama_ratio.clamp(
+            *Self::PANORAMA_RATIO_RANGE.start(),
+            *Self::PANORAMA_RATIO_RANGE.end(),
+        );
+        self.cache_size_mb = self.cache_size_mb.clamp(
+            *Self::CACHE_SIZE_RANGE.start(),
+            *Self::CACHE_SIZE_RANGE.end(),
+        );
+        self.max_megapixels = self.max_megapixels.clamp(
+            *Self::MAX_MEGAPIXELS_RANGE.start(),
+            *Self::MAX_MEGAPIXELS_RANGE.end(),
+        );
+        if !self.slideshow_seconds.is_finite() {
+            self.slideshow_seconds = Settings::default().slideshow_seconds;
+        }
+        self.slideshow_seconds = self
+            .slideshow_seconds
+            .clamp(*Self::SLIDESHOW_RANGE.start(), *Self::SLIDESHOW_RANGE.end());
+        if !self.ken_burns_intensity.is_finite() {
+            self.ken_burns_intensity = Settings::default().ken_burns_intensity;
+        }
+        self.ken_burns_intensity = self
+            .ken_burns_intensity
+            .clamp(*Self::KEN_BURNS_RANGE.start(), *Self::KEN_BURNS_RANGE.end());
+        if !self.scroll_speed.is_finite() {
+            self.scroll_speed = Settings::default().scroll_speed;
+        }
+        self.scroll_speed = self.scroll_speed.clamp(
+            *Self::SCROLL_SPEED_RANGE.start(),
+            *Self::SCROLL_SPEED_RANGE.end(),
+        );
+        if let Zoom::Scale(scale) = self.zoom {
+            if !scale.is_finite() || scale <= 0.0 {
+                self.zoom = Zoom::Fit;
+            }
+        }
+    }
+
+    /// The colors behind transparent pixels, see `Transparency::checks`.
+    pub fn checks(&self) -> [[f32; 3]; 2] {
+        self.transparency.checks(self.transparency_color)
+    }
+}
+
+/// The file written to the config directory.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub width: f64,
+    pub height: f64,
+    /// Outer position of the window in physical pixels, the way the windowing system
+    /// reports and takes it. `None` until the window was closed once.
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    /// Whether the window was fullscreen when it closed, the size and position are where it
+    /// goes back to.
+    pub fullscreen: bool,
+    pub settings: Settings,
+    /// Rebound keyboard shortcuts, see `Keymap`.
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            width: 1100f64,
+            height: 720f64,
+            x: None,
+            y: None,
+            fullscreen: false,
+            settings: Settings::default(),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let mut config: Config = confy::load(CONFIG_NAME).unwrap_or_default();
+        config.settings.sanitize();
+        config
+    }
+}
+
+impl App {
+    /// What `save_config` writes. The window is created with a logical size so that is stored.
+    /// A fullscreen window stores the place it goes back to.
+    pub fn config(&self) -> Config {
+        let (position, size) = match self.windowed_geometry {
+            Some((position, size)) if self.fullscreen => (
+                Vec2::new(position.x, position.y),
+                Vec2::new(size.width as f32, size.height as f32),
+            ),
+            _ => (self.position, self.size),
+        };
+        Config {
+            width: (size.x() / self.scale_factor) as f64,
+            height: (size.y() / self.scale_factor) as f64,
+            x: Some(position.x()),
+            y: Some(position.y()),
+            fullscreen: self.fullscreen,
+            settings: self.settings.clone(),
+            keys: self.keymap.to_config(),
+        }
+    }
+
+    /// Writes the window size and settings to the config file.
+    pub fn save_config(&self) -> Result<(), String> {
+        confy::store(CONFIG_NAME, self.config())
+            .map_err(|error| format!("Could not save the settings: {}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_backdrops_are_one_color() {
+        let [light, dark] = Transparency::Custom.checks([255, 0, 255]);
+        assert_eq!(light, [1.0, 0.0, 1.0]);
+        assert_eq!(light, dark);
+        let [light, dark] = Transparency::White.checks([255, 0, 255]);
+        assert_eq!(light, [1.0; 3]);
+        assert_eq!(light, dark);
+        let [light, dark] = Transparency::Checkerboard.checks([255, 0, 255]);
+        assert_ne!(light, dark);
+    }
+
+    #[test]
+    fn steps_stay_in_range() {
+        let mut settings = Settings {
+            pan_step: 0.0,
+            grid_spacing: 0,
+            zoom_step: f32::NAN,
+            crop_custom_aspect: [0, 500],
+            ken_burns_intensity: f32::INFINITY,
+            ..Settings::default()
+        };
+        settings.sanitize();
+        assert_eq!(settings.pan_step, *Settings::PAN_STEP_RANGE.start());
+        assert_eq!(settings.grid_spacing, 1);
+        assert_eq!(settings.crop_custom_aspect, [1, 100]);
+        assert_eq!(settings.zoom_step, Settings::default().zoom_step);
+        assert_eq!(
+            settings.ken_burns_intensity,
+            Settings::default().ken_burns_intensity
+        );
+    }
+
+    #[test]
+    fn settings_survive_the_config_file() {
+        let path = std::env::temp_dir().join(format!("simp-test-{}.toml", nanoid::nanoid!()));
+        let mut config = Config {
+            fullscreen: true,
+            ..Config::default()
+        };
+        config.settings.zoom = Zoom::Scale(2.0);
+        config.settings.sort = Sort::Newest;
+        config.settings.slideshow_seconds = 3.0;
+        config
+            .keys
+            .insert(String::from("RotateLeft"), vec![String::from("Ctrl + J")]);
+        confy::store_path(&path, &config).unwrap();
+
+        let loaded: Config = confy::load_path(&path).unwrap();
+        assert_eq!(loaded.settings, config.settings);
+        assert_eq!(loaded.keys, config.keys);
+        assert!(loaded.fullscreen);
+
+        // settings missing from an older file get their defaults, unreadable ones as well
+        std::fs::write(
+            &path,
+            "width = 800.0\nheight = 600.0\n[settings]\nzoom_step = 20.0\nzoom = \"huge\"\n",
+        )
+        .unwrap();
+        let loaded: Config = confy::load_path(&path).unwrap();
+        assert_eq!(loaded.settings.zoom_step, 20.0);
+        assert_eq!(loaded.settings.zoom, Zoom::Fit);
+        assert_eq!(loaded.settings.sort, Sort::NameReversed);
+        assert!(!loaded.fullscreen);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
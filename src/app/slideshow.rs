@@ -0,0 +1,215 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use glium::Display;
+
+use super::App;
+use crate::vec2::Vec2;
+
+/// Time between two frames of the Ken Burns pan.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// Moves on to the next image once the current one was shown long enough.
+#[derive(Default)]
+pub struct Slideshow {
+    /// How long each image is shown, `None` while stopped.
+    interval: Option<Duration>,
+    /// When the current image appeared.
+    shown: Option<Instant>,
+    /// The pan over the current image, `None` until its first frame.
+    ken_burns: Option<KenBurns>,
+    /// Whether the current image was handed to the auto-scroll already, it gets one pass.
+    scrolled: bool,
+}
+
+/// A slow zoom out from close in on one corner of the image to where it was opened at.
+/// It only moves the view, the image itself is never touched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct KenBurns {
+    from: (f32, Vec2<f32>),
+    to: (f32, Vec2<f32>),
+    /// The scale of the last frame. Once the view has another one it was zoomed by hand
+    /// and the pan stops, the position is no sign of that since it is clamped to the window.
+    shown: Option<f32>,
+}
+
+impl KenBurns {
+    /// Starts `zoom` times closer in on one of the corners, `corner` is -1 or 1 on each
+    /// axis and `size` how large the image is at `scale`.
+    fn new(scale: f32, position: Vec2<f32>, size: Vec2<f32>, zoom: f32, corner: Vec2<f32>) -> Self {
+        // keeps the corner where it is at the end, the rest grows away from it
+        let shift = Vec2::new(corner.x() * size.x(), corner.y() * size.y()) * ((zoom - 1.0) / 2.0);
+        Self {
+            from: (scale * zoom, position - shift),
+            to: (scale, position),
+            shown: None,
+        }
+    }
+
+    /// The scale and position `progress` of the way through, from 0 to 1.
+    fn at(&self, progress: f32) -> (f32, Vec2<f32>) {
+        let t = progress.clamp(0.0, 1.0);
+        // eases in and out, so neither end of the slide jerks
+        let t = t * t * (3.0 - 2.0 * t);
+        let (from_scale, from_position) = self.from;
+        let (to_scale, to_position) = self.to;
+        (
+            from_scale + (to_scale - from_scale) * t,
+            from_position + (to_position - from_position) * t,
+        )
+    }
+}
+
+/// One of the four corners, picked from the clock since the order does not need to be fair.
+fn random_corner() -> Vec2<f32> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    let sign = |bit: u32| if nanos >> bit & 1 == 0 { -1.0 } else { 1.0 };
+    Vec2::new(sign(4), sign(5))
+}
+
+impl Slideshow {
+    pub fn active(&self) -> bool {
+        self.interval.is_some()
+    }
+
+    pub fn start(&mut self, interval: Duration) {
+        self.interval = Some(interval);
+        self.shown = Some(Instant::now());
+        self.ken_burns = None;
+        self.scrolled = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.interval = None;
+        self.shown = None;
+        self.ken_burns = None;
+        self.scrolled = false;
+    }
+
+    /// Counts the time again from now, a slow load does not eat into the next image's turn.
+    pub fn restart(&mut self) {
+        if self.active() {
+            self.shown = Some(Instant::now());
+            self.ken_burns = None;
+            self.scrolled = false;
+        }
+    }
+}
+
+impl App {
+    pub fn toggle_slideshow(&mut self) {
+        if self.slideshow.active() {
+            self.slideshow.stop();
+        } else if self.image_view.is_some() {
+            self.slideshow.start(self.settings.slideshow_interval());
+        }
+    }
+
+    /// Shows the next image when the current one had its time, returns when that is due.
+    /// Without an image it waits for one to be loaded. Panoramas are scrolled through first
+    /// and get their time once the end is reached.
+    pub fn step_slideshow(&mut self, display: &Display) -> Option<Duration> {
+        let interval = self.slideshow.interval?;
+        self.slideshow.shown?;
+        self.image_view.as_ref()?;
+
+        if !self.slideshow.scrolled {
+            self.slideshow.scrolled = true;
+            self.start_auto_scroll(None);
+        }
+        if self.auto_scroll.active() {
+            self.slideshow.shown = Some(Instant::now());
+            return Some(FRAME);
+        }
+
+        let shown = self.slideshow.shown?;
+        let elapsed = shown.elapsed();
+        if elapsed < interval {
+            let panning = self.step_ken_burns(elapsed.as_secs_f32() / interval.as_secs_f32());
+            let left = interval - elapsed;
+            return Some(if panning { left.min(FRAME) } else { left });
+        }
+        // an open crop or a running op holds the slideshow, their end wakes it again
+        if self.crop.inner.is_some() || !self.view_available() {
+            return None;
+        }
+        self.slideshow.shown = Some(Instant::now());
+        self.browse(display, true);
+        Some(interval)
+    }
+
+    /// Moves the view along the Ken Burns pan, returns whether it is still going. Animated
+    /// images and panoramas are left alone and so is a view that was zoomed by hand.
+    fn step_ken_burns(&mut self, progress: f32) -> bool {
+        // panning is motion, so it stops with autoplay for those that asked for less of it
+        if !self.settings.ken_burns
+            || !self.settings.autoplay()
+            || self.crop.inner.is_some()
+            || self.panorama().is_some()
+        {
+            return false;
+        }
+        let view = match self.image_view.as_mut() {
+            Some(view) => view,
+            None => return false,
+        };
+        if view.image_data.read().unwrap().frames.len() > 1 {
+            return false;
+        }
+
+        let zoom = 1.0 + self.settings.ken_burns_intensity / 100.0;
+        let pan = *self.slideshow.ken_burns.get_or_insert_with(|| {
+            KenBurns::new(
+                view.scale,
+                view.position,
+                view.scaled(),
+                zoom,
+                random_corner(),
+            )
+        });
+        if pan.shown.is_some_and(|shown| shown != view.scale) {
+            return false;
+        }
+
+        let (scale, position) = pan.at(progress);
+        view.scale = scale;
+        view.position = position;
+        self.slideshow.ken_burns = Some(KenBurns {
+            shown: Some(scale),
+            ..pan
+        });
+        progress < 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ken_burns_ends_where_the_image_was_opened() {
+        let position = Vec2::new(400.0, 300.0);
+        let pan = KenBurns::new(
+            0.5,
+            position,
+            Vec2::new(800.0, 600.0),
+            1.2,
+            Vec2::new(-1.0, -1.0),
+        );
+
+        let (scale, start) = pan.at(0.0);
+        assert_eq!(scale, 0.6);
+        // the top left corner of the image stays put while it zooms out
+        let corner =
+            |scale: f32, center: Vec2<f32>| center - Vec2::new(1600.0, 1200.0) * (scale / 2.0);
+        let end = corner(0.5, position);
+        let begin = corner(scale, start);
+        assert!((begin.x() - end.x()).abs() < 0.01 && (begin.y() - end.y()).abs() < 0.01);
+
+        assert_eq!(pan.at(1.0), (0.5, position));
+        assert_eq!(pan.at(2.0), (0.5, position));
+        let (halfway, _) = pan.at(0.5);
+        assert!(halfway < 0.6 && halfway > 0.5);
+    }
+}
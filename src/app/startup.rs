@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use super::{settings::Zoom, App, MAX_SCALE, MIN_SCALE};
+
+/// The presentation state simp is launched into, from the command line.
+#[derive(Debug, Default, PartialEq)]
+pub struct Startup {
+    pub fullscreen: bool,
+    pub slideshow: bool,
+    /// How long each image is shown, the interval from the settings if not given.
+    pub interval: Option<Duration>,
+    /// Overrides the zoom from the settings.
+    pub zoom: Option<Zoom>,
+}
+
+impl Startup {
+    /// Takes `--fullscreen`, `--slideshow [seconds]` and `--zoom <fit|largest|actual|N%>` out
+    /// of `args`, leaving the program and the paths behind.
+    pub fn from_args(args: &mut Vec<String>) -> Result<Self, String> {
+        let mut startup = Startup::default();
+        let mut rest = Vec::with_capacity(args.len());
+        let mut taken = std::mem::take(args).into_iter().peekable();
+        while let Some(arg) = taken.next() {
+            match arg.as_str() {
+                "--fullscreen" => startup.fullscreen = true,
+                "--slideshow" => {
+                    startup.slideshow = true;
+                    // the seconds are optional, a path never parses as a number of them
+                    let seconds = taken.peek().and_then(|next| next.parse::<f32>().ok());
+                    if let Some(seconds) = seconds {
+                        taken.next();
+                        match Duration::try_from_secs_f32(seconds) {
+                            Ok(interval) if !interval.is_zero() => {
+                                startup.interval = Some(interval)
+                            }
+                            _ => return Err(String::from("--slideshow needs a positive time")),
+                        }
+                    }
+                }
+                "--zoom" => {
+                    let text = taken.next().unwrap_or_default();
+                    startup.zoom = Some(Zoom::parse(&text).ok_or_else(|| {
+                        format!(
+                            "--zoom takes fit, largest, actual or a percentage, not {:?}",
+                            text
+                        )
+                    })?);
+                }
+                _ => rest.push(arg),
+            }
+        }
+        *args = rest;
+        Ok(startup)
+    }
+}
+
+impl App {
+    /// Scales a newly shown image the way the command line or the settings ask.
+    pub fn initial_zoom(&mut self) {
+        match self.zoom.unwrap_or(self.settings.zoom) {
+            Zoom::Fit => self.best_fit(),
+            Zoom::Largest => self.largest_fit(),
+            Zoom::Scale(scale) => {
+                let position = self.fit_position();
+                if let Some(view) = self.image_view.as_mut() {
+                    view.scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+                    view.position = position;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> (Result<Startup, String>, Vec<String>) {
+        let mut args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        let startup = Startup::from_args(&mut args);
+        (startup, args)
+    }
+
+    #[test]
+    fn flags_are_taken_out_of_the_arguments() {
+        let (startup, rest) = parse("simp --fullscreen --slideshow 2.5 --zoom 200% a.png");
+        let startup = startup.unwrap();
+        assert!(startup.fullscreen);
+        assert!(startup.slideshow);
+        assert_eq!(startup.interval, Some(Duration::from_millis(2500)));
+        assert_eq!(startup.zoom, Some(Zoom::Scale(2.0)));
+        assert_eq!(rest, ["simp", "a.png"]);
+
+        let (startup, rest) = parse("simp --slideshow a.png --zoom Largest");
+        let startup = startup.unwrap();
+        assert!(startup.slideshow);
+        assert_eq!(startup.interval, None);
+        assert_eq!(startup.zoom, Some(Zoom::Largest));
+        assert_eq!(rest, ["simp", "a.png"]);
+
+        assert_eq!(parse("simp a.png").0, Ok(Startup::default()));
+    }
+
+    #[test]
+    fn invalid_values_are_errors() {
+        assert_eq!(Zoom::parse("actual"), Some(Zoom::Scale(1.0)));
+        assert!(parse("simp --zoom").0.is_err());
+        assert!(parse("simp --zoom huge a.png").0.is_err());
+        assert!(parse("simp --zoom 0% a.png").0.is_err());
+        assert!(parse("simp --slideshow 0 a.png").0.is_err());
+        assert!(parse("simp --slideshow -1").0.is_err());
+        assert!(parse("simp --slideshow 1e30 a.png").0.is_err());
+        assert!(parse("simp --slideshow NaN a.png").0.is_err());
+    }
+}
@@ -0,0 +1,482 @@
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use egui::{RichText, SelectableLabel, TopBottomPanel};
+use glium::Display;
+
+use super::{
+    action::Action,
+    crop::Selection,
+    i18n::{tr, trf},
+    image_view::ImageView,
+    op_queue::Op,
+    undo_stack::UndoStack,
+    App,
+};
+use crate::{util::ImageData, vec2::Vec2};
+
+/// Height of the tab strip, it is only shown with more than one tab.
+pub const TAB_BAR_SIZE: f32 = 24.0;
+
+/// An open image that is not on screen. The tab on screen keeps its state in `App`.
+struct Tab {
+    image_view: Option<Box<ImageView>>,
+    stack: UndoStack,
+    current_filename: String,
+    crop: Selection,
+    /// An op waiting for the full resolution frames of this tab's image.
+    pending_op: Option<(Op, Vec2<u32>)>,
+}
+
+impl Tab {
+    fn edited(&self) -> bool {
+        self.stack.edited()
+    }
+
+    fn name(&self) -> &str {
+        tab_name(&self.current_filename)
+    }
+}
+
+pub fn tab_name(filename: &str) -> &str {
+    if filename.is_empty() {
+        "New tab"
+    } else {
+        filename
+    }
+}
+
+/// Tabs in the order of the strip. The slot of the active tab is empty,
+/// everything it owns lives in `App` and the op queue.
+pub struct Tabs {
+    slots: Vec<Option<Tab>>,
+    /// An id for each slot that stays the same while tabs before it close.
+    ids: Vec<u64>,
+    next_id: u64,
+    active: usize,
+    /// The tab with unsaved edits the close prompt asks about.
+    closing: Option<u64>,
+    /// Images opened together that still wait for a tab, one is loaded at a time.
+    pending: VecDeque<PathBuf>,
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self {
+            slots: vec![None],
+            ids: vec![0],
+            next_id: 1,
+            active: 0,
+            closing: None,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl Tabs {
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// The names of the tabs that are not on screen and have unsaved edits.
+    pub fn edited(&self) -> Vec<&str> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|tab| tab.edited())
+            .map(Tab::name)
+            .collect()
+    }
+
+    /// The images of the tabs that are not on screen.
+    pub fn images(&self) -> impl Iterator<Item = &Arc<RwLock<ImageData>>> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter_map(|tab| tab.image_view.as_ref())
+            .map(|view| &view.image_data)
+    }
+
+    /// Whether the close prompt is open.
+    pub fn prompting(&self) -> bool {
+        self.closing.is_some()
+    }
+
+    fn push(&mut self, tab: Tab) {
+        self.slots.push(Some(tab));
+        self.ids.push(self.next_id);
+        self.next_id += 1;
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.slots.remove(index);
+        self.ids.remove(index);
+    }
+
+    fn index_of(&self, id: u64) -> Option<usize> {
+        self.ids.iter().position(|&other| other == id)
+    }
+}
+
+/// The warning shown when closing the window would throw away `pasted` unsaved pasted
+/// images and the images with unsaved edits in `edited`, `None` when nothing would be lost.
+pub fn close_warning(pasted: usize, edited: &[&str]) -> Option<String> {
+    let mut parts = Vec::new();
+    match edited {
+        [] => (),
+        [name] => parts.push(name.to_string()),
+        _ => parts.push(format!("{} edited images", edited.len())),
+    }
+    match pasted {
+        0 => (),
+        1 if parts.is_empty() => parts.push(String::from("A pasted image")),
+        1 => parts.push(String::from("a pasted image")),
+        _ => parts.push(format!("{} pasted images", pasted)),
+    }
+    let (verb, object) = match (parts.len(), edited.len() + pasted) {
+        (0, _) => return None,
+        (1, 1) => ("is", "it"),
+        _ => ("are", "them"),
+    };
+    Some(format!(
+        "{} {} not saved, close again to discard {}",
+        parts.join(" and "),
+        verb,
+        object
+    ))
+}
+
+impl App {
+    /// The strip is hidden with a single tab so nothing changes for people who never use them.
+    pub fn tab_bar_size(&self) -> f32 {
+        if self.tabs.len() > 1 && !self.fullscreen {
+            TAB_BAR_SIZE
+        } else {
+            0.0
+        }
+    }
+
+    /// Puts the tab on screen into its slot.
+    fn park_tab(&mut self) {
+        if let Some(view) = &self.image_view {
+            self.playback
+                .remember(view, self.op_queue.original().is_some());
+        }
+        let mut stack = UndoStack::new();
+        self.op_queue.swap_stack(&mut stack);
+        self.tabs.slots[self.tabs.active] = Some(Tab {
+            image_view: self.image_view.take(),
+            stack,
+            current_filename: std::mem::take(&mut self.current_filename),
+            crop: self.crop.take_selection(),
+            pending_op: self.pending_op.take(),
+        });
+        self.auto_scroll.stop();
+    }
+
+    /// Takes the tab at `index` out of its slot and puts it on screen.
+    fn restore_tab(&mut self, display: &Display, index: usize) {
+        self.tabs.active = index;
+        let mut tab = self.tabs.slots[index]
+            .take()
+            .expect("only the active slot is empty");
+        self.op_queue.swap_stack(&mut tab.stack);
+        self.image_view = tab.image_view;
+        self.current_filename = tab.current_filename;
+        self.crop.restore_selection(tab.crop);
+        self.pending_op = tab.pending_op;
+
+        match &self.image_view {
+            Some(view) => {
+                let size = view.true_size();
+                self.resize
+                    .set_size(Vec2::new(size.x() as u32, size.y() as u32));
+                self.scratch.show(&view.image_data);
+                if let Some(path) = &view.path {
+                    self.op_queue.image_list.change_dir(path);
+                }
+            }
+            None => self.scratch.hide(),
+        }
+        display.gl_window().window().set_title(&self.window_title());
+        self.update_pinned();
+    }
+
+    /// Tabs are only changed between ops so a finished load always lands in the tab that
+    /// started it.
+    fn can_switch_tabs(&self) -> bool {
+        !self.op_queue.working()
+    }
+
+    pub fn new_tab(&mut self, display: &Display) {
+        if !self.can_switch_tabs() {
+            return;
+        }
+        self.park_tab();
+        self.tabs.push(Tab {
+            image_view: None,
+            stack: UndoStack::new(),
+            current_filename: String::new(),
+            crop: Selection::default(),
+            pending_op: None,
+        });
+        self.restore_tab(display, self.tabs.slots.len() - 1);
+    }
+
+    pub fn switch_tab(&mut self, display: &Display, index: usize) {
+        if index == self.tabs.active || index >= self.tabs.len() || !self.can_switch_tabs() {
+            return;
+        }
+        self.park_tab();
+        self.restore_tab(display, index);
+    }
+
+    /// Opens each of `paths` in a tab of its own. The first one takes the tab on screen if
+    /// that is empty.
+    pub fn open_in_tabs(&mut self, paths: Vec<PathBuf>) {
+        let mut paths = VecDeque::from(paths);
+        if self.image_view.is_none() && !self.op_queue.working() {
+            if let Some(first) = paths.pop_front() {
+                self.open_path(first, false);
+            }
+        }
+        self.tabs.pending.extend(paths);
+    }
+
+    /// Starts loading the next image waiting for a tab once the last one is done.
+    pub fn open_pending_tab(&mut self, display: &Display) {
+        if self.op_queue.working() {
+            return;
+        }
+        if let Some(path) = self.tabs.pending.pop_front() {
+            self.new_tab(display);
+            self.open_path(path, false);
+        }
+    }
+
+    /// Moves to the next or previous tab, wrapping around at the ends.
+    pub fn cycle_tabs(&mut self, display: &Display, forward: bool) {
+        let len = self.tabs.len();
+        let index = if forward {
+            (self.tabs.active + 1) % len
+        } else {
+            (self.tabs.active + len - 1) % len
+        };
+        self.switch_tab(display, index);
+    }
+
+    /// Whether the tab at `index` has unsaved edits, and its name.
+    fn tab_edits(&self, index: usize) -> (bool, &str) {
+        if index == self.tabs.active {
+            let edited = self.op_queue.edited();
+            (edited, tab_name(&self.current_filename))
+        } else {
+            let tab = self.tabs.slots[index].as_ref().unwrap();
+            (tab.edited(), tab.name())
+        }
+    }
+
+    /// Closes a tab, one with unsaved edits asks first.
+    pub fn close_tab(&mut self, display: &Display, index: usize) {
+        if index >= self.tabs.len() || !self.can_switch_tabs() {
+            return;
+        }
+        if self.tab_edits(index).0 {
+            self.tabs.closing = Some(self.tabs.ids[index]);
+            return;
+        }
+        self.discard_tab(display, index);
+    }
+
+    fn discard_tab(&mut self, display: &Display, index: usize) {
+        if self.tabs.len() == 1 {
+            // the last tab stays open, only its image goes away
+            if self.image_view.is_some() {
+                self.queue(Op::Close);
+            }
+            return;
+        }
+
+        if index == self.tabs.active {
+            self.park_tab();
+            self.tabs.remove(index);
+            self.restore_tab(display, index.min(self.tabs.len() - 1));
+        } else {
+            self.tabs.remove(index);
+            if index < self.tabs.active {
+                self.tabs.active -= 1;
+            }
+            self.update_pinned();
+        }
+    }
+
+    pub fn tab_bar(&mut self, display: &Display, ctx: &egui::Context) {
+        if self.tab_bar_size() == 0.0 {
+            return;
+        }
+
+        let mut clicked = None;
+        let mut close = None;
+        TopBottomPanel::top("tabs")
+            .height_range(TAB_BAR_SIZE..=TAB_BAR_SIZE)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.set_enabled(self.can_switch_tabs());
+                    for (index, slot) in self.tabs.slots.iter().enumerate() {
+                        let name = match slot {
+                            Some(tab) => tab.name(),
+                            None => tab_name(&self.current_filename),
+                        };
+                        let active = index == self.tabs.active;
+                        if ui.add(SelectableLabel::new(active, name)).clicked() {
+                            clicked = Some(index);
+                        }
+                        if ui.small_button(RichText::new("×").weak()).clicked() {
+                            close = Some(index);
+                        }
+                        ui.separator();
+                    }
+                });
+            });
+
+        if let Some(index) = close {
+            self.close_tab(display, index);
+        } else if let Some(index) = clicked {
+            self.switch_tab(display, index);
+        }
+    }
+
+    /// Asks what happens to the edits of a tab that is being closed.
+    pub fn close_prompt_ui(&mut self, display: &Display, ctx: &egui::Context) {
+        let index = match self.tabs.closing.and_then(|id| self.tabs.index_of(id)) {
+            Some(index) => index,
+            None => {
+                self.tabs.closing = None;
+                return;
+            }
+        };
+
+        let mut choice = None;
+        let mut open = true;
+        egui::Window::new(tr("Unsaved edits"))
+            .id(egui::Id::new("close tab window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let name = self.tab_edits(index).1;
+                ui.label(RichText::new(name).strong());
+                ui.label(tr("has unsaved edits that closing the tab discards."));
+                ui.add_space(4.0);
+                ui.set_enabled(self.can_switch_tabs());
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(tr("Save as"))
+                        .on_hover_text(trf("Show {} and save it", &[&name]))
+                        .clicked()
+                    {
+                        choice = Some(Choice::Save);
+                    }
+                    if ui.button(tr("Discard")).clicked() {
+                        choice = Some(Choice::Discard);
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        choice = Some(Choice::Cancel);
+                    }
+                });
+            });
+
+        if !open || ctx.input().key_pressed(egui::Key::Escape) {
+            choice = Some(Choice::Cancel);
+        }
+
+        let choice = match choice {
+            Some(choice) => choice,
+            None => return,
+        };
+        self.tabs.closing = None;
+        match choice {
+            Choice::Save => {
+                // the tab stays open so it can be closed once the edits are safe
+                self.switch_tab(display, index);
+                self.dispatch(display, Action::SaveAs);
+            }
+            Choice::Discard => self.discard_tab(display, index),
+            Choice::Cancel => (),
+        }
+    }
+}
+
+enum Choice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::undo_stack::UndoFrame;
+
+    fn tab(name: &str, edited: bool) -> Tab {
+        let mut stack = UndoStack::new();
+        if edited {
+            stack.push(UndoFrame::Rotate(1));
+        }
+        Tab {
+            image_view: None,
+            stack,
+            current_filename: name.to_string(),
+            crop: Selection::default(),
+            pending_op: None,
+        }
+    }
+
+    #[test]
+    fn closing_warns_about_edited_background_tabs() {
+        let mut tabs = Tabs::default();
+        tabs.push(tab("a.png", true));
+        tabs.push(tab("b.png", false));
+        assert_eq!(tabs.edited(), ["a.png"]);
+
+        assert_eq!(close_warning(0, &[]), None);
+        assert_eq!(
+            close_warning(0, &tabs.edited()).as_deref(),
+            Some("a.png is not saved, close again to discard it")
+        );
+        assert_eq!(
+            close_warning(1, &[]).as_deref(),
+            Some("A pasted image is not saved, close again to discard it")
+        );
+        assert_eq!(
+            close_warning(2, &["a.png", "c.jpg"]).as_deref(),
+            Some("2 edited images and 2 pasted images are not saved, close again to discard them")
+        );
+    }
+
+    #[test]
+    fn the_prompt_follows_its_tab_when_others_close() {
+        let mut tabs = Tabs::default();
+        tabs.push(tab("a.png", false));
+        tabs.push(tab("b.png", true));
+        tabs.closing = Some(tabs.ids[2]);
+
+        tabs.remove(1);
+        let index = tabs.closing.and_then(|id| tabs.index_of(id)).unwrap();
+        assert_eq!(tabs.slots[index].as_ref().unwrap().name(), "b.png");
+
+        tabs.remove(index);
+        assert_eq!(tabs.closing.and_then(|id| tabs.index_of(id)), None);
+        // ids are not handed out twice
+        tabs.push(tab("c.png", false));
+        assert_eq!(tabs.closing.and_then(|id| tabs.index_of(id)), None);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use egui::{ColorImage, Image, Rect, Stroke, TextureHandle, Vec2};
+use image::{imageops::FilterType, RgbaImage};
+
+use super::{
+    cache::{Cache, Thumbnail},
+    load_image::{load_uncached, LoadOptions},
+};
+use crate::util::{Proxy, UserEvent};
+
+/// The longest side of the thumbnails that are shown, in pixels.
+pub const SIZE: u32 = 160;
+
+/// How many thumbnails are made at the same time.
+const WORKERS: usize = 2;
+
+/// Decodes an image no larger than it has to be and scales it to fit `size`.
+/// Returns `None` for anything that can not be shown.
+pub fn thumbnail(path: &Path, size: u32) -> Option<RgbaImage> {
+    let options = LoadOptions {
+        fit: Some((size, size)),
+        // developing a raw file is far slower than showing its preview
+        raw_preview: true,
+        ..LoadOptions::default()
+    };
+    let image_data = load_uncached(path, options).ok()?;
+    let buffer = image_data.frames.first()?.buffer();
+    let buffer = if buffer.width() > size || buffer.height() > size {
+        buffer.resize(size, size, FilterType::Triangle)
+    } else {
+        buffer.clone()
+    };
+    Some(buffer.into_rgba8())
+}
+
+/// The part of `cell` an image of `size` takes when it is fit into it and centered.
+pub fn fit_into(cell: Rect, size: Vec2) -> Rect {
+    let scale = (cell.width() / size.x).min(cell.height() / size.y);
+    Rect::from_center_size(cell.center(), size * scale)
+}
+
+/// Draws a thumbnail in `cell`, or an ellipsis while it is made, with a frame around the
+/// current image.
+pub fn paint_cell(
+    ui: &mut egui::Ui,
+    cell: Rect,
+    texture: Option<&TextureHandle>,
+    current: bool,
+    hovered: bool,
+) {
+    let visuals = ui.visuals().clone();
+    ui.painter()
+        .rect_filled(cell, 2.0, visuals.extreme_bg_color);
+    match texture {
+        Some(texture) => Image::new(texture.id(), texture.size_vec2())
+            .paint_at(ui, fit_into(cell, texture.size_vec2())),
+        None => {
+            ui.painter().text(
+                cell.center(),
+                egui::Align2::CENTER_CENTER,
+                "…",
+                egui::TextStyle::Body.resolve(ui.style()),
+                visuals.weak_text_color(),
+            );
+        }
+    }
+    if current {
+        ui.painter().rect_stroke(
+            cell.expand(1.0),
+            2.0,
+            Stroke::new(2.0, visuals.selection.bg_fill),
+        );
+    } else if hovered {
+        ui.painter()
+            .rect_stroke(cell, 2.0, visuals.widgets.hovered.bg_stroke);
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    /// Paths waiting for a thumbnail, the one asked for last is made first.
+    pending: Vec<PathBuf>,
+    workers: usize,
+}
+
+/// Makes thumbnails on worker threads and keeps them in the cache. They run next to the op
+/// queue instead of in it, so browsing is never refused while thumbnails are made.
+pub struct Thumbnails {
+    queue: Arc<Mutex<Queue>>,
+    cache: Arc<Cache>,
+    proxy: Proxy,
+}
+
+impl Thumbnails {
+    pub fn new(cache: Arc<Cache>, proxy: Proxy) -> Self {
+        Self {
+            queue: Arc::new(Mutex::new(Queue::default())),
+            cache,
+            proxy,
+        }
+    }
+
+    /// The thumbnail of `path` if it is made, otherwise it is queued and the window is woken
+    /// once it is there.
+    pub fn get(&self, path: &Path) -> Option<Thumbnail> {
+        if let Some(thumbnail) = self.cache.thumbnail(path) {
+            return Some(thumbnail);
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.pending.retain(|pending| pending != path);
+        queue.pending.push(path.to_path_buf());
+        if queue.workers < WORKERS {
+            queue.workers += 1;
+            let queue = self.queue.clone();
+            let cache = self.cache.clone();
+            let proxy = self.proxy.clone();
+            thread::spawn(move || loop {
+                let path = {
+                    let mut queue = queue.lock().unwrap();
+                    match queue.pending.pop() {
+                        Some(path) => path,
+                        None => {
+                            queue.workers -= 1;
+                            return;
+                        }
+                    }
+                };
+                if cache.thumbnail(&path).is_none() {
+                    let thumbnail = thumbnail(&path, SIZE).map(Arc::new);
+                    cache.put_thumbnail(path, thumbnail);
+                    let _ = proxy.send_event(UserEvent::Wake);
+                }
+            });
+        }
+        None
+    }
+}
+
+/// Thumbnails uploaded for drawing. Only the ones drawn in a frame are kept for the next.
+#[derive(Default)]
+pub struct ThumbnailTextures {
+    /// The texture with the thumbnail it was made from, to notice when that was remade.
+    textures: HashMap<PathBuf, (Arc<RgbaImage>, TextureHandle)>,
+    drawn: Vec<PathBuf>,
+}
+
+impl ThumbnailTextures {
+    /// The texture of the thumbnail of `path`, `None` while it is made or if it can not be.
+    pub fn get(
+        &mut self,
+        ctx: &egui::Context,
+        thumbnails: &Thumbnails,
+        path: &Path,
+    ) -> Option<TextureHandle> {
+        self.drawn.push(path.to_path_buf());
+        let image = match (thumbnails.get(path), self.textures.get(path)) {
+            (Some(Some(image)), Some((uploaded, texture))) if Arc::ptr_eq(&image, uploaded) => {
+                return Some(texture.clone())
+            }
+            (Some(Some(image)), _) => image,
+            (Some(None), _) => {
+                self.textures.remove(path);
+                return None;
+            }
+            // the file changed and the thumbnail is made again, the old one stays until then
+            (None, uploaded) => return uploaded.map(|(_, texture)| texture.clone()),
+        };
+        let size = [image.width() as usize, image.height() as usize];
+        let texture = ctx.load_texture(
+            path.to_string_lossy(),
+            ColorImage::from_rgba_unmultiplied(size, image.as_raw()),
+        );
+        self.textures
+            .insert(path.to_path_buf(), (image, texture.clone()));
+        Some(texture)
+    }
+
+    /// Frees the textures that were not drawn since the last call.
+    pub fn end_frame(&mut self) {
+        let drawn = std::mem::take(&mut self.drawn);
+        self.textures.retain(|path, _| drawn.contains(path));
+    }
+
+    pub fn clear(&mut self) {
+        self.textures.clear();
+        self.drawn.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::pos2;
+
+    use super::*;
+
+    #[test]
+    fn thumbnails_fit_the_size() {
+        let path = std::env::temp_dir().join(format!("simp-test-{}.png", nanoid::nanoid!()));
+        RgbaImage::new(400, 100).save(&path).unwrap();
+
+        let made = thumbnail(&path, 160).unwrap();
+        assert_eq!(made.dimensions(), (160, 40));
+        let made = thumbnail(&path, 1000).unwrap();
+        assert_eq!(made.dimensions(), (400, 100));
+        assert!(thumbnail(Path::new("does/not/exist.png"), 160).is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn thumbnails_keep_their_shape() {
+        let cell = Rect::from_min_size(pos2(10.0, 10.0), egui::vec2(64.0, 64.0));
+        let wide = fit_into(cell, egui::vec2(160.0, 40.0));
+        assert_eq!(
+            wide,
+            Rect::from_min_size(pos2(10.0, 34.0), egui::vec2(64.0, 16.0))
+        );
+        let tall = fit_into(cell, egui::vec2(10.0, 20.0));
+        assert_eq!(
+            tall,
+            Rect::from_min_size(pos2(26.0, 10.0), egui::vec2(32.0, 64.0))
+        );
+    }
+}
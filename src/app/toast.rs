@@ -0,0 +1,120 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use egui::{Align2, Frame};
+
+use super::App;
+
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+/// Toasts with a button stay longer so there is time to reach it.
+const REVEAL_TOAST_DURATION: Duration = Duration::from_secs(10);
+
+struct Toast {
+    text: String,
+    created: Instant,
+    /// A file to show in the file manager when the button is clicked.
+    reveal: Option<PathBuf>,
+}
+
+impl Toast {
+    fn duration(&self) -> Duration {
+        if self.reveal.is_some() {
+            REVEAL_TOAST_DURATION
+        } else {
+            TOAST_DURATION
+        }
+    }
+}
+
+/// Opens the file manager on the folder of `path`, with the file selected where that is possible.
+fn reveal(path: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    Command::new("explorer")
+        .arg(format!("/select,{}", path.to_string_lossy()))
+        .spawn()?;
+    #[cfg(target_os = "macos")]
+    Command::new("open").arg("-R").arg(path).spawn()?;
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Command::new("xdg-open")
+        .arg(path.parent().unwrap_or(path))
+        .spawn()?;
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.toasts.push(Toast {
+            text: text.into(),
+            created: Instant::now(),
+            reveal: None,
+        });
+    }
+
+    pub fn push_reveal(&mut self, text: impl Into<String>, path: PathBuf) {
+        self.toasts.push(Toast {
+            text: text.into(),
+            created: Instant::now(),
+            reveal: Some(path),
+        });
+    }
+
+    /// Time until the oldest toast should disappear.
+    pub fn next_expiry(&self) -> Option<Duration> {
+        self.toasts
+            .iter()
+            .map(|toast| toast.duration().saturating_sub(toast.created.elapsed()))
+            .min()
+    }
+
+    fn remove_expired(&mut self) {
+        self.toasts
+            .retain(|toast| toast.created.elapsed() < toast.duration());
+    }
+}
+
+impl App {
+    pub fn toast_ui(&mut self, ctx: &egui::Context) {
+        self.toasts.remove_expired();
+        if self.toasts.toasts.is_empty() {
+            return;
+        }
+
+        let interactable = self
+            .toasts
+            .toasts
+            .iter()
+            .any(|toast| toast.reveal.is_some());
+        let mut clicked = None;
+        egui::Area::new("toasts")
+            .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0 - self.bottom_bar_size])
+            .interactable(interactable)
+            .show(ctx, |ui| {
+                for toast in &self.toasts.toasts {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(&toast.text);
+                        if let Some(path) = &toast.reveal {
+                            if ui.button("Show in folder").clicked() {
+                                clicked = Some(path.clone());
+                            }
+                        }
+                    });
+                }
+            });
+
+        if let Some(path) = clicked {
+            if let Err(error) = reveal(&path) {
+                self.toasts
+                    .push(format!("Could not open the file manager: {}", error));
+            }
+        }
+    }
+}
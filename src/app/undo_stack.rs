@@ -1,17 +1,85 @@
 use crate::util::Image;
 
+/// What finished edits and their undo change on the image on screen. The view uploads the new
+/// frames to the GPU, the op queue tests use a stand-in that only keeps them.
+pub trait EditTarget {
+    fn rotate(&mut self, rotation: i32);
+    fn flip_horizontal(&mut self);
+    fn flip_vertical(&mut self);
+    fn swap_frames(&mut self, frames: &mut Vec<Image>);
+    fn swap_resolution(&mut self, resolution: &mut Option<(f32, f32)>);
+    fn swap_rotation(&mut self, rotation: &mut i32);
+    /// Drops the free rotation that was only shown, once it is baked into the pixels.
+    fn clear_angle_preview(&mut self);
+    /// Drops the color adjustments that were only shown, once they are baked into the pixels.
+    fn clear_color_preview(&mut self);
+    /// Drops the levels that were only shown, once they are baked into the pixels.
+    fn clear_levels_preview(&mut self);
+}
+
 pub enum UndoFrame {
     Rotate(i32),
     FlipHorizontal,
     FlipVertical,
-    Crop { frames: Vec<Image>, rotation: i32 },
+    Crop {
+        frames: Vec<Image>,
+        rotation: i32,
+    },
     Resize(Vec<Image>),
+    RotateArbitrary(Vec<Image>),
     Color(Vec<Image>),
+    Frames(Vec<Image>),
+    BakeAspect {
+        frames: Vec<Image>,
+        resolution: Option<(f32, f32)>,
+    },
+}
+
+impl UndoFrame {
+    /// Takes the target back to before the edit, or forward to after it again. The frames
+    /// and values are swapped so the same frame can go back and forth.
+    fn apply(&mut self, target: &mut impl EditTarget, forward: bool) {
+        match self {
+            UndoFrame::Rotate(rotation) => {
+                target.rotate(if forward { *rotation } else { -*rotation })
+            }
+            UndoFrame::FlipHorizontal => target.flip_horizontal(),
+            UndoFrame::FlipVertical => target.flip_vertical(),
+            UndoFrame::Crop { frames, rotation } => {
+                target.swap_frames(frames);
+                target.swap_rotation(rotation);
+            }
+            UndoFrame::Resize(frames)
+            | UndoFrame::RotateArbitrary(frames)
+            | UndoFrame::Color(frames)
+            | UndoFrame::Frames(frames) => target.swap_frames(frames),
+            UndoFrame::BakeAspect { frames, resolution } => {
+                target.swap_resolution(resolution);
+                target.swap_frames(frames);
+            }
+        }
+    }
+
+    /// The frames an applied edit replaced, `None` for edits that leave the pixels alone.
+    fn frames(&self) -> Option<&[Image]> {
+        match self {
+            UndoFrame::Rotate(_) | UndoFrame::FlipHorizontal | UndoFrame::FlipVertical => None,
+            UndoFrame::Crop { frames, .. }
+            | UndoFrame::Resize(frames)
+            | UndoFrame::RotateArbitrary(frames)
+            | UndoFrame::Color(frames)
+            | UndoFrame::Frames(frames)
+            | UndoFrame::BakeAspect { frames, .. } => Some(frames),
+        }
+    }
 }
 
 pub struct UndoStack {
     stack: Vec<UndoFrame>,
     index: usize,
+    /// How many edits were applied when the image matched its file, `None` once that
+    /// state was thrown away by an edit after undoing past it.
+    saved: Option<usize>,
 }
 
 impl UndoStack {
@@ -19,37 +87,148 @@ impl UndoStack {
         Self {
             stack: Vec::new(),
             index: 0,
+            saved: Some(0),
         }
     }
 
     pub fn clear(&mut self) {
         self.stack.clear();
         self.index = 0;
+        self.saved = Some(0);
+    }
+
+    fn applied(&self) -> usize {
+        self.stack.len() - self.index
     }
 
     pub fn push(&mut self, item: UndoFrame) {
-        self.stack.truncate(self.stack.len() - self.index);
+        if self.saved > Some(self.applied()) {
+            self.saved = None;
+        }
+        self.stack.truncate(self.applied());
         self.index = 0;
         self.stack.push(item);
     }
 
-    pub fn undo(&mut self) -> Option<&mut UndoFrame> {
+    /// Whether the image differs from its file, undoing back to where it was loaded or
+    /// saved makes it unedited again.
+    pub fn edited(&self) -> bool {
+        self.saved != Some(self.applied())
+    }
+
+    /// Marks the image as matching its file, after it was saved.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.applied());
+    }
+
+    /// The frames from before the first applied edit that changed the pixels, `None` while
+    /// the pixels are as they were loaded. The undo stack keeps them anyway, so nothing
+    /// has to hold a copy to compare against.
+    pub fn original(&self) -> Option<&[Image]> {
+        self.stack[..self.applied()]
+            .iter()
+            .find_map(UndoFrame::frames)
+    }
+
+    pub fn undo(&mut self, target: &mut impl EditTarget) {
         if self.stack.len() - self.index > 0 {
             self.index += 1;
             let index = self.stack.len() - self.index;
-            Some(&mut self.stack[index])
-        } else {
-            None
+            self.stack[index].apply(target, false);
         }
     }
 
-    pub fn redo(&mut self) -> Option<&mut UndoFrame> {
+    pub fn redo(&mut self, target: &mut impl EditTarget) {
         if self.index > 0 {
             let index = self.stack.len() - self.index;
             self.index -= 1;
-            Some(&mut self.stack[index])
-        } else {
-            None
+            self.stack[index].apply(target, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Target {
+        frames: Vec<Image>,
+        rotation: i32,
+    }
+
+    impl EditTarget for Target {
+        fn rotate(&mut self, rotation: i32) {
+            self.rotation += rotation;
+        }
+
+        fn flip_horizontal(&mut self) {}
+
+        fn flip_vertical(&mut self) {}
+
+        fn swap_frames(&mut self, frames: &mut Vec<Image>) {
+            std::mem::swap(&mut self.frames, frames);
+        }
+
+        fn swap_resolution(&mut self, _: &mut Option<(f32, f32)>) {}
+
+        fn swap_rotation(&mut self, rotation: &mut i32) {
+            std::mem::swap(&mut self.rotation, rotation);
         }
+
+        fn clear_angle_preview(&mut self) {}
+
+        fn clear_color_preview(&mut self) {}
+
+        fn clear_levels_preview(&mut self) {}
+    }
+
+    fn frames(width: u32) -> Vec<Image> {
+        vec![Image::from(RgbaImage::new(width, 1))]
+    }
+
+    fn width(frames: &[Image]) -> u32 {
+        frames[0].buffer().width()
+    }
+
+    #[test]
+    fn undoing_every_edit_leaves_the_image_unedited() {
+        let mut target = Target {
+            frames: frames(1),
+            ..Target::default()
+        };
+        let mut stack = UndoStack::new();
+        assert!(!stack.edited());
+
+        stack.push(UndoFrame::Rotate(1));
+        assert!(stack.edited());
+        assert!(stack.original().is_none());
+
+        let mut resized = frames(2);
+        target.swap_frames(&mut resized);
+        stack.push(UndoFrame::Resize(resized));
+        let mut colored = frames(3);
+        target.swap_frames(&mut colored);
+        stack.push(UndoFrame::Color(colored));
+        assert_eq!(width(stack.original().unwrap()), 1);
+
+        stack.undo(&mut target);
+        stack.undo(&mut target);
+        assert_eq!(width(&target.frames), 1);
+        assert!(stack.original().is_none());
+        stack.undo(&mut target);
+        assert!(!stack.edited());
+
+        stack.redo(&mut target);
+        stack.mark_saved();
+        assert!(!stack.edited());
+        stack.undo(&mut target);
+        assert!(stack.edited());
+        // an edit on top of the undone one loses the saved state for good
+        stack.push(UndoFrame::FlipVertical);
+        stack.undo(&mut target);
+        assert!(stack.edited());
     }
 }
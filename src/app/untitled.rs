@@ -0,0 +1,127 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use super::{action::Action, download, App};
+
+/// What the title and the tabs show for an image that only exists in memory.
+pub const UNTITLED: &str = "Untitled (clipboard)";
+/// The name the save dialog suggests for it.
+const UNTITLED_FILE: &str = "untitled.png";
+
+/// Why `action` can not be used on an image without a file, `None` if it can.
+pub fn needs_file(action: Action) -> Option<&'static str> {
+    match action {
+        Action::Reload => Some("A pasted image has no file to reload"),
+        Action::Delete | Action::TrashNow | Action::DeletePermanently => {
+            Some("A pasted image has no file to delete, close it instead")
+        }
+        _ => None,
+    }
+}
+
+/// The name the save dialog suggests for an image loaded from `path`, kept as it is on disk
+/// so names that are not valid UTF-8 survive until they reach the dialog.
+pub fn save_name(path: Option<&Path>) -> OsString {
+    path.and_then(Path::file_name)
+        .map_or_else(|| OsString::from(UNTITLED_FILE), |name| name.to_os_string())
+}
+
+/// Where the save dialog starts, next to the image or where the last save went.
+pub fn save_directory(path: Option<&Path>, last: Option<&Path>) -> Option<PathBuf> {
+    path.and_then(Path::parent)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .or(last)
+        .map(Path::to_path_buf)
+}
+
+impl App {
+    /// Whether the image on screen was pasted and never saved.
+    pub fn pathless(&self) -> bool {
+        self.image_view
+            .as_ref()
+            .is_some_and(|view| view.path.is_none())
+    }
+
+    /// Why `action` does nothing for the image on screen, `None` if it works.
+    pub fn file_required(&self, action: Action) -> Option<&'static str> {
+        if self.pathless() {
+            needs_file(action)
+        } else {
+            None
+        }
+    }
+
+    /// What the save dialog suggests, downloaded images keep the name they had online.
+    pub fn save_name(&self) -> OsString {
+        let view = match self.image_view.as_ref() {
+            Some(view) => view,
+            None => return save_name(None),
+        };
+        let guard = view.image_data.read().unwrap();
+        let downloaded = guard.source.as_deref().and_then(download::file_name);
+        match (&view.path, downloaded) {
+            (None, Some(name)) => OsString::from(name),
+            (path, _) => save_name(path.as_deref()),
+        }
+    }
+
+    pub fn save_directory(&self) -> Option<PathBuf> {
+        save_directory(
+            self.image_view
+                .as_ref()
+                .and_then(|view| view.path.as_deref()),
+            self.last_save_dir.as_deref(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasted_images_only_lose_the_file_actions() {
+        assert!(needs_file(Action::Reload).is_some());
+        assert!(needs_file(Action::Delete).is_some());
+        assert!(needs_file(Action::DeletePermanently).is_some());
+        for action in [
+            Action::SaveAs,
+            Action::Undo,
+            Action::Redo,
+            Action::RotateLeft,
+            Action::RotateRight,
+            Action::FlipHorizontal,
+            Action::FlipVertical,
+            Action::Resize,
+            Action::Crop,
+            Action::Copy,
+        ] {
+            assert_eq!(needs_file(action), None, "{}", action.name());
+        }
+    }
+
+    #[test]
+    fn pasted_images_are_saved_as_untitled_where_the_last_save_went() {
+        let last = Path::new("/pictures/exports");
+        assert_eq!(save_name(None), "untitled.png");
+        assert_eq!(
+            save_directory(None, Some(last)),
+            Some(PathBuf::from("/pictures/exports"))
+        );
+        assert_eq!(save_directory(None, None), None);
+
+        let path = Path::new("/pictures/cat.jpg");
+        assert_eq!(save_name(Some(path)), "cat.jpg");
+        assert_eq!(
+            save_directory(Some(path), Some(last)),
+            Some(PathBuf::from("/pictures"))
+        );
+        // a bare file name has no directory of its own
+        assert_eq!(
+            save_directory(Some(Path::new("cat.jpg")), Some(last)),
+            Some(PathBuf::from("/pictures/exports"))
+        );
+    }
+}
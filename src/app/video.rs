@@ -0,0 +1,111 @@
+use std::{io, path::Path, time::Duration};
+
+use egui::{Color32, RichText};
+
+use super::App;
+use crate::util::ImageData;
+
+const BADGE_COLOR: Color32 = Color32::from_rgb(170, 60, 60);
+
+/// A clip length the way video players show it, `1:05` or `1:02:03`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// The badge in the bottom bar for a still from a video, `None` for images.
+pub fn badge(image_data: &ImageData) -> Option<RichText> {
+    if !image_data.video {
+        return None;
+    }
+    let text = match image_data.duration {
+        Some(duration) => format!(" VIDEO {} ", format_duration(duration)),
+        None => String::from(" VIDEO "),
+    };
+    Some(
+        RichText::new(text)
+            .small()
+            .strong()
+            .color(Color32::WHITE)
+            .background_color(BADGE_COLOR),
+    )
+}
+
+/// Opens `path` in the application the system uses for it. The path is handed over as it
+/// is and never goes through a shell, file names can contain anything.
+#[cfg(windows)]
+fn open_externally(path: &Path) -> io::Result<()> {
+    use std::{ffi::OsStr, iter, os::windows::ffi::OsStrExt, ptr};
+
+    use winapi::um::{shellapi::ShellExecuteW, winuser::SW_SHOWNORMAL};
+
+    let wide = |text: &OsStr| -> Vec<u16> { text.encode_wide().chain(iter::once(0)).collect() };
+    let (verb, file) = (wide(OsStr::new("open")), wide(path.as_os_str()));
+    let result = unsafe {
+        ShellExecuteW(
+            ptr::null_mut(),
+            verb.as_ptr(),
+            file.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // anything up to 32 is an error code instead of a handle
+    if result as usize <= 32 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn open_externally(path: &Path) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener).arg(path).spawn()?;
+    Ok(())
+}
+
+impl App {
+    /// Whether the image on screen is a still from a video.
+    pub fn showing_video(&self) -> bool {
+        self.image_view
+            .as_ref()
+            .is_some_and(|view| view.image_data.read().unwrap().video)
+    }
+
+    /// Hands the video on screen to the system video player, simp only shows a still of it.
+    pub fn play_video(&mut self) {
+        if !self.showing_video() {
+            return;
+        }
+        let path = match self.image_view.as_ref().and_then(|view| view.path.as_ref()) {
+            Some(path) => path,
+            None => return,
+        };
+        if let Err(error) = open_externally(path) {
+            self.toasts
+                .push(format!("Could not open the video player: {}", error));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_read_like_a_player() {
+        assert_eq!(format_duration(Duration::from_secs_f64(4.6)), "0:04");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1:05");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+}
@@ -0,0 +1,111 @@
+use std::{path::Path, thread};
+
+use egui::{pos2, Align2, CursorIcon, Rect, Sense, Stroke, TextStyle};
+use glium::Display;
+use image::DynamicImage;
+
+use super::{
+    load_image::{load_uncached, LoadOptions},
+    App,
+};
+use crate::util::{display_name, Proxy, UserEvent};
+
+/// How far from the divider it can be grabbed, in points.
+const GRAB: f32 = 6.0;
+
+/// Asks for the image to compare with and decodes it off the main thread.
+pub fn open(proxy: Proxy, display: &Display) {
+    let dialog = rfd::AsyncFileDialog::new().set_parent(display.gl_window().window());
+    thread::spawn(move || {
+        if let Some(file) = futures_executor::block_on(dialog.pick_file()) {
+            let path = file.path().to_path_buf();
+            let event = match load_uncached(&path, LoadOptions::default()) {
+                // an animation is compared by its first frame
+                Ok(mut image_data) => {
+                    UserEvent::WipeLoaded(path, image_data.frames.swap_remove(0).image)
+                }
+                Err(error) => UserEvent::Toast(format!(
+                    "Could not open {} to compare: {}",
+                    display_name(&path),
+                    error
+                )),
+            };
+            let _ = proxy.send_event(event);
+        }
+        let _ = proxy.send_event(UserEvent::DialogClosed);
+    });
+}
+
+impl App {
+    /// Starts comparing with another image, or stops when already comparing.
+    pub fn toggle_wipe(&mut self, display: &Display) {
+        match self.image_view.as_mut() {
+            Some(view) if view.wiping() => view.clear_wipe(),
+            Some(_) if !self.dialog_open => {
+                self.dialog_open = true;
+                open(self.proxy.clone(), display);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn wipe_loaded(&mut self, display: &Display, path: &Path, image: &DynamicImage) {
+        let name = display_name(path);
+        if let Some(view) = self.image_view.as_mut() {
+            if !view.set_wipe(display, image, name.clone()) {
+                let size = view.true_size();
+                self.toasts.push(format!(
+                    "{} is {} x {}, only an image of the same size as this {} x {} can be compared",
+                    name,
+                    image.width(),
+                    image.height(),
+                    size.x(),
+                    size.y()
+                ));
+            }
+        }
+    }
+
+    /// The divider between the two images, it has to take the drag before the image does.
+    pub fn wipe_ui(&mut self, ui: &mut egui::Ui) {
+        let view = match self.image_view.as_mut() {
+            Some(view) if view.wiping() => view,
+            _ => return,
+        };
+        let screen = ui.ctx().input().screen_rect();
+        let area = ui.max_rect();
+        let x = screen.left() + view.wipe_split * screen.width();
+        let grab = Rect::from_x_y_ranges(x - GRAB..=x + GRAB, area.y_range());
+        let response = ui
+            .interact(grab, ui.id().with("wipe"), Sense::drag())
+            .on_hover_cursor(CursorIcon::ResizeHorizontal);
+        if let (true, Some(pointer)) = (response.dragged(), response.interact_pointer_pos()) {
+            view.wipe_split = ((pointer.x - screen.left()) / screen.width()).clamp(0.0, 1.0);
+        }
+
+        let x = screen.left() + view.wipe_split * screen.width();
+        let painter = ui.painter();
+        let visuals = ui.visuals();
+        let stroke = if response.hovered() || response.dragged() {
+            Stroke::new(2.0, visuals.selection.bg_fill)
+        } else {
+            Stroke::new(2.0, visuals.strong_text_color())
+        };
+        painter.line_segment([pos2(x, area.top()), pos2(x, area.bottom())], stroke);
+        painter.circle(
+            pos2(x, area.center().y),
+            GRAB + 2.0,
+            visuals.extreme_bg_color,
+            stroke,
+        );
+        if let Some(name) = view.wipe_name() {
+            painter.text(
+                pos2(x + GRAB, area.top() + GRAB),
+                Align2::LEFT_TOP,
+                name,
+                TextStyle::Small.resolve(ui.style()),
+                visuals.strong_text_color(),
+            );
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::path::{Path, PathBuf};
+
+use image::{imageops::FilterType, GenericImageView};
+
+use crate::{
+    app::{
+        load_image::{load_uncached, LoadOptions},
+        resize::resized,
+        save_conflict::free_path,
+        save_image::{format_for, write, SaveFormat, FORMATS},
+    },
+    image_io::save::Encoding,
+    vec2::Vec2,
+};
+
+const USAGE: &str =
+    "Usage: simp convert <file>... --format <format> [--resize <50% | 800x600 | 800x | x600>] [--out <dir>]";
+
+/// How the converted images are sized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Size {
+    Percent(f32),
+    /// A missing side keeps the aspect ratio.
+    Exact(Option<u32>, Option<u32>),
+}
+
+impl Size {
+    fn parse(text: &str) -> Option<Self> {
+        let positive = |text: &str| text.parse().ok().filter(|&side: &u32| side > 0);
+        if let Some(percent) = text.strip_suffix('%') {
+            let percent: f32 = percent.parse().ok()?;
+            return (percent > 0.0 && percent.is_finite()).then_some(Size::Percent(percent));
+        }
+        let (width, height) = text.split_once(['x', 'X'])?;
+        let width = (!width.is_empty()).then(|| positive(width));
+        let height = (!height.is_empty()).then(|| positive(height));
+        match (width, height) {
+            (None, None) | (Some(None), _) | (_, Some(None)) => None,
+            (width, height) => Some(Size::Exact(width.flatten(), height.flatten())),
+        }
+    }
+
+    fn apply(self, (width, height): (u32, u32)) -> Vec2<u32> {
+        let scaled = |side: u32, scale: f32| ((side as f32 * scale).round() as u32).max(1);
+        match self {
+            Size::Percent(percent) => Vec2::new(
+                scaled(width, percent / 100.0),
+                scaled(height, percent / 100.0),
+            ),
+            Size::Exact(Some(new_width), Some(new_height)) => Vec2::new(new_width, new_height),
+            Size::Exact(Some(new_width), None) => {
+                Vec2::new(new_width, scaled(height, new_width as f32 / width as f32))
+            }
+            Size::Exact(None, Some(new_height)) => {
+                Vec2::new(scaled(width, new_height as f32 / height as f32), new_height)
+            }
+            Size::Exact(None, None) => Vec2::new(width, height),
+        }
+    }
+}
+
+struct Job {
+    inputs: Vec<PathBuf>,
+    format: &'static SaveFormat,
+    size: Option<Size>,
+    out: Option<PathBuf>,
+}
+
+/// The format named by an extension like `webp` or a name like `JPEG`, ignoring case.
+fn find_format(name: &str) -> Option<&'static SaveFormat> {
+    let name = name.trim_start_matches('.').to_lowercase();
+    format_for(&name).or_else(|| {
+        FORMATS
+            .iter()
+            .find(|format| format.name.eq_ignore_ascii_case(&name))
+    })
+}
+
+fn parse(args: &[String]) -> Result<Job, String> {
+    let mut inputs = Vec::new();
+    let mut format = None;
+    let mut size = None;
+    let mut out = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--format" => {
+                let name = value()?;
+                let found = find_format(name)
+                    .ok_or_else(|| format!("simp can not save {} images", name))?;
+                format = Some(found);
+            }
+            "--resize" => {
+                let text = value()?;
+                let parsed = Size::parse(text).ok_or_else(|| format!("invalid size {}", text))?;
+                size = Some(parsed);
+            }
+            "--out" => out = Some(PathBuf::from(value()?)),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err(String::from("no files to convert"));
+    }
+    Ok(Job {
+        inputs,
+        format: format.ok_or_else(|| String::from("--format is required"))?,
+        size,
+        out,
+    })
+}
+
+/// Where `input` is written, next to it or in `out`, numbered instead of replacing a file.
+fn output_path(input: &Path, format: &SaveFormat, out: Option<&Path>) -> PathBuf {
+    let directory = match out {
+        Some(out) => out.to_path_buf(),
+        None => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let stem = input.file_stem().unwrap_or_default();
+    let path = directory.join(stem).with_extension(format.extensions[0]);
+    free_path(&path, Path::exists)
+}
+
+fn convert(input: &Path, job: &Job) -> Result<PathBuf, String> {
+    let image_data =
+        load_uncached(input, LoadOptions::default()).map_err(|error| error.to_string())?;
+    let mut frames = image_data.frames;
+    if !job.format.animation {
+        frames.truncate(1);
+    }
+    if let Some(size) = job.size {
+        let new_size = size.apply(frames[0].buffer().dimensions());
+        frames = frames
+            .iter()
+            .map(|frame| resized(frame, new_size, FilterType::Lanczos3))
+            .collect();
+    }
+
+    let path = output_path(input, job.format, job.out.as_deref());
+    write(
+        path.clone(),
+        job.format,
+        frames,
+        Encoding::default(),
+        image_data.loop_count,
+        image_data.resolution,
+    )?;
+    Ok(path)
+}
+
+/// Converts every file given after `convert` without opening a window and returns the exit
+/// code. Files that fail are reported and the rest are still converted.
+pub fn run(args: &[String]) -> i32 {
+    let job = match parse(args) {
+        Ok(job) => job,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            return 2;
+        }
+    };
+    if let Some(out) = &job.out {
+        if let Err(error) = std::fs::create_dir_all(out) {
+            eprintln!("{}: {}", out.to_string_lossy(), error);
+            return 1;
+        }
+    }
+
+    let mut code = 0;
+    for input in &job.inputs {
+        match convert(input, &job) {
+            Ok(path) => println!("{} -> {}", input.to_string_lossy(), path.to_string_lossy()),
+            Err(error) => {
+                eprintln!("{}: {}", input.to_string_lossy(), error);
+                code = 1;
+            }
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use image::{DynamicImage, RgbaImage};
+
+    use super::*;
+    use crate::{image_io::save::png, util::Image};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn sizes_keep_the_aspect_ratio_unless_both_sides_are_given() {
+        assert_eq!(Size::parse("50%"), Some(Size::Percent(50.0)));
+        assert_eq!(
+            Size::parse("800x600"),
+            Some(Size::Exact(Some(800), Some(600)))
+        );
+        assert_eq!(Size::parse("800x"), Some(Size::Exact(Some(800), None)));
+        assert_eq!(Size::parse("x600"), Some(Size::Exact(None, Some(600))));
+        for invalid in ["x", "0x10", "-5%", "0%", "800", "axb", "10x-1"] {
+            assert_eq!(Size::parse(invalid), None, "{}", invalid);
+        }
+
+        assert_eq!(Size::Percent(50.0).apply((101, 40)), Vec2::new(51, 20));
+        assert_eq!(Size::Percent(1.0).apply((10, 10)), Vec2::new(1, 1));
+        assert_eq!(
+            Size::Exact(Some(50), None).apply((200, 100)),
+            Vec2::new(50, 25)
+        );
+        assert_eq!(
+            Size::Exact(None, Some(50)).apply((200, 100)),
+            Vec2::new(100, 50)
+        );
+        assert_eq!(
+            Size::Exact(Some(3), Some(4)).apply((200, 100)),
+            Vec2::new(3, 4)
+        );
+    }
+
+    #[test]
+    fn arguments_need_files_and_a_known_format() {
+        let job = parse(&args("a.png b.jpg --format WEBP --resize 50% --out small")).unwrap();
+        assert_eq!(job.inputs, [PathBuf::from("a.png"), PathBuf::from("b.jpg")]);
+        assert_eq!(job.format.name, "WEBP");
+        assert_eq!(job.size, Some(Size::Percent(50.0)));
+        assert_eq!(job.out, Some(PathBuf::from("small")));
+        assert_eq!(
+            parse(&args("a.png --format jpeg")).unwrap().format.name,
+            "JPEG"
+        );
+        assert_eq!(
+            parse(&args("a.png --format .tif")).unwrap().format.name,
+            "TIFF"
+        );
+
+        assert!(parse(&args("--format png")).is_err());
+        assert!(parse(&args("a.png")).is_err());
+        assert!(parse(&args("a.png --format psd")).is_err());
+        assert!(parse(&args("a.png --format")).is_err());
+        assert!(parse(&args("a.png --format png --quality 5")).is_err());
+    }
+
+    #[test]
+    fn files_are_converted_next_to_the_originals() {
+        let dir = std::env::temp_dir().join(format!("simp-test-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("photo.png");
+        let buffer = DynamicImage::ImageRgba8(RgbaImage::new(8, 4));
+        png(&input, &Image::new(buffer)).unwrap();
+        fs::write(dir.join("photo.bmp"), b"").unwrap();
+
+        let job = parse(&[
+            input.to_string_lossy().to_string(),
+            String::from("--format"),
+            String::from("bmp"),
+            String::from("--resize"),
+            String::from("x2"),
+        ])
+        .unwrap();
+        let output = convert(&input, &job).unwrap();
+        assert_eq!(output, dir.join("photo (1).bmp"));
+        let converted = image::open(&output).unwrap();
+        assert_eq!(converted.dimensions(), (4, 2));
+
+        assert!(convert(&dir.join("missing.png"), &job).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+/// Whether the image crate was built with its AVIF decoder, which needs the dav1d library.
+/// The `avif` feature turns it on. The decoder only reads the primary item, so an animated
+/// AVIF shows its still image and one without a still image does not open.
+pub const SUPPORTED: bool = cfg!(feature = "avif");
+
+const BRANDS: [&[u8]; 2] = [b"avif", b"avis"];
+
+/// Whether the bytes start with an ISO media `ftyp` box that names an AVIF brand, as the
+/// major brand or one of the compatible ones. The image crate only spots the two most common
+/// box sizes and no image sequences.
+pub fn is_avif(bytes: &[u8]) -> bool {
+    if bytes.get(4..8) != Some(b"ftyp") {
+        return false;
+    }
+    let size = match bytes.get(..4) {
+        Some(size) => u32::from_be_bytes(size.try_into().unwrap()) as usize,
+        None => return false,
+    };
+    let brands = match bytes.get(8..size.min(bytes.len())) {
+        Some(brands) => brands,
+        None => return false,
+    };
+    // the major brand, the minor version and then the compatible brands
+    brands
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(index, _)| *index != 1)
+        .any(|(_, brand)| BRANDS.contains(&brand))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let size = 16 + 4 * compatible.len() as u32;
+        let mut bytes = size.to_be_bytes().to_vec();
+        bytes.extend(b"ftyp");
+        bytes.extend(major);
+        bytes.extend([0; 4]);
+        for brand in compatible {
+            bytes.extend(*brand);
+        }
+        bytes.extend(b"\0\0\0\x08meta");
+        bytes
+    }
+
+    #[test]
+    fn brands_are_found_anywhere_in_the_box() {
+        assert!(is_avif(&ftyp(b"avif", &[b"mif1", b"miaf"])));
+        assert!(is_avif(&ftyp(b"avis", &[])));
+        assert!(is_avif(&ftyp(b"mif1", &[b"miaf", b"MA1B", b"avif"])));
+        assert!(!is_avif(&ftyp(b"heic", &[b"mif1", b"heix"])));
+        assert!(!is_avif(&ftyp(b"isom", &[b"mp41"])));
+        // the minor version is not a brand
+        assert!(!is_avif(b"\0\0\0\x10ftypmif1avif"));
+        assert!(!is_avif(b"\0\0\0\x18ftypav"));
+        assert!(!is_avif(b""));
+    }
+}
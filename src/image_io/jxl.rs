@@ -0,0 +1,104 @@
+use crate::util::Image;
+
+/// Whether JPEG XL files can be opened and saved. Opening is pure rust, saving needs the
+/// libjxl library.
+pub const SUPPORTED: bool = cfg!(feature = "jxl");
+
+const CODESTREAM: &[u8] = &[0xff, 0x0a];
+const CONTAINER: &[u8] = b"\0\0\0\x0cJXL \r\n\x87\n";
+
+/// Whether the bytes start like a bare JPEG XL codestream or one in its container.
+pub fn is_jxl(bytes: &[u8]) -> bool {
+    bytes.starts_with(CODESTREAM) || bytes.starts_with(CONTAINER)
+}
+
+/// The size from the header, without rendering any pixels.
+#[cfg(feature = "jxl")]
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let image = jxl_oxide::JxlImage::builder().read(bytes).ok()?;
+    Some((image.width(), image.height()))
+}
+
+#[cfg(not(feature = "jxl"))]
+pub fn dimensions(_bytes: &[u8]) -> Option<(u32, u32)> {
+    None
+}
+
+/// Decodes with 16 bits per channel, so deep images keep their precision. Animations come
+/// out as their first frame.
+#[cfg(feature = "jxl")]
+pub fn decode(bytes: &[u8]) -> Option<Vec<Image>> {
+    use image::{DynamicImage, ImageBuffer};
+
+    let image = jxl_oxide::JxlImage::builder().read(bytes).ok()?;
+    let render = image.render_frame(0).ok()?;
+    let mut stream = render.stream();
+    let (width, height, channels) = (stream.width(), stream.height(), stream.channels());
+    let mut samples = vec![0.0f32; width as usize * height as usize * channels as usize];
+    stream.write_to_buffer(&mut samples);
+    let samples: Vec<u16> = samples
+        .into_iter()
+        .map(|sample| (sample.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16)
+        .collect();
+
+    let image = match channels {
+        1 => DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, samples)?),
+        2 => DynamicImage::ImageLumaA16(ImageBuffer::from_raw(width, height, samples)?),
+        3 => DynamicImage::ImageRgb16(ImageBuffer::from_raw(width, height, samples)?),
+        4 => DynamicImage::ImageRgba16(ImageBuffer::from_raw(width, height, samples)?),
+        _ => return None,
+    };
+    Some(vec![Image::new(image)])
+}
+
+#[cfg(not(feature = "jxl"))]
+pub fn decode(_bytes: &[u8]) -> Option<Vec<Image>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_signatures_are_recognized() {
+        assert!(is_jxl(&[0xff, 0x0a, 0xfa, 0x1f]));
+        assert!(is_jxl(b"\0\0\0\x0cJXL \r\n\x87\n\0\0\0\x14ftypjxl "));
+        // a jpeg starts with ff d8
+        assert!(!is_jxl(&[0xff, 0xd8, 0xff, 0xe0]));
+        assert!(!is_jxl(b"\0\0\0\x0cJXL "));
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn saved_images_open_again() {
+        use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+        use crate::{
+            app::load_image::{load_bytes, LoadError, LoadOptions},
+            image_io::save,
+        };
+
+        let path = std::env::temp_dir().join(format!("simp-test-{}.jxl", nanoid::nanoid!()));
+        let pixels = RgbaImage::from_pixel(40, 30, Rgba([200, 100, 50, 128]));
+        save::jxl(&path, &Image::new(DynamicImage::ImageRgba8(pixels))).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(is_jxl(&bytes));
+        assert_eq!(dimensions(&bytes), Some((40, 30)));
+        let image_data = load_bytes(&path, &bytes, LoadOptions::default()).unwrap();
+        let image = image_data.frames[0].buffer();
+        assert_eq!(image.dimensions(), (40, 30));
+        assert_eq!(image.to_rgba8().get_pixel(3, 4), &Rgba([200, 100, 50, 128]));
+
+        let limited = LoadOptions {
+            max_pixels: Some(1000),
+            ..LoadOptions::default()
+        };
+        assert!(matches!(
+            load_bytes(&path, &bytes, limited),
+            Err(LoadError::TooLarge((40, 30), false))
+        ));
+    }
+}
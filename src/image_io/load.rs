@@ -1,14 +1,27 @@
-use std::{io::Cursor, time::Duration};
+use std::{
+    fs::File,
+    io::{Cursor, Read},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 
 use image::{
-    codecs::gif::GifDecoder, io::Reader as ImageReader, AnimationDecoder, DynamicImage, Frame,
-    ImageBuffer, ImageFormat, Rgb, Rgba,
+    codecs::{
+        bmp::BmpDecoder, farbfeld::FarbfeldDecoder, gif::GifDecoder, hdr::HdrDecoder,
+        ico::IcoDecoder, jpeg::JpegDecoder, png::PngDecoder, pnm::PnmDecoder, tiff::TiffDecoder,
+    },
+    io::{Limits, Reader as ImageReader},
+    AnimationDecoder, ColorType, DynamicImage, Frame, ImageBuffer, ImageDecoder, ImageFormat, Rgb,
+    Rgba,
 };
 use imagepipe::{ImageSource, Pipeline};
+use jpeg_decoder::{CodingProcess, PixelFormat};
 use psd::Psd;
 use usvg::{fontdb::Database, FitTo, Options, Tree};
 
-use crate::util::Image;
+use super::avif;
+use crate::util::{Image, Palette};
 
 pub fn decode_images<T, E>(frames: T) -> Vec<Image>
 where
@@ -24,8 +37,14 @@ where
 }
 
 pub fn load_raster(bytes: &[u8]) -> Option<Vec<Image>> {
+    load_raster_with_limits(bytes, Limits::default())
+}
+
+/// Decodes with the image crate, which refuses to allocate more than `limits` allow.
+pub fn load_raster_with_limits(bytes: &[u8], mut limits: Limits) -> Option<Vec<Image>> {
     let format = match image::guess_format(bytes) {
         Ok(format) => format,
+        Err(_) if avif::is_avif(bytes) => ImageFormat::Avif,
         Err(_) => return None,
     };
 
@@ -36,6 +55,23 @@ pub fn load_raster(bytes: &[u8]) -> Option<Vec<Image>> {
             }
             None
         }
+        // the image crate would tone map it to 8 bits
+        ImageFormat::Hdr => {
+            let decoder = HdrDecoder::new(bytes).ok()?;
+            let (width, height) = (decoder.metadata().width, decoder.metadata().height);
+            limits.check_dimensions(width, height).ok()?;
+            limits
+                .reserve(width as u64 * height as u64 * 3 * std::mem::size_of::<f32>() as u64)
+                .ok()?;
+            let data = decoder
+                .read_image_hdr()
+                .ok()?
+                .into_iter()
+                .flat_map(|pixel| pixel.0)
+                .collect();
+            let buffer = ImageBuffer::from_raw(width, height, data)?;
+            Some(vec![Image::new(DynamicImage::ImageRgb32F(buffer))])
+        }
         ImageFormat::WebP => {
             if let Ok(decoder) = webp_animation::Decoder::new(bytes) {
                 let mut time = 0;
@@ -67,11 +103,324 @@ pub fn load_raster(bytes: &[u8]) -> Option<Vec<Image>> {
             }
             None
         }
-        format => match ImageReader::with_format(Cursor::new(&bytes), format).decode() {
-            Ok(image) => Some(vec![Image::new(image)]),
-            Err(_) => None,
-        },
+        format => {
+            let mut reader = ImageReader::with_format(Cursor::new(&bytes), format);
+            reader.limits(limits);
+            match reader.decode() {
+                Ok(image) => {
+                    let mut image = Image::new(image);
+                    if format == ImageFormat::Png {
+                        image.palette = png_palette(bytes);
+                    }
+                    Some(vec![image])
+                }
+                Err(_) => None,
+            }
+        }
+    }
+}
+
+/// The palette of an indexed png with the alpha from its tRNS chunk, `None` for other pngs.
+/// The image crate expands indexed pngs to rgb(a), this is read separately to save them back.
+pub fn png_palette(bytes: &[u8]) -> Option<Palette> {
+    let reader = png::Decoder::new(bytes).read_info().ok()?;
+    let info = reader.info();
+    if info.color_type != png::ColorType::Indexed {
+        return None;
+    }
+    let trns = info.trns.as_deref().unwrap_or_default();
+    let palette = info
+        .palette
+        .as_deref()?
+        .chunks_exact(3)
+        .enumerate()
+        .map(|(index, rgb)| {
+            [
+                rgb[0],
+                rgb[1],
+                rgb[2],
+                trns.get(index).copied().unwrap_or(255),
+            ]
+        })
+        .collect();
+    Some(Arc::new(palette))
+}
+
+/// How many times an animated gif or webp plays, 0 meaning forever.
+pub fn loop_count(bytes: &[u8]) -> u32 {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Gif) => gif_loop_count(bytes),
+        Ok(ImageFormat::WebP) => webp_loop_count(bytes).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Gifs store how often to repeat after the first play in the netscape extension,
+/// without it they play once.
+fn gif_loop_count(bytes: &[u8]) -> u32 {
+    const NETSCAPE: &[u8] = b"\x21\xff\x0bNETSCAPE2.0\x03\x01";
+    let start = match bytes
+        .windows(NETSCAPE.len())
+        .position(|window| window == NETSCAPE)
+    {
+        Some(position) => position + NETSCAPE.len(),
+        None => return 1,
+    };
+    match bytes.get(start..start + 2) {
+        Some(&[0, 0]) => 0,
+        Some(&[low, high]) => u16::from_le_bytes([low, high]) as u32 + 1,
+        _ => 1,
+    }
+}
+
+/// How many frames a gif or webp holds, counted from its blocks without decoding them.
+/// Every other format has one.
+pub fn frame_count(bytes: &[u8]) -> u64 {
+    let frames = match image::guess_format(bytes) {
+        Ok(ImageFormat::Gif) => gif_frame_count(bytes),
+        Ok(ImageFormat::WebP) => webp_frame_count(bytes),
+        _ => 1,
+    };
+    frames.max(1)
+}
+
+/// Counts the image descriptors of a gif, a truncated file counts the frames it has.
+fn gif_frame_count(bytes: &[u8]) -> u64 {
+    // data comes in sub-blocks led by their length and ended by an empty one
+    let skip_sub_blocks = |mut offset: usize| loop {
+        let length = *bytes.get(offset)? as usize;
+        offset += 1 + length;
+        if length == 0 {
+            return Some(offset);
+        }
+    };
+    let color_table = |flags: u8| {
+        if flags & 0x80 != 0 {
+            3 << ((flags & 7) + 1)
+        } else {
+            0
+        }
+    };
+
+    let mut frames = 0;
+    let mut offset = match bytes.get(10) {
+        Some(&flags) => 13 + color_table(flags),
+        None => return 0,
+    };
+    loop {
+        let next = match bytes.get(offset) {
+            // an extension has a label before its sub-blocks
+            Some(0x21) => skip_sub_blocks(offset + 2),
+            Some(0x2C) => {
+                frames += 1;
+                // the descriptor, its color table and the minimum code size of the data
+                bytes
+                    .get(offset + 9)
+                    .and_then(|&flags| skip_sub_blocks(offset + 11 + color_table(flags)))
+            }
+            _ => None,
+        };
+        match next {
+            Some(next) => offset = next,
+            None => return frames,
+        }
+    }
+}
+
+/// Counts the frame chunks of an animated webp.
+fn webp_frame_count(bytes: &[u8]) -> u64 {
+    let mut frames = 0;
+    let mut offset = 12;
+    while let Some(header) = bytes.get(offset..offset + 8) {
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        if &header[..4] == b"ANMF" {
+            frames += 1;
+        }
+        offset += 8 + size + (size & 1);
+    }
+    frames
+}
+
+/// Webps store the number of plays in the chunk describing the animation.
+fn webp_loop_count(bytes: &[u8]) -> Option<u32> {
+    let data = bytes.get(webp_anim_chunk(bytes)?..)?;
+    Some(u16::from_le_bytes([*data.get(4)?, *data.get(5)?]) as u32)
+}
+
+/// Where the data of the ANIM chunk of a webp starts.
+pub fn webp_anim_chunk(bytes: &[u8]) -> Option<usize> {
+    let mut offset = 12;
+    while let Some(header) = bytes.get(offset..offset + 8) {
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        if &header[..4] == b"ANIM" {
+            return Some(offset + 8);
+        }
+        // chunks are padded to an even size
+        offset += 8 + size + (size & 1);
+    }
+    None
+}
+
+/// What kind of image a file holds according to its first bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Content {
+    Raster(ImageFormat),
+    Vector,
+    Photoshop,
+}
+
+/// Guesses the content from the magic bytes alone. Tiff is left out since
+/// most camera raw formats share its header, the extension has to tell those apart.
+pub fn sniff(bytes: &[u8]) -> Option<Content> {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Tiff) => None,
+        Ok(format) => Some(Content::Raster(format)),
+        Err(_) if bytes.starts_with(b"8BPS") => Some(Content::Photoshop),
+        Err(_) if avif::is_avif(bytes) => Some(Content::Raster(ImageFormat::Avif)),
+        Err(_) => {
+            let start = &bytes[..bytes.len().min(256)];
+            let text = String::from_utf8_lossy(start);
+            let text = text.trim_start_matches('\u{feff}').trim_start();
+            (text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg")))
+                .then_some(Content::Vector)
+        }
+    }
+}
+
+fn read_start(path: &Path) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(512);
+    File::open(path)
+        .and_then(|file| file.take(512).read_to_end(&mut bytes))
+        .ok()?;
+    Some(bytes)
+}
+
+/// Sniffs a file from disk, reading only its first bytes.
+pub fn sniff_file(path: &Path) -> Option<Content> {
+    sniff(&read_start(path)?)
+}
+
+/// Whether the start of the file looks like any image, tiff and raw files included.
+/// Only reads the first bytes so it is cheap enough to run while browsing.
+pub fn probe_file(path: &Path) -> bool {
+    match read_start(path) {
+        Some(bytes) => {
+            sniff(&bytes).is_some() || matches!(image::guess_format(&bytes), Ok(ImageFormat::Tiff))
+        }
+        None => false,
+    }
+}
+
+/// The size of the image without decoding it, as far as the header tells. Files that
+/// sniff as nothing are tried as camera raw and then as tiff, which raw files are built on.
+pub fn dimensions(bytes: &[u8], content: Option<Content>) -> Option<(u32, u32)> {
+    let content = match content {
+        Some(content) => content,
+        None => {
+            return raw_dimensions(bytes).or_else(|| {
+                ImageReader::with_format(Cursor::new(bytes), ImageFormat::Tiff)
+                    .into_dimensions()
+                    .ok()
+            })
+        }
+    };
+    match content {
+        Content::Raster(format) => ImageReader::with_format(Cursor::new(bytes), format)
+            .into_dimensions()
+            .ok(),
+        Content::Vector => {
+            let tree = Tree::from_data(bytes, &Options::default().to_ref()).ok()?;
+            let size = tree.svg_node().size;
+            Some((size.width().ceil() as u32, size.height().ceil() as u32))
+        }
+        // the header starts with the signature, version and reserved bytes
+        Content::Photoshop => {
+            let height = u32::from_be_bytes(bytes.get(14..18)?.try_into().ok()?);
+            let width = u32::from_be_bytes(bytes.get(18..22)?.try_into().ok()?);
+            Some((width, height))
+        }
+    }
+}
+
+/// What can be read from the header of a single frame image without decoding the pixels.
+pub struct Header {
+    pub width: u32,
+    pub height: u32,
+    pub color: ColorType,
+}
+
+/// Reads the header of the common single frame formats. Returns `None` for everything
+/// else, including animated pngs, which then have to be decoded to be inspected.
+pub fn probe_header(bytes: &[u8]) -> Option<Header> {
+    fn header<'a>(decoder: impl ImageDecoder<'a>) -> Header {
+        let (width, height) = decoder.dimensions();
+        Header {
+            width,
+            height,
+            color: decoder.color_type(),
+        }
+    }
+
+    let format = image::guess_format(bytes).ok()?;
+    let cursor = Cursor::new(bytes);
+    match format {
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(cursor).ok()?;
+            if decoder.is_apng() {
+                return None;
+            }
+            Some(header(decoder))
+        }
+        ImageFormat::Jpeg => Some(header(JpegDecoder::new(cursor).ok()?)),
+        ImageFormat::Bmp => Some(header(BmpDecoder::new(cursor).ok()?)),
+        ImageFormat::Tiff => Some(header(TiffDecoder::new(cursor).ok()?)),
+        ImageFormat::Ico => Some(header(IcoDecoder::new(cursor).ok()?)),
+        ImageFormat::Pnm => Some(header(PnmDecoder::new(cursor).ok()?)),
+        ImageFormat::Farbfeld => Some(header(FarbfeldDecoder::new(cursor).ok()?)),
+        _ => None,
+    }
+}
+
+/// Decodes a jpeg at a reduced scale when the image is at least twice as large as `fit`.
+/// Returns the frames together with the full size of the image.
+pub fn load_jpeg_scaled(bytes: &[u8], fit: (u32, u32)) -> Option<(Vec<Image>, (u32, u32))> {
+    if image::guess_format(bytes).ok()? != ImageFormat::Jpeg {
+        return None;
+    }
+
+    let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(bytes));
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    let (full_width, full_height) = (info.width as u32, info.height as u32);
+
+    let scale = f32::min(
+        fit.0 as f32 / full_width as f32,
+        fit.1 as f32 / full_height as f32,
+    );
+    // the decoder can only do 1/2, 1/4 and 1/8 so anything less is not worth it
+    if scale > 0.5 {
+        return None;
+    }
+
+    let requested_width = (full_width as f32 * scale).ceil().max(1.0) as u16;
+    let requested_height = (full_height as f32 * scale).ceil().max(1.0) as u16;
+    let (width, height) = decoder.scale(requested_width, requested_height).ok()?;
+    if width as u32 >= full_width {
+        return None;
     }
+
+    let pixels = decoder.decode().ok()?;
+    let (width, height) = (width as u32, height as u32);
+    let image = match info.pixel_format {
+        PixelFormat::L8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, pixels)?),
+        PixelFormat::RGB24 => {
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, pixels)?)
+        }
+        // leave the rest to the full decoder so conversions stay in one place
+        PixelFormat::L16 | PixelFormat::CMYK32 => return None,
+    };
+
+    Some((vec![Image::new(image)], (full_width, full_height)))
 }
 
 pub fn load_un_detectable_raster(bytes: &[u8]) -> Option<Vec<Image>> {
@@ -82,6 +431,16 @@ pub fn load_un_detectable_raster(bytes: &[u8]) -> Option<Vec<Image>> {
 }
 
 pub fn load_svg(bytes: &[u8]) -> Option<Vec<Image>> {
+    load_svg_scaled(bytes, 1.0, None).map(|(frames, _)| frames)
+}
+
+/// Renders an svg at `zoom` times the size it declares, scaled down to at most `max_pixels`
+/// if given. Returns the frames together with the size the svg declares.
+pub fn load_svg_scaled(
+    bytes: &[u8],
+    zoom: f32,
+    max_pixels: Option<u64>,
+) -> Option<(Vec<Image>, (u32, u32))> {
     let mut fontdb = Database::new();
     fontdb.load_system_fonts();
     let options = Options {
@@ -95,12 +454,21 @@ pub fn load_svg(bytes: &[u8]) -> Option<Vec<Image>> {
     };
 
     let svg = tree.svg_node();
-    let mut pix_map =
-        tiny_skia::Pixmap::new((*svg).size.width() as u32, (*svg).size.height() as u32).unwrap();
+    let full_size = (svg.size.width() as u32, svg.size.height() as u32);
+    let pixels = full_size.0 as f64 * full_size.1 as f64 * (zoom as f64).powi(2);
+    let zoom = match max_pixels {
+        Some(max_pixels) if pixels > max_pixels as f64 => {
+            zoom * (max_pixels as f64 / pixels).sqrt() as f32
+        }
+        _ => zoom,
+    };
+    let width = ((full_size.0 as f32 * zoom) as u32).max(1);
+    let height = ((full_size.1 as f32 * zoom) as u32).max(1);
+    let mut pix_map = tiny_skia::Pixmap::new(width, height)?;
 
     resvg::render(
         &tree,
-        FitTo::Original,
+        FitTo::Zoom(zoom),
         tiny_skia::Transform::identity(),
         pix_map.as_mut(),
     )?;
@@ -108,9 +476,12 @@ pub fn load_svg(bytes: &[u8]) -> Option<Vec<Image>> {
     let width = pix_map.width();
     let height = pix_map.height();
     let data = pix_map.take();
-    Some(vec![Image::from(
-        ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data).unwrap(),
-    )])
+    Some((
+        vec![Image::from(
+            ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data).unwrap(),
+        )],
+        full_size,
+    ))
 }
 
 pub fn load_psd(bytes: &[u8]) -> Option<Vec<Image>> {
@@ -151,14 +522,138 @@ pub fn load_raw(bytes: &[u8]) -> Option<Vec<Image>> {
         image.width as u32,
         image.height as u32,
         image.data,
-    );
-
-    let image = match image {
-        Some(image) => image,
-        None => return None,
-    };
+    )?;
 
     let dyn_img = image::DynamicImage::ImageRgb8(image);
     let rgba_image: ImageBuffer<Rgba<u8>, Vec<u8>> = dyn_img.into_rgba8();
     Some(vec![Image::from(rgba_image)])
 }
+
+/// The size a camera raw file develops to, its sensor less the masked borders and turned
+/// the way the camera was held. Only the metadata is read, not the sensor data.
+pub fn raw_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    use rawloader::Orientation;
+
+    let raw = rawloader::decode_dummy(&mut Cursor::new(bytes)).ok()?;
+    let [top, right, bottom, left] = raw.crops;
+    let width = raw.width.checked_sub(left + right)? as u32;
+    let height = raw.height.checked_sub(top + bottom)? as u32;
+    match raw.orientation {
+        Orientation::Transpose
+        | Orientation::Rotate90
+        | Orientation::Transverse
+        | Orientation::Rotate270 => Some((height, width)),
+        _ => Some((width, height)),
+    }
+}
+
+/// Decodes the largest jpeg a camera raw file carries as its preview. Far faster than
+/// developing the raw data, but only as large and as processed as the camera made it.
+/// The preview is not turned upright, see `orient`.
+pub fn load_raw_preview(bytes: &[u8]) -> Option<DynamicImage> {
+    let mut largest: Option<(usize, u32)> = None;
+    let mut start = 0;
+    while let Some(offset) = bytes[start..]
+        .windows(3)
+        .position(|window| window == [0xFF, 0xD8, 0xFF])
+    {
+        let position = start + offset;
+        start = position + 3;
+        let mut decoder = jpeg_decoder::Decoder::new(&bytes[position..]);
+        if decoder.read_info().is_err() {
+            continue;
+        }
+        // the sensor data of some formats is a lossless jpeg itself
+        let info = match decoder.info() {
+            Some(info) if info.coding_process != CodingProcess::Lossless => info,
+            _ => continue,
+        };
+        let pixels = info.width as u32 * info.height as u32;
+        if largest.is_none_or(|(_, most)| pixels > most) {
+            largest = Some((position, pixels));
+        }
+    }
+    let (position, _) = largest?;
+    image::load_from_memory_with_format(&bytes[position..], ImageFormat::Jpeg).ok()
+}
+
+/// Turns an image upright the way its exif orientation says, from 1 to 8.
+pub fn orient(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Looks for things in a jpeg that the decoder only approximates.
+/// Returns an empty list for anything that is not a jpeg.
+pub fn jpeg_warnings(bytes: &[u8]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return warnings;
+    }
+
+    let mut icc_header = None;
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        match marker {
+            // padding before a marker
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            // markers without a length
+            0x01 | 0xD0..=0xD8 => {
+                pos += 2;
+                continue;
+            }
+            // start of scan or end of image, no more headers after this
+            0xD9 | 0xDA => break,
+            _ => (),
+        }
+
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let segment = match bytes.get(pos + 4..pos + 2 + length) {
+            Some(segment) => segment,
+            None => break,
+        };
+
+        match marker {
+            // start of frame, the sixth byte is the number of components
+            0xC0..=0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF if segment.get(5) == Some(&4) => {
+                warnings.push(String::from(
+                    "CMYK image was converted to RGB without color management, colors may be off",
+                ));
+            }
+            // the first chunk of an embedded icc profile holds the profile header
+            0xE2 if segment.starts_with(b"ICC_PROFILE\0") && icc_header.is_none() => {
+                icc_header = segment.get(14..);
+            }
+            _ => (),
+        }
+        pos += 2 + length;
+    }
+
+    if let Some(intent) = icc_header.and_then(|header| header.get(64..68)) {
+        let intent = match u32::from_be_bytes([intent[0], intent[1], intent[2], intent[3]]) {
+            2 => Some("saturation"),
+            3 => Some("absolute colorimetric"),
+            _ => None,
+        };
+        if let Some(intent) = intent {
+            warnings.push(format!(
+                "ICC profile uses the {} rendering intent which is not supported, colors are approximated",
+                intent
+            ));
+        }
+    }
+
+    warnings
+}
@@ -1,2 +1,5 @@
+pub mod avif;
+pub mod jxl;
 pub mod load;
 pub mod save;
+pub mod video;
@@ -1,20 +1,54 @@
 use std::{
+    collections::HashMap,
     error, fmt,
     fs::{rename, File, OpenOptions},
-    io::Write,
+    io::{Cursor, Write},
     path::{Path, PathBuf},
 };
 
 use image::{
-    codecs::{farbfeld::FarbfeldEncoder, gif::GifEncoder, tiff::TiffEncoder},
-    EncodableLayout, Frame, GenericImageView, ImageError, ImageOutputFormat,
+    codecs::{
+        farbfeld::FarbfeldEncoder,
+        gif::{GifEncoder, Repeat},
+        jpeg::{JpegEncoder, PixelDensity, PixelDensityUnit},
+        tiff::TiffEncoder,
+    },
+    ColorType, DynamicImage, EncodableLayout, Frame, GenericImageView, ImageError,
+    ImageOutputFormat,
 };
-use libwebp::WebPEncodeLosslessRGBA;
-use webp_animation::{Encoder, EncoderOptions, EncodingConfig};
+use libwebp::{WebPEncodeLosslessRGBA, WebPEncodeRGBA};
+use tiff::{
+    encoder::{colortype, Rational},
+    tags::ResolutionUnit,
+};
+use webp_animation::{Encoder, EncoderOptions, EncodingConfig, EncodingType};
 
+use super::load::webp_anim_chunk;
 use crate::util::Image;
 
-type SaveResult<T> = Result<T, SaveError>;
+pub type SaveResult<T> = Result<T, SaveError>;
+
+/// How the formats that have options are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding {
+    /// From 1 to 100, used by jpeg and lossy webp.
+    pub quality: u8,
+    /// Only webp can be saved either way.
+    pub lossless: bool,
+    /// How many times an animated gif or webp plays, 0 forever.
+    /// `None` keeps the count the image was loaded with.
+    pub loop_count: Option<u32>,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            quality: 100,
+            lossless: true,
+            loop_count: None,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum SaveError {
@@ -22,6 +56,10 @@ pub enum SaveError {
     Io(std::io::Error),
     WebpAnimation(webp_animation::Error),
     LibWebp(libwebp::error::WebPSimpleError),
+    Tiff(tiff::TiffError),
+    Png(png::EncodingError),
+    #[cfg(feature = "jxl")]
+    Jxl(jpegxl_rs::EncodeError),
 }
 
 impl fmt::Display for SaveError {
@@ -30,8 +68,12 @@ impl fmt::Display for SaveError {
         match *self {
             SaveError::Image(ref e) => e.fmt(f),
             SaveError::Io(ref e) => e.fmt(f),
-            SaveError::WebpAnimation(_) => write!(f, "error encoding webp"),
+            SaveError::WebpAnimation(ref e) => write!(f, "error encoding webp: {:?}", e),
             SaveError::LibWebp(ref e) => e.fmt(f),
+            SaveError::Tiff(ref e) => e.fmt(f),
+            SaveError::Png(ref e) => e.fmt(f),
+            #[cfg(feature = "jxl")]
+            SaveError::Jxl(ref e) => e.fmt(f),
         }
     }
 }
@@ -44,6 +86,10 @@ impl error::Error for SaveError {
             SaveError::Io(ref e) => Some(e),
             SaveError::WebpAnimation(_) => None,
             SaveError::LibWebp(ref e) => Some(e),
+            SaveError::Tiff(ref e) => Some(e),
+            SaveError::Png(ref e) => Some(e),
+            #[cfg(feature = "jxl")]
+            SaveError::Jxl(ref e) => Some(e),
         }
     }
 }
@@ -76,6 +122,28 @@ impl From<libwebp::error::WebPSimpleError> for SaveError {
     }
 }
 
+impl From<tiff::TiffError> for SaveError {
+    #[inline]
+    fn from(err: tiff::TiffError) -> SaveError {
+        SaveError::Tiff(err)
+    }
+}
+
+impl From<png::EncodingError> for SaveError {
+    #[inline]
+    fn from(err: png::EncodingError) -> SaveError {
+        SaveError::Png(err)
+    }
+}
+
+#[cfg(feature = "jxl")]
+impl From<jpegxl_rs::EncodeError> for SaveError {
+    #[inline]
+    fn from(err: jpegxl_rs::EncodeError) -> SaveError {
+        SaveError::Jxl(err)
+    }
+}
+
 fn open_file(path: impl AsRef<Path>) -> Result<File, std::io::Error> {
     OpenOptions::new()
         .write(true)
@@ -92,6 +160,14 @@ fn get_temp_path(path: impl AsRef<Path>) -> PathBuf {
     buf
 }
 
+fn write_file(path: impl AsRef<Path>, bytes: &[u8]) -> SaveResult<()> {
+    let temp_path = get_temp_path(path.as_ref());
+    let mut file = open_file(&temp_path)?;
+    file.write_all(bytes)?;
+
+    Ok(rename(temp_path, path)?)
+}
+
 #[inline]
 pub fn save_with_format(
     path: impl AsRef<Path>,
@@ -105,6 +181,156 @@ pub fn save_with_format(
     Ok(rename(temp_path, path)?)
 }
 
+/// The palette index of every pixel, `None` if some pixel is not in the palette
+/// because an edit added colors.
+fn palette_indices(buffer: &DynamicImage, palette: &[[u8; 4]]) -> Option<Vec<u8>> {
+    if palette.len() > 256 || !matches!(buffer.color(), ColorType::Rgb8 | ColorType::Rgba8) {
+        return None;
+    }
+    // the first index wins for colors that are in the palette twice
+    let mut lookup = HashMap::with_capacity(palette.len());
+    for (index, color) in palette.iter().enumerate().rev() {
+        lookup.insert(*color, index as u8);
+    }
+    buffer
+        .to_rgba8()
+        .pixels()
+        .map(|pixel| lookup.get(&pixel.0).copied())
+        .collect()
+}
+
+/// Encodes a png in the color type of the source, indexed if the image still has its
+/// palette and no edit added colors to it.
+pub fn encode_png(image: &Image) -> SaveResult<Vec<u8>> {
+    let buffer = image.buffer();
+    let mut bytes = Vec::new();
+    let indexed = image
+        .palette
+        .as_ref()
+        .and_then(|palette| Some((palette, palette_indices(buffer, palette)?)));
+
+    match indexed {
+        Some((palette, indices)) => {
+            let mut encoder = png::Encoder::new(&mut bytes, buffer.width(), buffer.height());
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_palette(
+                palette
+                    .iter()
+                    .flat_map(|color| [color[0], color[1], color[2]])
+                    .collect::<Vec<u8>>(),
+            );
+            // trailing opaque entries can be left out of tRNS
+            let alpha: Vec<u8> = palette.iter().map(|color| color[3]).collect();
+            let used = alpha
+                .iter()
+                .rposition(|&a| a != 255)
+                .map_or(0, |last| last + 1);
+            if used > 0 {
+                encoder.set_trns(alpha[..used].to_vec());
+            }
+            encoder.write_header()?.write_image_data(&indices)?;
+        }
+        None => match sixteen_bit_samples(buffer) {
+            Some((color, samples)) => {
+                let mut encoder = png::Encoder::new(&mut bytes, buffer.width(), buffer.height());
+                encoder.set_color(color);
+                encoder.set_depth(png::BitDepth::Sixteen);
+                let data: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+                encoder.write_header()?.write_image_data(&data)?;
+            }
+            None => buffer.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?,
+        },
+    }
+    Ok(bytes)
+}
+
+/// The samples of a 16 bit image with its png color type. These are written with the png
+/// crate because the image crate encoder gets their byte order wrong.
+fn sixteen_bit_samples(buffer: &DynamicImage) -> Option<(png::ColorType, &[u16])> {
+    match buffer {
+        DynamicImage::ImageLuma16(buffer) => Some((png::ColorType::Grayscale, buffer.as_raw())),
+        DynamicImage::ImageLumaA16(buffer) => {
+            Some((png::ColorType::GrayscaleAlpha, buffer.as_raw()))
+        }
+        DynamicImage::ImageRgb16(buffer) => Some((png::ColorType::Rgb, buffer.as_raw())),
+        DynamicImage::ImageRgba16(buffer) => Some((png::ColorType::Rgba, buffer.as_raw())),
+        _ => None,
+    }
+}
+
+#[inline]
+pub fn png(path: impl AsRef<Path>, image: &Image) -> SaveResult<()> {
+    write_file(path, &encode_png(image)?)
+}
+
+/// Adds a pHYs chunk with `resolution` in dots per inch right after the header of a png.
+fn set_png_resolution(png: &mut Vec<u8>, resolution: (f32, f32)) {
+    // the signature and the IHDR chunk always take the first 33 bytes
+    const HEADER_END: usize = 8 + 12 + 13;
+    let per_meter = |dpi: f32| (dpi / 0.0254).round() as u32;
+
+    let mut chunk = Vec::with_capacity(21);
+    chunk.extend_from_slice(&9u32.to_be_bytes());
+    chunk.extend_from_slice(b"pHYs");
+    chunk.extend_from_slice(&per_meter(resolution.0).to_be_bytes());
+    chunk.extend_from_slice(&per_meter(resolution.1).to_be_bytes());
+    chunk.push(1);
+    let crc = crc32fast::hash(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+    png.splice(HEADER_END..HEADER_END, chunk);
+}
+
+pub fn png_with_resolution(
+    path: impl AsRef<Path>,
+    image: &Image,
+    resolution: (f32, f32),
+) -> SaveResult<()> {
+    let mut bytes = encode_png(image)?;
+    set_png_resolution(&mut bytes, resolution);
+    write_file(path, &bytes)
+}
+
+/// Compresses losslessly with libjxl, images with more than 8 bits per channel keep 16.
+#[cfg(feature = "jxl")]
+pub fn jxl(path: impl AsRef<Path>, image: &Image) -> SaveResult<()> {
+    use jpegxl_rs::encoder_builder;
+
+    let buffer = image.buffer();
+    let (width, height) = buffer.dimensions();
+    let alpha = buffer.color().has_alpha();
+    let deep = buffer.color().bytes_per_pixel() > buffer.color().channel_count();
+    let mut encoder = encoder_builder()
+        .lossless(true)
+        .uses_original_profile(true)
+        .has_alpha(alpha)
+        .build()?;
+
+    let bytes = match (deep, alpha) {
+        (false, false) => {
+            encoder
+                .encode::<u8, u8>(buffer.to_rgb8().as_raw(), width, height)?
+                .data
+        }
+        (false, true) => {
+            encoder
+                .encode::<u8, u8>(buffer.to_rgba8().as_raw(), width, height)?
+                .data
+        }
+        (true, false) => {
+            encoder
+                .encode::<u16, u16>(buffer.to_rgb16().as_raw(), width, height)?
+                .data
+        }
+        (true, true) => {
+            encoder
+                .encode::<u16, u16>(buffer.to_rgba16().as_raw(), width, height)?
+                .data
+        }
+    };
+    write_file(path, &bytes)
+}
+
 #[inline]
 pub fn tiff(path: impl AsRef<Path>, image: &Image) -> SaveResult<()> {
     let temp_path = get_temp_path(path.as_ref());
@@ -123,13 +349,48 @@ pub fn tiff(path: impl AsRef<Path>, image: &Image) -> SaveResult<()> {
     Ok(rename(temp_path, path)?)
 }
 
+/// Writes a tiff that keeps the original resolution tags so
+/// images with non square pixels are still displayed correctly.
+pub fn tiff_with_resolution(
+    path: impl AsRef<Path>,
+    image: &Image,
+    resolution: (f32, f32),
+) -> SaveResult<()> {
+    let temp_path = get_temp_path(path.as_ref());
+    let file = open_file(&temp_path)?;
+
+    let rational = |dpi: f32| Rational {
+        n: (dpi * 100.0).round() as u32,
+        d: 100,
+    };
+
+    let buffer = image.buffer().to_rgba8();
+    let mut encoder = tiff::encoder::TiffEncoder::new(file)?;
+    let mut tiff = encoder.new_image::<colortype::RGBA8>(buffer.width(), buffer.height())?;
+    tiff.resolution_unit(ResolutionUnit::Inch);
+    tiff.x_resolution(rational(resolution.0));
+    tiff.y_resolution(rational(resolution.1));
+    tiff.write_data(buffer.as_raw())?;
+
+    Ok(rename(temp_path, path)?)
+}
+
+/// The netscape extension counts repeats after the first play, a single play leaves it out.
+fn gif_repeat(loop_count: u32) -> Repeat {
+    match loop_count {
+        0 => Repeat::Infinite,
+        plays => Repeat::Finite((plays - 1).min(u16::MAX as u32) as u16),
+    }
+}
+
 #[inline]
-pub fn gif(path: impl AsRef<Path>, images: Vec<Image>) -> SaveResult<()> {
+pub fn gif(path: impl AsRef<Path>, images: Vec<Image>, loop_count: u32) -> SaveResult<()> {
     let temp_path = get_temp_path(path.as_ref());
     let file = open_file(&temp_path)?;
 
     let frames: Vec<Frame> = images.into_iter().map(|image| image.into()).collect();
     let mut encoder = GifEncoder::new(file);
+    encoder.set_repeat(gif_repeat(loop_count))?;
     encoder.encode_frames(frames)?;
 
     Ok(rename(temp_path, path)?)
@@ -149,12 +410,33 @@ pub fn farbfeld(path: impl AsRef<Path>, image: &Image) -> SaveResult<()> {
     Ok(rename(temp_path, path)?)
 }
 
+/// The encoder always writes 0, the count sits after the background color.
+fn set_webp_loop_count(webp_data: &mut [u8], loop_count: u32) {
+    if let Some(anim) = webp_anim_chunk(webp_data) {
+        let count = (loop_count.min(u16::MAX as u32) as u16).to_le_bytes();
+        webp_data[anim + 4..anim + 6].copy_from_slice(&count);
+    }
+}
+
 #[inline]
-pub fn webp_animation(path: impl AsRef<Path>, images: Vec<Image>) -> SaveResult<()> {
-    let config = EncodingConfig {
-        encoding_type: webp_animation::prelude::EncodingType::Lossless,
-        quality: 100.0,
-        method: 6,
+pub fn webp_animation(
+    path: impl AsRef<Path>,
+    images: Vec<Image>,
+    encoding: Encoding,
+    loop_count: u32,
+) -> SaveResult<()> {
+    let config = if encoding.lossless {
+        EncodingConfig {
+            encoding_type: EncodingType::Lossless,
+            quality: 100.0,
+            method: 6,
+        }
+    } else {
+        EncodingConfig {
+            encoding_type: EncodingType::new_lossy(),
+            quality: encoding.quality as f32,
+            method: 6,
+        }
     };
     let dimensions = images[0].buffer().dimensions();
     let options = EncoderOptions {
@@ -168,28 +450,171 @@ pub fn webp_animation(path: impl AsRef<Path>, images: Vec<Image>) -> SaveResult<
         timestamp += image.delay.as_millis() as i32;
     }
 
-    let webp_data = encoder.finalize(timestamp)?;
-
-    let temp_path = get_temp_path(path.as_ref());
-    let mut file = open_file(&temp_path)?;
-    file.write_all(&*webp_data)?;
+    let mut webp_data = encoder.finalize(timestamp)?.to_vec();
+    set_webp_loop_count(&mut webp_data, loop_count);
+    write_file(path, &webp_data)
+}
 
-    Ok(rename(temp_path, path)?)
+pub fn encode_webp(buffer: &DynamicImage, encoding: Encoding) -> SaveResult<Vec<u8>> {
+    let (width, height) = buffer.dimensions();
+    let rgba = buffer.to_rgba8().into_raw();
+    let webp_data = if encoding.lossless {
+        WebPEncodeLosslessRGBA(&rgba, width, height, width * 4)?
+    } else {
+        WebPEncodeRGBA(&rgba, width, height, width * 4, encoding.quality as f32)?
+    };
+    Ok(webp_data.to_vec())
 }
 
 #[inline]
-pub fn webp(path: impl AsRef<Path>, image: &Image) -> SaveResult<()> {
-    let (width, height) = image.buffer().dimensions();
-    let webp_data = WebPEncodeLosslessRGBA(
-        &image.buffer().to_rgba8().into_raw(),
-        width,
-        height,
-        width * 4,
+pub fn webp(path: impl AsRef<Path>, image: &Image, encoding: Encoding) -> SaveResult<()> {
+    write_file(path, &encode_webp(image.buffer(), encoding)?)
+}
+
+pub fn encode_jpeg(buffer: &DynamicImage, quality: u8) -> SaveResult<Vec<u8>> {
+    encode_jpeg_with_resolution(buffer, quality, None)
+}
+
+/// Encodes a jpeg whose JFIF header has `resolution` in dots per inch. Without one it
+/// only says the pixels are square.
+fn encode_jpeg_with_resolution(
+    buffer: &DynamicImage,
+    quality: u8,
+    resolution: Option<(f32, f32)>,
+) -> SaveResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut bytes, quality);
+    if let Some((x, y)) = resolution {
+        let density = |dpi: f32| dpi.round().clamp(1.0, u16::MAX as f32) as u16;
+        encoder.set_pixel_density(PixelDensity {
+            density: (density(x), density(y)),
+            unit: PixelDensityUnit::Inches,
+        });
+    }
+    encoder.encode(
+        buffer.as_bytes(),
+        buffer.width(),
+        buffer.height(),
+        buffer.color(),
     )?;
+    Ok(bytes)
+}
 
-    let temp_path = get_temp_path(path.as_ref());
-    let mut file = open_file(&temp_path)?;
-    file.write_all(&*webp_data)?;
+/// A single frame, enough to see how the palette treats an image.
+pub fn encode_gif(buffer: &DynamicImage) -> SaveResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    buffer.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Gif)?;
+    Ok(bytes)
+}
 
-    Ok(rename(temp_path, path)?)
+#[inline]
+pub fn jpeg(path: impl AsRef<Path>, image: &Image, quality: u8) -> SaveResult<()> {
+    write_file(path, &encode_jpeg(image.buffer(), quality)?)
+}
+
+pub fn jpeg_with_resolution(
+    path: impl AsRef<Path>,
+    image: &Image,
+    quality: u8,
+    resolution: (f32, f32),
+) -> SaveResult<()> {
+    let bytes = encode_jpeg_with_resolution(image.buffer(), quality, Some(resolution))?;
+    write_file(path, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::image_io::load::{load_raster, loop_count};
+
+    fn frames() -> Vec<Frame> {
+        (0..2)
+            .map(|i| {
+                Frame::new(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    image::Rgba([i * 200, 0, 0, 255]),
+                ))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gif_loop_count_survives_a_round_trip() {
+        for plays in [0, 1, 3] {
+            let mut bytes = Vec::new();
+            let mut encoder = GifEncoder::new(&mut bytes);
+            encoder.set_repeat(gif_repeat(plays)).unwrap();
+            encoder.encode_frames(frames()).unwrap();
+            drop(encoder);
+            assert_eq!(loop_count(&bytes), plays);
+        }
+    }
+
+    #[test]
+    fn webp_loop_count_survives_a_round_trip() {
+        let mut encoder = Encoder::new((4, 4)).unwrap();
+        for (i, frame) in frames().into_iter().enumerate() {
+            encoder
+                .add_frame(frame.buffer().as_raw(), i as i32 * 100)
+                .unwrap();
+        }
+        let mut bytes = encoder.finalize(200).unwrap().to_vec();
+        assert_eq!(loop_count(&bytes), 0);
+        set_webp_loop_count(&mut bytes, 3);
+        assert_eq!(loop_count(&bytes), 3);
+    }
+
+    #[test]
+    fn indexed_pngs_are_saved_indexed_until_an_edit_adds_colors() {
+        let palette = vec![[0, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 0]];
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 3, 1);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(vec![0, 0, 0, 255, 0, 0, 0, 0, 255]);
+        encoder.set_trns(vec![255, 255, 0]);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[2, 1, 0])
+            .unwrap();
+
+        let image = load_raster(&bytes).unwrap().remove(0);
+        assert_eq!(image.palette.as_deref(), Some(&palette));
+        assert_eq!(image.color_name(), "RGBA 8-bit, from 3 palette colors");
+
+        let saved = load_raster(&encode_png(&image).unwrap()).unwrap().remove(0);
+        assert_eq!(saved.palette.as_deref(), Some(&palette));
+        assert_eq!(saved.buffer().to_rgba8(), image.buffer().to_rgba8());
+
+        let mut edited = image.clone();
+        let mut pixels = edited.buffer().to_rgba8();
+        pixels.put_pixel(0, 0, image::Rgba([0, 255, 0, 255]));
+        *edited.buffer_mut() = DynamicImage::ImageRgba8(pixels);
+        let saved = load_raster(&encode_png(&edited).unwrap())
+            .unwrap()
+            .remove(0);
+        assert_eq!(saved.palette, None);
+        assert_eq!(saved.buffer().color(), ColorType::Rgba8);
+    }
+
+    #[test]
+    fn grayscale_pngs_stay_grayscale() {
+        for buffer in [
+            DynamicImage::ImageLuma8(image::GrayImage::from_pixel(2, 2, image::Luma([90]))),
+            DynamicImage::ImageLuma16(image::ImageBuffer::from_pixel(2, 2, image::Luma([9000]))),
+            DynamicImage::ImageLumaA16(image::ImageBuffer::from_pixel(
+                2,
+                2,
+                image::LumaA([9000, 300]),
+            )),
+        ] {
+            let image = Image::new(buffer.clone());
+            let saved = load_raster(&encode_png(&image).unwrap()).unwrap().remove(0);
+            assert_eq!(saved.buffer().color(), buffer.color());
+            assert_eq!(saved.buffer(), &buffer);
+        }
+    }
 }
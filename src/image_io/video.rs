@@ -0,0 +1,99 @@
+use std::{path::Path, time::Duration};
+
+use crate::util::Image;
+
+/// Whether a frame of video files can be shown, this needs simp to be built with ffmpeg.
+pub const SUPPORTED: bool = cfg!(feature = "video");
+
+/// How far into the clip the frame is taken, the first frames are often black.
+#[cfg(feature = "video")]
+const FRAME_AT: f64 = 1.0;
+
+/// Decodes one frame near the start of a video with ffmpeg. Seeking lands on the key
+/// frame before the wanted time, so the frame is at most that far in.
+/// Returns the frame and the length of the clip if the container knows it.
+#[cfg(feature = "video")]
+pub fn first_frame(path: &Path) -> Option<(Image, Option<Duration>)> {
+    use ffmpeg_next::{
+        codec, ffi,
+        format::{self, Pixel},
+        media::Type,
+        software::scaling::{Context as Scaler, Flags},
+        util::frame::video::Video,
+    };
+    use image::{DynamicImage, RgbaImage};
+
+    use crate::util::log;
+
+    ffmpeg_next::init().ok()?;
+    ffmpeg_next::log::set_level(ffmpeg_next::log::Level::Error);
+
+    let mut input = format::input(&path).ok()?;
+    // the container duration is in AV_TIME_BASE units, negative when unknown
+    let duration = (input.duration() > 0)
+        .then(|| Duration::from_secs_f64(input.duration() as f64 / f64::from(ffi::AV_TIME_BASE)));
+    let (index, parameters) = {
+        let stream = input.streams().best(Type::Video)?;
+        (stream.index(), stream.parameters())
+    };
+    let mut decoder = codec::context::Context::from_parameters(parameters)
+        .ok()?
+        .decoder()
+        .video()
+        .ok()?;
+
+    // clips shorter than the leader get their middle frame instead
+    let seek = duration.map_or(0.0, |duration| FRAME_AT.min(duration.as_secs_f64() / 2.0));
+    let target = (seek * f64::from(ffi::AV_TIME_BASE)) as i64;
+    if input.seek(target, ..=target).is_err() {
+        log::debug!("could not seek, taking the first frame");
+    }
+
+    let mut frame = Video::empty();
+    let mut decoded = false;
+    for (stream, packet) in input.packets() {
+        if stream.index() == index
+            && decoder.send_packet(&packet).is_ok()
+            && decoder.receive_frame(&mut frame).is_ok()
+        {
+            decoded = true;
+            break;
+        }
+    }
+    // a clip of a single frame only gives it up once the decoder is flushed
+    if !decoded {
+        decoder.send_eof().ok()?;
+        decoder.receive_frame(&mut frame).ok()?;
+    }
+
+    let (width, height) = (frame.width(), frame.height());
+    let mut scaler = Scaler::get(
+        frame.format(),
+        width,
+        height,
+        Pixel::RGBA,
+        width,
+        height,
+        Flags::BILINEAR,
+    )
+    .ok()?;
+    let mut rgba = Video::empty();
+    scaler.run(&frame, &mut rgba).ok()?;
+
+    // rows are padded to the stride
+    let row = width as usize * 4;
+    let pixels: Vec<u8> = rgba
+        .data(0)
+        .chunks(rgba.stride(0))
+        .take(height as usize)
+        .flat_map(|line| &line[..row])
+        .copied()
+        .collect();
+    let image = RgbaImage::from_raw(width, height, pixels)?;
+    Some((Image::new(DynamicImage::ImageRgba8(image)), duration))
+}
+
+#[cfg(not(feature = "video"))]
+pub fn first_frame(_path: &Path) -> Option<(Image, Option<Duration>)> {
+    None
+}
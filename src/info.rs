@@ -0,0 +1,197 @@
+use std::{fs, path::Path, time::Duration};
+
+use image::ColorType;
+use rexif::ExifTag;
+
+use crate::{
+    app::load_image::{load_bytes, LoadOptions},
+    image_io::{
+        jxl,
+        load::{probe_header, sniff, Content},
+    },
+    util::{extensions::RAW, json},
+};
+
+/// Exif fields that are printed, with the key used for them in json.
+const EXIF_FIELDS: &[(ExifTag, &str)] = &[
+    (ExifTag::DateTimeOriginal, "date_taken"),
+    (ExifTag::Make, "make"),
+    (ExifTag::Model, "model"),
+    (ExifTag::LensModel, "lens"),
+    (ExifTag::ExposureTime, "exposure"),
+    (ExifTag::FNumber, "aperture"),
+    (ExifTag::ISOSpeedRatings, "iso"),
+    (ExifTag::FocalLength, "focal_length"),
+    (ExifTag::Orientation, "orientation"),
+];
+
+pub struct Info {
+    /// What the content is, the extension only decides for formats without magic bytes.
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub color: ColorType,
+    pub frames: usize,
+    /// Total length of an animation.
+    pub duration: Option<Duration>,
+    pub file_size: u64,
+    pub exif: Vec<(ExifTag, &'static str, String)>,
+}
+
+impl Info {
+    fn bit_depth(&self) -> u16 {
+        self.color.bits_per_pixel() / self.color.channel_count() as u16
+    }
+
+    /// The value of one of the `EXIF_FIELDS`, empty when the file does not have it.
+    pub fn exif(&self, tag: ExifTag) -> &str {
+        self.exif
+            .iter()
+            .find(|(field, _, _)| *field == tag)
+            .map_or("", |(_, _, value)| value)
+    }
+}
+
+/// The name of the format in `bytes`. Camera raw files share their magic bytes with tiff
+/// so those are named by the extension.
+fn format_name(bytes: &[u8], path: &Path) -> String {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    match sniff(bytes) {
+        Some(Content::Raster(format)) => format!("{:?}", format).to_uppercase(),
+        Some(Content::Vector) => String::from("SVG"),
+        Some(Content::Photoshop) => String::from("PSD"),
+        None if jxl::is_jxl(bytes) => String::from("JXL"),
+        None if RAW.contains(&*extension) => extension.to_uppercase(),
+        None => match image::guess_format(bytes) {
+            Ok(format) => format!("{:?}", format).to_uppercase(),
+            Err(_) => extension.to_uppercase(),
+        },
+    }
+}
+
+/// Reads the details of an image, the file is read once. Pixels are only decoded for
+/// formats whose header does not say everything, like animations and raw files.
+pub fn read(path: &Path) -> Result<Info, String> {
+    let bytes = fs::read(path).map_err(|error| error.to_string())?;
+
+    let mut exif = Vec::new();
+    if let Ok(data) = rexif::parse_buffer_quiet(&bytes).0 {
+        for (tag, key) in EXIF_FIELDS {
+            if let Some(entry) = data.entries.iter().find(|entry| entry.tag == *tag) {
+                exif.push((*tag, *key, entry.value_more_readable.trim().to_string()));
+            }
+        }
+    }
+
+    let format = format_name(&bytes, path);
+    if let Some(header) = probe_header(&bytes) {
+        return Ok(Info {
+            format,
+            width: header.width,
+            height: header.height,
+            color: header.color,
+            frames: 1,
+            duration: None,
+            file_size: bytes.len() as u64,
+            exif,
+        });
+    }
+
+    let image_data =
+        load_bytes(path, &bytes, LoadOptions::default()).map_err(|error| error.to_string())?;
+    let first = image_data.frames[0].buffer();
+    let (width, height) = image_data
+        .full_size
+        .unwrap_or((first.width(), first.height()));
+    let frames = image_data.frames.len();
+
+    Ok(Info {
+        format,
+        width,
+        height,
+        color: first.color(),
+        frames,
+        duration: (frames > 1).then(|| image_data.frames.iter().map(|frame| frame.delay).sum()),
+        file_size: bytes.len() as u64,
+        exif,
+    })
+}
+
+fn human_readable(path: &Path, info: &Info) -> String {
+    let mut lines = vec![
+        path.to_string_lossy().to_string(),
+        format!("  Format:      {}", info.format),
+        format!("  Dimensions:  {} x {}", info.width, info.height),
+        format!("  Color type:  {:?}", info.color),
+        format!("  Bit depth:   {}", info.bit_depth()),
+        format!("  Frames:      {}", info.frames),
+    ];
+    if let Some(duration) = info.duration {
+        lines.push(format!("  Duration:    {:.2} s", duration.as_secs_f64()));
+    }
+    lines.push(format!("  File size:   {} bytes", info.file_size));
+    for (tag, _, value) in &info.exif {
+        lines.push(format!("  {}: {}", tag, value));
+    }
+    lines.join("\n")
+}
+
+fn to_json(path: &Path, info: &Info) -> String {
+    let mut fields = vec![
+        format!("\"path\": {}", json::string(&path.to_string_lossy())),
+        format!("\"format\": {}", json::string(&info.format)),
+        format!("\"width\": {}", info.width),
+        format!("\"height\": {}", info.height),
+        format!(
+            "\"color_type\": {}",
+            json::string(&format!("{:?}", info.color))
+        ),
+        format!("\"bit_depth\": {}", info.bit_depth()),
+        format!("\"frames\": {}", info.frames),
+        format!(
+            "\"duration_ms\": {}",
+            info.duration
+                .map_or(String::from("null"), |d| d.as_millis().to_string())
+        ),
+        format!("\"file_size\": {}", info.file_size),
+    ];
+    let exif: Vec<_> = info
+        .exif
+        .iter()
+        .map(|(_, key, value)| format!("{}: {}", json::string(key), json::string(value)))
+        .collect();
+    fields.push(format!("\"exif\": {{{}}}", exif.join(", ")));
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Prints the details of every path, one record each, and returns the exit code.
+/// With `json` every record is a json object on its own line.
+pub fn run(paths: &[String], json: bool) -> i32 {
+    if paths.is_empty() {
+        eprintln!("Usage: simp --info [--json] <file>...");
+        return 2;
+    }
+
+    let mut code = 0;
+    for (index, path) in paths.iter().enumerate() {
+        let path = Path::new(path);
+        match read(path) {
+            Ok(info) if json => println!("{}", to_json(path, &info)),
+            Ok(info) => {
+                if index > 0 {
+                    println!();
+                }
+                println!("{}", human_readable(path, &info));
+            }
+            Err(error) => {
+                eprintln!("{}: {}", path.to_string_lossy(), error);
+                code = 1;
+            }
+        }
+    }
+    code
+}
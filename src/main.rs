@@ -2,48 +2,31 @@
 #![warn(rust_2018_idioms)]
 #![warn(clippy::all)]
 
-use std::{
-    env, panic,
-    path::PathBuf,
-    time::{Duration, Instant},
-};
+use std::{env, panic, path::PathBuf, process, time::Instant};
 
 use egui_glium::EguiGlium;
 use glium::{
     glutin::{
         self,
-        event::{Event, WindowEvent},
+        event::{Event, StartCause, WindowEvent},
         event_loop::{ControlFlow, EventLoop, EventLoopProxy},
         window::WindowBuilder,
     },
     Display, Surface,
 };
-use serde::{Deserialize, Serialize};
 
 mod app;
-use app::{op_queue::Op, App};
+use app::{keymap::Keymap, placement, settings::Config, startup::Startup, App};
 mod icon;
 mod vec2;
 use vec2::Vec2;
 mod rect;
 mod util;
-use util::UserEvent;
+use util::{log, UserEvent};
+mod convert;
 mod image_io;
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Config {
-    width: f64,
-    height: f64,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            width: 1100f64,
-            height: 720f64,
-        }
-    }
-}
+mod info;
+mod single_instance;
 
 pub struct System {
     pub event_loop: EventLoop<UserEvent>,
@@ -54,11 +37,11 @@ pub struct System {
 }
 
 impl System {
-    pub fn new() -> Self {
-        let config: Config = confy::load("simp").unwrap_or_default();
-
+    pub fn new(startup: Startup, config: Config) -> Self {
         let event_loop: EventLoop<UserEvent> = EventLoop::with_user_event();
         let proxy = event_loop.create_proxy();
+        // answered while the window is made, not on the first frame
+        app::settings::detect_system_preferences(proxy.clone().into());
         let context = glutin::ContextBuilder::new()
             .with_vsync(true)
             .with_depth_buffer(0)
@@ -74,6 +57,27 @@ impl System {
 
         let display =
             Display::new(builder, context, &event_loop).expect("Failed to initialize display");
+        log::info!(
+            "opengl {} on {} by {}",
+            display.get_opengl_version_string(),
+            display.get_opengl_renderer_string(),
+            display.get_opengl_vendor_string()
+        );
+
+        placement::restore(display.gl_window().window(), &config);
+        // fullscreen goes on the monitor the window was just put back on
+        let startup = Startup {
+            fullscreen: startup.fullscreen || config.fullscreen,
+            ..startup
+        };
+
+        // clear right away so the first buffer swap is never an uninitialized white frame
+        {
+            let mut target = display.draw();
+            let (r, g, b, a) = config.settings.background.color();
+            target.clear_color_srgb(r, g, b, a);
+            target.finish().unwrap();
+        }
 
         let app = {
             let window_context = display.gl_window();
@@ -83,17 +87,18 @@ impl System {
             let size = window.inner_size();
 
             App::new(
-                proxy.clone(),
+                proxy.clone().into(),
                 [size.width as f32, size.height as f32],
                 [pos.x, pos.y],
+                config.settings,
+                Keymap::from_config(&config.keys),
+                startup,
                 &display,
             )
         };
 
         let egui = egui_glium::EguiGlium::new(&display);
 
-        display.gl_window().window().set_visible(true);
-
         let ctrl_proxy = proxy.clone();
         ctrlc::set_handler(move || {
             let _ = ctrl_proxy.send_event(UserEvent::Exit);
@@ -112,7 +117,7 @@ impl System {
 
 impl Default for System {
     fn default() -> Self {
-        Self::new()
+        Self::new(Startup::default(), Config::load())
     }
 }
 
@@ -126,75 +131,198 @@ impl System {
             ..
         } = self;
 
+        // the window is created hidden and only shown once a complete frame has been drawn
+        let mut visible = false;
+        // frames are only drawn when something asked for one, an idle window costs nothing
+        let mut dirty = true;
+
         event_loop.run(move |event, _, control_flow| {
+            // returns whether egui wants another frame right away
             let mut redraw = || {
+                // a zero sized framebuffer can not be drawn to, and animating is wasted work
+                if app.minimized() {
+                    *control_flow = ControlFlow::Wait;
+                    return false;
+                }
+
                 let needs_repaint = egui.run(&display, |egui_ctx| {
                     app.handle_ui(&display, egui_ctx);
                 });
 
+                // the delay covers animation frames, toasts, progress and every other timer
                 let (exit, delay) = app.update(&display);
                 *control_flow = if exit {
                     ControlFlow::Exit
-                } else if needs_repaint {
-                    display.gl_window().window().request_redraw();
-                    glutin::event_loop::ControlFlow::Poll
                 } else if let Some(delay) = delay {
                     ControlFlow::WaitUntil(Instant::now() + delay)
                 } else {
-                    ControlFlow::WaitUntil(Instant::now() + Duration::from_secs(1))
+                    ControlFlow::Wait
                 };
 
                 {
                     let mut target = display.draw();
 
-                    target.clear_color_srgb(0.172, 0.172, 0.172, 1.0);
+                    let (r, g, b, a) = app.settings.background.color();
+                    target.clear_color_srgb(r, g, b, a);
 
                     // draw things behind egui here
                     let dimensions = display.get_framebuffer_dimensions();
                     let size = Vec2::new(dimensions.0 as f32, dimensions.1 as f32);
                     //background.render(&mut target, size, app.top_bar_size);
 
-                    if let Some(image) = app.image_view.as_mut() {
-                        image.render(&mut target, size);
+                    // the gallery covers the image
+                    let gallery = app.gallery_visible();
+
+                    if let Some(image) = app.image_view.as_mut().filter(|_| !gallery) {
+                        image.render(
+                            &mut target,
+                            size,
+                            app.settings.checks(),
+                            app.settings.pixel_grid,
+                        );
                     }
 
                     egui.paint(&display, &mut target);
 
                     // draw things on top of egui here
-                    app.crop.render(&mut target, size);
+                    app.crop.render(
+                        &mut target,
+                        size,
+                        app.image_view.as_deref(),
+                        app.settings.crop_guides,
+                    );
 
                     target.finish().unwrap();
                 }
+
+                if !visible {
+                    display.gl_window().window().set_visible(true);
+                    visible = true;
+                }
+                needs_repaint
             };
 
-            match event {
-                Event::RedrawEventsCleared if cfg!(windows) => redraw(),
-                Event::RedrawRequested(_) if !cfg!(windows) => redraw(),
+            let wants_frame = match event {
+                Event::RedrawEventsCleared if cfg!(windows) => {
+                    if dirty {
+                        dirty = redraw();
+                    }
+                    dirty
+                }
+                Event::RedrawRequested(_) if !cfg!(windows) => {
+                    dirty = redraw();
+                    dirty
+                }
+                // the os asks for a frame, windows draws it once the events are handled.
+                // Asking for another redraw here would bring this event back forever.
+                Event::RedrawRequested(_) => {
+                    dirty = true;
+                    false
+                }
+                // a deadline from `App::update` passed
+                Event::NewEvents(StartCause::ResumeTimeReached { .. } | StartCause::Init) => true,
+                Event::Resumed => true,
                 Event::WindowEvent {
                     event: WindowEvent::CloseRequested,
                     ..
-                } => *control_flow = ControlFlow::Exit,
+                } => {
+                    if app.confirm_close() {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    // the warning toast needs a frame
+                    true
+                }
                 Event::LoopDestroyed => {
-                    let data = Config {
-                        width: app.size.x() as f64,
-                        height: app.size.y() as f64,
-                    };
-                    confy::store("simp", data).unwrap();
+                    if let Err(error) = app.save_config() {
+                        log::warn!("{}", error);
+                    }
+                    false
                 }
                 Event::WindowEvent { event, .. } => {
-                    if !egui.on_event(&event) || matches!(event, WindowEvent::MouseWheel { .. }) {
+                    let blocked = !app.accepts_input() && is_input_event(&event);
+                    if !blocked
+                        && (!egui.on_event(&event)
+                            || matches!(event, WindowEvent::MouseWheel { .. }))
+                    {
                         app.handle_window_event(&display, &event);
                     }
-                    display.gl_window().window().request_redraw();
+                    true
+                }
+                // op results, finished dialogs and wake ups from worker threads
+                Event::UserEvent(mut event) => {
+                    app.handle_user_event(&display, &mut event);
+                    true
                 }
-                Event::UserEvent(mut event) => app.handle_user_event(&display, &mut event),
-                _ => display.gl_window().window().request_redraw(),
+                // device events arrive for input anywhere on the screen and change nothing here
+                _ => false,
+            };
+
+            if wants_frame {
+                dirty = true;
+                display.gl_window().window().request_redraw();
             }
         });
     }
 }
 
+fn is_input_event(event: &WindowEvent<'_>) -> bool {
+    matches!(
+        event,
+        WindowEvent::KeyboardInput { .. }
+            | WindowEvent::ReceivedCharacter(_)
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::DroppedFile(_)
+    )
+}
+
+/// Lets `convert`, `--info` and `--verbose` print to the terminal it was started from, release builds
+/// have no console.
+#[cfg(windows)]
+fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
 fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("convert") {
+        #[cfg(windows)]
+        attach_console();
+        log::init(false);
+        process::exit(convert::run(&args[2..]));
+    }
+    if args.iter().any(|arg| arg == "--info") {
+        #[cfg(windows)]
+        attach_console();
+        let json = args.iter().any(|arg| arg == "--json");
+        let paths: Vec<String> = args
+            .into_iter()
+            .skip(1)
+            .filter(|arg| arg != "--info" && arg != "--json")
+            .collect();
+        process::exit(info::run(&paths, json));
+    }
+
+    if let Some(index) = args.iter().position(|arg| arg == "--verbose") {
+        #[cfg(windows)]
+        attach_console();
+        args.remove(index);
+        log::init(true);
+    } else {
+        log::init(false);
+    }
+
+    let startup = match Startup::from_args(&mut args) {
+        Ok(startup) => startup,
+        Err(error) => {
+            #[cfg(windows)]
+            attach_console();
+            eprintln!("{}", error);
+            process::exit(2);
+        }
+    };
+
     panic::set_hook(Box::new(|panic_info| {
         let _ = msgbox::create(
             "Error",
@@ -203,12 +331,24 @@ fn main() {
         );
     }));
 
-    let mut system = System::new();
+    let config = Config::load();
+    let single_instance = config.settings.single_instance && single_instance::SUPPORTED;
+    if single_instance && args.len() > 1 && single_instance::send(&args[1..]) {
+        return;
+    }
+
+    let mut system = System::new(startup, config);
+    if single_instance {
+        single_instance::listen(system.proxy.clone().into());
+    }
 
-    let mut args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
+    if args.len() > 2 {
+        // several paths, each gets a tab
+        let paths = args.drain(1..).map(PathBuf::from).collect();
+        system.app.open_in_tabs(paths);
+    } else if args.len() > 1 {
         if let Some(arg) = args.pop() {
-            system.app.queue(Op::LoadPath(PathBuf::from(arg), true))
+            system.app.open_path(PathBuf::from(arg), true)
         }
     }
 
@@ -53,6 +53,15 @@ impl Rect {
         self.position.y() + self.size.y()
     }
 
+    /// The same rectangle in an image that is `factor` times as large on each axis.
+    #[inline]
+    pub fn scaled(&self, factor: Vec2<f32>) -> Self {
+        Rect::new(
+            Vec2::new(self.x() * factor.x(), self.y() * factor.y()),
+            Vec2::new(self.width() * factor.x(), self.height() * factor.y()),
+        )
+    }
+
     #[inline]
     #[rustfmt::skip]
     pub fn intersects(&self, rect: &Self) -> bool {
@@ -5,10 +5,20 @@ out vec4 color;
 uniform vec2 start;
 uniform vec2 end;
 uniform vec2 size;
+// where the first composition guide is as a fraction of the selection, 0 draws none
+uniform float guide = 0.0;
 
 const vec4 background_color = vec4(0.0, 0.0, 0.0, 0.5);
 const vec4 transparent = vec4(0.0, 0.0, 0.0, 0.0);
 const vec4 line_color = vec4(0.0, 0.0, 0.0, 1.0);
+const vec4 guide_color = vec4(1.0, 1.0, 1.0, 0.6);
+
+// whether `value` is on one of the two guides between `low` and `high`
+bool onGuide(float value, float low, float high) {
+	float first = mix(low, high, guide);
+	float second = mix(high, low, guide);
+	return abs(value - first) < 0.75 || abs(value - second) < 0.75;
+}
 
 void main() {
 	float x = gl_FragCoord[0];
@@ -44,6 +54,9 @@ void main() {
 
 	if(x > start_inv.x && x < end_inv.x && y < start_inv.y && y > end_inv.y) {
 		color = transparent;
+		if(guide > 0.0 && (onGuide(x, start_inv.x, end_inv.x) || onGuide(y, end_inv.y, start_inv.y))) {
+			color = guide_color;
+		}
 	} else {
 		if(!line) {
 			color = background_color;
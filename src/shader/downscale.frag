@@ -0,0 +1,46 @@
+#version 420
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D tex;
+// log2 of how much smaller the output is than the source, 0 when enlarging
+uniform float lod;
+// false samples the mipmaps linearly, true uses a catmull-rom kernel
+uniform bool cubic;
+
+float catmullRom(float x) {
+    x = abs(x);
+    if(x < 1.0) {
+        return 1.5 * x * x * x - 2.5 * x * x + 1.0;
+    } else if(x < 2.0) {
+        return -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0;
+    }
+    return 0.0;
+}
+
+void main() {
+    if(!cubic) {
+        color = textureLod(tex, v_tex_coords, lod);
+        return;
+    }
+
+    // the kernel runs on the closest larger mipmap so it only has to cover a few texels
+    int level = int(floor(lod));
+    ivec2 size = textureSize(tex, level);
+    vec2 position = v_tex_coords * vec2(size) - 0.5;
+    ivec2 base = ivec2(floor(position));
+    vec2 fraction = position - vec2(base);
+
+    vec4 sum = vec4(0.0);
+    float total = 0.0;
+    for(int y = -1; y <= 2; y++) {
+        for(int x = -1; x <= 2; x++) {
+            float weight = catmullRom(float(x) - fraction.x) * catmullRom(float(y) - fraction.y);
+            ivec2 texel = clamp(base + ivec2(x, y), ivec2(0), size - 1);
+            sum += texelFetch(tex, texel, level) * weight;
+            total += weight;
+        }
+    }
+    color = clamp(sum / total, 0.0, 1.0);
+}
@@ -9,6 +9,34 @@ uniform float hue = 0.0;
 uniform float contrast = 0.0;
 uniform float lightness = 0.0;
 uniform float saturation = 0.0;
+// the tone of the color window, app/color.rs applies it with the same formulas
+uniform float brightness = 0.0;
+uniform float gamma = 1.0;
+uniform float stops = 0.0;
+// the white balance of the color window, what linear red, green and blue are multiplied by
+uniform vec3 gains = vec3(1.0);
+// the levels window, of every channel together and then red, green and blue, app/levels.rs
+// applies them with the same formulas
+uniform vec4 levels_black = vec4(0.0);
+uniform vec4 levels_white = vec4(1.0);
+uniform vec4 levels_gamma = vec4(1.0);
+uniform vec4 levels_low = vec4(0.0);
+uniform vec4 levels_high = vec4(1.0);
+// 0 composite, 1 alpha, 2 opaque, 3 translucent highlight, 4 red, 5 green, 6 blue
+uniform int channel_mode = 0;
+// onion skin overlay, rgb is the tint and a the opacity, 0 draws the image normally
+uniform vec4 tint = vec4(0.0);
+// 0 srgb color texture, 1 gray texture, 2 gray texture with alpha in the second channel,
+// 3 linear floating point texture
+uniform int gray = 0;
+// what linear floating point values are multiplied by, and whether they are tone mapped after
+uniform float exposure = 1.0;
+uniform int tone_map = 0;
+// the two colors of the checks behind transparent pixels, the same for a solid backdrop
+uniform vec3 check_light = vec3(64.0 / 255.0);
+uniform vec3 check_dark = vec3(48.0 / 255.0);
+// 1 draws a line between the pixels of the texture
+uniform int pixel_grid = 0;
 
 const float PI = 3.141592653589793238462643383279502884197169399375105820974944;
 const float max_value = 255;
@@ -54,6 +82,16 @@ vec3 gammaCorrection(vec3 color, float gamma) {
     return pow(color, vec3(1. / gamma));
 }
 
+// what sampling an srgb texture does, gray textures are stored as plain values
+vec3 srgbToLinear(vec3 color) {
+    return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+// https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+vec3 acesFilm(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
 vec3 inverseGamma(vec3 color, float gamma) {
     return pow(color, vec3(gamma));
 }
@@ -110,32 +148,95 @@ vec3 adjustSaturation(vec3 p, float sat) {
     return hsl2rgb(hsl);
 }
 
-vec3 getCheckColor() {
-    vec3 color1 = vec3(64, 64, 64) / max_value;
-    vec3 color2 = vec3(48, 48, 48) / max_value;
+float level(float value, int index) {
+    float range = max(levels_white[index] - levels_black[index], 1.0 / 255.0);
+    value = pow(clamp((value - levels_black[index]) / range, 0.0, 1.0), 1.0 / levels_gamma[index]);
+    return mix(levels_low[index], levels_high[index], value);
+}
+
+// gray images only have the levels of every channel
+vec3 adjustLevels(vec3 p) {
+    if(gray == 1 || gray == 2) {
+        return vec3(level(p.r, 0));
+    }
+    return vec3(level(level(p.r, 1), 0), level(level(p.g, 2), 0), level(level(p.b, 3), 0));
+}
+
+vec3 balanceWhite(vec3 p) {
+    if(gray == 1 || gray == 2) {
+        return p;
+    }
+    return clamp(p * pow(gains, vec3(1.0 / 2.2)), 0.0, 1.0);
+}
 
+vec3 adjustTone(vec3 p) {
+    p = p * exp2(stops / 2.2);
+    p = p + brightness / 100.0;
+    return clamp(pow(max(p, 0.0), vec3(1.0 / gamma)), 0.0, 1.0);
+}
+
+vec3 getCheckColor() {
     float checkSize = 12.0;
     float x = floor(gl_FragCoord[0] / checkSize);
     float y = floor((gl_FragCoord[1] - size.y) / checkSize);
 
     if(mod(x + y, 2) == 0) {
-        return color1;
+        return check_light;
     } else {
-        return color2;
+        return check_dark;
     }
 }
 
 void main() {
     vec4 p = texture(tex, v_tex_coords);
+    if(gray == 1) {
+        p = vec4(srgbToLinear(p.rrr), 1.0);
+    } else if(gray == 2) {
+        p = vec4(srgbToLinear(p.rrr), p.g);
+    } else if(gray == 3) {
+        p.rgb = max(p.rgb * exposure, 0.0);
+        p.rgb = tone_map == 1 ? acesFilm(p.rgb) : min(p.rgb, 1.0);
+        p.a = clamp(p.a, 0.0, 1.0);
+    }
     p.rgb = gammaCorrection(p.rgb, 2.2);
 
+    p.rgb = adjustLevels(p.rgb);
+    p.rgb = balanceWhite(p.rgb);
+    p.rgb = adjustTone(p.rgb);
     p.rgb = rotateHue(p.rgb, hue);
     p.rgb = adjustContrast(p.rgb, contrast);
     p.rgb = lighten(p.rgb, lightness);
     p.rgb = adjustSaturation(p.rgb, saturation);
 
+    if(tint.a > 0.0) {
+        color.rgb = inverseGamma(mix(p.rgb, tint.rgb, 0.5), 2.2);
+        color.a = p.a * tint.a;
+        return;
+    }
+
     vec3 check_color = getCheckColor();
-    color.rgb = check_color * (1 - p.a) + p.a * p.rgb;
+    if(channel_mode == 1) {
+        color.rgb = vec3(p.a);
+    } else if(channel_mode == 2) {
+        color.rgb = p.rgb;
+    } else if(channel_mode >= 4) {
+        vec3 channel = vec3(p[channel_mode - 4]);
+        color.rgb = check_color * (1 - p.a) + p.a * channel;
+    } else {
+        color.rgb = check_color * (1 - p.a) + p.a * p.rgb;
+        if(channel_mode == 3 && p.a > 0.0 && p.a < 1.0) {
+            color.rgb = mix(color.rgb, vec3(1.0, 0.0, 1.0), 0.6);
+        }
+    }
     color.rgb = inverseGamma(color.rgb, 2.2);
     color.a = 1;
+
+    if(pixel_grid == 1) {
+        // one screen pixel wide whatever the zoom, along the left and top edge of each pixel
+        vec2 texel = v_tex_coords * vec2(textureSize(tex, 0));
+        vec2 edge = fract(texel) / fwidth(texel);
+        if(min(edge.x, edge.y) < 1.0) {
+            color.rgb = mix(color.rgb, vec3(0.5), 0.4);
+        }
+    }
 }
\ No newline at end of file
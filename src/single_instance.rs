@@ -0,0 +1,261 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    app::download,
+    util::{Proxy, UserEvent},
+};
+
+/// Whether a second launch can hand its images to a running window, this needs unix sockets
+/// or named pipes.
+pub const SUPPORTED: bool = cfg!(any(unix, windows));
+
+/// Paths can not contain a nul on any platform, so it separates them in a message.
+const SEPARATOR: char = '\0';
+
+/// `arg` as the running window should see it. Relative paths are resolved here since that
+/// window has its own working directory, web addresses are passed on as they are.
+fn absolute(arg: &str) -> String {
+    if download::url(arg).is_some() || Path::new(arg).is_absolute() {
+        return arg.to_string();
+    }
+    match std::env::current_dir() {
+        Ok(dir) => dir.join(arg).to_string_lossy().to_string(),
+        Err(_) => arg.to_string(),
+    }
+}
+
+fn encode(args: &[String]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| absolute(arg)).collect();
+    args.join(&SEPARATOR.to_string())
+}
+
+fn decode(message: &str) -> Vec<PathBuf> {
+    message
+        .split(SEPARATOR)
+        .filter(|arg| !arg.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::{
+        env,
+        fs::{self, DirBuilder},
+        io::{self, Read, Write},
+        os::unix::{
+            fs::{DirBuilderExt, MetadataExt},
+            net::{UnixListener, UnixStream},
+        },
+        path::{Path, PathBuf},
+        thread,
+    };
+
+    use crate::util::{log, Proxy};
+
+    /// Creates `dir` so only the user can get into it, or checks that it already is like that.
+    fn private_dir(dir: &Path) -> io::Result<()> {
+        match DirBuilder::new().mode(0o700).create(dir) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                let metadata = fs::symlink_metadata(dir)?;
+                let uid = unsafe { libc::getuid() };
+                if metadata.is_dir() && metadata.uid() == uid && metadata.mode() & 0o077 == 0 {
+                    Ok(())
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        format!("{} is not private", dir.to_string_lossy()),
+                    ))
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// One socket per user, in the runtime directory or else in a directory in the temp
+    /// directory that only the user can get into.
+    fn socket_path() -> io::Result<PathBuf> {
+        if let Some(dir) = env::var_os("XDG_RUNTIME_DIR") {
+            return Ok(PathBuf::from(dir).join("simp.sock"));
+        }
+        let uid = unsafe { libc::getuid() };
+        let dir = env::temp_dir().join(format!("simp-{}", uid));
+        private_dir(&dir)?;
+        Ok(dir.join("simp.sock"))
+    }
+
+    pub fn send(message: &str) -> bool {
+        let path = match socket_path() {
+            Ok(path) => path,
+            Err(_) => return false,
+        };
+        match UnixStream::connect(path) {
+            Ok(mut stream) => stream.write_all(message.as_bytes()).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn listen(proxy: Proxy, received: fn(&Proxy, String)) {
+        let path = match socket_path() {
+            Ok(path) => path,
+            Err(error) => {
+                log::warn!("could not listen for other launches: {}", error);
+                return;
+            }
+        };
+        if UnixStream::connect(&path).is_ok() {
+            log::debug!("another window listens on {}", path.to_string_lossy());
+            return;
+        }
+        // nobody answered on it, so it was left behind by a window that did not exit cleanly
+        let _ = fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(error) => {
+                log::warn!("could not listen on {}: {}", path.to_string_lossy(), error);
+                return;
+            }
+        };
+        log::debug!("listening for other launches on {}", path.to_string_lossy());
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut message = String::new();
+                if stream.read_to_string(&mut message).is_ok() && !message.is_empty() {
+                    received(&proxy, message);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::{
+        env,
+        fs::{File, OpenOptions},
+        io::{Read, Write},
+        iter,
+        os::windows::io::FromRawHandle,
+        ptr, thread,
+    };
+
+    use winapi::{
+        shared::winerror::ERROR_PIPE_CONNECTED,
+        um::{
+            errhandlingapi::GetLastError,
+            handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+            namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW},
+            winbase::{
+                FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND, PIPE_READMODE_BYTE,
+                PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
+            },
+        },
+    };
+
+    use crate::util::{log, Proxy};
+
+    /// One pipe per user. Pipes get a security descriptor that only lets the user who
+    /// created them, and administrators, write to them.
+    fn pipe_name() -> String {
+        let user = env::var("USERNAME").unwrap_or_default();
+        format!(r"\\.\pipe\simp-{}", user)
+    }
+
+    pub fn send(message: &str) -> bool {
+        match OpenOptions::new().write(true).open(pipe_name()) {
+            Ok(mut pipe) => pipe.write_all(message.as_bytes()).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn listen(proxy: Proxy, received: fn(&Proxy, String)) {
+        let name: Vec<u16> = pipe_name().encode_utf16().chain(iter::once(0)).collect();
+        thread::spawn(move || loop {
+            // the first instance flag makes this fail while another window has the pipe
+            let pipe = unsafe {
+                CreateNamedPipeW(
+                    name.as_ptr(),
+                    PIPE_ACCESS_INBOUND | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                    1,
+                    0,
+                    4096,
+                    0,
+                    ptr::null_mut(),
+                )
+            };
+            if pipe == INVALID_HANDLE_VALUE {
+                log::warn!("could not listen for other launches: {}", unsafe {
+                    GetLastError()
+                });
+                return;
+            }
+            let connected = unsafe { ConnectNamedPipe(pipe, ptr::null_mut()) } != 0
+                || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+            if !connected {
+                unsafe { CloseHandle(pipe) };
+                continue;
+            }
+            // the file closes the handle, which frees the pipe for the next launch
+            let mut pipe = unsafe { File::from_raw_handle(pipe as _) };
+            let mut message = String::new();
+            if pipe.read_to_string(&mut message).is_ok() && !message.is_empty() {
+                received(&proxy, message);
+            }
+        });
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use crate::util::Proxy;
+
+    pub fn send(_message: &str) -> bool {
+        false
+    }
+
+    pub fn listen(_proxy: Proxy, _received: fn(&Proxy, String)) {}
+}
+
+/// Hands `args` to the window that is already running, returns whether there was one.
+pub fn send(args: &[String]) -> bool {
+    platform::send(&encode(args))
+}
+
+/// Opens what later launches hand over in this window.
+pub fn listen(proxy: Proxy) {
+    platform::listen(proxy, |proxy, message| {
+        let paths = decode(&message);
+        if !paths.is_empty() {
+            let _ = proxy.send_event(UserEvent::HandOff(paths));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_relative_paths_change() {
+        let dir = std::env::current_dir().unwrap();
+        assert_eq!(absolute("a.png"), dir.join("a.png").to_string_lossy());
+        assert_eq!(
+            absolute("https://example.com/a.png"),
+            "https://example.com/a.png"
+        );
+        let root = if cfg!(windows) { "C:\\a.png" } else { "/a.png" };
+        assert_eq!(absolute(root), root);
+    }
+
+    #[test]
+    fn every_path_is_handed_over() {
+        let root = if cfg!(windows) { "C:\\" } else { "/" };
+        let args = [format!("{}a b.png", root), format!("{}c.png", root)];
+        assert_eq!(
+            decode(&encode(&args)),
+            [PathBuf::from(&args[0]), PathBuf::from(&args[1])]
+        );
+        assert!(decode("").is_empty());
+    }
+}
@@ -2,6 +2,8 @@ use std::collections::HashSet;
 
 use lazy_static::*;
 
+use crate::image_io::jxl;
+
 fn create_set(input: &[&'static str]) -> HashSet<&'static str> {
     input.iter().cloned().collect()
 }
@@ -9,7 +11,7 @@ fn create_set(input: &[&'static str]) -> HashSet<&'static str> {
 lazy_static! {
     pub static ref RASTER: HashSet<&'static str> = create_set(&[
         "png", "jpg", "jpeg", "jpe", "jif", "jfif", "gif", "bmp", "ico", "tiff", "webp", "avif",
-        "pnm", "pbm", "pgm", "ppm", "pam", "dds", "tga", "ff", "farbfeld"
+        "pnm", "pbm", "pgm", "ppm", "pam", "dds", "tga", "ff", "farbfeld", "exr", "hdr"
     ]);
     pub static ref UNDETECTABLE_RASTER: HashSet<&'static str> = create_set(&["tga"]);
     pub static ref VECTOR: HashSet<&'static str> = create_set(&["svg"]);
@@ -18,12 +20,20 @@ lazy_static! {
         "dcr", "dng", "pef", "crw", "iiq", "3fr", "nrw", "nef", "mos", "cr2", "ari"
     ]);
     pub static ref PHOTOSHOP: HashSet<&'static str> = create_set(&["psd"]);
+    /// Only listed with the `video` feature, they are shown as a still.
+    pub static ref VIDEO: HashSet<&'static str> =
+        create_set(&["mp4", "m4v", "mov", "webm", "mkv", "avi"]);
+    /// Only listed with the `jxl` feature.
+    pub static ref JPEG_XL: HashSet<&'static str> = create_set(&["jxl"]);
     pub static ref EXTENSIONS: HashSet<&'static str> = {
         let mut set: HashSet<&'static str> = HashSet::new();
         set.extend(RASTER.iter());
         set.extend(VECTOR.iter());
         set.extend(PHOTOSHOP.iter());
         set.extend(RAW.iter());
+        if jxl::SUPPORTED {
+            set.extend(JPEG_XL.iter());
+        }
         set
     };
 }
@@ -0,0 +1,134 @@
+//! The last few thousand things that happened, kept in memory for the log window and
+//! diagnostic bundles. Everything is logged through the `log` crate macros re-exported
+//! here, records below the max level are dropped before their message is formatted.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Instant,
+};
+
+use lazy_static::lazy_static;
+pub use log::{debug, error, info, trace, warn, Level, LevelFilter};
+
+const CAPACITY: usize = 4000;
+
+lazy_static! {
+    static ref START: Instant = Instant::now();
+    static ref RECORDS: Mutex<VecDeque<Record>> = Mutex::new(VecDeque::with_capacity(CAPACITY));
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Seconds since the first record.
+    pub time: f64,
+    pub level: Level,
+    /// The module that logged it, without the crate name.
+    pub target: String,
+    pub message: String,
+}
+
+impl Record {
+    pub fn line(&self) -> String {
+        format!(
+            "[{:>9.3}] {:<5} {}: {}",
+            self.time, self.level, self.target, self.message
+        )
+    }
+}
+
+struct RingLogger {
+    /// Whether records are printed to stderr too.
+    verbose: bool,
+}
+
+impl log::Log for RingLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = record.target();
+        let target = target.strip_prefix("simp::").unwrap_or(target);
+        let record = push(record.level(), target, record.args().to_string());
+        if self.verbose {
+            eprintln!("{}", record.line());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static QUIET: RingLogger = RingLogger { verbose: false };
+static VERBOSE: RingLogger = RingLogger { verbose: true };
+/// Set by `--verbose`, which keeps every record no matter the level setting.
+static KEEP_ALL: AtomicBool = AtomicBool::new(false);
+
+/// Starts collecting records. Debug records are kept until `set_level` says otherwise,
+/// with `verbose` trace records are always kept and everything is mirrored to stderr.
+pub fn init(verbose: bool) {
+    let (logger, level) = if verbose {
+        (&VERBOSE, LevelFilter::Trace)
+    } else {
+        (&QUIET, LevelFilter::Debug)
+    };
+    if log::set_logger(logger).is_ok() {
+        KEEP_ALL.store(verbose, Ordering::Relaxed);
+        log::set_max_level(level);
+    }
+}
+
+/// Keeps records down to `level` from now on.
+pub fn set_level(level: Level) {
+    if !KEEP_ALL.load(Ordering::Relaxed) {
+        log::set_max_level(level.to_level_filter());
+    }
+}
+
+/// Adds a record, dropping the oldest one when full.
+fn push(level: Level, target: &str, message: String) -> Record {
+    let record = Record {
+        time: START.elapsed().as_secs_f64(),
+        level,
+        target: target.to_string(),
+        message,
+    };
+    let mut records = RECORDS.lock().unwrap();
+    if records.len() == CAPACITY {
+        records.pop_front();
+    }
+    records.push_back(record.clone());
+    record
+}
+
+/// The records kept so far, oldest first. Nothing can be logged while they are borrowed.
+pub fn records() -> MutexGuard<'static, VecDeque<Record>> {
+    RECORDS.lock().unwrap()
+}
+
+pub fn lines() -> Vec<String> {
+    RECORDS.lock().unwrap().iter().map(Record::line).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_latest_records_are_kept() {
+        for index in 0..CAPACITY + 10 {
+            push(Level::Info, "test", format!("line {}", index));
+        }
+        let lines = lines();
+        assert_eq!(lines.len(), CAPACITY);
+        assert!(lines
+            .iter()
+            .any(|line| line.ends_with(&format!("test: line {}", CAPACITY + 9))));
+    }
+}
@@ -1,8 +1,19 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-use image::{Delay, DynamicImage, Frame, ImageBuffer, Rgba};
+use glium::glutin::event_loop::EventLoopProxy;
+use image::{ColorType, Delay, DynamicImage, Frame, ImageBuffer, Rgba};
 
 pub mod extensions;
+pub mod json;
+pub mod log;
+pub mod taskbar;
+pub mod zip;
 
 #[macro_export]
 macro_rules! min {
@@ -30,10 +41,18 @@ macro_rules! max {
     }}
 }
 
+/// The colors of an indexed image as RGBA, in index order.
+pub type Palette = Arc<Vec<[u8; 4]>>;
+
 #[derive(Clone, Debug)]
 pub struct Image {
     pub image: DynamicImage,
     pub delay: Duration,
+    /// The palette of an indexed png, only kept so saving can write it indexed again. The
+    /// pixels are expanded to rgb(a) and take as much memory as any true color image; gif and
+    /// webp frames have none. Edits that mix colors build a new `Image` without one, which
+    /// makes it a true color image.
+    pub palette: Option<Palette>,
 }
 
 impl Image {
@@ -41,11 +60,47 @@ impl Image {
         Image {
             image,
             delay: Duration::default(),
+            palette: None,
         }
     }
 
     pub fn with_delay(image: DynamicImage, delay: Duration) -> Self {
-        Image { image, delay }
+        Image {
+            image,
+            delay,
+            palette: None,
+        }
+    }
+
+    /// A new frame from `image`, made by moving this frame's pixels around without changing
+    /// their colors, so it keeps the palette.
+    pub fn derive(&self, image: DynamicImage) -> Self {
+        Image {
+            image,
+            delay: self.delay,
+            palette: self.palette.clone(),
+        }
+    }
+
+    /// The color type as the metadata panel shows it, the one the pixels are held in.
+    pub fn color_name(&self) -> String {
+        let name = match self.image.color() {
+            ColorType::L8 => "Gray 8-bit",
+            ColorType::La8 => "Gray + alpha 8-bit",
+            ColorType::L16 => "Gray 16-bit",
+            ColorType::La16 => "Gray + alpha 16-bit",
+            ColorType::Rgb8 => "RGB 8-bit",
+            ColorType::Rgba8 => "RGBA 8-bit",
+            ColorType::Rgb16 => "RGB 16-bit",
+            ColorType::Rgba16 => "RGBA 16-bit",
+            ColorType::Rgb32F => "RGB 32-bit float",
+            ColorType::Rgba32F => "RGBA 32-bit float",
+            _ => "Unknown",
+        };
+        match &self.palette {
+            Some(palette) => format!("{}, from {} palette colors", name, palette.len()),
+            None => name.to_string(),
+        }
     }
 
     pub fn buffer(&self) -> &DynamicImage {
@@ -62,6 +117,7 @@ impl From<ImageBuffer<Rgba<u8>, Vec<u8>>> for Image {
         Image {
             image: DynamicImage::ImageRgba8(buffer),
             delay: Duration::default(),
+            palette: None,
         }
     }
 }
@@ -74,6 +130,7 @@ impl From<Frame> for Image {
         Image {
             image: DynamicImage::ImageRgba8(buffer),
             delay,
+            palette: None,
         }
     }
 }
@@ -86,22 +143,124 @@ impl From<Image> for Frame {
     }
 }
 
+/// Sends `UserEvent`s to the event loop from any thread. Tests can not create an event loop,
+/// their proxy collects the events in a channel instead.
+#[derive(Clone)]
+pub struct Proxy(ProxyTarget);
+
+#[derive(Clone)]
+enum ProxyTarget {
+    EventLoop(EventLoopProxy<UserEvent>),
+    #[cfg(test)]
+    Channel(std::sync::mpsc::Sender<UserEvent>),
+}
+
+impl Proxy {
+    /// A proxy for tests and the receiving end of its events.
+    #[cfg(test)]
+    pub fn channel() -> (Self, std::sync::mpsc::Receiver<UserEvent>) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        (Self(ProxyTarget::Channel(sender)), receiver)
+    }
+
+    /// Hands the event back when the event loop is gone.
+    pub fn send_event(&self, event: UserEvent) -> Result<(), UserEvent> {
+        match &self.0 {
+            ProxyTarget::EventLoop(proxy) => proxy.send_event(event).map_err(|closed| closed.0),
+            #[cfg(test)]
+            ProxyTarget::Channel(sender) => sender.send(event).map_err(|closed| closed.0),
+        }
+    }
+}
+
+impl From<EventLoopProxy<UserEvent>> for Proxy {
+    fn from(proxy: EventLoopProxy<UserEvent>) -> Self {
+        Self(ProxyTarget::EventLoop(proxy))
+    }
+}
+
 pub enum UserEvent {
     ErrorMessage(String),
+    /// Loading an image failed with the reason.
+    LoadFailed(PathBuf, String),
+    /// The image is over the size limit, with its size and whether it can be downsampled.
+    TooLarge(PathBuf, (u32, u32), bool),
+    Toast(String),
+    /// A toast with a button that shows the file in the file manager.
+    RevealToast(String, PathBuf),
     QueueLoad(PathBuf),
+    /// Stills picked to be put together into an animation.
+    ImportFrames(Vec<PathBuf>),
+    /// Several images picked at once, each opens in a tab.
+    OpenTabs(Vec<PathBuf>),
+    /// The image to wipe compare the current one with.
+    WipeLoaded(PathBuf, DynamicImage),
+    /// A later launch handed its paths or addresses to this window.
+    HandOff(Vec<PathBuf>),
     QueueSave(PathBuf),
+    QueueExport(PathBuf),
+    /// The folder to write every frame of the animation into.
+    ExportFrames(PathBuf),
+    SavePlaylist(PathBuf),
+    ExportReport(PathBuf),
+    ExportContactSheet(PathBuf),
+    ExportDiagnostics(PathBuf),
+    Deleted(PathBuf),
+    /// The image data was written to the path.
+    Saved(PathBuf, Arc<RwLock<ImageData>>),
+    DialogClosed,
     Wake,
     Exit,
 }
 
+#[derive(Debug)]
 pub struct ImageData {
     pub frames: Vec<Image>,
     pub metadata: Vec<(String, String)>,
+    /// Horizontal and vertical resolution in dots per inch if the file specified it.
+    pub resolution: Option<(f32, f32)>,
+    /// Problems that did not stop the image from loading but may make it look wrong.
+    pub warnings: Vec<String>,
+    /// The real size of the image if the frames were decoded at a reduced scale,
+    /// or the size an svg declares when it was rendered larger.
+    pub full_size: Option<(u32, u32)>,
+    /// How many times an animation plays before stopping, 0 plays it forever.
+    pub loop_count: u32,
+    /// Whether the frame is a still from a video file.
+    pub video: bool,
+    /// Whether the frames were rendered from an svg, which can be rendered again at any size.
+    pub vector: bool,
+    /// Whether the frame is the jpeg preview of a camera raw file, not its developed data.
+    pub raw_preview: bool,
+    /// The address an image without a file was downloaded from.
+    pub source: Option<String>,
+    /// The length of the video if it is known.
+    pub duration: Option<Duration>,
 }
 
 impl ImageData {
     pub fn new(frames: Vec<Image>, metadata: Vec<(String, String)>) -> Self {
-        Self { frames, metadata }
+        Self {
+            frames,
+            metadata,
+            resolution: None,
+            warnings: Vec::new(),
+            full_size: None,
+            loop_count: 0,
+            video: false,
+            vector: false,
+            raw_preview: false,
+            source: None,
+            duration: None,
+        }
+    }
+
+    /// Memory held by the decoded frames.
+    pub fn bytes(&self) -> usize {
+        self.frames
+            .iter()
+            .map(|frame| frame.buffer().as_bytes().len())
+            .sum()
     }
 }
 
@@ -110,6 +269,52 @@ impl From<Vec<Image>> for ImageData {
         Self {
             frames,
             metadata: Vec::new(),
+            resolution: None,
+            warnings: Vec::new(),
+            full_size: None,
+            loop_count: 0,
+            video: false,
+            vector: false,
+            raw_preview: false,
+            source: None,
+            duration: None,
         }
     }
 }
+
+/// Writes `contents` to a temporary file next to `path` that then replaces it, so a crash or
+/// a full disk never leaves half of a file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", nanoid::nanoid!(8)));
+    let temp = path.with_file_name(name);
+
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// The file name of `path` as it is shown to the user. Names that are not valid UTF-8 are
+/// converted lossily and control characters are replaced so they can not mangle the title.
+/// Only for display, files are always opened through the original path.
+pub fn display_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_control() {
+                char::REPLACEMENT_CHARACTER
+            } else {
+                c
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use glium::glutin::window::Window;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskbarProgress {
+    None,
+    Indeterminate,
+    /// Fraction done between 0 and 1.
+    Normal(f32),
+    Error,
+}
+
+/// Shows the progress of a long operation on the taskbar button of the window.
+/// This does nothing on platforms without a taskbar progress bar.
+#[cfg(target_os = "windows")]
+pub fn set_progress(window: &Window, progress: TaskbarProgress) {
+    use std::{cell::Cell, ptr};
+
+    use glium::glutin::platform::windows::WindowExtWindows;
+    use winapi::{
+        shared::{windef::HWND, winerror::SUCCEEDED, wtypesbase::CLSCTX_INPROC_SERVER},
+        um::{
+            combaseapi::{CoCreateInstance, CoInitializeEx},
+            objbase::COINIT_APARTMENTTHREADED,
+            shobjidl_core::{
+                CLSID_TaskbarList, ITaskbarList3, TBPF_ERROR, TBPF_INDETERMINATE, TBPF_NOPROGRESS,
+                TBPF_NORMAL,
+            },
+        },
+        Interface,
+    };
+
+    thread_local! {
+        // the taskbar object is created once per thread and kept for the lifetime of the app
+        static TASKBAR: Cell<*mut ITaskbarList3> = Cell::new(ptr::null_mut());
+    }
+
+    const STEPS: u64 = 1000;
+
+    TASKBAR.with(|taskbar| unsafe {
+        if taskbar.get().is_null() {
+            CoInitializeEx(ptr::null_mut(), COINIT_APARTMENTTHREADED);
+            let mut instance = ptr::null_mut();
+            let res = CoCreateInstance(
+                &CLSID_TaskbarList,
+                ptr::null_mut(),
+                CLSCTX_INPROC_SERVER,
+                &ITaskbarList3::uuidof(),
+                &mut instance,
+            );
+            if !SUCCEEDED(res) || instance.is_null() {
+                return;
+            }
+            let instance = instance as *mut ITaskbarList3;
+            if !SUCCEEDED((*instance).HrInit()) {
+                (*instance).Release();
+                return;
+            }
+            taskbar.set(instance);
+        }
+
+        let taskbar = &*taskbar.get();
+        let hwnd = window.hwnd() as HWND;
+        match progress {
+            TaskbarProgress::None => {
+                taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS);
+            }
+            TaskbarProgress::Indeterminate => {
+                taskbar.SetProgressState(hwnd, TBPF_INDETERMINATE);
+            }
+            TaskbarProgress::Normal(fraction) => {
+                taskbar.SetProgressState(hwnd, TBPF_NORMAL);
+                let done = (fraction.clamp(0.0, 1.0) * STEPS as f32) as u64;
+                taskbar.SetProgressValue(hwnd, done, STEPS);
+            }
+            TaskbarProgress::Error => {
+                taskbar.SetProgressState(hwnd, TBPF_ERROR);
+                taskbar.SetProgressValue(hwnd, STEPS, STEPS);
+            }
+        }
+    });
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn set_progress(_window: &Window, _progress: TaskbarProgress) {}
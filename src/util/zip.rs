@@ -0,0 +1,88 @@
+//! Just enough of the zip format to bundle a few files, without compression.
+
+/// Packs the files into a zip archive, each file is a name and its contents.
+pub fn write(files: &[(&str, &[u8])]) -> Vec<u8> {
+    // 1980-01-01, the earliest date the format can hold
+    const DATE: u16 = (1 << 5) | 1;
+    // names are utf-8
+    const FLAGS: u16 = 1 << 11;
+
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    let u16 = |out: &mut Vec<u8>, value: u16| out.extend_from_slice(&value.to_le_bytes());
+    let u32 = |out: &mut Vec<u8>, value: u32| out.extend_from_slice(&value.to_le_bytes());
+
+    for (name, data) in files {
+        let offset = out.len() as u32;
+        let crc = crc32fast::hash(data);
+
+        u32(&mut out, 0x0403_4b50);
+        u16(&mut out, 20);
+        u16(&mut out, FLAGS);
+        u16(&mut out, 0);
+        u16(&mut out, 0);
+        u16(&mut out, DATE);
+        u32(&mut out, crc);
+        u32(&mut out, data.len() as u32);
+        u32(&mut out, data.len() as u32);
+        u16(&mut out, name.len() as u16);
+        u16(&mut out, 0);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        u32(&mut central, 0x0201_4b50);
+        u16(&mut central, 20);
+        u16(&mut central, 20);
+        u16(&mut central, FLAGS);
+        u16(&mut central, 0);
+        u16(&mut central, 0);
+        u16(&mut central, DATE);
+        u32(&mut central, crc);
+        u32(&mut central, data.len() as u32);
+        u32(&mut central, data.len() as u32);
+        u16(&mut central, name.len() as u16);
+        u16(&mut central, 0);
+        u16(&mut central, 0);
+        u16(&mut central, 0);
+        u16(&mut central, 0);
+        u32(&mut central, 0);
+        u32(&mut central, offset);
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    let central_size = central.len() as u32;
+    out.append(&mut central);
+
+    u32(&mut out, 0x0605_4b50);
+    u16(&mut out, 0);
+    u16(&mut out, 0);
+    u16(&mut out, files.len() as u16);
+    u16(&mut out, files.len() as u16);
+    u32(&mut out, central_size);
+    u32(&mut out, central_offset);
+    u16(&mut out, 0);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_points_at_its_entries() {
+        let zip = write(&[("a.txt", b"hello"), ("b.txt", b"")]);
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        assert_eq!(&zip[30..35], b"a.txt");
+        assert_eq!(&zip[35..40], b"hello");
+
+        let end = &zip[zip.len() - 22..];
+        assert_eq!(&end[..4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+        let central = u32::from_le_bytes([end[16], end[17], end[18], end[19]]) as usize;
+        assert_eq!(&zip[central..central + 4], b"PK\x01\x02");
+        // the second central entry follows the 46 byte header and name of the first
+        let offset = u32::from_le_bytes(zip[central + 93..central + 97].try_into().unwrap());
+        assert_eq!(offset, 40);
+    }
+}